@@ -0,0 +1,65 @@
+//! Lookup timings for comparing probe implementations.
+//!
+//! Run `cargo bench --bench lookup` for byte-at-a-time probing and
+//! `cargo bench --bench lookup --features simd` for group probing.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use oahm::OAHashMap;
+
+const LOOKUPS: u64 = 1_000_000;
+const ROUNDS: usize = 7;
+
+/// Reports the fastest of a few rounds, since the slower ones mostly measure
+/// whatever else the machine was doing.
+fn time(name: &str, oa: &OAHashMap<u64, u64>, keys: impl Fn(u64) -> u64) {
+    let mut best = f64::MAX;
+    let mut found = 0;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        found = 0;
+        for i in 0..LOOKUPS {
+            if oa.get(black_box(&keys(i))).is_some() {
+                found += 1;
+            }
+        }
+        best = best.min(start.elapsed().as_nanos() as f64 / LOOKUPS as f64);
+    }
+
+    println!("{name:<28} {best:>7.2} ns/lookup ({found} found)");
+}
+
+/// A table filled to just below its growth point, where chains are longest.
+fn full_table(len: u64) -> OAHashMap<u64, u64> {
+    let mut oa = OAHashMap::with_capacity(len as usize);
+    let mut key = 0;
+    while oa.len() < oa.capacity() {
+        oa.insert(key, key);
+        key += 1;
+    }
+    assert!(oa.len() as u64 >= len);
+
+    oa
+}
+
+fn main() {
+    let probing = if cfg!(feature = "simd") {
+        "groups"
+    } else {
+        "bytes"
+    };
+    println!("probing: {probing}");
+
+    let oa = full_table(1 << 16);
+    let len = oa.len() as u64;
+    time("hit, full table", &oa, |i| i % len);
+    time("miss, full table", &oa, |i| len + i);
+
+    let mut churned = full_table(1 << 16);
+    for key in (0..len).step_by(2) {
+        churned.remove(&key);
+    }
+    time("hit, half removed", &churned, |i| (i % len) | 1);
+    time("miss, half removed", &churned, |i| len + i);
+}
@@ -0,0 +1,38 @@
+//! Replays arbitrary operations against both `OAHashMap` and std's `HashMap`
+//! and checks they agree after every step, through the interpreter the
+//! crate's `model` tests run under proptest.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use oahm::{OAHashMap, Probing};
+
+#[path = "../../src/model/ops.rs"]
+mod ops;
+
+use ops::{replay, Config, Op};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    initial: OAHashMap<u8, u32>,
+    probing: u8,
+    incremental_resize: bool,
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    let config = Config {
+        probing: match input.probing % 4 {
+            0 => Probing::Linear,
+            1 => Probing::Quadratic,
+            2 => Probing::DoubleHashing,
+            _ => Probing::Hopscotch,
+        },
+        incremental_resize: input.incremental_resize,
+    };
+
+    if let Err(reason) = replay(config, input.initial, &input.ops) {
+        panic!("{config:?}: {reason}");
+    }
+});
@@ -0,0 +1,86 @@
+//! Links `oahm` without its `alloc` feature into a bare-metal binary that
+//! has no global allocator, to prove `ArrayOAHashMap` and the hashers need
+//! no heap. Build it for a bare-metal target:
+//!
+//! ```sh
+//! cargo build --target thumbv7em-none-eabihf
+//! ```
+
+#![no_std]
+#![no_main]
+
+use core::hash::{BuildHasher, Hasher};
+use core::panic::PanicInfo;
+
+use oahm::{ArrayOAHashMap, DeterministicState};
+
+/// A caller-supplied hasher, as maps built without any hasher feature need.
+#[derive(Clone, Copy, Default)]
+struct Fnv;
+
+struct FnvHasher(u64);
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+impl BuildHasher for Fnv {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+/// Counts the distinct bytes in `data` in a map on the stack.
+fn distinct_bytes(data: &[u8]) -> usize {
+    let mut seen: ArrayOAHashMap<u8, (), 256, Fnv> = ArrayOAHashMap::with_hasher(Fnv);
+    for &byte in data {
+        let _ = seen.insert(byte, ());
+    }
+
+    seen.len()
+}
+
+/// Tallies each value below 16 under a seeded hasher.
+fn tally(data: &[u8]) -> u32 {
+    let mut counts: ArrayOAHashMap<u8, u32, 32, DeterministicState> =
+        ArrayOAHashMap::with_hasher(DeterministicState::new(81));
+    for &byte in data {
+        let key = byte % 16;
+        match counts.get_mut(&key) {
+            Some(count) => *count += 1,
+            None => {
+                let _ = counts.insert(key, 1);
+            }
+        }
+    }
+
+    counts.iter().map(|(_, count)| count).sum()
+}
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    let data = *b"open addressing without a heap";
+    let total = distinct_bytes(&data) + tally(&data) as usize;
+    core::hint::black_box(total);
+
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &PanicInfo) -> ! {
+    loop {
+        core::hint::spin_loop();
+    }
+}
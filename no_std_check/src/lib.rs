@@ -0,0 +1,76 @@
+//! Links `oahm` into a `#![no_std]` crate to prove the `std` feature is
+//! optional; `no_alloc/` does the same without `alloc`. Build it for a
+//! bare-metal target:
+//!
+//! ```sh
+//! cargo build --target thumbv7em-none-eabihf
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use core::hash::{BuildHasher, Hasher};
+
+use oahm::{ArrayOAHashMap, Entry, OAHashMap, TryReserveError};
+
+/// A caller-supplied hasher, as maps built without any hasher feature need.
+#[derive(Clone, Copy, Default)]
+pub struct Fnv;
+
+pub struct FnvHasher(u64);
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+impl BuildHasher for Fnv {
+    type Hasher = FnvHasher;
+
+    fn build_hasher(&self) -> FnvHasher {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+pub fn count_words(words: &[&'static str]) -> OAHashMap<&'static str, u32, Fnv> {
+    let mut counts = OAHashMap::with_hasher(Fnv);
+    for word in words {
+        match counts.entry(*word) {
+            Entry::Occupied(mut entry) => *entry.get_mut() += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(1);
+            }
+        }
+    }
+
+    counts
+}
+
+pub fn squares(limit: u32) -> Result<u64, TryReserveError> {
+    let mut squares: OAHashMap<u32, u64> = OAHashMap::new();
+    squares.try_reserve(limit as usize)?;
+    squares.extend((0..limit).map(|i| (i, u64::from(i) * u64::from(i))));
+    squares.retain(|key, _| key % 2 == 0);
+
+    Ok(squares.values().sum())
+}
+
+/// Counts the distinct bytes in `data` without allocating.
+pub fn distinct_bytes(data: &[u8]) -> usize {
+    const EMPTY: ArrayOAHashMap<u8, (), 256, Fnv> = ArrayOAHashMap::with_hasher(Fnv);
+
+    let mut seen = EMPTY;
+    for &byte in data {
+        let _ = seen.insert(byte, ());
+    }
+
+    seen.len()
+}
@@ -0,0 +1,103 @@
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::OAHashMap;
+
+impl<'a, K, V, S, A> Arbitrary<'a> for OAHashMap<K, V, S, A>
+where
+    K: Arbitrary<'a> + Hash + Eq,
+    V: Arbitrary<'a>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    /// Draws a length bounded by the remaining input, then that many pairs.
+    /// Repeated keys keep the last value, so the map may end up shorter.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.arbitrary_len::<(K, V)>()?;
+        let mut map = OAHashMap::with_hasher_in(S::default(), A::default());
+        for _ in 0..len {
+            let (key, value) = u.arbitrary()?;
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        let mut map = OAHashMap::with_hasher_in(S::default(), A::default());
+        for pair in u.arbitrary_take_rest_iter()? {
+            let (key, value) = pair?;
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::test_util::Rng;
+    use crate::OAHashMap;
+
+    fn bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut rng = Rng::new(seed);
+
+        (0..len).map(|_| rng.next() as u8).collect()
+    }
+
+    #[test]
+    fn empty_input_gives_empty_map() {
+        let oa = OAHashMap::<u32, u32>::arbitrary(&mut Unstructured::new(&[])).unwrap();
+
+        assert!(oa.is_empty());
+    }
+
+    #[test]
+    fn large_inputs_force_growth() {
+        let mut resized = 0;
+        for seed in 0..50 {
+            let data = bytes(seed, 4096);
+            let mut u = Unstructured::new(&data);
+            let oa = OAHashMap::<u16, u8>::arbitrary(&mut u).unwrap();
+
+            let mut u = Unstructured::new(&data);
+            let len = u.arbitrary_len::<(u16, u8)>().unwrap();
+            let keys: HashSet<u16> = (0..len)
+                .map(|_| u.arbitrary::<(u16, u8)>().unwrap().0)
+                .collect();
+            assert_eq!(keys.len(), oa.len());
+            assert!(keys.iter().all(|key| oa.contains_key(key)));
+
+            if oa.resizes > 0 {
+                resized += 1;
+            }
+        }
+
+        assert!(resized > 25);
+    }
+
+    #[test]
+    fn take_rest_collapses_duplicates() {
+        let mut data: Vec<u8> = (0..50u8).flat_map(|i| [1, i * 3, i, 0]).collect();
+        data.extend([1, 6, 0xff, 0xff, 0]);
+        let oa = OAHashMap::<u8, u16>::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+
+        assert_eq!(50, oa.len());
+        assert_eq!(Some(&u16::MAX), oa.get(&6));
+        assert_eq!(Some(&49), oa.get(&147));
+        assert_eq!(
+            HashMap::<u8, u16>::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+            HashMap::from(oa)
+        );
+    }
+}
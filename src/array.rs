@@ -0,0 +1,483 @@
+//! A fixed-capacity map whose slots live inline, for targets without an
+//! allocator.
+//!
+//! It probes like [`OAHashMap`](crate::OAHashMap) does by default: linear
+//! probing with Robin Hood insertion and backward-shift removal, and the same
+//! control bytes in front of the slots. Probes wrap within the `N` slots,
+//! which need not be a power of two, and the map never resizes: an insert
+//! into a full map hands the pair back.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+use core::mem::MaybeUninit;
+
+use crate::raw::{self, EMPTY};
+use crate::{Bucket, DefaultHashBuilder, Equivalent};
+
+pub struct ArrayOAHashMap<K, V, const N: usize, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    ctrl: [u8; N],
+    slots: [MaybeUninit<Bucket<K, V>>; N],
+    len: usize,
+    hash_builder: S,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V, const N: usize> ArrayOAHashMap<K, V, N>
+where
+    K: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, const N: usize, S> ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+{
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            ctrl: [EMPTY; N],
+            slots: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+            hash_builder,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            ctrl: self.ctrl.iter(),
+            slots: self.slots.iter(),
+            remaining: self.len,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for (ctrl, slot) in self.ctrl.iter_mut().zip(&mut self.slots) {
+            if raw::is_full(*ctrl) {
+                *ctrl = EMPTY;
+                // SAFETY: the slot was full and is now marked empty, so the
+                // bucket is dropped exactly once.
+                unsafe { slot.assume_init_drop() };
+            }
+        }
+        self.len = 0;
+    }
+
+    fn bucket(&self, index: usize) -> Option<&Bucket<K, V>> {
+        // SAFETY: a full control byte marks an initialized slot.
+        raw::is_full(self.ctrl[index]).then(|| unsafe { self.slots[index].assume_init_ref() })
+    }
+
+    fn bucket_mut(&mut self, index: usize) -> Option<&mut Bucket<K, V>> {
+        // SAFETY: as in `bucket`.
+        raw::is_full(self.ctrl[index]).then(|| unsafe { self.slots[index].assume_init_mut() })
+    }
+
+    fn take(&mut self, index: usize) -> Bucket<K, V> {
+        assert!(
+            raw::is_full(self.ctrl[index]),
+            "Slot {index} is not occupied"
+        );
+        self.ctrl[index] = EMPTY;
+
+        // SAFETY: the slot was full and is now marked empty, so the bucket is
+        // read out exactly once.
+        unsafe { self.slots[index].assume_init_read() }
+    }
+
+    fn put(&mut self, index: usize, bucket: Bucket<K, V>) {
+        assert!(
+            !raw::is_full(self.ctrl[index]),
+            "Slot {index} is already occupied"
+        );
+        self.ctrl[index] = raw::h2(bucket.hash);
+        self.slots[index].write(bucket);
+    }
+
+    /// Only called with `N > 0`.
+    fn starting_index(hash: u64) -> usize {
+        (hash % N as u64) as usize
+    }
+
+    fn distance(hash: u64, index: usize) -> usize {
+        (index + N - Self::starting_index(hash)) % N
+    }
+
+    fn next(index: usize) -> usize {
+        if index + 1 == N {
+            0
+        } else {
+            index + 1
+        }
+    }
+
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<usize>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        if self.len == 0 {
+            return None;
+        }
+
+        let h2 = raw::h2(hash);
+        let mut index = Self::starting_index(hash);
+        for distance in 0..N {
+            let bucket = self.bucket(index)?;
+            if self.ctrl[index] == h2 && bucket.hash == hash && key.equivalent(&bucket.key) {
+                return Some(index);
+            }
+            if Self::distance(bucket.hash, index) < distance {
+                return None;
+            }
+
+            index = Self::next(index);
+        }
+
+        None
+    }
+
+    /// Robin Hood insertion of a key known to be absent, into a map with a
+    /// free slot.
+    fn insert_new(&mut self, mut bucket: Bucket<K, V>) {
+        let mut index = Self::starting_index(bucket.hash);
+        let mut distance = 0;
+        while let Some(resident) = self.bucket_mut(index) {
+            let resident_distance = Self::distance(resident.hash, index);
+            if resident_distance < distance {
+                core::mem::swap(resident, &mut bucket);
+                let h2 = raw::h2(resident.hash);
+                self.ctrl[index] = h2;
+                distance = resident_distance;
+            }
+
+            index = Self::next(index);
+            distance += 1;
+        }
+
+        self.put(index, bucket);
+        self.len += 1;
+    }
+
+    /// Shifts the entries after a freed slot back until one is already home.
+    fn remove_at(&mut self, index: usize) -> Bucket<K, V> {
+        let bucket = self.take(index);
+        self.len -= 1;
+
+        let mut hole = index;
+        let mut next = Self::next(index);
+        while let Some(resident) = self.bucket(next) {
+            if Self::distance(resident.hash, next) == 0 {
+                break;
+            }
+
+            let moved = self.take(next);
+            self.put(hole, moved);
+            hole = next;
+            next = Self::next(next);
+        }
+
+        bucket
+    }
+}
+
+impl<K, V, const N: usize, S> ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Hands the pair back if the key is new and all `N` slots are taken.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        let hash = self.hash_builder.hash_one(&key);
+        if let Some(index) = self.find(hash, &key) {
+            let bucket = self.bucket_mut(index).unwrap();
+
+            return Ok(Some(core::mem::replace(&mut bucket.value, value)));
+        }
+        if self.len == N {
+            return Err((key, value));
+        }
+
+        self.insert_new(Bucket::new(hash, key, value));
+
+        Ok(None)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(self.hash_builder.hash_one(key), key)?;
+
+        Some(&self.bucket(index)?.value)
+    }
+
+    /// Alias of [`ArrayOAHashMap::get`], matching [`OAHashMap::search`](crate::OAHashMap::search).
+    pub fn search<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(self.hash_builder.hash_one(key), key)?;
+
+        Some(&mut self.bucket_mut(index)?.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find(self.hash_builder.hash_one(key), key).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(self.hash_builder.hash_one(key), key)?;
+
+        Some(self.remove_at(index).into_pair())
+    }
+}
+
+impl<K, V, const N: usize, S> Default for ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+    S: Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, const N: usize, S> Drop for ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+{
+    fn drop(&mut self) {
+        if core::mem::needs_drop::<Bucket<K, V>>() {
+            self.clear();
+        }
+    }
+}
+
+impl<K, V, const N: usize, S> fmt::Debug for ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V, const N: usize, S> IntoIterator for &'a ArrayOAHashMap<K, V, N, S>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    ctrl: core::slice::Iter<'a, u8>,
+    slots: core::slice::Iter<'a, MaybeUninit<Bucket<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let ctrl = *self.ctrl.next()?;
+            let slot = self.slots.next()?;
+            if raw::is_full(ctrl) {
+                self.remaining -= 1;
+                // SAFETY: a full control byte marks an initialized slot.
+                let bucket = unsafe { slot.assume_init_ref() };
+
+                return Some((&bucket.key, &bucket.value));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> Clone for Iter<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            ctrl: self.ctrl.clone(),
+            slots: self.slots.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasherDefault, DefaultHasher};
+    use std::rc::Rc;
+
+    use super::ArrayOAHashMap;
+    use crate::test_util::Rng;
+
+    type Fixed = BuildHasherDefault<DefaultHasher>;
+
+    static EMPTY: ArrayOAHashMap<u32, u32, 4, Fixed> =
+        ArrayOAHashMap::with_hasher(BuildHasherDefault::new());
+
+    #[test]
+    fn starts_empty_in_a_static() {
+        assert!(EMPTY.is_empty());
+        assert_eq!(4, EMPTY.capacity());
+        assert_eq!(None, EMPTY.get(&1));
+        assert_eq!(0, EMPTY.iter().count());
+    }
+
+    #[test]
+    fn fills_to_exactly_n_then_refuses_new_keys() {
+        let mut map: ArrayOAHashMap<u32, u32, 7> = ArrayOAHashMap::new();
+        for key in 0..7 {
+            assert_eq!(Ok(None), map.insert(key, key * 10));
+        }
+
+        assert_eq!(7, map.len());
+        assert_eq!(Err((7, 70)), map.insert(7, 70));
+        assert_eq!(Ok(Some(30)), map.insert(3, 31));
+        assert!((0..7).all(|key| map.contains_key(&key)));
+        assert_eq!(None, map.get(&7));
+
+        let mut entries: Vec<(u32, u32)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        entries.sort_unstable();
+        assert_eq!(7, entries.len());
+        assert_eq!((3, 31), entries[3]);
+    }
+
+    #[test]
+    fn removal_frees_a_slot_for_reuse() {
+        let mut map: ArrayOAHashMap<u32, u32, 4> = ArrayOAHashMap::new();
+        for key in 0..4 {
+            map.insert(key, key).unwrap();
+        }
+
+        assert_eq!(Some(2), map.remove(&2));
+        assert_eq!(None, map.remove(&2));
+        assert_eq!(Ok(None), map.insert(9, 9));
+        assert_eq!(Err((10, 10)), map.insert(10, 10));
+        assert_eq!(Some(&9), map.search(&9));
+        *map.get_mut(&9).unwrap() += 1;
+        assert_eq!(Some((9, 10)), map.remove_entry(&9));
+    }
+
+    #[test]
+    fn matches_std_under_random_churn() {
+        let mut rng = Rng::new(81);
+        let mut map: ArrayOAHashMap<u64, u64, 50> = ArrayOAHashMap::new();
+        let mut expected = std::collections::HashMap::new();
+        for step in 0..5000 {
+            let key = rng.below(80);
+            if rng.below(2) == 0 {
+                assert_eq!(expected.remove(&key), map.remove(&key));
+            } else if expected.len() < 50 || expected.contains_key(&key) {
+                assert_eq!(Ok(expected.insert(key, step)), map.insert(key, step));
+            } else {
+                assert_eq!(Err((key, step)), map.insert(key, step));
+            }
+            assert_eq!(expected.len(), map.len());
+        }
+
+        assert!(expected
+            .iter()
+            .all(|(key, value)| map.get(key) == Some(value)));
+    }
+
+    #[test]
+    fn drops_the_inline_pairs_once() {
+        let marker = Rc::new(());
+        let mut map: ArrayOAHashMap<u32, Rc<()>, 8> = ArrayOAHashMap::new();
+        for key in 0..8 {
+            map.insert(key, Rc::clone(&marker)).unwrap();
+        }
+        let (_, refused) = map.insert(8, Rc::clone(&marker)).unwrap_err();
+        drop(refused);
+        assert_eq!(9, Rc::strong_count(&marker));
+
+        drop(map.insert(0, Rc::clone(&marker)));
+        drop(map.remove(&1));
+        assert_eq!(8, Rc::strong_count(&marker));
+
+        map.clear();
+        assert_eq!(1, Rc::strong_count(&marker));
+        assert!(map.is_empty());
+
+        map.insert(3, Rc::clone(&marker)).unwrap();
+        drop(map);
+        assert_eq!(1, Rc::strong_count(&marker));
+    }
+
+    #[test]
+    fn zero_slots_hold_nothing() {
+        let mut map: ArrayOAHashMap<u32, u32, 0> = ArrayOAHashMap::new();
+
+        assert_eq!(Err((1, 1)), map.insert(1, 1));
+        assert_eq!(None, map.get(&1));
+        assert_eq!(None, map.remove(&1));
+        assert_eq!("{}", format!("{map:?}"));
+    }
+}
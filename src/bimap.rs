@@ -0,0 +1,495 @@
+//! A one-to-one map between left and right values, searchable from either
+//! side.
+//!
+//! The pairs sit in a dense `Vec`, found through a table of their positions
+//! there for each side. Each value is stored once, so neither side needs to
+//! be `Clone`, and a pair is always found from both sides or from neither.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+use core::slice;
+
+use crate::position_index::PositionIndex;
+use crate::{DefaultHashBuilder, Equivalent};
+
+struct Pair<L, R> {
+    left_hash: u64,
+    right_hash: u64,
+    left: L,
+    right: R,
+}
+
+pub struct OABiMap<L, R, LS = DefaultHashBuilder, RS = DefaultHashBuilder>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+{
+    pairs: Vec<Pair<L, R>>,
+    /// The pairs' positions under their left hashes.
+    left_indices: PositionIndex<LS>,
+    /// The pairs' positions under their right hashes.
+    right_indices: PositionIndex<RS>,
+}
+
+/// The pairs an [`insert`](OABiMap::insert) removed to keep the map one to
+/// one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overwritten<L, R> {
+    /// Neither value was in the map.
+    Neither,
+    /// The left value was in a pair with another right value.
+    Left(L, R),
+    /// The right value was in a pair with another left value.
+    Right(L, R),
+    /// The two were already paired with each other.
+    Pair(L, R),
+    /// Each value was in a pair of its own: the left value's first, then the
+    /// right value's.
+    Both((L, R), (L, R)),
+}
+
+impl<L, R> Overwritten<L, R> {
+    /// Whether the insert removed any pair.
+    pub fn did_overwrite(&self) -> bool {
+        !matches!(self, Self::Neither)
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<L, R> OABiMap<L, R>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_hashers(DefaultHashBuilder::new(), DefaultHashBuilder::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hashers(
+            capacity,
+            DefaultHashBuilder::new(),
+            DefaultHashBuilder::new(),
+        )
+    }
+}
+
+impl<L, R, LS, RS> OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+    LS: BuildHasher,
+    RS: BuildHasher,
+{
+    pub const fn with_hashers(left: LS, right: RS) -> Self {
+        Self {
+            pairs: Vec::new(),
+            left_indices: PositionIndex::with_hasher(left),
+            right_indices: PositionIndex::with_hasher(right),
+        }
+    }
+
+    pub fn with_capacity_and_hashers(capacity: usize, left: LS, right: RS) -> Self {
+        Self {
+            pairs: Vec::with_capacity(capacity),
+            left_indices: PositionIndex::with_capacity_and_hasher(capacity, left),
+            right_indices: PositionIndex::with_capacity_and_hasher(capacity, right),
+        }
+    }
+
+    /// Pairs `left` with `right`, first removing any pair that holds either.
+    /// The result says which pairs went: one that already paired the two
+    /// with each other is replaced whole, with the values it held returned.
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let left_hash = self.left_indices.hash_key(&left);
+        let right_hash = self.right_indices.hash_key(&right);
+        let by_left = self.left_index_of(left_hash, &left);
+        let by_right = self.right_index_of(right_hash, &right);
+
+        let overwritten = match (by_left, by_right) {
+            (None, None) => Overwritten::Neither,
+            (Some(index), Some(other)) if index == other => {
+                let pair = &mut self.pairs[index];
+                let old_left = core::mem::replace(&mut pair.left, left);
+                let old_right = core::mem::replace(&mut pair.right, right);
+
+                return Overwritten::Pair(old_left, old_right);
+            }
+            (Some(index), None) => {
+                let (l, r) = self.remove_index(index);
+                Overwritten::Left(l, r)
+            }
+            (None, Some(index)) => {
+                let (l, r) = self.remove_index(index);
+                Overwritten::Right(l, r)
+            }
+            (Some(by_left), Some(by_right)) => {
+                // Removing the later pair first leaves the earlier one where
+                // it was, since only the last pair moves.
+                let (later, earlier) = (by_left.max(by_right), by_left.min(by_right));
+                let later = self.remove_index(later);
+                let earlier = self.remove_index(earlier);
+                if by_left < by_right {
+                    Overwritten::Both(earlier, later)
+                } else {
+                    Overwritten::Both(later, earlier)
+                }
+            }
+        };
+        self.push(left_hash, left, right_hash, right);
+
+        overwritten
+    }
+
+    /// Inserts the pair only if neither value is in the map yet, and
+    /// otherwise hands it back.
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        let left_hash = self.left_indices.hash_key(&left);
+        let right_hash = self.right_indices.hash_key(&right);
+        if self.left_index_of(left_hash, &left).is_some()
+            || self.right_index_of(right_hash, &right).is_some()
+        {
+            return Err((left, right));
+        }
+        self.push(left_hash, left, right_hash, right);
+
+        Ok(())
+    }
+
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        Q: Hash + Equivalent<L> + ?Sized,
+    {
+        let index = self.left_index_of(self.left_indices.hash_key(left), left)?;
+
+        Some(&self.pairs[index].right)
+    }
+
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        Q: Hash + Equivalent<R> + ?Sized,
+    {
+        let index = self.right_index_of(self.right_indices.hash_key(right), right)?;
+
+        Some(&self.pairs[index].left)
+    }
+
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        Q: Hash + Equivalent<L> + ?Sized,
+    {
+        self.get_by_left(left).is_some()
+    }
+
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        Q: Hash + Equivalent<R> + ?Sized,
+    {
+        self.get_by_right(right).is_some()
+    }
+
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        Q: Hash + Equivalent<L> + ?Sized,
+    {
+        let index = self.left_index_of(self.left_indices.hash_key(left), left)?;
+
+        Some(self.remove_index(index))
+    }
+
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+    where
+        Q: Hash + Equivalent<R> + ?Sized,
+    {
+        let index = self.right_index_of(self.right_indices.hash_key(right), right)?;
+
+        Some(self.remove_index(index))
+    }
+
+    pub fn iter(&self) -> Iter<'_, L, R> {
+        Iter {
+            pairs: self.pairs.iter(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pairs.clear();
+        self.left_indices.clear();
+        self.right_indices.clear();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.pairs.reserve(additional);
+        self.left_indices.reserve(additional);
+        self.right_indices.reserve(additional);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    fn left_index_of<Q>(&self, hash: u64, left: &Q) -> Option<usize>
+    where
+        Q: Equivalent<L> + ?Sized,
+    {
+        self.left_indices
+            .find(hash, |index| left.equivalent(&self.pairs[index].left))
+    }
+
+    fn right_index_of<Q>(&self, hash: u64, right: &Q) -> Option<usize>
+    where
+        Q: Equivalent<R> + ?Sized,
+    {
+        self.right_indices
+            .find(hash, |index| right.equivalent(&self.pairs[index].right))
+    }
+
+    fn push(&mut self, left_hash: u64, left: L, right_hash: u64, right: R) {
+        let index = self.pairs.len();
+        self.left_indices.insert(left_hash, index);
+        self.right_indices.insert(right_hash, index);
+        self.pairs.push(Pair {
+            left_hash,
+            right_hash,
+            left,
+            right,
+        });
+    }
+
+    fn remove_index(&mut self, index: usize) -> (L, R) {
+        let Pair {
+            left_hash,
+            right_hash,
+            ..
+        } = self.pairs[index];
+        self.left_indices.remove(left_hash, index);
+        self.right_indices.remove(right_hash, index);
+
+        let last = self.pairs.len() - 1;
+        if index != last {
+            let moved = &self.pairs[last];
+            self.left_indices.replace(moved.left_hash, last, index);
+            self.right_indices.replace(moved.right_hash, last, index);
+        }
+
+        let pair = self.pairs.swap_remove(index);
+
+        (pair.left, pair.right)
+    }
+}
+
+impl<L, R, LS, RS> Default for OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+    LS: BuildHasher + Default,
+    RS: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hashers(LS::default(), RS::default())
+    }
+}
+
+impl<L, R, LS, RS> FromIterator<(L, R)> for OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+    LS: BuildHasher + Default,
+    RS: BuildHasher + Default,
+{
+    /// Later pairs overwrite earlier ones sharing either value.
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (L, R)>,
+    {
+        let iter = iter.into_iter();
+        let mut map =
+            Self::with_capacity_and_hashers(iter.size_hint().0, LS::default(), RS::default());
+        map.extend(iter);
+
+        map
+    }
+}
+
+impl<L, R, LS, RS> Extend<(L, R)> for OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+    LS: BuildHasher,
+    RS: BuildHasher,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (L, R)>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
+
+impl<L, R, LS, RS> fmt::Debug for OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq + fmt::Debug,
+    R: Hash + Eq + fmt::Debug,
+    LS: BuildHasher,
+    RS: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, L, R, LS, RS> IntoIterator for &'a OABiMap<L, R, LS, RS>
+where
+    L: Hash + Eq,
+    R: Hash + Eq,
+    LS: BuildHasher,
+    RS: BuildHasher,
+{
+    type Item = (&'a L, &'a R);
+    type IntoIter = Iter<'a, L, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, L, R> {
+    pairs: slice::Iter<'a, Pair<L, R>>,
+}
+
+impl<'a, L, R> Iterator for Iter<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pairs.next().map(|pair| (&pair.left, &pair.right))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.pairs.size_hint()
+    }
+}
+
+impl<L, R> ExactSizeIterator for Iter<'_, L, R> {}
+
+impl<L, R> FusedIterator for Iter<'_, L, R> {}
+
+impl<L, R> Clone for Iter<'_, L, R> {
+    fn clone(&self) -> Self {
+        Self {
+            pairs: self.pairs.clone(),
+        }
+    }
+}
+
+impl<L, R> fmt::Debug for Iter<'_, L, R>
+where
+    L: fmt::Debug,
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{OABiMap, Overwritten};
+    use crate::test_util::Rng;
+
+    #[test]
+    fn insert_reports_what_it_overwrote() {
+        let mut map = OABiMap::new();
+        assert_eq!(Overwritten::Neither, map.insert(1, "one"));
+        assert_eq!(Overwritten::Neither, map.insert(2, "two"));
+        assert_eq!(Overwritten::Pair(1, "one"), map.insert(1, "one"));
+        assert_eq!(Overwritten::Left(1, "one"), map.insert(1, "uno"));
+        assert_eq!(Overwritten::Right(2, "two"), map.insert(3, "two"));
+        assert_eq!(
+            Overwritten::Both((1, "uno"), (3, "two")),
+            map.insert(1, "two")
+        );
+        assert_eq!(1, map.len());
+        assert_eq!(Some(&"two"), map.get_by_left(&1));
+        assert_eq!(Some(&1), map.get_by_right("two"));
+
+        assert_eq!(Err((4, "two")), map.insert_no_overwrite(4, "two"));
+        assert_eq!(Ok(()), map.insert_no_overwrite(4, "four"));
+        assert_eq!(Some((4, "four")), map.remove_by_right("four"));
+        assert_eq!(Some((1, "two")), map.remove_by_left(&1));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn both_directions_always_agree() {
+        let mut rng = Rng::new(87);
+        let mut map = OABiMap::new();
+        let mut by_left: HashMap<u64, u64> = HashMap::new();
+        for _ in 0..10_000 {
+            let (left, right) = (rng.below(300), rng.below(300));
+            match rng.below(5) {
+                0 => {
+                    let removed = by_left.remove(&left).map(|right| (left, right));
+                    assert_eq!(removed, map.remove_by_left(&left));
+                }
+                1 => {
+                    let owner = by_left.iter().find(|(_, r)| **r == right).map(|(l, _)| *l);
+                    let removed = owner.map(|owner| (owner, by_left.remove(&owner).unwrap()));
+                    assert_eq!(removed, map.remove_by_right(&right));
+                }
+                _ => {
+                    let old_right = by_left.remove(&left);
+                    let owner = by_left.iter().find(|(_, r)| **r == right).map(|(l, _)| *l);
+                    let old_left = owner.inspect(|owner| {
+                        by_left.remove(owner);
+                    });
+                    by_left.insert(left, right);
+                    let expected = match (old_right, old_left) {
+                        (None, None) => Overwritten::Neither,
+                        (Some(r), None) if r == right => Overwritten::Pair(left, r),
+                        (Some(r), None) => Overwritten::Left(left, r),
+                        (None, Some(l)) => Overwritten::Right(l, right),
+                        (Some(r), Some(l)) => Overwritten::Both((left, r), (l, right)),
+                    };
+                    assert_eq!(expected, map.insert(left, right));
+                }
+            }
+
+            assert_eq!(by_left.len(), map.len());
+        }
+
+        for (left, right) in &map {
+            assert_eq!(Some(right), by_left.get(left));
+            assert_eq!(Some(left), map.get_by_right(right));
+            assert_eq!(Some(right), map.get_by_left(left));
+        }
+        for right in 0..300 {
+            let owner = by_left.iter().find(|(_, r)| **r == right).map(|(l, _)| l);
+            assert_eq!(owner, map.get_by_right(&right));
+        }
+    }
+
+    #[test]
+    fn collecting_keeps_the_last_pairing() {
+        let map: OABiMap<char, u32> = [('a', 1), ('b', 2), ('a', 3), ('c', 2)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(2, map.len());
+        assert_eq!(Some(&3), map.get_by_left(&'a'));
+        assert_eq!(Some(&'c'), map.get_by_right(&2));
+        assert!(!map.contains_left(&'b'));
+        assert!(!map.contains_right(&1));
+    }
+}
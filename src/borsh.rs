@@ -0,0 +1,127 @@
+//! Borsh encoding of [`OAHashMap`].
+//!
+//! The format matches borsh's own `HashMap` encoding and is stable: the entry
+//! count as a little-endian `u32`, followed by each key and value in ascending
+//! key order. Sorting makes the bytes independent of the hasher and of the
+//! table layout. Decoding rejects input that repeats a key.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use borsh::io::{Error, ErrorKind, Read, Result, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::OAHashMap;
+
+/// Upper bound on the entries pre-allocated from an untrusted length prefix.
+const MAX_PREALLOCATED: usize = 1 << 16;
+
+impl<K, V, S, A> BorshSerialize for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Ord + BorshSerialize,
+    V: BorshSerialize,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut entries: alloc::vec::Vec<_> = self.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+
+        u32::try_from(entries.len())
+            .map_err(|_| Error::from(ErrorKind::InvalidData))?
+            .serialize(writer)?;
+        for (key, value) in entries {
+            key.serialize(writer)?;
+            value.serialize(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<K, V, S, A> BorshDeserialize for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + BorshDeserialize,
+    V: BorshDeserialize,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let len = u32::deserialize_reader(reader)? as usize;
+        let mut map = OAHashMap::with_capacity_and_hasher_in(
+            len.min(MAX_PREALLOCATED),
+            S::default(),
+            A::default(),
+        );
+        for _ in 0..len {
+            let key = K::deserialize_reader(reader)?;
+            let value = V::deserialize_reader(reader)?;
+            if map.insert(key, value).is_some() {
+                return Err(Error::new(ErrorKind::InvalidData, "duplicate map key"));
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::test_util::Rng;
+    use crate::OAHashMap;
+
+    const GOLDEN: [u8; 22] = [
+        3, 0, 0, 0, // len
+        1, 0, 10, 0, 0, 0, // 1 => 10
+        2, 0, 20, 0, 0, 0, // 2 => 20
+        7, 0, 70, 0, 0, 0, // 7 => 70
+    ];
+
+    #[test]
+    fn golden_bytes() {
+        let mut oa: OAHashMap<u16, u32> = OAHashMap::new();
+        for key in [7, 100, 2, 1] {
+            oa.insert(key, u32::from(key) * 10);
+        }
+        oa.remove(&100);
+
+        assert_eq!(GOLDEN.to_vec(), borsh::to_vec(&oa).unwrap());
+
+        let back: OAHashMap<u16, u32> = borsh::from_slice(&GOLDEN).unwrap();
+        assert_eq!(oa, back);
+    }
+
+    #[test]
+    fn encoding_ignores_hasher_and_layout() {
+        let mut rng = Rng::new(5);
+        let keys: Vec<u64> = (0..300).map(|_| rng.next()).collect();
+        let forward: OAHashMap<u64, u64> = keys.iter().map(|&k| (k, k / 2)).collect();
+        let backward: OAHashMap<u64, u64, std::hash::RandomState> =
+            keys.iter().rev().map(|&k| (k, k / 2)).collect();
+
+        let bytes = borsh::to_vec(&forward).unwrap();
+        assert_eq!(bytes, borsh::to_vec(&backward).unwrap());
+
+        let back: OAHashMap<u64, u64, std::hash::RandomState> = borsh::from_slice(&bytes).unwrap();
+        assert_eq!(forward, back);
+    }
+
+    #[test]
+    fn duplicate_keys_are_rejected() {
+        let mut bytes = GOLDEN;
+        bytes[10] = 1;
+        let error = borsh::from_slice::<OAHashMap<u16, u32>>(&bytes).unwrap_err();
+
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let mut bytes = GOLDEN.to_vec();
+        bytes[0] = 200;
+
+        assert!(borsh::from_slice::<OAHashMap<u16, u32>>(&bytes).is_err());
+    }
+}
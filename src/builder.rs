@@ -0,0 +1,447 @@
+//! Tuning for how full a map gets and how it grows.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::raw::RawTable;
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+use crate::DefaultHashBuilder;
+#[cfg(feature = "tracing")]
+use crate::MAX_PROBE_LENGTH;
+#[cfg(feature = "rayon")]
+use crate::PARALLEL_REHASH_THRESHOLD;
+use crate::{OAHashMap, TryReserveError, EXTEND_LIMIT, INITIAL_CAPACITY};
+
+/// How full a table may get, how large its first allocation is, how much
+/// each growth multiplies it by and how many entries it may hold.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Config {
+    pub(crate) max_load_factor: f32,
+    pub(crate) initial_slots: usize,
+    pub(crate) growth_factor: f32,
+    pub(crate) max_entries: usize,
+    #[cfg(feature = "rayon")]
+    pub(crate) parallel_rehash_threshold: usize,
+    #[cfg(feature = "tracing")]
+    pub(crate) probe_event_threshold: usize,
+}
+
+impl Config {
+    pub(crate) const DEFAULT: Self = Self {
+        max_load_factor: EXTEND_LIMIT,
+        initial_slots: INITIAL_CAPACITY,
+        growth_factor: 2.0,
+        max_entries: usize::MAX,
+        #[cfg(feature = "rayon")]
+        parallel_rehash_threshold: PARALLEL_REHASH_THRESHOLD,
+        #[cfg(feature = "tracing")]
+        probe_event_threshold: MAX_PROBE_LENGTH,
+    };
+
+    pub(crate) fn try_slots_for(
+        &self,
+        len: usize,
+        max_slots: usize,
+    ) -> Result<usize, TryReserveError> {
+        self.try_slots_from(self.initial_slots.min(max_slots), len, max_slots)
+    }
+
+    /// The fewest slots, at least `slots`, that hold `len` entries within the
+    /// load limit and no more than `max_slots`.
+    pub(crate) fn try_slots_from(
+        &self,
+        slots: usize,
+        len: usize,
+        max_slots: usize,
+    ) -> Result<usize, TryReserveError> {
+        let mut slots = slots
+            .checked_next_power_of_two()
+            .ok_or(TryReserveError::CapacityOverflow)?;
+        while len as f32 / slots as f32 > self.max_load_factor {
+            slots = slots
+                .checked_mul(2)
+                .ok_or(TryReserveError::CapacityOverflow)?;
+        }
+
+        if slots > max_slots {
+            return Err(TryReserveError::CapacityOverflow);
+        }
+
+        Ok(slots)
+    }
+
+    /// The slot count a table of `slots` grows to: the growth factor's
+    /// multiple, rounded up to a power of two.
+    pub(crate) fn next_slots(
+        &self,
+        slots: usize,
+        max_slots: usize,
+    ) -> Result<usize, TryReserveError> {
+        let next = if slots == 0 {
+            self.initial_slots.min(max_slots)
+        } else {
+            let grown = (slots as f64 * f64::from(self.growth_factor)) as usize;
+            grown
+                .max(slots + 1)
+                .checked_next_power_of_two()
+                .ok_or(TryReserveError::CapacityOverflow)?
+        };
+
+        if next > max_slots {
+            return Err(TryReserveError::CapacityOverflow);
+        }
+
+        Ok(next)
+    }
+}
+
+/// Builds maps with a load factor, first allocation or growth factor other
+/// than the defaults, which are 0.6, 64 slots and 2, or with a limit on how
+/// many entries they hold.
+///
+/// ```
+/// use oahm::{OAHashMap, OAHashMapBuilder};
+///
+/// let mut map: OAHashMap<&str, i32> = OAHashMapBuilder::new()
+///     .max_load_factor(0.85)
+///     .initial_capacity(2)
+///     .build();
+/// map.insert("a", 1);
+///
+/// assert_eq!(0.85, map.max_load_factor());
+/// ```
+#[derive(Clone, Debug)]
+pub struct OAHashMapBuilder {
+    config: Config,
+    initial_capacity: Option<usize>,
+}
+
+impl OAHashMapBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: Config::DEFAULT,
+            initial_capacity: None,
+        }
+    }
+
+    /// The fraction of the slots that may be in use before the table grows.
+    /// Lower factors keep probes short, higher ones waste fewer slots.
+    ///
+    /// # Panics
+    ///
+    /// If `factor` is not strictly between 0 and 1.
+    pub fn max_load_factor(mut self, factor: f32) -> Self {
+        assert!(
+            factor > 0.0 && factor < 1.0,
+            "Load factor {factor} is not between 0 and 1"
+        );
+        self.config.max_load_factor = factor;
+
+        self
+    }
+
+    /// Allocates room for `capacity` entries up front, and sizes the table
+    /// for that many whenever it is next allocated from nothing, such as
+    /// after [`shrink_to_fit`](OAHashMap::shrink_to_fit) on an empty map.
+    pub fn initial_capacity(mut self, capacity: usize) -> Self {
+        self.initial_capacity = Some(capacity);
+
+        self
+    }
+
+    /// How much each growth multiplies the slot count by. Slot counts stay
+    /// powers of two, so the product is rounded up to one: any factor up to
+    /// 2 doubles the table, and up to 4 quadruples it.
+    ///
+    /// # Panics
+    ///
+    /// If `factor` is not greater than 1.
+    pub fn growth_factor(mut self, factor: f32) -> Self {
+        assert!(factor > 1.0, "Growth factor {factor} is not greater than 1");
+        self.config.growth_factor = factor;
+
+        self
+    }
+
+    /// Caps the map at `max` entries. Once it holds that many,
+    /// [`insert_within_limit`](OAHashMap::insert_within_limit) hands new
+    /// keys back and [`insert`](OAHashMap::insert) panics on them, while
+    /// overwrites still succeed. Neither those nor
+    /// [`reserve`](OAHashMap::reserve) grow the table past the slots `max`
+    /// entries need.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.config.max_entries = max;
+
+        self
+    }
+
+    /// Resizes and rehashes of at least `len` entries place them from
+    /// rayon's threads, under linear probing, rather than this one. The
+    /// table comes out the same either way. Defaults to 131072.
+    #[cfg(feature = "rayon")]
+    pub fn parallel_rehash_threshold(mut self, len: usize) -> Self {
+        self.config.parallel_rehash_threshold = len;
+
+        self
+    }
+
+    /// Probes that inspect more than `slots` slots emit a `tracing` warning
+    /// with the walk's length and the map's size. Defaults to 64.
+    #[cfg(feature = "tracing")]
+    pub fn probe_event_threshold(mut self, slots: usize) -> Self {
+        self.config.probe_event_threshold = slots;
+
+        self
+    }
+
+    #[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+    pub fn build<K, V>(&self) -> OAHashMap<K, V>
+    where
+        K: Hash + Eq,
+    {
+        self.build_with_hasher(DefaultHashBuilder::new())
+    }
+
+    pub fn build_with_hasher<K, V, S>(&self, hash_builder: S) -> OAHashMap<K, V, S>
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+    {
+        self.build_with_hasher_in(hash_builder, Global)
+    }
+
+    pub fn build_with_hasher_in<K, V, S, A>(
+        &self,
+        hash_builder: S,
+        alloc: A,
+    ) -> OAHashMap<K, V, S, A>
+    where
+        K: Hash + Eq,
+        S: BuildHasher,
+        A: Allocator + Clone,
+    {
+        let max_slots = RawTable::<K, V, A>::MAX_SLOTS;
+        let mut config = self.config;
+        let capacity = self.initial_capacity.unwrap_or(0).min(config.max_entries);
+        if self.initial_capacity.is_some() {
+            config.initial_slots = config
+                .try_slots_from(1, capacity, max_slots)
+                .unwrap_or_else(|error| error.handle());
+        }
+        if let Ok(limit) = config.try_slots_from(1, config.max_entries, max_slots) {
+            config.initial_slots = config.initial_slots.min(limit);
+        }
+
+        OAHashMap::try_with_capacity_and_config(capacity, config, hash_builder, alloc)
+            .unwrap_or_else(|error| error.handle())
+    }
+}
+
+impl Default for OAHashMapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use allocator_api2::alloc::Global;
+
+    use super::{Config, OAHashMapBuilder};
+    use crate::test_util::{assert_layout, CountingAlloc};
+    use crate::{OAHashMap, INITIAL_CAPACITY};
+
+    /// The most entries the map held before its table first grew, as a
+    /// fraction of the slots it had.
+    fn occupancy_before_growth(mut oa: OAHashMap<u64, u64>) -> f32 {
+        oa.insert(0, 0);
+        let slots = oa.table.num_slots();
+        let mut key = 1;
+        while oa.table.num_slots() == slots {
+            oa.insert(key, key);
+            key += 1;
+        }
+
+        (oa.len() - 1) as f32 / slots as f32
+    }
+
+    #[test]
+    fn load_factor_sets_the_occupancy_before_growth() {
+        let dense = OAHashMapBuilder::new()
+            .max_load_factor(0.9)
+            .initial_capacity(900)
+            .build();
+        let occupancy = occupancy_before_growth(dense);
+        assert!((0.89..0.91).contains(&occupancy), "grew at {occupancy}");
+
+        let sparse = OAHashMapBuilder::new()
+            .max_load_factor(0.3)
+            .initial_capacity(300)
+            .build();
+        let occupancy = occupancy_before_growth(sparse);
+        assert!((0.29..0.31).contains(&occupancy), "grew at {occupancy}");
+
+        let default = occupancy_before_growth(OAHashMap::with_capacity(600));
+        assert!((0.59..0.61).contains(&default), "grew at {default}");
+    }
+
+    #[test]
+    fn config_survives_resizes_and_clones() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new()
+            .max_load_factor(0.85)
+            .growth_factor(4.0)
+            .build();
+        oa.extend((0..5000).map(|i| (i, i)));
+        assert_eq!((oa.table.num_slots() as f32 * 0.85) as usize, oa.capacity());
+        oa.retain(|&k, _| k < 10);
+        oa.shrink_to_fit();
+        assert_eq!(16, oa.table.num_slots());
+
+        let cloned = oa.clone();
+        for map in [&oa, &cloned] {
+            assert_eq!(0.85, map.max_load_factor());
+            assert_eq!(4.0, map.growth_factor());
+            assert_eq!(
+                (INITIAL_CAPACITY as f32 * 0.85) as usize,
+                map.initial_capacity()
+            );
+            assert_layout(map);
+        }
+        assert_eq!(oa, cloned);
+
+        let mut target = OAHashMap::new();
+        target.clone_from(&oa);
+        assert_eq!(0.85, target.max_load_factor());
+    }
+
+    #[test]
+    fn growth_factor_multiplies_slots() {
+        for (factor, multiple) in [(1.5, 2), (2.0, 2), (3.0, 4), (4.0, 4), (8.0, 8)] {
+            let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new().growth_factor(factor).build();
+            oa.insert(0, 0);
+            let mut slots = oa.table.num_slots();
+            for key in 1..2000 {
+                oa.insert(key, key);
+                let grown = oa.table.num_slots();
+                if grown != slots {
+                    assert_eq!(slots * multiple, grown, "factor {factor}");
+                    slots = grown;
+                }
+            }
+            assert_eq!(factor, oa.growth_factor());
+        }
+    }
+
+    #[test]
+    fn small_initial_capacity_allocates_little() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new().initial_capacity(2).build();
+        assert_eq!(4, oa.table.num_slots());
+        assert_eq!(2, oa.initial_capacity());
+
+        for key in 1..4 {
+            oa.insert(key, key);
+        }
+        assert_eq!(4, oa.table.num_slots());
+        oa.insert(4, 4);
+        assert_eq!(8, oa.table.num_slots());
+
+        oa.clear();
+        oa.shrink_to_fit();
+        oa.insert(1, 1);
+        assert_eq!(4, oa.table.num_slots());
+    }
+
+    #[test]
+    fn defaults_match_the_constants() {
+        let built: OAHashMap<u64, u64> =
+            OAHashMapBuilder::new().build_with_hasher_in(Default::default(), Global);
+        assert_eq!(Config::DEFAULT, built.config);
+        assert_eq!(0, built.table.num_slots());
+
+        let oa: OAHashMap<u64, u64> = OAHashMap::new();
+        assert_eq!(oa.max_load_factor(), built.max_load_factor());
+        assert_eq!(oa.initial_capacity(), built.initial_capacity());
+        assert_eq!(oa.growth_factor(), built.growth_factor());
+    }
+
+    #[test]
+    fn max_entries_refuses_new_keys() {
+        let alloc = CountingAlloc::default();
+        let mut oa: OAHashMap<u64, u64, _, _> = OAHashMapBuilder::new()
+            .max_entries(1000)
+            .build_with_hasher_in(crate::DefaultHashBuilder::default(), alloc.clone());
+        assert_eq!(Some(1000), oa.max_entries());
+        for key in 0..1000 {
+            assert_eq!(Ok(None), oa.insert_within_limit(key, key));
+        }
+
+        let allocations = alloc.stats().allocations;
+        let slots = oa.table.num_slots();
+        assert_eq!(Err((1000, 0)), oa.insert_within_limit(1000, 0));
+        assert_eq!(Err((1001, 0)), oa.insert_within_capacity(1001, 0));
+        assert_eq!(Some(5), oa.insert(5, 50));
+        assert_eq!(Ok(Some(6)), oa.insert_within_limit(6, 60));
+        assert_eq!(allocations, alloc.stats().allocations);
+        assert_eq!(slots, oa.table.num_slots());
+        assert_eq!(1000, oa.len());
+        assert_eq!(Some(&50), oa.get(&5));
+
+        assert_eq!(Some(0), oa.remove(&0));
+        assert_eq!(Ok(None), oa.insert_within_limit(1000, 0));
+        assert_eq!(Err((1001, 0)), oa.insert_within_limit(1001, 0));
+        assert_eq!(allocations, alloc.stats().allocations);
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn max_entries_bounds_reservations() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new()
+            .max_entries(1000)
+            .growth_factor(8.0)
+            .build();
+        let needed = Config::DEFAULT.try_slots_from(1, 1000, usize::MAX).unwrap();
+        oa.reserve(1 << 30);
+        assert_eq!(needed, oa.table.num_slots());
+        assert_eq!(Ok(()), oa.try_reserve(usize::MAX));
+        assert_eq!(needed, oa.table.num_slots());
+
+        let mut grown: OAHashMap<u64, u64> = OAHashMapBuilder::new()
+            .max_entries(1000)
+            .growth_factor(8.0)
+            .build();
+        for key in 0..1000 {
+            grown.insert(key, key);
+        }
+        assert_eq!(needed, grown.table.num_slots());
+
+        let mut tiny: OAHashMap<u64, u64> = OAHashMapBuilder::new().max_entries(2).build();
+        tiny.insert(1, 1);
+        assert_eq!(4, tiny.table.num_slots());
+    }
+
+    #[test]
+    #[should_panic(expected = "limit of 3 entries")]
+    fn insert_past_max_entries_panics() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new().max_entries(3).build();
+        oa.extend((0..4).map(|i| (i, i)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Load factor")]
+    fn full_load_factor_panics() {
+        let _ = OAHashMapBuilder::new().max_load_factor(1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Load factor")]
+    fn zero_load_factor_panics() {
+        let _ = OAHashMapBuilder::new().max_load_factor(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Growth factor")]
+    fn shrinking_growth_factor_panics() {
+        let _ = OAHashMapBuilder::new().growth_factor(1.0);
+    }
+}
@@ -0,0 +1,329 @@
+//! A raw-bytes dump of maps whose keys and values are plain bytes, for
+//! snapshots too large to go through serde pair by pair.
+//!
+//! The dump is a header followed by the table's arrays as they are: every
+//! control byte, then every slot's key, then every slot's value, with empty
+//! slots written as zeroes. A table an incremental resize is still emptying
+//! follows the current one in each array. Keys and values keep the byte
+//! order of the machine that wrote them, which the header records.
+//!
+//! Cached hashes are not written, so the arrays are not loaded as they are.
+//! Where a key sits depends on its hash, and the loading map's hasher is a
+//! fresh one: the default hasher draws new keys for every map. Loading
+//! instead hashes every key again and files it in slot order into a table of
+//! the dumped slot count, which works with any hasher, and a corrupt dump
+//! can only yield an error or wrong entries, never undefined behaviour.
+//! Builder settings are not part of the dump either, so the loaded map has
+//! the default ones.
+
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::hash::{BuildHasher, Hash};
+use core::mem::size_of;
+
+use allocator_api2::alloc::Allocator;
+use bytemuck::Pod;
+
+use crate::builder::Config;
+use crate::raw::{self, RawTable, EMPTY};
+use crate::{DecodeError, OAHashMap};
+
+const MAGIC: [u8; 4] = *b"OAHM";
+const VERSION: u16 = 1;
+const BIG_ENDIAN: u16 = 1;
+
+/// Magic, version, flags, key and value sizes, then the slot counts of the
+/// current and old tables and the live count.
+const HEADER: usize = 4 + 2 + 2 + 4 + 4 + 8 + 8 + 8;
+
+fn flags() -> u16 {
+    if cfg!(target_endian = "big") {
+        BIG_ENDIAN
+    } else {
+        0
+    }
+}
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Pod,
+    V: Pod,
+    A: Allocator + Clone,
+{
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        let Ok(()) = self.encode(&mut |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok::<_, Infallible>(())
+        });
+
+        bytes
+    }
+
+    /// Writes what [`to_bytes`](Self::to_bytes) returns, a buffer at a time
+    /// rather than building it whole.
+    #[cfg(feature = "std")]
+    pub fn write_to<W>(&self, mut writer: W) -> std::io::Result<()>
+    where
+        W: std::io::Write,
+    {
+        const BUFFER: usize = 1 << 16;
+
+        let mut buffer = Vec::with_capacity(BUFFER);
+        self.encode(&mut |chunk| {
+            if buffer.len() + chunk.len() > BUFFER {
+                writer.write_all(&buffer)?;
+                buffer.clear();
+            }
+            if chunk.len() > BUFFER {
+                writer.write_all(chunk)
+            } else {
+                buffer.extend_from_slice(chunk);
+                Ok(())
+            }
+        })?;
+
+        writer.write_all(&buffer)
+    }
+
+    fn tables(&self) -> impl Iterator<Item = &RawTable<K, V, A>> {
+        core::iter::once(&self.table).chain(self.migration.as_ref().map(|m| m.table()))
+    }
+
+    fn encoded_len(&self) -> usize {
+        let slots: usize = self.tables().map(RawTable::num_slots).sum();
+
+        HEADER + slots * (1 + size_of::<K>() + size_of::<V>())
+    }
+
+    fn encode<E>(&self, emit: &mut impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        let old_slots = self.tables().nth(1).map_or(0, RawTable::num_slots);
+        emit(&MAGIC)?;
+        emit(&VERSION.to_le_bytes())?;
+        emit(&flags().to_le_bytes())?;
+        emit(&(size_of::<K>() as u32).to_le_bytes())?;
+        emit(&(size_of::<V>() as u32).to_le_bytes())?;
+        emit(&(self.table.num_slots() as u64).to_le_bytes())?;
+        emit(&(old_slots as u64).to_le_bytes())?;
+        emit(&(self.len as u64).to_le_bytes())?;
+
+        for table in self.tables() {
+            emit(table.ctrl_bytes())?;
+        }
+        for table in self.tables() {
+            for index in 0..table.num_slots() {
+                match table.get(index) {
+                    Some(hashed) => emit(bytemuck::bytes_of(&hashed.key))?,
+                    None => zeroes(size_of::<K>(), emit)?,
+                }
+            }
+        }
+        for table in self.tables() {
+            for index in 0..table.num_slots() {
+                match table.get_pair(index) {
+                    Some((_, value)) => emit(bytemuck::bytes_of(value))?,
+                    None => zeroes(size_of::<V>(), emit)?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn zeroes<E>(mut len: usize, emit: &mut impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+    const ZEROES: [u8; 64] = [0; 64];
+
+    while len > 0 {
+        let chunk = len.min(ZEROES.len());
+        emit(&ZEROES[..chunk])?;
+        len -= chunk;
+    }
+
+    Ok(())
+}
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Pod + Hash + Eq,
+    V: Pod,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    /// Loads a map [`to_bytes`](Self::to_bytes) dumped, into a table of the
+    /// slot count it had, hashing every key again.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let header = bytes
+            .get(..HEADER)
+            .ok_or(DecodeError::Length { found: bytes.len() })?;
+        let field = |at: usize, len: usize| {
+            let mut word = [0; 8];
+            word[..len].copy_from_slice(&header[at..at + len]);
+            u64::from_le_bytes(word)
+        };
+        if header[..4] != MAGIC {
+            return Err(DecodeError::Magic);
+        }
+        let version = field(4, 2) as u16;
+        if version != VERSION {
+            return Err(DecodeError::Version { found: version });
+        }
+        if field(6, 2) != u64::from(flags())
+            || field(8, 4) != size_of::<K>() as u64
+            || field(12, 4) != size_of::<V>() as u64
+        {
+            return Err(DecodeError::Layout);
+        }
+
+        let count = |value: u64| usize::try_from(value).map_err(|_| DecodeError::Corrupt);
+        let (slots, old_slots, len) = (
+            count(field(16, 8))?,
+            count(field(24, 8))?,
+            count(field(32, 8))?,
+        );
+        let max = RawTable::<K, V, A>::MAX_SLOTS;
+        let valid = |slots: usize| slots == 0 || slots.is_power_of_two() && slots <= max;
+        if !valid(slots) || !valid(old_slots) || old_slots > 0 && slots == 0 {
+            return Err(DecodeError::Corrupt);
+        }
+        let all_slots = slots + old_slots;
+        let expected = all_slots
+            .checked_mul(1 + size_of::<K>() + size_of::<V>())
+            .and_then(|body| body.checked_add(HEADER));
+        if expected != Some(bytes.len()) {
+            return Err(DecodeError::Length { found: bytes.len() });
+        }
+
+        let (ctrl, rest) = bytes[HEADER..].split_at(all_slots);
+        let (keys, values) = rest.split_at(all_slots * size_of::<K>());
+        if ctrl
+            .iter()
+            .any(|&byte| !raw::is_full(byte) && byte != EMPTY)
+            || ctrl.iter().filter(|&&byte| raw::is_full(byte)).count() != len
+        {
+            return Err(DecodeError::Corrupt);
+        }
+
+        let mut map = Self::try_with_slots(slots, Config::DEFAULT, S::default(), A::default())
+            .map_err(DecodeError::Reserve)?;
+        for (index, _) in ctrl
+            .iter()
+            .enumerate()
+            .filter(|(_, &byte)| raw::is_full(byte))
+        {
+            let key: K =
+                bytemuck::pod_read_unaligned(&keys[index * size_of::<K>()..][..size_of::<K>()]);
+            let value: V =
+                bytemuck::pod_read_unaligned(&values[index * size_of::<V>()..][..size_of::<V>()]);
+            if map.insert(key, value).is_some() {
+                return Err(DecodeError::DuplicateKey);
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::Rng;
+    use crate::{DecodeError, DeterministicState, OAHashMap};
+
+    fn sample(seed: u64, len: u64) -> OAHashMap<u64, u64> {
+        let mut rng = Rng::new(seed);
+        (0..len).map(|_| (rng.next(), rng.next())).collect()
+    }
+
+    #[test]
+    fn maps_round_trip_through_bytes() {
+        let oa = sample(112, 10_000);
+        let bytes = oa.to_bytes();
+        let mut written = Vec::new();
+        oa.write_to(&mut written).unwrap();
+        assert_eq!(bytes, written);
+
+        let loaded: OAHashMap<u64, u64> = OAHashMap::from_bytes(&bytes).unwrap();
+        assert_eq!(oa, loaded);
+        assert_eq!(oa.memory_stats().slots, loaded.memory_stats().slots);
+        assert_eq!(0, loaded.stats().resizes);
+
+        // Keys are filed again under the loading map's own hasher.
+        let seeded: OAHashMap<u64, u64, DeterministicState> =
+            OAHashMap::from_bytes(&bytes).unwrap();
+        seeded.check_invariants().unwrap();
+        assert!(oa.iter().all(|(key, value)| seeded.get(key) == Some(value)));
+
+        let empty: OAHashMap<u32, [u8; 3]> = OAHashMap::new();
+        let loaded: OAHashMap<u32, [u8; 3]> = OAHashMap::from_bytes(&empty.to_bytes()).unwrap();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn maps_mid_resize_round_trip_through_bytes() {
+        let mut oa = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        oa.extend((0..28u32).map(|i| (i, [i as u16; 3])));
+        oa.insert(28, [28; 3]);
+        assert!(oa.memory_stats().slots > oa.capacity());
+
+        let mut loaded: OAHashMap<u32, [u16; 3]> = OAHashMap::from_bytes(&oa.to_bytes()).unwrap();
+        assert_eq!(oa, loaded);
+        loaded.check_invariants().unwrap();
+        loaded.insert(29, [0; 3]);
+        assert_eq!(30, loaded.len());
+    }
+
+    #[test]
+    fn corrupt_dumps_are_rejected() {
+        let bytes = sample(1120, 100).to_bytes();
+        let load = |bytes: &[u8]| OAHashMap::<u64, u64>::from_bytes(bytes).err();
+        let patched = |at: usize, with: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + with.len()].copy_from_slice(with);
+            bytes
+        };
+
+        assert_eq!(Some(DecodeError::Magic), load(&patched(0, b"OAHN")));
+        assert_eq!(
+            Some(DecodeError::Version { found: 2 }),
+            load(&patched(4, &2u16.to_le_bytes()))
+        );
+        assert_eq!(Some(DecodeError::Layout), load(&patched(6, &[1])));
+        assert_eq!(
+            Some(DecodeError::Layout),
+            OAHashMap::<u32, u64>::from_bytes(&bytes).err()
+        );
+        assert_eq!(
+            Some(DecodeError::Corrupt),
+            load(&patched(16, &3u64.to_le_bytes()))
+        );
+        assert_eq!(
+            Some(DecodeError::Length { found: bytes.len() }),
+            load(&patched(16, &4096u64.to_le_bytes()))
+        );
+        assert_eq!(
+            Some(DecodeError::Corrupt),
+            load(&patched(32, &99u64.to_le_bytes()))
+        );
+        assert_eq!(
+            Some(DecodeError::Length {
+                found: bytes.len() - 1
+            }),
+            load(&bytes[..bytes.len() - 1])
+        );
+        assert_eq!(Some(DecodeError::Length { found: 10 }), load(&bytes[..10]));
+
+        // A control byte that is neither empty nor full, and a full one over
+        // a second copy of a key.
+        let ctrl = super::HEADER;
+        assert_eq!(Some(DecodeError::Corrupt), load(&patched(ctrl, &[0x80])));
+        let small: OAHashMap<u64, u64> = (0..2).map(|i| (i, i)).collect();
+        let mut bytes = small.to_bytes();
+        let slots = small.memory_stats().slots;
+        let full: Vec<_> = (0..slots).filter(|&i| bytes[ctrl + i] != 0xff).collect();
+        let keys = ctrl + slots;
+        let second = keys + 8 * full[1];
+        bytes.copy_within(keys + 8 * full[0]..keys + 8 * full[0] + 8, second);
+        assert_eq!(Some(DecodeError::DuplicateKey), load(&bytes));
+    }
+}
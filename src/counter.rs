@@ -0,0 +1,121 @@
+//! Tallying keys in a map of counts.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+
+use crate::OAHashMap;
+
+/// An integer a map can count with. Counts saturate at the type's bounds
+/// rather than wrapping, so a count that overflowed reads as the largest
+/// one the type holds.
+pub trait Count: Copy {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn saturating_add(self, other: Self) -> Self;
+}
+
+macro_rules! impl_count {
+    ($($int:ty),*) => {
+        $(
+            impl Count for $int {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn saturating_add(self, other: Self) -> Self {
+                    <$int>::saturating_add(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_count!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K> OAHashMap<K, usize>
+where
+    K: Hash + Eq,
+{
+    /// Counts how many times each key occurs in `iter`.
+    pub fn from_counts<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let iter = iter.into_iter();
+        let mut counts = Self::with_capacity(iter.size_hint().0);
+        for key in iter {
+            counts.increment(key);
+        }
+
+        counts
+    }
+}
+
+impl<K, C, S, A> OAHashMap<K, C, S, A>
+where
+    K: Hash + Eq,
+    C: Count,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Adds one to the count for `key`, starting it from zero if the key is
+    /// absent, and returns the new count.
+    pub fn increment(&mut self, key: K) -> C {
+        self.increment_by(key, C::ONE)
+    }
+
+    /// Adds `n` to the count for `key`, saturating at `C`'s bounds, and
+    /// returns the new count. The key is looked up once.
+    pub fn increment_by(&mut self, key: K, n: C) -> C {
+        let count = self.entry(key).or_insert(C::ZERO);
+        *count = count.saturating_add(n);
+
+        *count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::OAHashMap;
+
+    #[test]
+    fn counts_words_as_std_does() {
+        let text = "the quick brown fox jumps over the lazy dog and the dog \
+                    sleeps while the fox runs over the hill and far away";
+        let mut expected = HashMap::new();
+        for word in text.split_whitespace() {
+            *expected.entry(word).or_insert(0) += 1;
+        }
+
+        let counts = OAHashMap::from_counts(text.split_whitespace());
+        assert_eq!(expected.len(), counts.len());
+        assert!(expected.iter().all(|(word, &n)| counts[word] == n));
+        assert_eq!(5, counts["the"]);
+
+        let mut by_length: OAHashMap<usize, u32> = OAHashMap::new();
+        for word in text.split_whitespace() {
+            by_length.increment_by(word.len(), 10);
+        }
+        assert_eq!(120, by_length[&3]);
+    }
+
+    #[test]
+    fn counts_saturate_at_the_type_bounds() {
+        let mut counts: OAHashMap<&str, u8> = OAHashMap::new();
+        assert_eq!(250, counts.increment_by("a", 250));
+        for expected in 251..=255 {
+            assert_eq!(expected, counts.increment("a"));
+        }
+        assert_eq!(255, counts.increment("a"));
+        assert_eq!(255, counts.increment_by("a", u8::MAX));
+
+        let mut signed: OAHashMap<&str, i8> = OAHashMap::new();
+        assert_eq!(-128, signed.increment_by("b", i8::MIN));
+        assert_eq!(-128, signed.increment_by("b", -1));
+        assert_eq!(-127, signed.increment("b"));
+    }
+}
@@ -0,0 +1,215 @@
+//! What changed between two versions of a map.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{DefaultHashBuilder, Iter, OAHashMap};
+
+/// One difference a [`MapDiff`] yields.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Change<'a, K, V> {
+    /// The key is only in the newer map.
+    Added { key: &'a K, value: &'a V },
+    /// The key is only in the older map.
+    Removed { key: &'a K, value: &'a V },
+    /// Both maps hold the key, with values that differ.
+    Changed { key: &'a K, old: &'a V, new: &'a V },
+}
+
+impl<'a, K, V> Change<'a, K, V> {
+    pub fn key(&self) -> &'a K {
+        match *self {
+            Change::Added { key, .. }
+            | Change::Removed { key, .. }
+            | Change::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Yields what turns this map into `other`, skipping the entries they
+    /// share. Walks this map looking each key up in `other`, for the keys
+    /// removed and changed, then `other` looking each up here, for those
+    /// added: one lookup per entry of either map.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> MapDiff<'a, K, V, S, A>
+    where
+        V: PartialEq,
+    {
+        MapDiff {
+            old: self.iter(),
+            new: other.iter(),
+            old_map: self,
+            new_map: other,
+        }
+    }
+}
+
+pub struct MapDiff<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    A: Allocator + Clone,
+{
+    old: Iter<'a, K, V>,
+    new: Iter<'a, K, V>,
+    old_map: &'a OAHashMap<K, V, S, A>,
+    new_map: &'a OAHashMap<K, V, S, A>,
+}
+
+impl<'a, K, V, S, A> Iterator for MapDiff<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = Change<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let new_map = self.new_map;
+        let change = self.old.find_map(|(key, old)| match new_map.get(key) {
+            None => Some(Change::Removed { key, value: old }),
+            Some(new) if new != old => Some(Change::Changed { key, old, new }),
+            Some(_) => None,
+        });
+        if change.is_some() {
+            return change;
+        }
+
+        let old_map = self.old_map;
+        self.new
+            .find(|(key, _)| !old_map.contains_key(*key))
+            .map(|(key, value)| Change::Added { key, value })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let old = self.old.size_hint().1;
+        let new = self.new.size_hint().1;
+
+        (0, old.zip(new).and_then(|(old, new)| old.checked_add(new)))
+    }
+}
+
+impl<K, V, S, A> FusedIterator for MapDiff<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, S, A> Clone for MapDiff<'_, K, V, S, A>
+where
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            old: self.old.clone(),
+            new: self.new.clone(),
+            old_map: self.old_map,
+            new_map: self.new_map,
+        }
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for MapDiff<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: PartialEq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change;
+    use crate::OAHashMap;
+
+    fn config(entries: &[(&'static str, i32)]) -> OAHashMap<&'static str, i32> {
+        entries.iter().copied().collect()
+    }
+
+    #[test]
+    fn identical_and_empty_maps() {
+        let oa = config(&[("a", 1), ("b", 2), ("c", 3)]);
+        assert_eq!(0, oa.diff(&oa.clone()).count());
+
+        let empty = OAHashMap::new();
+        let mut added: Vec<_> = empty.diff(&oa).collect();
+        added.sort_unstable_by_key(|change| *change.key());
+        let expected: Vec<_> = [("a", 1), ("b", 2), ("c", 3)]
+            .iter()
+            .map(|(key, value)| Change::Added { key, value })
+            .collect();
+        assert_eq!(expected, added);
+        assert!(oa
+            .diff(&empty)
+            .all(|change| matches!(change, Change::Removed { .. })));
+        assert_eq!(3, oa.diff(&empty).count());
+        assert_eq!(0, empty.diff(&OAHashMap::new()).count());
+    }
+
+    #[test]
+    fn mixed_changes_match_a_hand_built_expectation() {
+        let old = config(&[("port", 80), ("workers", 4), ("debug", 0), ("timeout", 30)]);
+        let new = config(&[
+            ("port", 8080),
+            ("workers", 4),
+            ("timeout", 30),
+            ("retries", 3),
+        ]);
+
+        let mut changes: Vec<_> = old.diff(&new).collect();
+        changes.sort_unstable_by_key(|change| *change.key());
+        assert_eq!(
+            vec![
+                Change::Removed {
+                    key: &"debug",
+                    value: &0
+                },
+                Change::Changed {
+                    key: &"port",
+                    old: &80,
+                    new: &8080
+                },
+                Change::Added {
+                    key: &"retries",
+                    value: &3
+                },
+            ],
+            changes
+        );
+        assert_eq!((0, Some(8)), old.diff(&new).size_hint());
+
+        // The diff the other way round swaps every side.
+        let mut back: Vec<_> = new.diff(&old).collect();
+        back.sort_unstable_by_key(|change| *change.key());
+        assert!(matches!(back[0], Change::Added { key: &"debug", .. }));
+        assert!(matches!(
+            back[1],
+            Change::Changed {
+                old: &8080,
+                new: &80,
+                ..
+            }
+        ));
+        assert!(matches!(
+            back[2],
+            Change::Removed {
+                key: &"retries",
+                ..
+            }
+        ));
+    }
+}
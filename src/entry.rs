@@ -0,0 +1,694 @@
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{Bucket, DefaultHashBuilder, HashedKey, OAHashMap};
+
+pub enum Entry<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    Occupied(OccupiedEntry<'a, K, V, S, A>),
+    Vacant(VacantEntry<'a, K, V, S, A>),
+}
+
+impl<'a, K, V, S, A> Entry<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_try_insert_with<F, E>(self, default: F) -> Result<&'a mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(default()?)),
+        }
+    }
+
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+impl<'a, K, V, S, A> Entry<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    V: Default,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub struct OccupiedEntry<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    index: usize,
+    key: Option<K>,
+}
+
+impl<'a, K, V, S, A> OccupiedEntry<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>, index: usize, key: Option<K>) -> Self {
+        Self { map, index, key }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.bucket().0.key
+    }
+
+    pub fn get(&self) -> &V {
+        self.bucket().1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.bucket_mut().1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.table.value_mut(self.index).unwrap()
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let bucket = self.map.take_bucket(self.index);
+        self.map.shrink_if_sparse();
+
+        bucket.into_pair()
+    }
+
+    pub fn replace_entry(mut self, value: V) -> (K, V) {
+        let key = self.take_key();
+        let (hashed, slot) = self.bucket_mut();
+        let old_key = core::mem::replace(&mut hashed.key, key);
+        let old_value = core::mem::replace(slot, value);
+
+        (old_key, old_value)
+    }
+
+    pub fn replace_key(mut self) -> K {
+        let key = self.take_key();
+
+        core::mem::replace(&mut self.bucket_mut().0.key, key)
+    }
+
+    fn take_key(&mut self) -> K {
+        self.key
+            .take()
+            .expect("Entry was not created with an owned key")
+    }
+
+    fn bucket(&self) -> (&HashedKey<K>, &V) {
+        self.map.table.get_pair(self.index).unwrap()
+    }
+
+    fn bucket_mut(&mut self) -> (&mut HashedKey<K>, &mut V) {
+        self.map.table.get_pair_mut(self.index).unwrap()
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for OccupiedEntry<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", self.key())
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for Entry<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Occupied(entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+pub struct OccupiedError<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    pub entry: OccupiedEntry<'a, K, V, S, A>,
+    pub value: V,
+}
+
+impl<K, V, S, A> fmt::Debug for OccupiedError<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K, V, S, A> fmt::Display for OccupiedError<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get(),
+        )
+    }
+}
+
+impl<K, V, S, A> Error for OccupiedError<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+pub struct VacantEntry<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    hash: u64,
+    key: K,
+    index: usize,
+}
+
+impl<'a, K, V, S, A> VacantEntry<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>, hash: u64, key: K, index: usize) -> Self {
+        Self {
+            map,
+            hash,
+            key,
+            index,
+        }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V {
+        let index = if self.map.reserve_one() {
+            self.map.insertion_index(self.hash)
+        } else {
+            self.index
+        };
+
+        let index = self
+            .map
+            .insert_at(index, Bucket::new(self.hash, self.key, value));
+
+        self.map.table.value_mut(index).unwrap()
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for VacantEntry<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(self.key()).finish()
+    }
+}
+
+pub enum EntryRef<'a, 'b, K, Q, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    Q: ?Sized,
+    A: Allocator + Clone,
+{
+    Occupied(OccupiedEntry<'a, K, V, S, A>),
+    Vacant(VacantEntryRef<'a, 'b, K, Q, V, S, A>),
+}
+
+impl<'a, 'b, K, Q, V, S, A> EntryRef<'a, 'b, K, Q, V, S, A>
+where
+    K: Hash + Eq + Borrow<Q> + From<&'b Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+
+        self
+    }
+}
+
+impl<'a, 'b, K, Q, V, S, A> EntryRef<'a, 'b, K, Q, V, S, A>
+where
+    K: Hash + Eq + Borrow<Q> + From<&'b Q>,
+    Q: Hash + Eq + ?Sized,
+    V: Default,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub struct VacantEntryRef<'a, 'b, K, Q, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    Q: ?Sized,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    hash: u64,
+    key: &'b Q,
+    index: usize,
+}
+
+impl<'a, 'b, K, Q, V, S, A> VacantEntryRef<'a, 'b, K, Q, V, S, A>
+where
+    K: Hash + Eq,
+    Q: Hash + ?Sized,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(
+        map: &'a mut OAHashMap<K, V, S, A>,
+        hash: u64,
+        key: &'b Q,
+        index: usize,
+    ) -> Self {
+        Self {
+            map,
+            hash,
+            key,
+            index,
+        }
+    }
+
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    pub fn insert(self, value: V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        let index = if self.map.reserve_one() {
+            self.map.insertion_index(self.hash)
+        } else {
+            self.index
+        };
+
+        let index = self
+            .map
+            .insert_at(index, Bucket::new(self.hash, self.key.into(), value));
+
+        self.map.table.value_mut(index).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::Counted;
+    use crate::test_util::{assert_layout, keys_starting_at, Tagged};
+    use crate::{Entry, EntryRef, OAHashMap};
+
+    #[test]
+    fn word_count() {
+        let mut oa: OAHashMap<&str, i32> = OAHashMap::new();
+        for word in "the quick fox jumps over the lazy dog the end".split(' ') {
+            *oa.entry(word).or_insert(0) += 1;
+        }
+
+        assert_eq!(Some(&3), oa.search(&"the"));
+        assert_eq!(Some(&1), oa.search(&"fox"));
+        assert_eq!(8, oa.len());
+    }
+
+    #[test]
+    fn adapters() {
+        let mut oa: OAHashMap<i32, Vec<i32>> = OAHashMap::new();
+        oa.entry(1).or_insert_with(|| vec![0]).push(1);
+        oa.entry(1).or_default().push(2);
+        oa.entry(1).and_modify(|values| values.push(3)).or_default();
+        oa.entry(2).and_modify(|values| values.push(3)).or_default();
+
+        assert_eq!(Some(&vec![0, 1, 2, 3]), oa.search(&1));
+        assert_eq!(Some(&vec![]), oa.search(&2));
+        assert!(matches!(oa.entry(1), Entry::Occupied(_)));
+        assert!(matches!(oa.entry(3), Entry::Vacant(_)));
+        assert_eq!(&3, oa.entry(3).key());
+    }
+
+    #[test]
+    fn occupied_behind_removed_entry() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 40, 2);
+        oa.insert(keys[0], 0);
+        oa.insert(keys[1], 1);
+        oa.delete(&keys[0]);
+
+        assert!(matches!(oa.entry(keys[1]), Entry::Occupied(_)));
+
+        *oa.entry(keys[0]).or_insert(5) += 1;
+
+        assert_eq!(Some(&6), oa.search(&keys[0]));
+        assert_eq!(2, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn occupied_methods() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 10);
+        oa.insert(2, 20);
+
+        match oa.entry(1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(&1, entry.key());
+                assert_eq!(&10, entry.get());
+
+                *entry.get_mut() += 1;
+
+                assert_eq!(11, entry.insert(12));
+                assert_eq!(12, entry.remove());
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        assert_eq!(None, oa.search(&1));
+
+        match oa.entry(2) {
+            Entry::Occupied(entry) => assert_eq!((2, 20), entry.remove_entry()),
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        assert!(oa.is_empty());
+    }
+
+    #[test]
+    fn occupied_into_mut_outlives_entry() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 10);
+
+        let value = match oa.entry(1) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(_) => unreachable!(),
+        };
+        *value += 1;
+
+        assert_eq!(Some(&11), oa.search(&1));
+    }
+
+    #[test]
+    fn replace_key_and_entry() {
+        let mut oa: OAHashMap<Tagged, i32> = OAHashMap::new();
+        oa.insert(Tagged::new(1, "first"), 10);
+
+        match oa.entry(Tagged::new(1, "second")) {
+            Entry::Occupied(entry) => assert_eq!("first", entry.replace_key().tag),
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        match oa.entry(Tagged::new(1, "third")) {
+            Entry::Occupied(entry) => {
+                let (key, value) = entry.replace_entry(30);
+
+                assert_eq!("second", key.tag);
+                assert_eq!(10, value);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+
+        let (key, value) = oa.remove_entry(&Tagged::new(1, "query")).unwrap();
+
+        assert_eq!("third", key.tag);
+        assert_eq!(30, value);
+    }
+
+    #[test]
+    fn vacant_methods() {
+        let mut oa: OAHashMap<String, usize> = OAHashMap::new();
+
+        match oa.entry("key".to_string()) {
+            Entry::Vacant(entry) => assert_eq!("key", entry.into_key()),
+            Entry::Occupied(_) => unreachable!(),
+        }
+
+        assert!(oa.is_empty());
+
+        match oa.entry("key".to_string()) {
+            Entry::Vacant(entry) => {
+                assert_eq!("key", entry.key());
+                *entry.insert(1) += 1;
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+
+        assert_eq!(Some(&2), oa.search(&"key".to_string()));
+        assert_eq!(
+            &mut 3,
+            oa.entry("abc".to_string()).or_insert_with_key(String::len)
+        );
+    }
+
+    #[test]
+    fn entry_ref_only_builds_key_on_insert() {
+        let mut oa: OAHashMap<Counted, i32> = OAHashMap::new();
+        for word in "a b a c a b".split(' ') {
+            *oa.entry_ref(word).or_insert(0) += 1;
+        }
+        oa.entry_ref("c").and_modify(|count| *count += 10);
+        *oa.entry_ref("d").or_insert_with(|| 4) += 1;
+
+        assert_eq!(4, Counted::constructed());
+        assert_eq!("e", oa.entry_ref("e").key());
+        assert!(matches!(oa.entry_ref("a"), EntryRef::Occupied(_)));
+        assert_eq!(4, Counted::constructed());
+        assert_eq!(Some(&3), oa.search(&Counted::from("a")));
+        assert_eq!(Some(&11), oa.search(&Counted::from("c")));
+        assert_eq!(Some(&5), oa.search(&Counted::from("d")));
+    }
+
+    #[test]
+    fn try_insert_reports_occupied() {
+        let mut oa: OAHashMap<i32, &str> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 50, 2);
+        oa.insert(keys[0], "first");
+        oa.insert(keys[1], "second");
+        oa.delete(&keys[0]);
+
+        let error = oa.try_insert(keys[1], "third").unwrap_err();
+
+        assert_eq!(&keys[1], error.entry.key());
+        assert_eq!("third", error.value);
+        assert_eq!(
+            format!(
+                "failed to insert \"third\", key {} already exists with value \"second\"",
+                keys[1]
+            ),
+            error.to_string()
+        );
+        assert!(format!("{error:?}").starts_with("OccupiedError"));
+        assert_eq!(Some(&"second"), oa.search(&keys[1]));
+
+        *oa.try_insert(keys[0], "fourth").unwrap() = "fifth";
+
+        assert_eq!(Some(&"fifth"), oa.search(&keys[0]));
+    }
+
+    #[test]
+    fn try_insert_grows() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 0..500 {
+            assert_eq!(i, *oa.try_insert(i, i).unwrap());
+        }
+        for i in 0..500 {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn or_try_insert_with_leaves_map_unchanged_on_error() {
+        let mut oa: OAHashMap<i32, String> = OAHashMap::new();
+        oa.insert(1, "one".to_string());
+        oa.delete(&1);
+        let result = oa.entry(2).or_try_insert_with(|| Err("disk on fire"));
+
+        assert_eq!(Err("disk on fire"), result);
+        assert!(oa.is_empty());
+        assert_layout(&oa);
+        assert_eq!(None, oa.search(&2));
+
+        let value = oa
+            .get_or_try_insert_with(2, || Ok::<_, &str>("two".to_string()))
+            .unwrap();
+        value.push('!');
+
+        assert_eq!(Some(&"two!".to_string()), oa.search(&2));
+        assert_eq!(
+            Ok(&mut "two!".to_string()),
+            oa.get_or_try_insert_with(2, || Err("not called"))
+        );
+    }
+
+    #[test]
+    fn or_try_insert_with_panic_leaves_map_consistent() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 1);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            oa.entry(2)
+                .or_try_insert_with::<_, ()>(|| panic!("initializer panicked"))
+                .ok();
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(1, oa.len());
+        assert_eq!(None, oa.search(&2));
+        assert_eq!(Some(&1), oa.search(&1));
+    }
+
+    #[test]
+    fn vacant_insert_grows() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 0..1000 {
+            *oa.entry(i).or_insert(i) += 1;
+        }
+
+        for i in 0..1000 {
+            assert_eq!(Some(&(i + 1)), oa.search(&i));
+        }
+        assert_eq!(1000, oa.len());
+    }
+}
@@ -0,0 +1,300 @@
+use core::alloc::Layout;
+use core::error::Error;
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::alloc::handle_alloc_error;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TryReserveError {
+    CapacityOverflow,
+    AllocError { layout: Layout },
+}
+
+#[cfg(feature = "alloc")]
+impl TryReserveError {
+    pub(crate) fn handle(self) -> ! {
+        match self {
+            TryReserveError::CapacityOverflow => panic!("Capacity overflow"),
+            TryReserveError::AllocError { layout } => handle_alloc_error(layout),
+        }
+    }
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryReserveError::CapacityOverflow => {
+                write!(f, "requested capacity exceeds the maximum table size")
+            }
+            TryReserveError::AllocError { layout } => {
+                write!(
+                    f,
+                    "failed to allocate {} bytes for the table",
+                    layout.size()
+                )
+            }
+        }
+    }
+}
+
+impl Error for TryReserveError {}
+
+/// The first repeated key [`try_from_iter`](crate::OAHashMap::try_from_iter)
+/// or [`try_extend`](crate::OAHashMap::try_extend) came across.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DuplicateKeyError<K, V> {
+    pub key: K,
+    /// The value the key came with first, or `None` if the map already held
+    /// the key before `try_extend`, and still does.
+    pub first: Option<V>,
+    /// The value the key came with again.
+    pub second: V,
+}
+
+impl<K, V> fmt::Display for DuplicateKeyError<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.first {
+            Some(first) => write!(
+                f,
+                "key {:?} repeats, with value {:?} and then {:?}",
+                self.key, first, self.second
+            ),
+            None => write!(
+                f,
+                "key {:?} with value {:?} is already in the map",
+                self.key, self.second
+            ),
+        }
+    }
+}
+
+impl<K, V> Error for DuplicateKeyError<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+}
+
+/// Why [`try_extend`](crate::OAHashMap::try_extend) left the map as it
+/// was.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TryExtendError<K, V> {
+    /// A key repeated, within the batch or a key the map already holds.
+    Duplicate(DuplicateKeyError<K, V>),
+    /// The batch would take the map past its
+    /// [`max_entries`](crate::OAHashMapBuilder::max_entries).
+    LimitReached { max_entries: usize },
+}
+
+impl<K, V> From<DuplicateKeyError<K, V>> for TryExtendError<K, V> {
+    fn from(error: DuplicateKeyError<K, V>) -> Self {
+        TryExtendError::Duplicate(error)
+    }
+}
+
+impl<K, V> fmt::Display for TryExtendError<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryExtendError::Duplicate(error) => error.fmt(f),
+            TryExtendError::LimitReached { max_entries } => {
+                write!(f, "the batch would take the map past {max_entries} entries")
+            }
+        }
+    }
+}
+
+impl<K, V> Error for TryExtendError<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+}
+
+/// Why [`change_key`](crate::OAHashMap::change_key) left the map as it
+/// was. Either way the proposed key comes back.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RekeyError<K> {
+    /// No entry has the old key.
+    NotFound(K),
+    /// Another entry already has the new key.
+    Occupied(K),
+}
+
+impl<K> RekeyError<K> {
+    pub fn into_key(self) -> K {
+        match self {
+            RekeyError::NotFound(key) | RekeyError::Occupied(key) => key,
+        }
+    }
+}
+
+impl<K> fmt::Display for RekeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RekeyError::NotFound(_) => write!(f, "no entry has the key to change"),
+            RekeyError::Occupied(_) => write!(f, "another entry already has the new key"),
+        }
+    }
+}
+
+impl<K> Error for RekeyError<K> where K: fmt::Debug {}
+
+/// The key [`try_get`](crate::OAHashMap::try_get) or
+/// [`try_get_mut`](crate::OAHashMap::try_get_mut) found no entry for.
+#[derive(PartialEq, Eq, Debug)]
+pub struct KeyNotFoundError<'a, Q>
+where
+    Q: ?Sized,
+{
+    key: &'a Q,
+}
+
+impl<'a, Q> KeyNotFoundError<'a, Q>
+where
+    Q: ?Sized,
+{
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new(key: &'a Q) -> Self {
+        Self { key }
+    }
+
+    pub fn key(&self) -> &'a Q {
+        self.key
+    }
+}
+
+impl<Q> Clone for KeyNotFoundError<'_, Q>
+where
+    Q: ?Sized,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Q> Copy for KeyNotFoundError<'_, Q> where Q: ?Sized {}
+
+impl<Q> fmt::Display for KeyNotFoundError<'_, Q>
+where
+    Q: fmt::Debug + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "key {:?} not found", self.key)
+    }
+}
+
+impl<Q> Error for KeyNotFoundError<'_, Q> where Q: fmt::Debug + ?Sized {}
+
+/// The first broken invariant [`check_invariants`](crate::OAHashMap::check_invariants)
+/// found. Slots of the table an incremental resize is still emptying are
+/// numbered after those of the current table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvariantError {
+    /// The table has a slot count other than zero or a power of two, or a
+    /// mask that does not match it.
+    SlotCount { slots: usize, mask: usize },
+    /// The occupancy bitmap disagrees with the control byte.
+    Occupancy { slot: usize },
+    /// The control byte does not hold the top bits of the cached hash.
+    Control { slot: usize },
+    /// The cached hash is not the one the hasher gives the key now.
+    Hash { slot: usize },
+    /// A lookup for the key probes past its slot without finding it.
+    Unreachable { slot: usize },
+    /// A lookup for the key finds an equal key in another slot first.
+    Duplicate { slot: usize, other: usize },
+    /// The stored length does not match the full slots.
+    Len { stored: usize, counted: usize },
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            InvariantError::SlotCount { slots, mask } => {
+                write!(f, "table of {slots} slots has mask {mask:#x}")
+            }
+            InvariantError::Occupancy { slot } => {
+                write!(
+                    f,
+                    "occupancy bit of slot {slot} disagrees with its control byte"
+                )
+            }
+            InvariantError::Control { slot } => {
+                write!(f, "control byte of slot {slot} does not match its hash")
+            }
+            InvariantError::Hash { slot } => {
+                write!(f, "cached hash of slot {slot} does not match its key")
+            }
+            InvariantError::Unreachable { slot } => {
+                write!(f, "key in slot {slot} is not found by probing")
+            }
+            InvariantError::Duplicate { slot, other } => {
+                write!(f, "key in slot {slot} is also in slot {other}")
+            }
+            InvariantError::Len { stored, counted } => {
+                write!(f, "length is {stored} but {counted} slots are full")
+            }
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+/// Why [`from_bytes`](crate::OAHashMap::from_bytes) rejected a dump.
+#[cfg(feature = "bytemuck")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    /// The input does not start with the dump's magic bytes.
+    Magic,
+    /// The dump is in another version of the format.
+    Version { found: u16 },
+    /// The dump holds keys or values of other sizes, or was written on a
+    /// machine of the other byte order.
+    Layout,
+    /// The input is not as long as its header says.
+    Length { found: usize },
+    /// A slot count, the live count or a control byte is impossible.
+    Corrupt,
+    /// Two slots hold equal keys.
+    DuplicateKey,
+    /// The table could not be allocated.
+    Reserve(TryReserveError),
+}
+
+#[cfg(feature = "bytemuck")]
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Magic => write!(f, "input is not a map dump"),
+            DecodeError::Version { found } => write!(f, "unsupported dump version {found}"),
+            DecodeError::Layout => {
+                write!(f, "dump was written for another key, value or byte layout")
+            }
+            DecodeError::Length { found } => {
+                write!(f, "dump of {found} bytes does not match its header")
+            }
+            DecodeError::Corrupt => write!(f, "dump header or control bytes are corrupt"),
+            DecodeError::DuplicateKey => write!(f, "dump holds a key twice"),
+            DecodeError::Reserve(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Reserve(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Maps of string keys laid out while compiling, for keyword tables and
+//! other lookups fixed before the program runs.
+//!
+//! [`static_oahashmap!`](crate::static_oahashmap) places every key in a
+//! const context, hashing with [`DeterministicState`](crate::DeterministicState)'s
+//! function under a fixed seed, and emits the table as a `static`. Lookups
+//! only read it, so they never allocate or lock and the table can be shared
+//! between threads as it is. A key given twice fails the build.
+
+use core::fmt;
+use core::iter::FusedIterator;
+
+use crate::hash::hash_str;
+
+const SEED: u64 = 0x6f61_686d;
+
+/// A lookup-only map from `&'static str` keys, built by
+/// [`static_oahashmap!`](crate::static_oahashmap) or by calling
+/// [`new`](Self::new) in a const context. Its `N` slots are probed
+/// linearly and kept at most half full.
+pub struct StaticOAHashMap<V, const N: usize> {
+    slots: [Option<Slot<V>>; N],
+    len: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Slot<V> {
+    hash: u64,
+    key: &'static str,
+    value: V,
+}
+
+/// The slots a [`StaticOAHashMap`] of `len` entries is built with: the
+/// power of two that keeps it at most half full.
+pub const fn slots_for(len: usize) -> usize {
+    (2 * len).next_power_of_two()
+}
+
+impl<V, const N: usize> StaticOAHashMap<V, N>
+where
+    V: Copy,
+{
+    /// Places `entries`, panicking if a key repeats, if `N` is not a power
+    /// of two, or if the entries would fill more than half the slots. Const
+    /// evaluation turns each of those into a compile error.
+    pub const fn new<const M: usize>(entries: [(&'static str, V); M]) -> Self {
+        assert!(N.is_power_of_two(), "the slot count must be a power of two");
+        assert!(2 * M <= N, "a static map must be at most half full");
+
+        let mut slots = [None::<Slot<V>>; N];
+        let mut i = 0;
+        while i < M {
+            let (key, value) = entries[i];
+            let hash = hash_str(SEED, key);
+            let mut index = hash as usize & (N - 1);
+            while let Some(slot) = &slots[index] {
+                if same(slot.key, key) {
+                    panic!("duplicate key in a static map");
+                }
+                index = (index + 1) & (N - 1);
+            }
+            slots[index] = Some(Slot { hash, key, value });
+            i += 1;
+        }
+
+        Self { slots, len: M }
+    }
+}
+
+impl<V, const N: usize> StaticOAHashMap<V, N> {
+    pub const fn get(&self, key: &str) -> Option<&V> {
+        match self.get_key_value(key) {
+            Some((_, value)) => Some(value),
+            None => None,
+        }
+    }
+
+    pub const fn get_key_value(&self, key: &str) -> Option<(&'static str, &V)> {
+        let hash = hash_str(SEED, key);
+        let mut index = hash as usize & (N - 1);
+        while let Some(slot) = &self.slots[index] {
+            if slot.hash == hash && same(slot.key, key) {
+                return Some((slot.key, &slot.value));
+            }
+            index = (index + 1) & (N - 1);
+        }
+
+        None
+    }
+
+    pub const fn contains_key(&self, key: &str) -> bool {
+        self.get_key_value(key).is_some()
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Visits the entries in slot order, which is fixed at compile time.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            slots: self.slots.iter(),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<V, const N: usize> fmt::Debug for StaticOAHashMap<V, N>
+where
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, V, const N: usize> IntoIterator for &'a StaticOAHashMap<V, N> {
+    type Item = (&'static str, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Iter<'a, V> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, V> {
+    slots: core::slice::Iter<'a, Option<Slot<V>>>,
+    remaining: usize,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'static str, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.slots.find_map(Option::as_ref)?;
+        self.remaining -= 1;
+
+        Some((slot.key, &slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<V> ExactSizeIterator for Iter<'_, V> {}
+
+impl<V> FusedIterator for Iter<'_, V> {}
+
+/// `str` equality, which is not yet callable in a const context.
+const fn same(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use core::hash::BuildHasher;
+    use std::thread;
+
+    use super::{hash_str, StaticOAHashMap, SEED};
+    use crate::DeterministicState;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Token {
+        If,
+        Else,
+        While,
+        For,
+        Return,
+        Fn,
+        Let,
+        Match,
+    }
+
+    crate::static_oahashmap! {
+        static KEYWORDS: Token = {
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "for" => Token::For,
+            "return" => Token::Return,
+            "fn" => Token::Fn,
+            "let" => Token::Let,
+            "match" => Token::Match,
+        };
+    }
+
+    const WORDS: [(&str, Token); 8] = [
+        ("if", Token::If),
+        ("else", Token::Else),
+        ("while", Token::While),
+        ("for", Token::For),
+        ("return", Token::Return),
+        ("fn", Token::Fn),
+        ("let", Token::Let),
+        ("match", Token::Match),
+    ];
+
+    #[test]
+    fn every_thread_resolves_every_keyword_and_misses_the_rest() {
+        assert_eq!(8, KEYWORDS.len());
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..1_000 {
+                        for (word, token) in WORDS {
+                            assert_eq!(Some(&token), KEYWORDS.get(word));
+                            assert_eq!(Some((word, &token)), KEYWORDS.get_key_value(word));
+                        }
+                        for word in ["", "i", "iff", "Else", "matches", "return "] {
+                            assert!(!KEYWORDS.contains_key(word), "{word}");
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut seen: Vec<_> = KEYWORDS.iter().map(|(word, _)| word).collect();
+        seen.sort_unstable();
+        let mut words = WORDS.map(|(word, _)| word);
+        words.sort_unstable();
+        assert_eq!(words[..], seen[..]);
+    }
+
+    #[test]
+    fn tables_can_be_read_at_compile_time() {
+        const SMALL: StaticOAHashMap<u8, 4> = StaticOAHashMap::new([("a", 1), ("b", 2)]);
+        const B: Option<&u8> = SMALL.get("b");
+        assert_eq!(Some(&2), B);
+        assert!(!SMALL.contains_key("c"));
+
+        let empty: StaticOAHashMap<u8, 1> = StaticOAHashMap::new([]);
+        assert!(empty.is_empty());
+        assert_eq!(None, empty.get(""));
+
+        // Long keys hash a word at a time, as the runtime hasher does.
+        for key in ["", "a", "exactly8", "more than eight bytes", "ünïcödé"] {
+            assert_eq!(DeterministicState(SEED).hash_one(key), hash_str(SEED, key));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate key")]
+    fn duplicate_keys_are_rejected() {
+        StaticOAHashMap::<u8, 8>::new([("a", 1), ("b", 2), ("a", 3)]);
+    }
+}
@@ -0,0 +1,264 @@
+//! Matching several control bytes at once.
+//!
+//! A probe loads the group of control bytes starting at its current slot and
+//! gets back bit masks of the slots whose byte matches the hash fragment or
+//! is empty. [`Scalar`] is a group of one byte and is always
+//! available; with the `simd` feature probes use [`Sse2`] where the target
+//! has SSE2 and the portable [`Swar`] elsewhere. Every group visits the same
+//! slots in the same order, so the choice never changes what a probe finds.
+
+// Only one group type probes in a given build; the others stay compiled so
+// the tests can compare them.
+#![cfg_attr(not(test), allow(dead_code))]
+
+use crate::raw::EMPTY;
+
+#[cfg(all(
+    feature = "simd",
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+pub(crate) type Active = Sse2;
+#[cfg(all(
+    feature = "simd",
+    not(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "sse2"
+    ))
+))]
+pub(crate) type Active = Swar;
+#[cfg(not(feature = "simd"))]
+pub(crate) type Active = Scalar;
+
+pub(crate) trait Group: Copy {
+    const WIDTH: usize;
+
+    /// Loads the `WIDTH` control bytes starting at `pos`, wrapping around the
+    /// end of the table.
+    fn load(ctrl: &[u8], pos: usize) -> Self {
+        if pos + Self::WIDTH <= ctrl.len() {
+            Self::load_exact(&ctrl[pos..pos + Self::WIDTH])
+        } else {
+            let mut bytes = [EMPTY; 16];
+            for (offset, byte) in bytes[..Self::WIDTH].iter_mut().enumerate() {
+                *byte = ctrl[(pos + offset) % ctrl.len()];
+            }
+
+            Self::load_exact(&bytes[..Self::WIDTH])
+        }
+    }
+
+    /// Loads exactly `WIDTH` bytes.
+    fn load_exact(bytes: &[u8]) -> Self;
+
+    /// Slots whose control byte is `byte`. May include false positives, which
+    /// probes reject when they compare the full hash.
+    fn match_byte(self, byte: u8) -> BitMask;
+
+    fn match_empty(self) -> BitMask;
+}
+
+/// One bit per slot of a group, lowest bit first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct BitMask(u16);
+
+impl BitMask {
+    /// The first `count` slots.
+    pub(crate) fn below(count: usize) -> Self {
+        Self(((1u32 << count) - 1) as u16)
+    }
+
+    pub(crate) fn and(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub(crate) fn lowest(self) -> Option<usize> {
+        (self.0 != 0).then(|| self.0.trailing_zeros() as usize)
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let lowest = self.lowest()?;
+        self.0 &= self.0 - 1;
+
+        Some(lowest)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Scalar(u8);
+
+impl Group for Scalar {
+    const WIDTH: usize = 1;
+
+    fn load_exact(bytes: &[u8]) -> Self {
+        Self(bytes[0])
+    }
+
+    fn match_byte(self, byte: u8) -> BitMask {
+        BitMask((self.0 == byte) as u16)
+    }
+
+    fn match_empty(self) -> BitMask {
+        BitMask((self.0 >> 7) as u16)
+    }
+}
+
+/// Eight control bytes in a `u64`, matched with integer arithmetic.
+#[derive(Clone, Copy)]
+pub(crate) struct Swar(u64);
+
+impl Swar {
+    const fn repeat(byte: u8) -> u64 {
+        u64::from_ne_bytes([byte; 8])
+    }
+
+    /// Gathers the high bit of every byte into one bit per slot.
+    fn compact(high_bits: u64) -> BitMask {
+        // The multiply moves bit 0 of byte k to bit 56 + k without carries.
+        BitMask(((high_bits >> 7).wrapping_mul(0x0102_0408_1020_4080) >> 56) as u16)
+    }
+}
+
+impl Group for Swar {
+    const WIDTH: usize = 8;
+
+    fn load_exact(bytes: &[u8]) -> Self {
+        Self(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn match_byte(self, byte: u8) -> BitMask {
+        // Bytes equal to `byte` become zero, and subtracting one from a zero
+        // byte sets its high bit. A borrow can also flag a byte just above a
+        // true match.
+        let cmp = self.0 ^ Self::repeat(byte);
+
+        Self::compact(cmp.wrapping_sub(Self::repeat(0x01)) & !cmp & Self::repeat(0x80))
+    }
+
+    fn match_empty(self) -> BitMask {
+        // Only EMPTY has its high bit set.
+        Self::compact(self.0 & Self::repeat(0x80))
+    }
+}
+
+/// Sixteen control bytes in an SSE2 register. SSE2 is part of the x86_64
+/// baseline, so no runtime detection is needed.
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+#[derive(Clone, Copy)]
+pub(crate) struct Sse2(sse2::__m128i);
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+mod sse2 {
+    #[cfg(target_arch = "x86")]
+    pub(super) use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    pub(super) use core::arch::x86_64::*;
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    target_feature = "sse2"
+))]
+impl Group for Sse2 {
+    const WIDTH: usize = 16;
+
+    fn load_exact(bytes: &[u8]) -> Self {
+        assert_eq!(Self::WIDTH, bytes.len());
+
+        // SAFETY: SSE2 is enabled for this target, and the unaligned load
+        // reads exactly the sixteen bytes just checked.
+        Self(unsafe { sse2::_mm_loadu_si128(bytes.as_ptr().cast()) })
+    }
+
+    fn match_byte(self, byte: u8) -> BitMask {
+        // SAFETY: SSE2 is enabled for this target.
+        unsafe {
+            let cmp = sse2::_mm_cmpeq_epi8(self.0, sse2::_mm_set1_epi8(byte as i8));
+
+            BitMask(sse2::_mm_movemask_epi8(cmp) as u16)
+        }
+    }
+
+    fn match_empty(self) -> BitMask {
+        // SAFETY: SSE2 is enabled for this target.
+        BitMask(unsafe { sse2::_mm_movemask_epi8(self.0) } as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitMask, Group, Scalar, Swar};
+    use crate::raw::{h2, EMPTY};
+    use crate::test_util::Rng;
+
+    fn random_ctrl(rng: &mut Rng, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| match rng.below(3) {
+                0 => EMPTY,
+                _ => h2(rng.next()) & 0x07,
+            })
+            .collect()
+    }
+
+    /// What a group must report, read one byte at a time.
+    fn expected<G: Group>(ctrl: &[u8], pos: usize, f: impl Fn(u8) -> bool) -> Vec<usize> {
+        (0..G::WIDTH)
+            .filter(|&offset| f(ctrl[(pos + offset) % ctrl.len()]))
+            .collect()
+    }
+
+    fn check<G: Group>(rng: &mut Rng) {
+        for len in [1, 2, 8, 16, 64] {
+            let ctrl = random_ctrl(rng, len);
+            for pos in 0..len {
+                let group = G::load(&ctrl, pos);
+                let byte = h2(rng.next()) & 0x07;
+
+                let matches: Vec<usize> = group.match_byte(byte).collect();
+                for offset in expected::<G>(&ctrl, pos, |c| c == byte) {
+                    assert!(matches.contains(&offset));
+                }
+                assert!(matches
+                    .iter()
+                    .all(|&offset| ctrl[(pos + offset) % len] & 0x80 == 0));
+
+                assert_eq!(
+                    expected::<G>(&ctrl, pos, |c| c == EMPTY),
+                    group.match_empty().collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn groups_agree_with_bytes() {
+        let mut rng = Rng::new(0x5eed);
+        for _ in 0..20 {
+            check::<Scalar>(&mut rng);
+            check::<Swar>(&mut rng);
+            #[cfg(all(
+                any(target_arch = "x86", target_arch = "x86_64"),
+                target_feature = "sse2"
+            ))]
+            check::<super::Sse2>(&mut rng);
+        }
+    }
+
+    #[test]
+    fn bit_mask_iterates_in_slot_order() {
+        assert_eq!(vec![0, 3, 15], BitMask(0x8009).collect::<Vec<_>>());
+        assert_eq!(Some(3), BitMask(0x8009).and(BitMask::below(8)).nth(1));
+        assert_eq!(None, BitMask::below(0).lowest());
+        assert_eq!(BitMask(0xffff), BitMask::below(16));
+    }
+}
@@ -0,0 +1,509 @@
+//! A map that hands out a [`Handle`] for each entry, to reach it again
+//! without hashing the key.
+//!
+//! The entries sit in an arena of slots that never move, found through a
+//! table of their slot numbers. A handle is a slot number and the
+//! generation the slot was at when the entry went in, so following one is
+//! a bounds check and a comparison, whatever resizes the lookup table went
+//! through since.
+//!
+//! A handle stays valid until its entry is removed, by key or by handle,
+//! or the map is cleared. Inserting other keys, growing, and replacing the
+//! entry's value all leave it valid. Removing an entry moves its slot to
+//! the next generation before an insert reuses it, so a handle to a removed
+//! entry finds nothing rather than the slot's new occupant; a slot whose
+//! generation would wrap is retired instead of reused. Handles are only
+//! meaningful to the map that issued them.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::{Enumerate, FusedIterator};
+use core::slice;
+
+use crate::position_index::PositionIndex;
+use crate::{Bucket, DefaultHashBuilder, Equivalent};
+
+/// Names one entry of an [`OAHandleMap`] for as long as it is in the map.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<K, V> {
+    generation: u32,
+    entry: Option<Bucket<K, V>>,
+}
+
+pub struct OAHandleMap<K, V, S = DefaultHashBuilder> {
+    slots: Vec<Slot<K, V>>,
+    /// Empty slots an insert can reuse.
+    free: Vec<u32>,
+    indices: PositionIndex<S>,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> OAHandleMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> OAHandleMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            indices: PositionIndex::with_hasher(hash_builder),
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            indices: PositionIndex::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.indices.hasher()
+    }
+
+    /// An insert of a key already present replaces its value and keeps its
+    /// handle.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like [`insert`](Self::insert), also returning the entry's handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would need more than `u32::MAX` slots.
+    pub fn insert_full(&mut self, key: K, value: V) -> (Handle, Option<V>) {
+        let hash = self.indices.hash_key(&key);
+        let slots = &self.slots;
+        match self
+            .indices
+            .find_or_vacant(hash, |index| occupant(slots, index).key == key)
+        {
+            Ok(index) => {
+                let slot = &mut self.slots[index];
+                let bucket = slot.entry.as_mut().unwrap();
+                let old = core::mem::replace(&mut bucket.value, value);
+                let handle = Handle {
+                    index: index as u32,
+                    generation: slot.generation,
+                };
+
+                (handle, Some(old))
+            }
+            Err(vacant) => {
+                let index = match self.free.pop() {
+                    Some(index) => index,
+                    None => {
+                        let index = u32::try_from(self.slots.len())
+                            .expect("a handle map holds at most u32::MAX slots");
+                        self.slots.push(Slot {
+                            generation: 0,
+                            entry: None,
+                        });
+                        index
+                    }
+                };
+                vacant.insert(index as usize);
+                let slot = &mut self.slots[index as usize];
+                slot.entry = Some(Bucket::new(hash, key, value));
+                let handle = Handle {
+                    index,
+                    generation: slot.generation,
+                };
+
+                (handle, None)
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(key)?;
+
+        Some(&occupant(&self.slots, index).value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(key)?;
+
+        Some(&mut self.slots[index].entry.as_mut().unwrap().value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// The handle of the key's entry, the one its insert returned.
+    pub fn handle_of<Q>(&self, key: &Q) -> Option<Handle>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find(key)?;
+
+        Some(Handle {
+            index: index as u32,
+            generation: self.slots[index].generation,
+        })
+    }
+
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.indices.hash_key(key);
+
+        self.indices.find(hash, |index| {
+            key.equivalent(&occupant(&self.slots, index).key)
+        })
+    }
+
+    /// The entry's value, or `None` if the handle's entry has been removed.
+    /// Never hashes.
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&V> {
+        self.get_key_value_by_handle(handle).map(|(_, value)| value)
+    }
+
+    pub fn get_mut_by_handle(&mut self, handle: Handle) -> Option<&mut V> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.entry.as_mut().map(|bucket| &mut bucket.value)
+    }
+
+    pub fn get_key_value_by_handle(&self, handle: Handle) -> Option<(&K, &V)> {
+        let slot = self.slots.get(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.entry
+            .as_ref()
+            .map(|bucket| (&bucket.key, &bucket.value))
+    }
+
+    pub fn contains_handle(&self, handle: Handle) -> bool {
+        self.get_key_value_by_handle(handle).is_some()
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.indices.hash_key(key);
+        let slots = &self.slots;
+        let index = self
+            .indices
+            .take(hash, |index| key.equivalent(&occupant(slots, index).key))?;
+
+        Some(self.vacate(index).value)
+    }
+
+    /// Removes the handle's entry, or returns `None` if it is already gone.
+    /// Uses the entry's cached hash rather than hashing the key.
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<(K, V)> {
+        self.get_key_value_by_handle(handle)?;
+        let index = handle.index as usize;
+        let hash = occupant(&self.slots, index).hash;
+        self.indices.remove(hash, index);
+
+        Some(self.vacate(index).into_pair())
+    }
+
+    /// Empties an indexed slot and moves it to its next generation, so no
+    /// handle to the entry it held can reach the next one.
+    fn vacate(&mut self, index: usize) -> Bucket<K, V> {
+        let slot = &mut self.slots[index];
+        let bucket = slot.entry.take().unwrap();
+        if let Some(generation) = slot.generation.checked_add(1) {
+            slot.generation = generation;
+            self.free.push(index as u32);
+        }
+
+        bucket
+    }
+
+    /// Visits the entries with their handles, in slot order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            slots: self.slots.iter().enumerate(),
+            remaining: self.len(),
+        }
+    }
+
+    /// Removes every entry, leaving every handle issued so far invalid.
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        for index in 0..self.slots.len() {
+            if self.slots[index].entry.is_some() {
+                self.vacate(index);
+            }
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots
+            .reserve(additional.saturating_sub(self.free.len()));
+        self.indices.reserve(additional);
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+/// The entry in a slot the lookup table points at, which is never empty.
+fn occupant<K, V>(slots: &[Slot<K, V>], index: usize) -> &Bucket<K, V> {
+    slots[index]
+        .entry
+        .as_ref()
+        .expect("indexed slots are occupied")
+}
+
+impl<K, V, S> Default for OAHandleMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for OAHandleMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut map = Self::with_capacity_and_hasher(iter.size_hint().0, S::default());
+        map.extend(iter);
+
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for OAHandleMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for OAHandleMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|(_, key, value)| (key, value)))
+            .finish()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a OAHandleMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (Handle, &'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    slots: Enumerate<slice::Iter<'a, Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (Handle, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (handle, bucket) = self.slots.find_map(|(index, slot)| {
+            let handle = Handle {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            Some((handle, slot.entry.as_ref()?))
+        })?;
+        self.remaining -= 1;
+
+        Some((handle, &bucket.key, &bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OAHandleMap;
+
+    #[test]
+    fn maps_of_keys_without_hash_can_be_named() {
+        struct NoHash;
+
+        struct Wrapper<K, V>(OAHandleMap<K, V>);
+
+        fn unwrap<K, V>(wrapper: Wrapper<K, V>) -> OAHandleMap<K, V> {
+            wrapper.0
+        }
+
+        let unhashable: Option<Wrapper<NoHash, ()>> = None;
+        assert!(unhashable.is_none());
+        let words = unwrap(Wrapper(OAHandleMap::<&str, i32>::new()));
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn handles_reach_their_entries_across_many_resizes() {
+        let mut oa = OAHandleMap::new();
+        let handles: Vec<_> = (0..10_000u64)
+            .map(|i| {
+                let (handle, old) = oa.insert_full(i, i * 3);
+                assert_eq!(None, old);
+                handle
+            })
+            .collect();
+        assert!(oa.indices.stats().resizes >= 8);
+
+        for (i, &handle) in (0..10_000u64).zip(&handles) {
+            assert_eq!(Some((&i, &(i * 3))), oa.get_key_value_by_handle(handle));
+            assert_eq!(Some(handle), oa.handle_of(&i));
+        }
+        *oa.get_mut_by_handle(handles[42]).unwrap() += 1;
+        assert_eq!(Some(&127), oa.get(&42));
+
+        // Replacing a value keeps the handle.
+        assert_eq!((handles[7], Some(21)), oa.insert_full(7, 0));
+        assert_eq!(Some(&0), oa.get_by_handle(handles[7]));
+        assert_eq!(10_000, oa.iter().len());
+        assert!(oa
+            .iter()
+            .all(|(handle, key, _)| handles[*key as usize] == handle));
+    }
+
+    #[test]
+    fn handles_to_removed_entries_find_nothing() {
+        let mut oa = OAHandleMap::new();
+        let (a, _) = oa.insert_full("a", 1);
+        let (b, _) = oa.insert_full("b", 2);
+
+        assert_eq!(Some(1), oa.remove("a"));
+        assert_eq!(None, oa.get_by_handle(a));
+        assert_eq!(None, oa.get_mut_by_handle(a));
+        assert_eq!(Some(("b", 2)), oa.remove_by_handle(b));
+        assert_eq!(None, oa.remove_by_handle(b));
+        assert!(!oa.contains_key("b"));
+        assert!(oa.is_empty());
+
+        // New entries take over the freed slots under new handles.
+        let (c, _) = oa.insert_full("c", 3);
+        let (d, _) = oa.insert_full("a", 4);
+        assert_eq!(2, oa.slots.len());
+        assert!(![a, b].contains(&c) && ![a, b].contains(&d));
+        assert_eq!(None, oa.get_by_handle(a));
+        assert_eq!(None, oa.get_by_handle(b));
+        assert_eq!(Some(&3), oa.get_by_handle(c));
+        assert_eq!(Some(&4), oa.get_by_handle(d));
+
+        oa.clear();
+        assert_eq!(None, oa.get_by_handle(c));
+        assert_eq!(None, oa.get_by_handle(d));
+        let (e, _) = oa.insert_full("c", 5);
+        assert_ne!(c, e);
+        assert_eq!(Some(&5), oa.get("c"));
+    }
+
+    #[test]
+    fn generations_keep_reused_slots_apart() {
+        let mut oa = OAHandleMap::new();
+        let mut stale = Vec::new();
+        for i in 0..1_000 {
+            let (handle, _) = oa.insert_full(i, i);
+            assert_eq!(1, oa.slots.len(), "the one slot is reused");
+            assert!(stale.iter().all(|&old| oa.get_by_handle(old).is_none()));
+            assert_eq!(Some((i, i)), oa.remove_by_handle(handle));
+            stale.push(handle);
+        }
+
+        // A slot on its last generation is retired rather than wrapped.
+        let (handle, _) = oa.insert_full(-1, -1);
+        oa.slots[0].generation = u32::MAX;
+        let last = oa.handle_of(&-1).unwrap();
+        assert_ne!(handle, last);
+        assert_eq!(Some((-1, -1)), oa.remove_by_handle(last));
+        let (next, _) = oa.insert_full(-2, -2);
+        assert_eq!(2, oa.slots.len());
+        assert_eq!(None, oa.get_by_handle(last));
+        assert_eq!(Some(&-2), oa.get_by_handle(next));
+    }
+}
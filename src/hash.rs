@@ -0,0 +1,460 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hasher};
+
+#[cfg(all(not(feature = "std"), any(feature = "ahash", feature = "fxhash")))]
+use alloc::boxed::Box;
+
+#[cfg(feature = "ahash")]
+type DefaultState = ahash::RandomState;
+#[cfg(all(feature = "fxhash", not(feature = "ahash")))]
+type DefaultState = rustc_hash::FxBuildHasher;
+#[cfg(all(feature = "std", not(any(feature = "ahash", feature = "fxhash"))))]
+type DefaultState = std::collections::hash_map::RandomState;
+
+#[cfg(feature = "std")]
+type LazyState = std::sync::OnceLock<DefaultState>;
+#[cfg(all(not(feature = "std"), any(feature = "ahash", feature = "fxhash")))]
+type LazyState = once_cell::race::OnceBox<DefaultState>;
+
+/// The hash builder used when a map is created without one.
+///
+/// Each builder draws its own SipHash keys the first time it hashes, so the
+/// probe layout of one map tells nothing about another. Construction stays
+/// `const` because the keys are only drawn lazily. Clones share the keys.
+///
+/// The `ahash` feature swaps SipHash for aHash, still randomly keyed. Without
+/// it, the `fxhash` feature switches to FxHash, which is fast but unkeyed.
+/// Without `std`, one of the two is required; aHash then has no entropy source
+/// and falls back to fixed keys.
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+#[derive(Default)]
+pub struct DefaultHashBuilder {
+    state: LazyState,
+}
+
+/// Without `std`, `ahash` or `fxhash` there is no default hasher, and maps
+/// must be given a [`BuildHasher`] explicitly.
+#[cfg(not(any(feature = "std", feature = "ahash", feature = "fxhash")))]
+#[derive(Clone)]
+pub enum DefaultHashBuilder {}
+
+#[cfg(not(any(feature = "std", feature = "ahash", feature = "fxhash")))]
+impl BuildHasher for DefaultHashBuilder {
+    type Hasher = Self;
+
+    fn build_hasher(&self) -> Self {
+        match *self {}
+    }
+}
+
+#[cfg(not(any(feature = "std", feature = "ahash", feature = "fxhash")))]
+impl Hasher for DefaultHashBuilder {
+    fn finish(&self) -> u64 {
+        match *self {}
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        match *self {}
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl DefaultHashBuilder {
+    pub const fn new() -> Self {
+        Self {
+            state: LazyState::new(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn state(&self) -> &DefaultState {
+        self.state.get_or_init(DefaultState::default)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn state(&self) -> &DefaultState {
+        #[cfg(feature = "ahash")]
+        let init = DefaultState::new;
+        #[cfg(not(feature = "ahash"))]
+        let init = DefaultState::default;
+
+        self.state.get_or_init(|| Box::new(init()))
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl BuildHasher for DefaultHashBuilder {
+    type Hasher = <DefaultState as BuildHasher>::Hasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        self.state().build_hasher()
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl Clone for DefaultHashBuilder {
+    // The FxHash state is `Copy`, the others are not.
+    #[allow(clippy::clone_on_copy)]
+    fn clone(&self) -> Self {
+        let state = LazyState::new();
+        #[cfg(feature = "std")]
+        let _ = state.set(self.state().clone());
+        #[cfg(not(feature = "std"))]
+        let _ = state.set(Box::new(self.state().clone()));
+
+        Self { state }
+    }
+}
+
+impl fmt::Debug for DefaultHashBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DefaultHashBuilder").finish_non_exhaustive()
+    }
+}
+
+/// A hash builder whose hashes depend only on its seed, for when a map has
+/// to behave the same on every run: maps built with the same seed that go
+/// through the same operations grow at the same points and iterate in the
+/// same order.
+///
+/// Opting into this gives up the protection against HashDoS that
+/// [`DefaultHashBuilder`] draws its random keys for. Anyone who knows or
+/// guesses the seed can pick keys that all collide, so it is for tests,
+/// replays and other trusted input only.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct DeterministicState(pub u64);
+
+impl DeterministicState {
+    pub const fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl BuildHasher for DeterministicState {
+    type Hasher = DeterministicHasher;
+
+    fn build_hasher(&self) -> DeterministicHasher {
+        DeterministicHasher { hash: self.0 }
+    }
+}
+
+/// The hasher a [`DeterministicState`] builds: FxHash's word step, seeded,
+/// with a finalizer so the bits probing uses depend on every input bit.
+#[derive(Clone, Debug)]
+pub struct DeterministicHasher {
+    hash: u64,
+}
+
+impl DeterministicHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = step(self.hash, word);
+    }
+}
+
+const fn step(hash: u64, word: u64) -> u64 {
+    (hash.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95)
+}
+
+/// MurmurHash3's 64-bit finalizer.
+const fn fmix(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+
+    hash ^ hash >> 33
+}
+
+/// What `DeterministicState(seed).hash_one(key)` gives, computed in a const
+/// context.
+pub(crate) const fn hash_str(seed: u64, key: &str) -> u64 {
+    let bytes = key.as_bytes();
+    let mut hash = seed;
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut word = 0;
+        let mut i = 0;
+        while i < 8 && start + i < bytes.len() {
+            word |= (bytes[start + i] as u64) << (8 * i);
+            i += 1;
+        }
+        hash = step(hash, word);
+        start += 8;
+    }
+
+    // `str`'s `Hash` ends with a 0xff byte, so no key hashes as a prefix of
+    // another.
+    fmix(step(hash, 0xff))
+}
+
+impl Hasher for DeterministicHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.add(n.into());
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.add(n.into());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.add(n.into());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        fmix(self.hash)
+    }
+}
+
+/// A hash builder for integer keys that are their own hashes, so nothing is
+/// hashed at all. The map mixes every hash before taking a slot from it, so
+/// sequential or strided keys still spread across the table.
+///
+/// Integer keys and tuples of them hash as their integers. Slices and
+/// arrays of integers, strings and anything else that hashes bytes have
+/// their bytes folded in eight at a time, so an array of `u64` hashes like
+/// the same integers written one by one.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct IdentityState;
+
+impl BuildHasher for IdentityState {
+    type Hasher = IdentityHasher;
+
+    fn build_hasher(&self) -> IdentityHasher {
+        IdentityHasher { hash: 0 }
+    }
+}
+
+/// The hasher an [`IdentityState`] builds. A key that writes one integer
+/// hashes to that integer.
+#[derive(Clone, Debug)]
+pub struct IdentityHasher {
+    hash: u64,
+}
+
+impl IdentityHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = self.hash.rotate_left(32) ^ word;
+    }
+}
+
+impl Hasher for IdentityHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            self.add(u64::from_ne_bytes(word.try_into().unwrap()));
+        }
+        let rest = words.remainder();
+        if !rest.is_empty() {
+            let mut word = [0; 8];
+            word[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_ne_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.add(n.into());
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.add(n.into());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.add(n.into());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.add(n);
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.add((n >> 64) as u64);
+        self.add(n as u64);
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+    use std::collections::HashMap;
+    use std::hash::{BuildHasher, Hasher};
+
+    use super::DeterministicState;
+    use crate::test_util::Rng;
+    use crate::{DefaultHashBuilder, IdentityState, IntOAHashMap, OAHashMap};
+
+    fn exercise<S>()
+    where
+        S: BuildHasher + Default,
+    {
+        let mut oa: OAHashMap<u64, u64, S> = OAHashMap::default();
+        let mut std = HashMap::new();
+        let mut rng = Rng::new(36);
+        for _ in 0..20_000 {
+            let key = rng.below(2_000);
+            match rng.below(4) {
+                0 => assert_eq!(std.remove(&key), oa.remove(&key)),
+                1 => assert_eq!(
+                    *std.entry(key).and_modify(|v| *v += 1).or_insert(0),
+                    *oa.entry(key).and_modify(|v| *v += 1).or_insert(0)
+                ),
+                _ => assert_eq!(std.insert(key, key), oa.insert(key, key)),
+            }
+        }
+
+        assert_eq!(std.len(), oa.len());
+        for (key, value) in &std {
+            assert_eq!(Some(value), oa.search(key));
+        }
+
+        let mut strings: OAHashMap<String, usize, S> = OAHashMap::default();
+        for i in 0..1_000 {
+            strings.insert(format!("key{i}"), i);
+        }
+        strings.retain(|_, v| *v % 3 == 0);
+        assert_eq!(334, strings.len());
+        assert_eq!(Some(&999), strings.search(&"key999".to_string()));
+        strings.shrink_to_fit();
+        assert_eq!(334, strings.iter().count());
+    }
+
+    #[test]
+    fn identity_hashes_are_the_keys_and_sequential_keys_do_not_cluster() {
+        let mut oa: IntOAHashMap<u64, u64> = IntOAHashMap::default();
+        for i in 0..1_000_000 {
+            oa.insert(i, i);
+        }
+        assert!((0..1_000_000)
+            .step_by(997)
+            .all(|i| IdentityState.hash_one(i) == i && oa[&i] == i));
+        assert!(oa.stats().max_probe_distance <= 8, "{}", oa.stats());
+        oa.check_invariants().unwrap();
+
+        let mut strided: IntOAHashMap<u32, ()> = IntOAHashMap::default();
+        strided.extend((0..100_000).map(|i| (i << 12, ())));
+        assert!(
+            strided.stats().max_probe_distance <= 32,
+            "{}",
+            strided.stats()
+        );
+
+        let pairs: IntOAHashMap<(u32, i8), u32> = (0..1_000).map(|i| ((i, -1), i)).collect();
+        assert_eq!(Some(&7), pairs.get(&(7, -1)));
+    }
+
+    #[test]
+    fn identity_hashing_folds_bytes_into_words() {
+        let mut hasher = IdentityState.build_hasher();
+        hasher.write_usize(3);
+        for word in [1, u64::MAX, 3] {
+            hasher.write_u64(word);
+        }
+        assert_eq!(hasher.finish(), IdentityState.hash_one([1, u64::MAX, 3]));
+        assert_ne!(
+            IdentityState.hash_one([1u8, 2, 3]),
+            IdentityState.hash_one([1u8, 2, 4])
+        );
+
+        let arrays: IntOAHashMap<[u32; 3], u32> = (0..1_000).map(|i| ([i, !i, 7], i)).collect();
+        assert_eq!(Some(&42), arrays.get(&[42, !42, 7]));
+        arrays.check_invariants().unwrap();
+
+        let strings: IntOAHashMap<String, usize> = (0..1_000).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(Some(&123), strings.get("123"));
+        assert!(
+            strings.stats().max_probe_distance <= 32,
+            "{}",
+            strings.stats()
+        );
+    }
+
+    #[test]
+    fn default_hasher() {
+        exercise::<DefaultHashBuilder>();
+    }
+
+    #[test]
+    fn deterministic_hasher() {
+        exercise::<DeterministicState>();
+    }
+
+    #[test]
+    fn same_seeds_give_the_same_maps() {
+        let build = |seed| {
+            let mut oa = OAHashMap::with_seed(seed);
+            let mut rng = Rng::new(108);
+            for _ in 0..5_000 {
+                let key = rng.below(1_000);
+                match rng.below(3) {
+                    0 => drop(oa.remove(&key)),
+                    _ => drop(oa.insert(key, format!("{key}"))),
+                }
+            }
+
+            oa
+        };
+
+        let (first, again, other) = (build(7), build(7), build(8));
+        let order = |oa: &OAHashMap<u64, String, DeterministicState>| -> Vec<_> {
+            oa.iter()
+                .map(|(&key, value)| (key, value.clone()))
+                .collect()
+        };
+        assert_eq!(order(&first), order(&again));
+        assert_eq!(first.debug_layout(), again.debug_layout());
+        assert_eq!(first.stats().resizes, again.stats().resizes);
+        assert_eq!(first, other);
+        assert_ne!(order(&first), order(&other));
+
+        let words = ["a", "bb", "ccc"].map(|word| DeterministicState(1).hash_one(word));
+        assert_eq!(
+            words,
+            ["a", "bb", "ccc"].map(|word| DeterministicState(1).hash_one(word))
+        );
+        assert_ne!(
+            DeterministicState(1).hash_one(1u64),
+            DeterministicState(2).hash_one(1u64)
+        );
+    }
+
+    #[test]
+    fn sip_hasher() {
+        exercise::<RandomState>();
+    }
+
+    #[cfg(feature = "ahash")]
+    #[test]
+    fn ahash_hasher() {
+        exercise::<ahash::RandomState>();
+    }
+
+    #[cfg(feature = "fxhash")]
+    #[test]
+    fn fx_hasher() {
+        exercise::<rustc_hash::FxBuildHasher>();
+    }
+}
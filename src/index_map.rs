@@ -0,0 +1,708 @@
+//! A map that remembers the order its keys were first inserted in.
+//!
+//! The entries sit in a dense `Vec` in that order, found through a table of
+//! their positions there; iterating walks the `Vec` and never touches the
+//! table.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+use core::slice;
+
+use crate::position_index::PositionIndex;
+use crate::{Bucket, DefaultHashBuilder, Equivalent};
+
+pub struct OAIndexMap<K, V, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    entries: Vec<Bucket<K, V>>,
+    indices: PositionIndex<S>,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> OAIndexMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            entries: Vec::new(),
+            indices: PositionIndex::with_hasher(hash_builder),
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            indices: PositionIndex::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.indices.hasher()
+    }
+
+    /// An insert of a key already present replaces its value and keeps its
+    /// position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_full(key, value).1
+    }
+
+    /// Like [`insert`](Self::insert), also returning the entry's position.
+    pub fn insert_full(&mut self, key: K, value: V) -> (usize, Option<V>) {
+        let hash = self.indices.hash_key(&key);
+        let entries = &self.entries;
+        match self
+            .indices
+            .find_or_vacant(hash, |index| entries[index].key == key)
+        {
+            Ok(index) => {
+                let old = core::mem::replace(&mut self.entries[index].value, value);
+
+                (index, Some(old))
+            }
+            Err(vacant) => {
+                let index = self.entries.len();
+                vacant.insert(index);
+                self.entries.push(Bucket::new(hash, key, value));
+
+                (index, None)
+            }
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+
+        Some(&self.entries[index].value)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+
+        Some(&mut self.entries[index].value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_index(self.get_index_of(key)?)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_index_of(key).is_some()
+    }
+
+    /// The position of the key's entry in insertion order.
+    pub fn get_index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.indices.hash_key(key);
+
+        self.indices
+            .find(hash, |index| key.equivalent(&self.entries[index].key))
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        let bucket = self.entries.get(index)?;
+
+        Some((&bucket.key, &bucket.value))
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        let bucket = self.entries.get_mut(index)?;
+
+        Some((&bucket.key, &mut bucket.value))
+    }
+
+    pub fn first(&self) -> Option<(&K, &V)> {
+        self.get_index(0)
+    }
+
+    pub fn last(&self) -> Option<(&K, &V)> {
+        self.get_index(self.len().checked_sub(1)?)
+    }
+
+    /// Removes the entry by moving the last one into its place, which takes
+    /// constant time but changes where that last entry sits.
+    pub fn swap_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+
+        self.swap_remove_index(index).map(|(_, value)| value)
+    }
+
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let hash = self.entries.get(index)?.hash;
+        self.indices.remove(hash, index);
+
+        let last = self.entries.len() - 1;
+        if index != last {
+            self.indices.replace(self.entries[last].hash, last, index);
+        }
+
+        Some(self.entries.swap_remove(index).into_pair())
+    }
+
+    /// Removes the entry by shifting every later one back a place, which
+    /// keeps the order of the rest but takes time linear in their number.
+    pub fn shift_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.get_index_of(key)?;
+
+        self.shift_remove_index(index).map(|(_, value)| value)
+    }
+
+    pub fn shift_remove_index(&mut self, index: usize) -> Option<(K, V)> {
+        let hash = self.entries.get(index)?.hash;
+        self.indices.remove(hash, index);
+        for later in index + 1..self.entries.len() {
+            self.indices
+                .replace(self.entries[later].hash, later, later - 1);
+        }
+
+        Some(self.entries.remove(index).into_pair())
+    }
+
+    /// Removes the last entry, in constant time.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.swap_remove_index(self.len().checked_sub(1)?)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            entries: self.entries.iter_mut(),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys {
+            entries: self.entries.iter(),
+        }
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values {
+            entries: self.entries.iter(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional);
+        self.indices.reserve(additional);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K, V, S> Default for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut map = Self::with_capacity_and_hasher(iter.size_hint().0, S::default());
+        map.extend(iter);
+
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, S> Clone for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        let entries = self.entries.iter();
+
+        Self {
+            entries: entries
+                .map(|bucket| Bucket::new(bucket.hash, bucket.key.clone(), bucket.value.clone()))
+                .collect(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+
+/// Maps are equal when they hold the same entries, whatever order those
+/// entries were inserted in.
+impl<K, V, S1, S2> PartialEq<OAIndexMap<K, V, S2>> for OAIndexMap<K, V, S1>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S1: BuildHasher,
+    S2: BuildHasher,
+{
+    fn eq(&self, other: &OAIndexMap<K, V, S2>) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|v| value == v))
+    }
+}
+
+impl<K, V, S> Eq for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> fmt::Debug for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> IntoIterator for OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            entries: self.entries.into_iter(),
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut OAIndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Yields the entries in insertion order.
+pub struct Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    entries: slice::Iter<'a, Bucket<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.next()?;
+
+        Some((&bucket.key, &bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.next_back()?;
+
+        Some((&bucket.key, &bucket.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> Clone for Iter<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Iter<'_, K, V>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+macro_rules! projection {
+    ($name:ident, $item:ty, |$bucket:ident| $project:expr) => {
+        pub struct $name<'a, K, V>
+        where
+            K: Hash + Eq,
+        {
+            entries: slice::Iter<'a, Bucket<K, V>>,
+        }
+
+        impl<'a, K, V> Iterator for $name<'a, K, V>
+        where
+            K: Hash + Eq,
+        {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.entries.next().map(|$bucket| $project)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.entries.size_hint()
+            }
+        }
+
+        impl<K, V> DoubleEndedIterator for $name<'_, K, V>
+        where
+            K: Hash + Eq,
+        {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.entries.next_back().map(|$bucket| $project)
+            }
+        }
+
+        impl<K, V> ExactSizeIterator for $name<'_, K, V> where K: Hash + Eq {}
+
+        impl<K, V> FusedIterator for $name<'_, K, V> where K: Hash + Eq {}
+
+        impl<K, V> Clone for $name<'_, K, V>
+        where
+            K: Hash + Eq,
+        {
+            fn clone(&self) -> Self {
+                Self {
+                    entries: self.entries.clone(),
+                }
+            }
+        }
+    };
+}
+
+projection!(Keys, &'a K, |bucket| &bucket.key);
+projection!(Values, &'a V, |bucket| &bucket.value);
+
+pub struct IterMut<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    entries: slice::IterMut<'a, Bucket<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.next()?;
+
+        Some((&bucket.key, &mut bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let bucket = self.entries.next_back()?;
+
+        Some((&bucket.key, &mut bucket.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> where K: Hash + Eq {}
+
+pub struct IntoIter<K, V>
+where
+    K: Hash + Eq,
+{
+    entries: alloc::vec::IntoIter<Bucket<K, V>>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(Bucket::into_pair)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V>
+where
+    K: Hash + Eq,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.entries.next_back().map(Bucket::into_pair)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> where K: Hash + Eq {}
+
+#[cfg(test)]
+mod tests {
+    use super::OAIndexMap;
+    use crate::test_util::Rng;
+
+    fn assert_indexed<V>(map: &OAIndexMap<u64, V>) {
+        assert_eq!(map.len(), map.indices.len());
+        for (position, (key, _)) in map.iter().enumerate() {
+            assert_eq!(Some(position), map.get_index_of(key));
+        }
+    }
+
+    #[test]
+    fn iterates_in_insertion_order_across_growth() {
+        let mut rng = Rng::new(80);
+        let keys: Vec<u64> = (0..2000).map(|_| rng.next()).collect();
+        let mut map = OAIndexMap::new();
+        for &key in &keys {
+            map.insert(key, key / 2);
+        }
+
+        assert!(map.indices.stats().resizes > 0);
+        assert_eq!(keys, map.keys().copied().collect::<Vec<_>>());
+        assert_eq!(
+            keys.iter().rev().collect::<Vec<_>>(),
+            map.keys().rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            keys.iter().map(|key| (*key, key / 2)).collect::<Vec<_>>(),
+            map.into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reinserting_keeps_the_position() {
+        let mut map: OAIndexMap<&str, i32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+
+        assert_eq!((1, Some(2)), map.insert_full("b", 20));
+        assert_eq!((3, None), map.insert_full("d", 4));
+        assert_eq!(
+            vec![("a", 1), ("b", 20), ("c", 3), ("d", 4)],
+            map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn swap_remove_moves_the_last_entry_into_the_hole() {
+        let mut map: OAIndexMap<u64, u64> = (0..6).map(|key| (key, key * 10)).collect();
+
+        assert_eq!(Some(10), map.swap_remove(&1));
+        assert_eq!(None, map.swap_remove(&1));
+        assert_eq!(vec![0, 5, 2, 3, 4], map.keys().copied().collect::<Vec<_>>());
+        assert_eq!(Some((4, 40)), map.pop());
+        assert_eq!(Some((0, 0)), map.swap_remove_index(0));
+        assert_eq!(vec![3, 5, 2], map.keys().copied().collect::<Vec<_>>());
+        assert_indexed(&map);
+    }
+
+    #[test]
+    fn shift_remove_keeps_the_order_of_the_rest() {
+        let mut map: OAIndexMap<u64, u64> = (0..6).map(|key| (key, key * 10)).collect();
+
+        assert_eq!(Some(10), map.shift_remove(&1));
+        assert_eq!(None, map.shift_remove(&1));
+        assert_eq!(vec![0, 2, 3, 4, 5], map.keys().copied().collect::<Vec<_>>());
+        assert_eq!(Some((0, 0)), map.shift_remove_index(0));
+        assert_eq!(Some((5, 50)), map.shift_remove_index(3));
+        assert_eq!(None, map.shift_remove_index(3));
+        assert_eq!(vec![2, 3, 4], map.keys().copied().collect::<Vec<_>>());
+        assert_indexed(&map);
+    }
+
+    #[test]
+    fn index_based_access() {
+        let mut map: OAIndexMap<u64, u64> = (10..15).map(|key| (key, key)).collect();
+
+        assert_eq!(Some((&12, &12)), map.get_index(2));
+        assert_eq!(None, map.get_index(5));
+        assert_eq!(Some(4), map.get_index_of(&14));
+        assert_eq!(None, map.get_index_of(&15));
+        *map.get_index_mut(0).unwrap().1 = 100;
+        assert_eq!(Some(&100), map.get(&10));
+        assert_eq!(Some((&10, &100)), map.first());
+        assert_eq!(Some((&14, &14)), map.last());
+        for (_, value) in &mut map {
+            *value += 1;
+        }
+        assert_eq!(Some(&15), map.get(&14));
+    }
+
+    #[test]
+    fn matches_a_vec_under_random_churn() {
+        let mut rng = Rng::new(800);
+        let mut map = OAIndexMap::new();
+        let mut model: Vec<(u64, u64)> = Vec::new();
+        for step in 0..3000 {
+            let key = rng.below(200);
+            let position = model.iter().position(|&(k, _)| k == key);
+            match rng.below(4) {
+                0 => {
+                    let removed = position.map(|position| model.swap_remove(position).1);
+                    assert_eq!(removed, map.swap_remove(&key));
+                }
+                1 => {
+                    let removed = position.map(|position| model.remove(position).1);
+                    assert_eq!(removed, map.shift_remove(&key));
+                }
+                _ => {
+                    match position {
+                        Some(position) => model[position].1 = step,
+                        None => model.push((key, step)),
+                    }
+                    map.insert(key, step);
+                }
+            }
+        }
+
+        assert_eq!(model, map.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>());
+        assert_indexed(&map);
+    }
+
+    #[test]
+    fn equality_ignores_order_and_clones_are_independent() {
+        let forward: OAIndexMap<u64, u64> = (0..100).map(|key| (key, key)).collect();
+        let backward: OAIndexMap<u64, u64> = (0..100).rev().map(|key| (key, key)).collect();
+        assert_eq!(forward, backward);
+
+        let mut clone = forward.clone();
+        clone.shift_remove(&0);
+        assert_ne!(forward, clone);
+        assert_eq!(Some(0), clone.get_index_of(&1));
+        assert_eq!(Some(1), forward.get_index_of(&1));
+
+        let map: OAIndexMap<i32, i32> = [(2, 1), (1, 2)].into_iter().collect();
+        assert_eq!("{2: 1, 1: 2}", format!("{map:?}"));
+    }
+}
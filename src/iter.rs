@@ -0,0 +1,1236 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::raw::{RawIntoIter, RawIter, RawIterMut, RawTable};
+use crate::{Bucket, DefaultHashBuilder, HashedKey, OAHashMap};
+
+pub struct Iter<'a, K, V> {
+    slots: RawIter<'a, K, V>,
+    /// The table an incremental resize is moving entries out of.
+    migrating: RawIter<'a, K, V>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    pub(crate) fn new(slots: RawIter<'a, K, V>, migrating: RawIter<'a, K, V>, len: usize) -> Self {
+        Self {
+            slots,
+            migrating,
+            remaining: len,
+        }
+    }
+
+    fn buckets(&self) -> impl Iterator<Item = (&'a HashedKey<K>, &'a V)> {
+        self.slots.clone().chain(self.migrating.clone())
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (hashed, value) = self.slots.next().or_else(|| self.migrating.next())?;
+        self.remaining -= 1;
+
+        Some((&hashed.key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+            migrating: self.migrating.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<K, V> Default for Iter<'_, K, V> {
+    fn default() -> Self {
+        Self::new(RawIter::default(), RawIter::default(), 0)
+    }
+}
+
+impl<K, V> fmt::Debug for Iter<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_pairs(f, self.buckets())
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    slots: RawIterMut<'a, K, V>,
+    migrating: RawIterMut<'a, K, V>,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    pub(crate) fn new(
+        slots: RawIterMut<'a, K, V>,
+        migrating: RawIterMut<'a, K, V>,
+        len: usize,
+    ) -> Self {
+        Self {
+            slots,
+            migrating,
+            remaining: len,
+        }
+    }
+
+    fn buckets(&self) -> impl Iterator<Item = (&HashedKey<K>, &V)> {
+        self.slots.as_iter().chain(self.migrating.as_iter())
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (hashed, value) = self.slots.next().or_else(|| self.migrating.next())?;
+        self.remaining -= 1;
+
+        Some((&hashed.key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+impl<K, V> Default for IterMut<'_, K, V> {
+    fn default() -> Self {
+        Self::new(RawIterMut::default(), RawIterMut::default(), 0)
+    }
+}
+
+impl<K, V> fmt::Debug for IterMut<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_pairs(f, self.buckets())
+    }
+}
+
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Keys<'a, K, V> {
+    pub(crate) fn new(inner: Iter<'a, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+impl<K, V> FusedIterator for Keys<'_, K, V> {}
+
+impl<K, V> Clone for Keys<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<K, V> Default for Keys<'_, K, V> {
+    fn default() -> Self {
+        Self::new(Iter::default())
+    }
+}
+
+impl<K, V> fmt::Debug for Keys<'_, K, V>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Values<'a, K, V> {
+    pub(crate) fn new(inner: Iter<'a, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+impl<K, V> FusedIterator for Values<'_, K, V> {}
+
+impl<K, V> Clone for Values<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone())
+    }
+}
+
+impl<K, V> Default for Values<'_, K, V> {
+    fn default() -> Self {
+        Self::new(Iter::default())
+    }
+}
+
+impl<K, V> fmt::Debug for Values<'_, K, V>
+where
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>,
+}
+
+impl<'a, K, V> ValuesMut<'a, K, V> {
+    pub(crate) fn new(inner: IterMut<'a, K, V>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for ValuesMut<'_, K, V> {}
+
+impl<K, V> Default for ValuesMut<'_, K, V> {
+    fn default() -> Self {
+        Self::new(IterMut::default())
+    }
+}
+
+impl<K, V> fmt::Debug for ValuesMut<'_, K, V>
+where
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_values(f, self.inner.buckets())
+    }
+}
+
+pub struct IntoIter<K, V, A = Global>
+where
+    A: Allocator + Clone,
+{
+    slots: RawIntoIter<K, V, A>,
+    remaining: usize,
+}
+
+impl<K, V, A> IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(table: RawTable<K, V, A>, len: usize) -> Self {
+        Self {
+            slots: RawIntoIter::new(table),
+            remaining: len,
+        }
+    }
+}
+
+impl<K, V, A> Iterator for IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let bucket = self.slots.next()?;
+        self.remaining -= 1;
+
+        Some(bucket.into_pair())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoIter<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> FusedIterator for IntoIter<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> Default for IntoIter<K, V, A>
+where
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(RawTable::new_in(A::default()), 0)
+    }
+}
+
+impl<K, V, A> fmt::Debug for IntoIter<K, V, A>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_pairs(f, self.slots.as_iter())
+    }
+}
+
+pub struct IntoKeys<K, V, A = Global>
+where
+    A: Allocator + Clone,
+{
+    inner: IntoIter<K, V, A>,
+}
+
+impl<K, V, A> IntoKeys<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(inner: IntoIter<K, V, A>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<K, V, A> Iterator for IntoKeys<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoKeys<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> FusedIterator for IntoKeys<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> Default for IntoKeys<K, V, A>
+where
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(IntoIter::default())
+    }
+}
+
+impl<K, V, A> fmt::Debug for IntoKeys<K, V, A>
+where
+    K: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_keys(f, self.inner.slots.as_iter())
+    }
+}
+
+pub struct IntoValues<K, V, A = Global>
+where
+    A: Allocator + Clone,
+{
+    inner: IntoIter<K, V, A>,
+}
+
+impl<K, V, A> IntoValues<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(inner: IntoIter<K, V, A>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<K, V, A> Iterator for IntoValues<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoValues<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> FusedIterator for IntoValues<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> Default for IntoValues<K, V, A>
+where
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(IntoIter::default())
+    }
+}
+
+impl<K, V, A> fmt::Debug for IntoValues<K, V, A>
+where
+    V: fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_values(f, self.inner.slots.as_iter())
+    }
+}
+
+/// A draining iterator over the entries of an [`OAHashMap`].
+///
+/// Every yielded entry is removed straight away, so forgetting the drain
+/// leaves a consistent map holding the entries that were not yet yielded.
+/// Dropping it removes whatever is left.
+pub struct Drain<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    start: usize,
+    offset: usize,
+}
+
+impl<'a, K, V, S, A> Drain<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>) -> Self {
+        // Walks from an empty slot for the same reason as `retain`.
+        let start = map.empty_index();
+
+        Self {
+            map,
+            start,
+            offset: 1,
+        }
+    }
+}
+
+impl<K, V, S, A> Iterator for Drain<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.map.len > 0 && self.offset < self.map.table.num_slots() {
+            let index = (self.start + self.offset) & self.map.mask;
+            if self.map.table.is_full(index) {
+                return Some(self.map.take_bucket(index).into_pair());
+            }
+
+            self.offset += self.map.table.distance_to_full(index);
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.map.len, Some(self.map.len))
+    }
+}
+
+impl<K, V, S, A> ExactSizeIterator for Drain<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, S, A> FusedIterator for Drain<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, S, A> Drop for Drain<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for Drain<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_pairs(f, self.map.table.iter())
+    }
+}
+
+/// An iterator that removes and yields the entries matching a predicate.
+///
+/// Entries that have not been visited when the iterator is dropped stay in
+/// the map, as do the ones the predicate rejected.
+pub struct ExtractIf<'a, K, V, F, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    F: FnMut(&K, &mut V) -> bool,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    start: usize,
+    offset: usize,
+    pred: F,
+}
+
+impl<'a, K, V, F, S, A> ExtractIf<'a, K, V, F, S, A>
+where
+    K: Hash + Eq,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>, pred: F) -> Self {
+        // Walks from an empty slot for the same reason as `retain`.
+        let start = map.empty_index();
+
+        Self {
+            map,
+            start,
+            offset: 1,
+            pred,
+        }
+    }
+
+    /// Takes the next matching entry with its cached hash.
+    pub(crate) fn next_bucket(&mut self) -> Option<Bucket<K, V>> {
+        while self.offset < self.map.table.num_slots() {
+            let index = (self.start + self.offset) & self.map.mask;
+            let Some((hashed, value)) = self.map.table.get_pair_mut(index) else {
+                self.offset += self.map.table.distance_to_full(index);
+                continue;
+            };
+            if (self.pred)(&hashed.key, value) {
+                return Some(self.map.take_bucket(index));
+            }
+
+            self.offset += 1;
+        }
+
+        None
+    }
+}
+
+impl<K, V, F, S, A> Iterator for ExtractIf<'_, K, V, F, S, A>
+where
+    K: Hash + Eq,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_bucket().map(Bucket::into_pair)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len))
+    }
+}
+
+impl<K, V, F, S, A> FusedIterator for ExtractIf<'_, K, V, F, S, A>
+where
+    K: Hash + Eq,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, F, S, A> fmt::Debug for ExtractIf<'_, K, V, F, S, A>
+where
+    K: Hash + Eq,
+    F: FnMut(&K, &mut V) -> bool,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtractIf").finish_non_exhaustive()
+    }
+}
+
+/// Steps through the entries once each, as [`retain`](OAHashMap::retain)
+/// does, and can remove the one it stopped at without looking it up again.
+///
+/// Entries that have not been stepped to when the cursor is dropped stay in
+/// the map.
+pub struct CursorMut<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    start: usize,
+    offset: usize,
+    /// The slot of the entry the cursor stopped at, until it is removed.
+    current: Option<usize>,
+}
+
+impl<'a, K, V, S, A> CursorMut<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>) -> Self {
+        // Walks from an empty slot for the same reason as `retain`.
+        let start = map.empty_index();
+
+        Self {
+            map,
+            start,
+            offset: 1,
+            current: None,
+        }
+    }
+
+    /// Moves on to the next entry, or returns `None` once every entry has
+    /// been stepped to.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&K, &mut V)> {
+        // A removal shifts the next entry back into the removed one's slot,
+        // so the walk only moves past slots whose entry stayed.
+        if self.current.take().is_some() {
+            self.offset += 1;
+        }
+        while self.offset < self.map.table.num_slots() {
+            let index = (self.start + self.offset) & self.map.mask;
+            if self.map.table.is_full(index) {
+                self.current = Some(index);
+                let (hashed, value) = self.map.table.get_pair_mut(index).unwrap();
+
+                return Some((&hashed.key, value));
+            }
+
+            self.offset += self.map.table.distance_to_full(index);
+        }
+
+        None
+    }
+
+    /// The entry the cursor stopped at, if it is still in the map.
+    pub fn current(&mut self) -> Option<(&K, &mut V)> {
+        let (hashed, value) = self.map.table.get_pair_mut(self.current?)?;
+
+        Some((&hashed.key, value))
+    }
+
+    /// Removes the entry the cursor stopped at. The next step goes on to the
+    /// entry that would have followed it.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        let index = self.current.take()?;
+
+        Some(self.map.take_bucket(index).into_pair())
+    }
+}
+
+impl<K, V, S, A> Drop for CursorMut<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        self.map.shrink_if_sparse();
+    }
+}
+
+impl<K, V, S, A> fmt::Debug for CursorMut<'_, K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current = self
+            .current
+            .and_then(|index| self.map.table.get_pair(index));
+
+        f.debug_struct("CursorMut")
+            .field(
+                "current",
+                &current.map(|(hashed, value)| (&hashed.key, value)),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, S, A> IntoIterator for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.finish_migration();
+
+        IntoIter::new(self.table, self.len)
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a OAHashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a mut OAHashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+fn debug_pairs<'a, K, V>(
+    f: &mut fmt::Formatter<'_>,
+    slots: impl Iterator<Item = (&'a HashedKey<K>, &'a V)>,
+) -> fmt::Result
+where
+    K: fmt::Debug + 'a,
+    V: fmt::Debug + 'a,
+{
+    f.debug_list()
+        .entries(slots.map(|(hashed, value)| (&hashed.key, value)))
+        .finish()
+}
+
+fn debug_keys<'a, K, V>(
+    f: &mut fmt::Formatter<'_>,
+    slots: impl Iterator<Item = (&'a HashedKey<K>, &'a V)>,
+) -> fmt::Result
+where
+    K: fmt::Debug + 'a,
+    V: 'a,
+{
+    f.debug_list()
+        .entries(slots.map(|(hashed, _)| &hashed.key))
+        .finish()
+}
+
+fn debug_values<'a, K, V>(
+    f: &mut fmt::Formatter<'_>,
+    slots: impl Iterator<Item = (&'a HashedKey<K>, &'a V)>,
+) -> fmt::Result
+where
+    K: 'a,
+    V: fmt::Debug + 'a,
+{
+    f.debug_list()
+        .entries(slots.map(|(_, value)| value))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use crate::raw::EMPTY;
+    use crate::test_util::keys_starting_at;
+    use crate::{OAHashMap, Probing};
+
+    #[test]
+    fn iter_skips_deleted() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 3, 3);
+        for &key in &keys {
+            oa.insert(key, key);
+        }
+        oa.delete(&keys[1]);
+
+        let mut seen: Vec<i32> = oa.iter().map(|(&key, _)| key).collect();
+        seen.sort();
+        let mut expected = vec![keys[0], keys[2]];
+        expected.sort();
+
+        assert_eq!(expected, seen);
+        assert_eq!(2, oa.iter().len());
+    }
+
+    #[test]
+    fn iter_after_churn() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        let mut std_map = HashMap::new();
+        for i in 0..5000 {
+            let key = (i * 31) % 700;
+            if i % 4 == 0 {
+                oa.delete(&key);
+                std_map.remove(&key);
+            } else {
+                oa.insert(key, i);
+                std_map.insert(key, i);
+            }
+        }
+
+        let mut iter = oa.iter();
+
+        assert_eq!((oa.len(), Some(oa.len())), iter.size_hint());
+
+        iter.next();
+
+        assert_eq!(oa.len() - 1, iter.len());
+
+        let collected: HashMap<i32, i32> = oa.iter().map(|(&k, &v)| (k, v)).collect();
+
+        assert_eq!(std_map, collected);
+        assert_eq!(oa.len(), oa.iter().count());
+    }
+
+    #[test]
+    fn iter_mut_changes_are_visible() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 0..100 {
+            oa.insert(i, i);
+        }
+        for i in 0..50 {
+            oa.delete(&i);
+        }
+
+        assert_eq!(50, oa.iter_mut().len());
+
+        for (key, value) in oa.iter_mut() {
+            *value = key * 2;
+        }
+
+        for i in 50..100 {
+            assert_eq!(Some(&(i * 2)), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn views_on_empty_and_churned_maps() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+
+        assert_eq!(0, oa.keys().len());
+        assert_eq!(None, oa.values().next());
+
+        for i in 0..30 {
+            oa.insert(i, i);
+        }
+        for i in 0..30 {
+            oa.delete(&i);
+        }
+
+        assert_eq!(None, oa.keys().next());
+        assert_eq!(None, oa.values().next());
+        assert_eq!(None, oa.values_mut().next());
+    }
+
+    #[test]
+    fn keys_values_and_values_mut() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 0..10 {
+            oa.insert(i, i * 10);
+        }
+        oa.delete(&3);
+
+        let mut keys: Vec<i32> = oa.keys().copied().collect();
+        keys.sort();
+
+        assert_eq!(vec![0, 1, 2, 4, 5, 6, 7, 8, 9], keys);
+        assert_eq!(9, oa.values().len());
+        assert_eq!(420, oa.values().sum::<i32>());
+
+        for v in oa.values_mut() {
+            *v *= 2;
+        }
+
+        assert_eq!(Some(&80), oa.search(&4));
+        assert_eq!(840, oa.values().sum::<i32>());
+    }
+
+    #[test]
+    fn into_iter_variants() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 0..20 {
+            oa.insert(i, i);
+        }
+        oa.delete(&0);
+
+        let mut sum = 0;
+        for (_, value) in &oa {
+            sum += value;
+        }
+
+        assert_eq!(190, sum);
+
+        for (_, value) in &mut oa {
+            *value += 1;
+        }
+
+        let mut pairs: Vec<(i32, i32)> = oa.into_iter().collect();
+        pairs.sort();
+
+        assert_eq!(19, pairs.len());
+        assert_eq!((1, 2), pairs[0]);
+    }
+
+    #[test]
+    fn into_keys_and_values() {
+        let mut oa: OAHashMap<i32, String> = OAHashMap::new();
+        for i in 0..5 {
+            oa.insert(i, i.to_string());
+        }
+        oa.delete(&2);
+
+        let mut keys: Vec<i32> = std::mem::take(&mut oa).into_keys().collect();
+        keys.sort();
+
+        assert_eq!(vec![0, 1, 3, 4], keys);
+
+        oa.insert(7, "seven".to_string());
+        let values = oa.into_values();
+
+        assert_eq!((1, Some(1)), values.size_hint());
+        assert_eq!(vec!["seven".to_string()], values.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn into_iter_drops_unconsumed() {
+        let tracker = Rc::new(());
+        let mut oa: OAHashMap<i32, Rc<()>> = OAHashMap::new();
+        for i in 0..10 {
+            oa.insert(i, Rc::clone(&tracker));
+        }
+        oa.delete(&0);
+
+        assert_eq!(10, Rc::strong_count(&tracker));
+
+        let mut iter = oa.into_iter();
+
+        assert_eq!(9, iter.len());
+
+        let first = iter.next();
+        drop(iter);
+
+        assert_eq!(2, Rc::strong_count(&tracker));
+
+        drop(first);
+
+        assert_eq!(1, Rc::strong_count(&tracker));
+    }
+
+    fn remaining_sum<I>(iter: I) -> (usize, i32)
+    where
+        I: ExactSizeIterator<Item = i32> + Clone,
+    {
+        (iter.len(), iter.clone().sum())
+    }
+
+    #[test]
+    fn cloned_iterators_see_remaining() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for i in 1..=10 {
+            oa.insert(i, i);
+        }
+
+        let mut iter = oa.iter();
+        let mut consumed = 0;
+        for _ in 0..4 {
+            consumed += iter.next().unwrap().1;
+        }
+        let rest = iter.clone();
+
+        assert_eq!(6, rest.len());
+        assert_eq!(55 - consumed, rest.map(|(_, v)| v).sum::<i32>());
+        assert_eq!(55 - consumed, iter.map(|(_, v)| v).sum::<i32>());
+        assert_eq!((10, 55), remaining_sum(oa.values().copied()));
+        assert_eq!((10, 55), remaining_sum(oa.keys().copied()));
+
+        let mut keys = oa.keys();
+        while keys.next().is_some() {}
+
+        assert_eq!(0, keys.len());
+        assert_eq!(None, keys.next());
+    }
+
+    #[test]
+    fn iterator_debug_and_default() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 2);
+
+        assert_eq!("[(1, 2)]", format!("{:?}", oa.iter()));
+        assert_eq!("[1]", format!("{:?}", oa.keys()));
+        assert_eq!("[2]", format!("{:?}", oa.values_mut()));
+        assert_eq!("[(1, 2)]", format!("{:?}", oa.iter_mut()));
+        assert_eq!("[2]", format!("{:?}", oa.into_values()));
+        assert_eq!(0, crate::Iter::<i32, i32>::default().len());
+        assert_eq!(None, crate::Values::<i32, i32>::default().next());
+        assert_eq!(None, crate::IntoIter::<i32, i32>::default().next());
+    }
+
+    #[test]
+    fn drain_fully() {
+        let mut oa: OAHashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        oa.delete(&0);
+        let buffer = oa.table.as_ptr();
+        let slots = oa.table.num_slots();
+
+        let mut drained: Vec<(i32, i32)> = oa.drain().collect();
+        drained.sort();
+
+        assert_eq!((1..100).map(|i| (i, i)).collect::<Vec<_>>(), drained);
+        assert!(oa.is_empty());
+        assert!((0..slots).all(|index| oa.table.ctrl(index) == EMPTY));
+        assert_eq!(slots, oa.table.num_slots());
+
+        for i in 0..50 {
+            oa.insert(i, i);
+        }
+
+        assert_eq!(buffer, oa.table.as_ptr());
+    }
+
+    #[test]
+    fn drain_partially() {
+        let tracker = Rc::new(());
+        let mut oa: OAHashMap<i32, Rc<()>> = OAHashMap::new();
+        for i in 0..10 {
+            oa.insert(i, Rc::clone(&tracker));
+        }
+
+        let mut drain = oa.drain();
+
+        assert_eq!(10, drain.len());
+
+        let first = drain.next().unwrap();
+
+        assert_eq!(9, drain.len());
+
+        drop(drain);
+
+        assert_eq!(2, Rc::strong_count(&tracker));
+        assert!(oa.is_empty());
+        assert_eq!(None, oa.search(&first.0));
+        assert_eq!(0, oa.iter().count());
+    }
+
+    #[test]
+    fn forgotten_drain_leaves_consistent_map() {
+        let mut oa: OAHashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+
+        let mut drain = oa.drain();
+        let (key, _) = drain.next().unwrap();
+        std::mem::forget(drain);
+
+        assert_eq!(9, oa.len());
+        assert_eq!(None, oa.search(&key));
+        for i in (0..10).filter(|&i| i != key) {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn extract_if_from_cluster_middle() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 12, 5);
+        for (position, &key) in keys.iter().enumerate() {
+            oa.insert(key, position as i32);
+        }
+
+        let extracted: Vec<(i32, i32)> = oa.extract_if(|_, &mut v| v == 1 || v == 2).collect();
+
+        assert_eq!(2, extracted.len());
+        assert_eq!(3, oa.len());
+        assert_eq!(None, oa.search(&keys[1]));
+        assert_eq!(None, oa.search(&keys[2]));
+        assert_eq!(Some(&0), oa.search(&keys[0]));
+        assert_eq!(Some(&3), oa.search(&keys[3]));
+        assert_eq!(Some(&4), oa.search(&keys[4]));
+    }
+
+    #[test]
+    fn extract_if_dropped_early_keeps_rest() {
+        let mut oa: OAHashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+
+        let first = oa
+            .extract_if(|_, value| {
+                *value += 1000;
+                true
+            })
+            .next()
+            .unwrap();
+
+        assert_eq!(99, oa.len());
+        assert_eq!(None, oa.search(&first.0));
+        assert_eq!(first.0 + 1000, first.1);
+        assert_eq!(99, oa.iter().count());
+
+        let evens: Vec<(i32, i32)> = oa.extract_if(|key, _| key % 2 == 0).collect();
+
+        assert!(evens.iter().all(|(key, _)| key % 2 == 0));
+        assert!(oa.keys().all(|key| key % 2 == 1));
+    }
+
+    #[test]
+    fn cursor_removals_in_a_cluster_visit_each_entry_once() {
+        for probing in [Probing::Linear, Probing::Quadratic, Probing::Hopscotch] {
+            let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+            oa.set_probing(probing);
+            let keys = keys_starting_at(&oa, 60, 8);
+            for &key in &keys {
+                oa.insert(key, 0);
+            }
+            for key in 0..20 {
+                oa.insert(key, 0);
+            }
+            let len = oa.len();
+
+            // Removes every other entry in the order they are stepped to, so
+            // which ones go depends on the walk so far.
+            let mut visited = HashMap::new();
+            let mut removed = Vec::new();
+            let mut cursor = oa.cursor_mut();
+            while let Some((&key, value)) = cursor.next() {
+                *value += 1;
+                *visited.entry(key).or_insert(0) += 1;
+                if visited.len() % 2 == 0 {
+                    assert_eq!(Some((key, 1)), cursor.remove_current());
+                    assert!(cursor.current().is_none());
+                    removed.push(key);
+                }
+            }
+            assert!(cursor.remove_current().is_none());
+            drop(cursor);
+
+            assert_eq!(len, visited.len());
+            assert!(visited.values().all(|&times| times == 1));
+            assert_eq!(len - len / 2, oa.len());
+            assert!(removed.iter().all(|key| !oa.contains_key(key)));
+            assert!(oa.values().all(|&value| value == 1));
+            assert_eq!(Ok(()), oa.check_invariants());
+        }
+    }
+
+    #[test]
+    fn cursor_stopped_early_keeps_the_rest() {
+        let mut oa: OAHashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let mut cursor = oa.cursor_mut();
+        let mut total = 0;
+        while total < 500 {
+            let (_, &mut value) = cursor.next().unwrap();
+            total += value;
+            cursor.remove_current();
+        }
+        let (&key, value) = cursor.next().unwrap();
+        let replaced = core::mem::replace(value, -1);
+        assert_eq!(
+            format!("CursorMut {{ current: Some(({key}, -1)), .. }}"),
+            format!("{cursor:?}")
+        );
+        drop(cursor);
+
+        assert_eq!(4950 - total - replaced - 1, oa.values().sum::<i32>());
+        assert_eq!(Ok(()), oa.check_invariants());
+    }
+}
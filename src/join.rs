@@ -0,0 +1,313 @@
+//! Joining two maps with the same key type on their keys.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{Iter, OAHashMap};
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Yields each key both maps hold with its value in each. Walks the
+    /// smaller map and looks each key up in the larger; the keys yielded are
+    /// this map's either way.
+    pub fn inner_join<'a, V2, S2, A2>(
+        &'a self,
+        other: &'a OAHashMap<K, V2, S2, A2>,
+    ) -> InnerJoin<'a, K, V, V2, S, S2, A, A2>
+    where
+        S2: BuildHasher,
+        A2: Allocator + Clone,
+    {
+        let side = if self.len() <= other.len() {
+            Side::Left {
+                iter: self.iter(),
+                other,
+            }
+        } else {
+            Side::Right {
+                iter: other.iter(),
+                map: self,
+            }
+        };
+
+        InnerJoin { side }
+    }
+
+    /// Yields every entry of this map with the value `other` holds for its
+    /// key, if any. Keys only `other` holds are left out.
+    pub fn left_join<'a, V2, S2, A2>(
+        &'a self,
+        other: &'a OAHashMap<K, V2, S2, A2>,
+    ) -> LeftJoin<'a, K, V, V2, S2, A2>
+    where
+        S2: BuildHasher,
+        A2: Allocator + Clone,
+    {
+        LeftJoin {
+            iter: self.iter(),
+            other,
+        }
+    }
+}
+
+pub struct InnerJoin<'a, K, V, V2, S, S2, A = Global, A2 = Global>
+where
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    side: Side<'a, K, V, V2, S, S2, A, A2>,
+}
+
+/// Which map the join walks.
+enum Side<'a, K, V, V2, S, S2, A, A2>
+where
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    Left {
+        iter: Iter<'a, K, V>,
+        other: &'a OAHashMap<K, V2, S2, A2>,
+    },
+    Right {
+        iter: Iter<'a, K, V2>,
+        map: &'a OAHashMap<K, V, S, A>,
+    },
+}
+
+impl<'a, K, V, V2, S, S2, A, A2> Iterator for InnerJoin<'a, K, V, V2, S, S2, A, A2>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    S2: BuildHasher,
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    type Item = (&'a K, &'a V, &'a V2);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.side {
+            Side::Left { iter, other } => {
+                let other = *other;
+                iter.find_map(|(key, value)| Some((key, value, other.get(key)?)))
+            }
+            Side::Right { iter, map } => {
+                let map = *map;
+                iter.find_map(|(key, other)| {
+                    let (key, value) = map.get_key_value(key)?;
+                    Some((key, value, other))
+                })
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.side {
+            Side::Left { iter, .. } => (0, iter.size_hint().1),
+            Side::Right { iter, .. } => (0, iter.size_hint().1),
+        }
+    }
+}
+
+impl<K, V, V2, S, S2, A, A2> FusedIterator for InnerJoin<'_, K, V, V2, S, S2, A, A2>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    S2: BuildHasher,
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+}
+
+impl<K, V, V2, S, S2, A, A2> Clone for InnerJoin<'_, K, V, V2, S, S2, A, A2>
+where
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        let side = match &self.side {
+            Side::Left { iter, other } => Side::Left {
+                iter: iter.clone(),
+                other: *other,
+            },
+            Side::Right { iter, map } => Side::Right {
+                iter: iter.clone(),
+                map: *map,
+            },
+        };
+
+        Self { side }
+    }
+}
+
+impl<K, V, V2, S, S2, A, A2> fmt::Debug for InnerJoin<'_, K, V, V2, S, S2, A, A2>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    V2: fmt::Debug,
+    S: BuildHasher,
+    S2: BuildHasher,
+    A: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct LeftJoin<'a, K, V, V2, S2, A2 = Global>
+where
+    A2: Allocator + Clone,
+{
+    iter: Iter<'a, K, V>,
+    other: &'a OAHashMap<K, V2, S2, A2>,
+}
+
+impl<'a, K, V, V2, S2, A2> Iterator for LeftJoin<'a, K, V, V2, S2, A2>
+where
+    K: Hash + Eq,
+    S2: BuildHasher,
+    A2: Allocator + Clone,
+{
+    type Item = (&'a K, &'a V, Option<&'a V2>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+
+        Some((key, value, self.other.get(key)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<K, V, V2, S2, A2> ExactSizeIterator for LeftJoin<'_, K, V, V2, S2, A2>
+where
+    K: Hash + Eq,
+    S2: BuildHasher,
+    A2: Allocator + Clone,
+{
+}
+
+impl<K, V, V2, S2, A2> FusedIterator for LeftJoin<'_, K, V, V2, S2, A2>
+where
+    K: Hash + Eq,
+    S2: BuildHasher,
+    A2: Allocator + Clone,
+{
+}
+
+impl<K, V, V2, S2, A2> Clone for LeftJoin<'_, K, V, V2, S2, A2>
+where
+    A2: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            other: self.other,
+        }
+    }
+}
+
+impl<K, V, V2, S2, A2> fmt::Debug for LeftJoin<'_, K, V, V2, S2, A2>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    V2: fmt::Debug,
+    S2: BuildHasher,
+    A2: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::Rng;
+    use crate::OAHashMap;
+
+    fn random_map(rng: &mut Rng, len: usize, keys: u64) -> OAHashMap<u64, u64> {
+        (0..len).map(|_| (rng.below(keys), rng.next())).collect()
+    }
+
+    fn check(a: &OAHashMap<u64, u64>, b: &OAHashMap<u64, u64>) {
+        let mut inner = Vec::new();
+        let mut left = Vec::new();
+        for (ka, va) in a {
+            let mut matched = None;
+            for (kb, vb) in b {
+                if ka == kb {
+                    inner.push((*ka, *va, *vb));
+                    matched = Some(*vb);
+                }
+            }
+            left.push((*ka, *va, matched));
+        }
+        inner.sort_unstable();
+        left.sort_unstable();
+
+        let mut joined: Vec<_> = a.inner_join(b).map(|(&k, &v, &w)| (k, v, w)).collect();
+        joined.sort_unstable();
+        assert_eq!(inner, joined);
+        let mut joined: Vec<_> = a
+            .left_join(b)
+            .map(|(&k, &v, w)| (k, v, w.copied()))
+            .collect();
+        joined.sort_unstable();
+        assert_eq!(left, joined);
+        assert_eq!(a.len(), a.left_join(b).len());
+    }
+
+    #[test]
+    fn joins_match_nested_loops() {
+        let mut rng = Rng::new(113);
+        for _ in 0..50 {
+            let (a_len, b_len) = (rng.below(200) as usize, rng.below(200) as usize);
+            let a = random_map(&mut rng, a_len, 300);
+            let b = random_map(&mut rng, b_len, 300);
+            check(&a, &b);
+            check(&b, &a);
+        }
+
+        let empty = OAHashMap::new();
+        let full = random_map(&mut rng, 100, 1_000);
+        check(&empty, &full);
+        check(&full, &empty);
+        assert_eq!(0, full.inner_join(&empty).count());
+        assert!(full.left_join(&empty).all(|(_, _, other)| other.is_none()));
+
+        let evens: OAHashMap<u64, u64> = (0..100).map(|i| (2 * i, i)).collect();
+        let odds: OAHashMap<u64, u64> = (0..300).map(|i| (2 * i + 1, i)).collect();
+        check(&evens, &odds);
+        check(&odds, &evens);
+        assert_eq!(0, odds.inner_join(&evens).count());
+    }
+
+    #[test]
+    fn inner_joins_walk_the_smaller_map_and_yield_this_maps_keys() {
+        let names: OAHashMap<String, &str> =
+            (0..1_000).map(|i| (format!("user{i}"), "name")).collect();
+        let admins: OAHashMap<String, bool> =
+            [("user3".to_string(), true), ("ghost".to_string(), false)].into();
+
+        let joined: Vec<_> = names.inner_join(&admins).collect();
+        assert_eq!(1, joined.len());
+        let (key, _, &admin) = joined[0];
+        assert!(admin);
+        assert!(core::ptr::eq(key, names.get_key_value("user3").unwrap().0));
+        assert_eq!((0, Some(2)), names.inner_join(&admins).size_hint());
+        assert_eq!(
+            Some(2),
+            admins.inner_join(&names).size_hint().1,
+            "walks the smaller map from either side"
+        );
+    }
+}
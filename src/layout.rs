@@ -0,0 +1,197 @@
+//! A slot-by-slot picture of a map's table, for looking at how probing
+//! placed its entries.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+
+use crate::raw::RawTable;
+use crate::{probe, OAHashMap};
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Describes every slot of the table, which takes time linear in the
+    /// number of slots. For people to read while learning or debugging: what
+    /// it reports and how it prints may change in any release, so nothing
+    /// should parse it.
+    pub fn debug_layout(&self) -> TableLayout<'_, K> {
+        let slots = (0..self.table.num_slots())
+            .map(|index| slot_layout(&self.table, index, |hash| self.distance(hash, index)))
+            .collect();
+        let migrating = self.migration.as_ref().map_or_else(Vec::new, |migration| {
+            let table = migration.table();
+            (0..table.num_slots())
+                .map(|index| slot_layout(table, index, |hash| migration.distance(hash, index)))
+                .collect()
+        });
+
+        TableLayout {
+            slots,
+            migrating,
+            tombstones: self.cleared,
+        }
+    }
+}
+
+fn slot_layout<'a, K, V, A, F>(
+    table: &'a RawTable<K, V, A>,
+    index: usize,
+    distance: F,
+) -> SlotLayout<'a, K>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+    F: FnOnce(u64) -> usize,
+{
+    match table.get(index) {
+        Some(bucket) => SlotLayout::Occupied {
+            key: &bucket.key,
+            home: probe::starting_index(bucket.hash, table.num_slots() - 1),
+            distance: distance(bucket.hash),
+        },
+        None => SlotLayout::Empty,
+    }
+}
+
+/// What [`OAHashMap::debug_layout`] found. Its `{}` form prints a line per
+/// slot.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TableLayout<'a, K> {
+    pub slots: Vec<SlotLayout<'a, K>>,
+    /// The slots of the table an incremental resize is still emptying.
+    pub migrating: Vec<SlotLayout<'a, K>>,
+    /// As in [`MemoryStats::tombstones`](crate::MemoryStats::tombstones).
+    /// The table does not mark which slots those are: they read as empty,
+    /// and lookups under scattered probing walk over every empty slot
+    /// within reach alike.
+    pub tombstones: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlotLayout<'a, K> {
+    Empty,
+    Occupied {
+        key: &'a K,
+        /// The slot the key's probe sequence starts from.
+        home: usize,
+        /// How many steps along its probe sequence the key sits from `home`.
+        distance: usize,
+    },
+}
+
+impl<K> fmt::Display for TableLayout<'_, K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_slots(f, &self.slots)?;
+        if !self.migrating.is_empty() {
+            writeln!(f, "migrating:")?;
+            write_slots(f, &self.migrating)?;
+        }
+
+        write!(f, "{} tombstones", self.tombstones)
+    }
+}
+
+fn write_slots<K>(f: &mut fmt::Formatter<'_>, slots: &[SlotLayout<'_, K>]) -> fmt::Result
+where
+    K: fmt::Debug,
+{
+    let width = slots
+        .len()
+        .saturating_sub(1)
+        .checked_ilog10()
+        .map_or(1, |digits| digits as usize + 1);
+    for (index, slot) in slots.iter().enumerate() {
+        match slot {
+            SlotLayout::Empty => writeln!(f, "[{index:>width$}] EMPTY")?,
+            SlotLayout::Occupied {
+                key,
+                home,
+                distance,
+            } => writeln!(
+                f,
+                "[{index:>width$}] OCCUPIED key={key:?} home={home} dist={distance}"
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlotLayout;
+    use crate::test_util::keys_starting_at;
+    use crate::{OAHashMap, Probing};
+
+    #[test]
+    fn colliding_keys_are_laid_out_along_their_chain() {
+        let mut oa = OAHashMap::with_capacity(64);
+        let keys = keys_starting_at(&oa, 10, 3);
+        for &key in &keys {
+            oa.insert(key, ());
+        }
+
+        let layout = oa.debug_layout();
+        assert_eq!(oa.memory_stats().slots, layout.slots.len());
+        for (distance, key) in keys.iter().enumerate() {
+            assert_eq!(
+                SlotLayout::Occupied {
+                    key,
+                    home: 10,
+                    distance,
+                },
+                layout.slots[10 + distance]
+            );
+        }
+        assert_eq!(SlotLayout::Empty, layout.slots[13]);
+        assert!(layout.migrating.is_empty());
+
+        oa.remove(&keys[0]);
+        let layout = oa.debug_layout();
+        let SlotLayout::Occupied { key, distance, .. } = layout.slots[11] else {
+            panic!("Slot 11 emptied");
+        };
+        assert_eq!((&keys[2], 1), (key, distance));
+        assert_eq!(SlotLayout::Empty, layout.slots[12]);
+        assert_eq!(0, layout.tombstones);
+    }
+
+    #[test]
+    fn scattered_removals_leave_tombstones_that_read_as_empty() {
+        let mut oa = OAHashMap::with_capacity(64);
+        oa.set_probing(Probing::Quadratic);
+        let keys = keys_starting_at(&oa, 20, 2);
+        for &key in &keys {
+            oa.insert(key, key);
+        }
+        oa.remove(&keys[0]);
+
+        let layout = oa.debug_layout();
+        assert_eq!(SlotLayout::Empty, layout.slots[20]);
+        assert_eq!(1, layout.tombstones);
+        let second = layout
+            .slots
+            .iter()
+            .find(|slot| matches!(slot, SlotLayout::Occupied { key, .. } if **key == keys[1]));
+        assert!(matches!(
+            second,
+            Some(SlotLayout::Occupied {
+                home: 20,
+                distance: 1,
+                ..
+            })
+        ));
+
+        let printed = layout.to_string();
+        assert_eq!(layout.slots.len() + 1, printed.lines().count());
+    }
+}
@@ -1,206 +1,5764 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+pub mod array;
+#[cfg(feature = "alloc")]
+pub mod bimap;
+#[cfg(feature = "borsh")]
+mod borsh;
+#[cfg(feature = "alloc")]
+mod builder;
+#[cfg(feature = "bytemuck")]
+mod bytes;
+#[cfg(feature = "alloc")]
+mod counter;
+#[cfg(feature = "alloc")]
+mod diff;
+#[cfg(feature = "alloc")]
+mod entry;
+mod error;
+pub mod frozen;
+#[cfg(feature = "alloc")]
+mod group;
+#[cfg(feature = "alloc")]
+pub mod handle_map;
+mod hash;
+#[cfg(feature = "alloc")]
+pub mod index_map;
+#[cfg(feature = "alloc")]
+mod iter;
+#[cfg(feature = "alloc")]
+mod join;
+#[cfg(feature = "alloc")]
+mod layout;
+#[cfg(feature = "alloc")]
+pub mod lru;
+mod macros;
+#[cfg(feature = "alloc")]
+mod migrate;
+#[cfg(test)]
+mod model;
+#[cfg(feature = "alloc")]
+pub mod multi_map;
+#[cfg(feature = "alloc")]
+mod position_index;
+mod probe;
+mod raw;
+#[cfg(feature = "alloc")]
+mod raw_entry;
+#[cfg(feature = "rayon")]
+mod rayon;
+#[cfg(feature = "alloc")]
+mod read_only;
+#[cfg(feature = "rayon")]
+mod rehash;
+#[cfg(feature = "rkyv")]
+mod rkyv;
+#[cfg(feature = "alloc")]
+mod sample;
+#[cfg(feature = "alloc")]
+pub mod scoped;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "serde")]
+pub mod serde_seq;
+#[cfg(feature = "alloc")]
+pub mod set;
+#[cfg(feature = "std")]
+pub mod sharded;
+#[cfg(test)]
+mod std_parity;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "alloc")]
+mod trace;
+#[cfg(feature = "std")]
+pub mod ttl;
+#[cfg(feature = "zeroize")]
+mod zeroize;
+
+#[cfg(feature = "alloc")]
+use core::borrow::Borrow;
+#[cfg(feature = "alloc")]
+use core::convert::Infallible;
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::hash::BuildHasher;
+use core::hash::{Hash, Hasher};
+#[cfg(feature = "alloc")]
+use core::mem::ManuallyDrop;
+#[cfg(feature = "alloc")]
+use core::ops::Index;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+
+#[cfg(feature = "alloc")]
+use allocator_api2::alloc::{Allocator, Global};
+
+#[cfg(feature = "alloc")]
+use builder::Config;
+#[cfg(feature = "alloc")]
+use group::{BitMask, Group};
+#[cfg(feature = "alloc")]
+use migrate::Migration;
+#[cfg(feature = "alloc")]
+use probe::{DoubleHashing, Linear, ProbeSequence, Quadratic};
+#[cfg(feature = "alloc")]
+use raw::{RawIntoIter, RawTable};
+
+pub use array::ArrayOAHashMap;
+#[cfg(feature = "alloc")]
+pub use bimap::OABiMap;
+#[cfg(feature = "alloc")]
+pub use builder::OAHashMapBuilder;
+#[cfg(feature = "alloc")]
+pub use counter::Count;
+#[cfg(feature = "alloc")]
+pub use diff::{Change, MapDiff};
+#[cfg(feature = "alloc")]
+pub use entry::{Entry, EntryRef, OccupiedEntry, OccupiedError, VacantEntry, VacantEntryRef};
+pub use equivalent::Equivalent;
+#[cfg(feature = "bytemuck")]
+pub use error::DecodeError;
+pub use error::{
+    DuplicateKeyError, InvariantError, KeyNotFoundError, RekeyError, TryExtendError,
+    TryReserveError,
+};
+pub use frozen::StaticOAHashMap;
+#[cfg(feature = "alloc")]
+pub use handle_map::{Handle, OAHandleMap};
+pub use hash::{
+    DefaultHashBuilder, DeterministicHasher, DeterministicState, IdentityHasher, IdentityState,
+};
+#[cfg(feature = "alloc")]
+pub use index_map::OAIndexMap;
+#[cfg(feature = "alloc")]
+pub use join::{InnerJoin, LeftJoin};
+#[cfg(feature = "alloc")]
+pub use layout::{SlotLayout, TableLayout};
+#[cfg(feature = "alloc")]
+pub use lru::LruOAHashMap;
+#[cfg(feature = "alloc")]
+pub use multi_map::OAMultiMap;
+#[cfg(feature = "alloc")]
+pub use probe::Probing;
+
+/// A map of integer keys that are their own hashes.
+#[cfg(feature = "alloc")]
+pub type IntOAHashMap<K, V> = OAHashMap<K, V, IdentityState>;
+#[cfg(feature = "ahash")]
+pub type AHashOAHashMap<K, V> = OAHashMap<K, V, ahash::RandomState>;
+#[cfg(feature = "fxhash")]
+pub type FxOAHashMap<K, V> = OAHashMap<K, V, rustc_hash::FxBuildHasher>;
+#[cfg(feature = "alloc")]
+pub use iter::{
+    CursorMut, Drain, ExtractIf, IntoIter, IntoKeys, IntoValues, Iter, IterMut, Keys, Values,
+    ValuesMut,
+};
+#[cfg(feature = "alloc")]
+pub use raw_entry::{
+    RawEntryBuilder, RawEntryBuilderMut, RawEntryMut, RawOccupiedEntryMut, RawVacantEntryMut,
+};
+#[cfg(feature = "rayon")]
+pub use rayon::{IntoParIter, ParIter, ParIterMut, ParKeys, ParValues, ParValuesMut};
+#[cfg(feature = "alloc")]
+pub use read_only::ReadOnlyOAHashMap;
+#[cfg(feature = "rkyv")]
+pub use rkyv::ArchivedOAHashMap;
+#[cfg(feature = "alloc")]
+pub use scoped::ScopedOAHashMap;
+#[cfg(feature = "alloc")]
+pub use set::OAHashSet;
+#[cfg(feature = "std")]
+pub use sharded::ShardedOAHashMap;
+#[cfg(feature = "std")]
+pub use ttl::TtlOAHashMap;
+
+#[cfg(feature = "alloc")]
 const EXTEND_LIMIT: f32 = 0.6;
+#[cfg(feature = "alloc")]
 const INITIAL_CAPACITY: usize = 64;
+#[cfg(feature = "alloc")]
+const AUTO_SHRINK_DIVISOR: usize = 8;
+/// Scattered probing rebuilds in place once this fraction of the slots has
+/// been cleared since the last rebuild.
+#[cfg(feature = "alloc")]
+const CLEARED_DIVISOR: usize = 4;
+#[cfg(feature = "alloc")]
+const MAX_PROBE_LENGTH: usize = 64;
+/// Long probes only grow tables at least this fraction full, so keys whose
+/// hashes collide outright cannot grow the table without bound.
+#[cfg(feature = "alloc")]
+const LONG_PROBE_FILL_DIVISOR: usize = 8;
+/// Old slots visited per operation while an incremental resize is running.
+#[cfg(feature = "alloc")]
+const MIGRATION_STEP: usize = 8;
+/// Rebuilds of at least this many entries place them from several threads.
+#[cfg(feature = "rayon")]
+const PARALLEL_REHASH_THRESHOLD: usize = 1 << 17;
 
-pub struct OAHashMap<K, V>
+/// A hash map using open addressing with linear probing and Robin Hood
+/// insertion by default; see [`Probing`] for the alternatives.
+///
+/// Every occupied slot caches the 64-bit hash of its key, which costs eight
+/// bytes per slot on top of the pair but means keys are hashed only once:
+/// resizes reuse the cached hash and probes compare it before calling `Eq`.
+/// The keys and their hashes sit in one array and the values in another, so
+/// probes never load a value and large values do not lengthen their walk.
+///
+/// Alongside the slots sits one control byte per slot holding seven bits of
+/// the hash, so probes reject most occupied slots without loading them.
+///
+/// The slot count is always zero or a power of two, so probes wrap with a
+/// mask instead of a division. It never exceeds the largest power of two
+/// whose slots fit in `isize::MAX` bytes: growing past that panics with
+/// "Capacity overflow", and the `try_` methods return
+/// [`TryReserveError::CapacityOverflow`] instead.
+///
+/// An insert takes the slot of the first entry sitting closer to its own
+/// starting slot and shifts the rest of the run along, and a removal shifts
+/// the entries after it back. No tombstones are ever left behind, so probe
+/// lengths depend only on the live entries however long the map churns.
+///
+/// With the `zeroize` feature, the table zeroes a slot's bytes once its
+/// entry is removed, moved along or dropped, and zeroes its whole buffer
+/// before freeing it, resizes included. That covers the table's own memory
+/// only. Memory the keys and values own, such as a `Vec`'s buffer, is freed
+/// as their `Drop` leaves it, and an entry handed back by `remove` or a
+/// drain is copied out before its slot is wiped. For keys and values that
+/// implement `Zeroize`, so does the map: zeroizing it, or dropping it inside
+/// `Zeroizing`, runs theirs on every entry before dropping it.
+#[cfg(feature = "alloc")]
+pub struct OAHashMap<K, V, S = DefaultHashBuilder, A = Global>
 where
-    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    table: RawTable<K, V, A>,
+    mask: usize,
+    hash_builder: S,
+    len: usize,
+    resizes: usize,
+    auto_shrink: bool,
+    probing: Probing,
+    /// The most steps any entry placed since the last rebuild sits from its
+    /// starting slot. Only maintained for scattered probing, where it bounds
+    /// every lookup.
+    max_distance: usize,
+    /// Slots scattered probing has emptied since the last rebuild. Lookups
+    /// keep walking as far as `max_distance` over them, like tombstones.
+    cleared: usize,
+    forced_resizes: usize,
+    max_probe_length: usize,
+    incremental_resize: bool,
+    config: Config,
+    /// Set for the index tables other maps keep, which file each entry under
+    /// a hash other than its key's, so the `check-hashes` feature skips them.
+    foreign_hashes: bool,
+    /// The slot [`pop`](Self::pop) starts looking from, so a run of pops
+    /// walks the table once rather than from the start every time.
+    pop_from: usize,
+    /// The table an incremental resize is still moving entries out of. Its
+    /// entries count towards `len`.
+    migration: Option<Migration<K, V, A>>,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> OAHashMap<K, V> {
+    pub const fn new() -> Self {
+        Self::new_in(Global)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, Global)
+    }
+
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, TryReserveError> {
+        Self::try_with_capacity_in(capacity, Global)
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V, A> OAHashMap<K, V, DefaultHashBuilder, A>
+where
+    A: Allocator + Clone,
 {
-    buffer: Vec<Option<Entry<K, V>>>,
+    pub const fn new_in(alloc: A) -> Self {
+        Self::with_hasher_in(DefaultHashBuilder::new(), alloc)
+    }
+
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self::with_capacity_and_hasher_in(capacity, DefaultHashBuilder::new(), alloc)
+    }
+
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, TryReserveError> {
+        Self::try_with_capacity_and_hasher_in(capacity, DefaultHashBuilder::new(), alloc)
+    }
 }
 
-impl<K, V> OAHashMap<K, V>
+#[cfg(feature = "alloc")]
+impl<K, V> OAHashMap<K, V, DeterministicState> {
+    /// A map hashing with [`DeterministicState`], which behaves the same on
+    /// every run but gives up resistance to HashDoS.
+    pub const fn with_seed(seed: u64) -> Self {
+        Self::with_hasher(DeterministicState::new(seed))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S> OAHashMap<K, V, S>
 where
-    K: Hash + Eq,
+    S: BuildHasher,
 {
-    pub fn new() -> Self {
-        Self::default()
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self::with_hasher_in(hash_builder, Global)
     }
 
-    pub fn insert(&mut self, key: K, value: V) {
-        if self.needs_extending() {
-            self.extend();
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher_in(capacity, hash_builder, Global)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub const fn with_hasher_in(hash_builder: S, alloc: A) -> Self {
+        Self {
+            table: RawTable::new_in(alloc),
+            mask: 0,
+            hash_builder,
+            len: 0,
+            resizes: 0,
+            auto_shrink: false,
+            probing: Probing::Linear,
+            max_distance: 0,
+            cleared: 0,
+            forced_resizes: 0,
+            max_probe_length: MAX_PROBE_LENGTH,
+            incremental_resize: false,
+            config: Config::DEFAULT,
+            foreign_hashes: false,
+            pop_from: 0,
+            migration: None,
+        }
+    }
+
+    pub fn with_capacity_and_hasher_in(capacity: usize, hash_builder: S, alloc: A) -> Self {
+        Self::try_with_capacity_and_hasher_in(capacity, hash_builder, alloc)
+            .unwrap_or_else(|error| error.handle())
+    }
+
+    pub fn try_with_capacity_and_hasher_in(
+        capacity: usize,
+        hash_builder: S,
+        alloc: A,
+    ) -> Result<Self, TryReserveError> {
+        Self::try_with_capacity_and_config(capacity, Config::DEFAULT, hash_builder, alloc)
+    }
+
+    fn try_with_capacity_and_config(
+        capacity: usize,
+        config: Config,
+        hash_builder: S,
+        alloc: A,
+    ) -> Result<Self, TryReserveError> {
+        if capacity == 0 {
+            let mut map = Self::with_hasher_in(hash_builder, alloc);
+            map.config = config;
+            return Ok(map);
         }
 
-        let entry = Entry::new(key, value);
-        self.insert_unchecked(entry);
+        let slots = config.try_slots_for(capacity, RawTable::<K, V, A>::MAX_SLOTS)?;
+
+        Self::try_with_slots(slots, config, hash_builder, alloc)
     }
 
-    pub fn search(&self, key: &K) -> Option<&V> {
-        self.find_index(key)
-            .map(|index| &self.buffer[index].as_ref().unwrap().value)
+    fn try_with_slots(
+        slots: usize,
+        config: Config,
+        hash_builder: S,
+        alloc: A,
+    ) -> Result<Self, TryReserveError> {
+        Ok(Self {
+            table: try_empty_table(slots, alloc)?,
+            mask: slots.saturating_sub(1),
+            hash_builder,
+            len: 0,
+            resizes: 0,
+            auto_shrink: false,
+            probing: Probing::Linear,
+            max_distance: 0,
+            cleared: 0,
+            forced_resizes: 0,
+            max_probe_length: MAX_PROBE_LENGTH,
+            incremental_resize: false,
+            config,
+            foreign_hashes: false,
+            pop_from: 0,
+            migration: None,
+        })
     }
 
-    pub fn delete(&mut self, key: &K) {
-        let mut index = self.starting_index(key);
-        while let Some(entry) = &self.buffer[index] {
-            if &entry.key == key {
-                std::mem::take(&mut self.buffer[index]);
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
 
-                return;
-            }
+    /// Marks the map as an index table, whose entries are filed through the
+    /// raw entry API under hashes other than their keys' own.
+    pub(crate) const fn with_foreign_hashes(mut self) -> Self {
+        self.foreign_hashes = true;
+        self
+    }
+
+    pub fn allocator(&self) -> &A {
+        self.table.allocator()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let migrating = self.migration.as_ref().map(|m| m.table().iter());
+
+        Iter::new(self.table.iter(), migrating.unwrap_or_default(), self.len)
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let migrating = self.migration.as_mut().map(|m| m.table_mut().iter_mut());
+
+        IterMut::new(
+            self.table.iter_mut(),
+            migrating.unwrap_or_default(),
+            self.len,
+        )
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys::new(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values::new(self.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut::new(self.iter_mut())
+    }
+
+    /// Hands `f` every entry to change its value in place.
+    pub fn transform_values<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V),
+    {
+        for (key, value) in self.iter_mut() {
+            f(key, value);
+        }
+    }
 
-            index += 1;
+    /// The map is empty afterwards even if dropping an entry panics; the
+    /// entries not yet dropped are then leaked.
+    pub fn clear(&mut self) {
+        self.max_distance = 0;
+        self.cleared = 0;
+        let migration = self.migration.take();
+        let len = core::mem::replace(&mut self.len, 0);
+        if len > 0 {
+            self.table.clear();
         }
+        drop(migration);
     }
 
-    fn needs_extending(&self) -> bool {
-        let number_of_elements = self
-            .buffer
-            .iter()
-            .filter(|entry| entry.as_ref().is_some_and(|entry| !entry.is_deleted))
-            .count();
-        let percentage = number_of_elements as f32 / self.buffer.capacity() as f32;
+    pub fn capacity(&self) -> usize {
+        (self.table.num_slots() as f32 * self.config.max_load_factor) as usize
+    }
+
+    pub fn max_load_factor(&self) -> f32 {
+        self.config.max_load_factor
+    }
+
+    /// The entries a table allocated from nothing has room for.
+    pub fn initial_capacity(&self) -> usize {
+        (self.config.initial_slots as f32 * self.config.max_load_factor) as usize
+    }
+
+    pub fn growth_factor(&self) -> f32 {
+        self.config.growth_factor
+    }
+
+    pub fn max_entries(&self) -> Option<usize> {
+        Some(self.config.max_entries).filter(|&max| max != usize::MAX)
+    }
 
-        percentage > EXTEND_LIMIT
+    /// Heap bytes owned by the table itself, not counting anything the keys
+    /// and values allocate.
+    pub fn allocated_bytes(&self) -> usize {
+        let migrating = self.migration.as_ref().map(|m| m.table().allocated_bytes());
+
+        self.table.allocated_bytes() + migrating.unwrap_or(0)
     }
 
-    fn extend(&mut self) {
-        let current_capacity = self.buffer.capacity();
+    pub fn memory_stats(&self) -> MemoryStats {
+        let slot_bytes = RawTable::<K, V, A>::SLOT_BYTES;
+        let slots =
+            self.table.num_slots() + self.migration.as_ref().map_or(0, |m| m.table().num_slots());
 
-        if current_capacity == usize::MAX {
-            panic!("Reached max capacity");
+        MemoryStats {
+            slot_bytes,
+            slots,
+            bitmap_bytes: self.allocated_bytes() - slot_bytes * slots,
+            live: self.len,
+            tombstones: self.cleared,
+            probe_limit: match self.probing {
+                Probing::Linear => 0,
+                _ => self.max_distance + 1,
+            },
+            forced_resizes: self.forced_resizes,
         }
+    }
+
+    pub fn probing(&self) -> Probing {
+        self.probing
+    }
 
-        let new_capacity = current_capacity.checked_mul(2);
-        let new_capacity = new_capacity.unwrap_or(usize::MAX);
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
 
-        let mut new_buffer: Vec<Option<Entry<K, V>>> = Vec::with_capacity(new_capacity);
-        for _ in 0..new_capacity {
-            new_buffer.push(None);
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Only a new key can grow the table; overwriting a value never
+    /// allocates.
+    ///
+    /// # Panics
+    ///
+    /// If `key` is new and the map already holds its
+    /// [`max_entries`](OAHashMapBuilder::max_entries).
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.insert_within_limit(key, value) {
+            Ok(old) => old,
+            Err(_) => self.limit_reached(),
         }
-        let old_buffer = std::mem::replace(&mut self.buffer, new_buffer);
-        for entry in old_buffer.into_iter().flatten() {
-            self.insert_unchecked(entry);
+    }
+
+    /// Like [`insert`](Self::insert), returning the value now stored for
+    /// `key` from the slot the insert used, and dropping any it replaced.
+    pub fn insert_mut(&mut self, key: K, value: V) -> &mut V {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |k| *k == key);
+        let (probe, walked) = self.probe_walk(hash, |k| *k == key);
+        self.trace_probe("insert", walked);
+        let index = match probe {
+            Ok(index) => {
+                *self.table.value_mut(index).unwrap() = value;
+
+                index
+            }
+            Err(_) if self.len >= self.config.max_entries => self.limit_reached(),
+            Err(index) => {
+                let index = if self.reserve_one() {
+                    self.insertion_index(hash)
+                } else {
+                    index
+                };
+
+                self.insert_at(index, Bucket::new(hash, key, value))
+            }
+        };
+
+        self.table.value_mut(index).unwrap()
+    }
+
+    /// Like [`insert`](Self::insert), but hands the pair back instead of
+    /// inserting a new key into a map that already holds its
+    /// [`max_entries`](OAHashMapBuilder::max_entries). Overwrites always
+    /// succeed.
+    pub fn insert_within_limit(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |k| *k == key);
+        let (probe, walked) = self.probe_walk(hash, |k| *k == key);
+        self.trace_probe("insert", walked);
+        match probe {
+            Ok(index) => {
+                let slot = self.table.value_mut(index).unwrap();
+
+                Ok(Some(core::mem::replace(slot, value)))
+            }
+            Err(_) if self.len >= self.config.max_entries => Err((key, value)),
+            Err(index) => {
+                let index = if self.reserve_one() {
+                    self.insertion_index(hash)
+                } else {
+                    index
+                };
+                self.insert_at(index, Bucket::new(hash, key, value));
+
+                Ok(None)
+            }
         }
     }
 
-    fn insert_unchecked(&mut self, entry: Entry<K, V>) {
-        if let Some(index) = self.find_index(&entry.key) {
-            self.buffer[index] = Some(entry);
+    pub fn insert_within_capacity(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |k| *k == key);
+        let (probe, walked) = self.probe_walk(hash, |k| *k == key);
+        self.trace_probe("insert", walked);
+        match probe {
+            Ok(index) => {
+                let slot = self.table.value_mut(index).unwrap();
 
-            return;
+                Ok(Some(core::mem::replace(slot, value)))
+            }
+            Err(_)
+                if self.len >= self.capacity()
+                    || self.len >= self.config.max_entries
+                    || self.is_saturated() =>
+            {
+                Err((key, value))
+            }
+            Err(index) => match self.try_occupy(index, Bucket::new(hash, key, value)) {
+                Ok(_) => Ok(None),
+                Err(bucket) => Err(bucket.into_pair()),
+            },
         }
+    }
 
-        let mut index = self.starting_index(&entry);
-        while let Some(existing_entry) = &self.buffer[index] {
-            if existing_entry.is_deleted {
-                self.buffer[index] = Some(entry);
+    /// Like [`insert`](Self::insert), but where the key is already present
+    /// the new key replaces the stored one too, and the old pair comes back.
+    pub fn replace(&mut self, key: K, value: V) -> Option<(K, V)> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Some(entry.replace_entry(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
 
-                return;
+                None
             }
+        }
+    }
+
+    /// Inserts a key the caller knows is not in the map, placing it without
+    /// comparing it to any key on the way, for bulk loads of distinct keys.
+    ///
+    /// Inserting a key that is already present is a logic error: the map
+    /// then holds both entries, and which one lookups, overwrites and
+    /// removals reach is unspecified, as is `len`. It stays memory safe.
+    pub fn insert_unique_unchecked(&mut self, key: K, value: V) -> (&K, &mut V) {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |_| false);
+        let index = self.insert_new(Bucket::new(hash, key, value));
+        let (hashed, value) = self.table.get_pair_mut(index).unwrap();
+
+        (&hashed.key, value)
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, A> {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |k| *k == key);
+        match self.probe_with(hash, |k| *k == key) {
+            Ok(index) => Entry::Occupied(OccupiedEntry::new(self, index, Some(key))),
+            Err(index) => Entry::Vacant(VacantEntry::new(self, hash, key, index)),
+        }
+    }
 
-            index += 1;
+    pub fn try_insert(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Result<&mut V, OccupiedError<'_, K, V, S, A>> {
+        match self.entry(key) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+            Entry::Vacant(entry) => Ok(entry.insert(value)),
         }
+    }
 
-        self.buffer[index] = Some(entry);
+    /// Drops `value` if the key is already present.
+    pub fn get_or_insert(&mut self, key: K, value: V) -> &mut V {
+        self.entry(key).or_insert(value)
     }
 
-    fn starting_index<H>(&self, hashable: &H) -> usize
+    /// Only calls `default` if the key is absent.
+    pub fn get_or_insert_with<F>(&mut self, key: K, default: F) -> &mut V
     where
-        H: Hash,
+        F: FnOnce() -> V,
     {
-        let h = calculate_hash(hashable);
+        self.entry(key).or_insert_with(default)
+    }
 
-        h as usize % self.buffer.capacity()
+    pub fn get_or_insert_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.entry(key).or_default()
     }
 
-    fn find_index(&self, key: &K) -> Option<usize> {
-        let mut index = self.starting_index(key);
-        while let Some(entry) = &self.buffer[index] {
-            if &entry.key == key {
-                return Some(index);
-            }
+    pub fn get_or_try_insert_with<F, E>(&mut self, key: K, default: F) -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        self.entry(key).or_try_insert_with(default)
+    }
 
-            index += 1;
+    pub fn entry_ref<'b, Q>(&mut self, key: &'b Q) -> EntryRef<'_, 'b, K, Q, V, S, A>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.make_hash(key);
+        self.settle(hash, |k| k.borrow() == key);
+        match self.probe_with(hash, |k| k.borrow() == key) {
+            Ok(index) => EntryRef::Occupied(OccupiedEntry::new(self, index, None)),
+            Err(index) => EntryRef::Vacant(VacantEntryRef::new(self, hash, key, index)),
         }
+    }
 
-        None
+    pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, S, A> {
+        RawEntryBuilder::new(self)
     }
-}
 
-impl<K, V> Default for OAHashMap<K, V>
-where
-    K: Hash + Eq,
-{
-    fn default() -> Self {
-        let mut buffer = Vec::with_capacity(INITIAL_CAPACITY);
-        for _ in 0..INITIAL_CAPACITY {
-            buffer.push(None);
+    /// Creates a builder for looking up entries by a caller-supplied hash.
+    ///
+    /// Hashes passed to the `*_nocheck` and `from_hash` methods should come
+    /// from [`OAHashMap::hash_key`]. A wrong hash can only make lookups miss
+    /// or let a key be inserted twice; it never causes memory unsafety.
+    pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, S, A> {
+        RawEntryBuilderMut::new(self)
+    }
+
+    pub fn hash_key<Q>(&self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized,
+    {
+        self.make_hash(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find_bucket(key).map(|(_, value)| value)
+    }
+
+    /// Looks up several keys at once, returning the values in input order.
+    ///
+    /// All keys are hashed and their first slots prefetched before any probe
+    /// runs, so cache misses on large tables overlap instead of queueing.
+    pub fn get_batch<'a, Q>(&'a self, keys: &[&Q]) -> alloc::vec::Vec<Option<&'a V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hashes: alloc::vec::Vec<u64> = keys.iter().map(|key| self.make_hash(*key)).collect();
+        if self.table.num_slots() != 0 {
+            let (ctrl, keys, _) = self.table.parts();
+            for &hash in &hashes {
+                let index = self.starting_index(hash);
+                prefetch(&ctrl[index]);
+                prefetch(&keys[index]);
+            }
         }
 
-        Self { buffer }
+        keys.iter()
+            .zip(hashes)
+            .map(|(key, hash)| {
+                self.find_bucket_with(hash, |k| key.equivalent(k))
+                    .map(|(_, value)| value)
+            })
+            .collect()
     }
-}
 
-struct Entry<K, V>
-where
-    K: Hash + Eq,
-{
-    key: K,
-    value: V,
-    is_deleted: bool,
-}
+    /// Alias of [`OAHashMap::get`], kept for existing callers.
+    pub fn search<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key)
+    }
 
-impl<K, V> Entry<K, V>
-where
-    K: Hash + Eq,
-{
-    fn new(key: K, value: V) -> Self {
-        Self {
-            key,
-            value,
-            is_deleted: false,
+    /// Like [`get`](Self::get), with a missing key as an error that names
+    /// it, for callers that report it rather than handle it.
+    pub fn try_get<'a, 'k, Q>(&'a self, key: &'k Q) -> Result<&'a V, KeyNotFoundError<'k, Q>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key).ok_or(KeyNotFoundError::new(key))
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (hashed, value) = self.find_bucket(key)?;
+
+        Some((&hashed.key, value))
+    }
+
+    pub fn get_key_value_mut<Q>(&mut self, key: &Q) -> Option<(&K, &mut V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find_index_mut(key)?;
+        let (hashed, value) = self.table.get_pair_mut(index).unwrap();
+
+        Some((&hashed.key, value))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find_bucket(key).is_some()
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find_index_mut(key)
+            .map(|index| self.table.value_mut(index).unwrap())
+    }
+
+    /// Like [`get_mut`](Self::get_mut), with a missing key as an error that
+    /// names it.
+    pub fn try_get_mut<'a, 'k, Q>(
+        &'a mut self,
+        key: &'k Q,
+    ) -> Result<&'a mut V, KeyNotFoundError<'k, Q>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_mut(key).ok_or(KeyNotFoundError::new(key))
+    }
+
+    /// Returns mutable references to the values of several keys at once.
+    ///
+    /// Missing keys yield `None`. Panics if two of the keys refer to the same
+    /// entry.
+    pub fn get_disjoint_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> [Option<&mut V>; N]
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        for key in keys {
+            self.settle(self.make_hash(key), |k| key.equivalent(k));
+        }
+        let indices = keys.map(|key| self.find_index(key));
+        for (i, index) in indices.iter().enumerate() {
+            if index.is_some() && indices[..i].contains(index) {
+                panic!("duplicate keys passed to get_disjoint_mut");
+            }
         }
+
+        // SAFETY: every index is in bounds and no index appears twice.
+        unsafe { self.values_at_mut(indices) }
     }
-}
 
-impl<K, V> Hash for Entry<K, V>
-where
-    K: Hash + Eq,
-{
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.key.hash(state)
+    /// Like [`OAHashMap::get_disjoint_mut`] without the duplicate check.
+    ///
+    /// # Safety
+    ///
+    /// No two of the keys that are present may refer to the same entry, or
+    /// the returned references alias.
+    pub unsafe fn get_disjoint_unchecked_mut<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> [Option<&mut V>; N]
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        for key in keys {
+            self.settle(self.make_hash(key), |k| key.equivalent(k));
+        }
+        let indices = keys.map(|key| self.find_index(key));
+
+        // SAFETY: the caller guarantees the found indices are distinct.
+        unsafe { self.values_at_mut(indices) }
     }
-}
 
-fn calculate_hash<H>(hashable: &H) -> u64
-where
-    H: Hash,
-{
-    let mut hasher = DefaultHasher::new();
-    hashable.hash(&mut hasher);
+    /// # Safety
+    ///
+    /// The indices must be in bounds, point at occupied slots and be distinct.
+    unsafe fn values_at_mut<const N: usize>(
+        &mut self,
+        indices: [Option<usize>; N],
+    ) -> [Option<&mut V>; N] {
+        let values = self.table.values_ptr();
 
-    hasher.finish()
-}
+        indices.map(|index| {
+            index.map(|index| {
+                // SAFETY: the slots are distinct and occupied, so the
+                // references are disjoint and initialized.
+                unsafe { &mut *values.add(index) }
+            })
+        })
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::OAHashMap;
+    /// Removes the key, discarding the value. Prefer [`OAHashMap::remove`].
+    pub fn delete<Q>(&mut self, key: &Q)
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_entry(key);
+    }
 
-    #[test]
-    fn basic() {
-        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
-        oa.insert(1, 10);
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
 
-        assert_eq!(Some(&10), oa.search(&1));
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.find_index_mut(key)?;
 
-        oa.delete(&1);
+        let bucket = self.take_bucket(index);
+        self.shrink_if_sparse();
 
-        assert_eq!(None, oa.search(&1));
+        Some(bucket.into_pair())
+    }
 
-        oa.insert(1, 10);
-        oa.insert(1, 20);
+    /// Hands `f` the value held for `key`, taken out, and stores what it
+    /// returns: `Some` inserts or updates, `None` removes or leaves the key
+    /// absent. Returns the stored value, if any. The key is looked up once;
+    /// where an entry already holds it, or nothing is inserted, `key` is
+    /// dropped.
+    pub fn compute<F>(&mut self, key: K, f: F) -> Option<&mut V>
+    where
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        let hash = self.make_hash(&key);
+        self.settle(hash, |k| *k == key);
+        let index = match self.probe_with(hash, |k| *k == key) {
+            Ok(index) => index,
+            Err(index) => return Some(VacantEntry::new(self, hash, key, index).insert(f(None)?)),
+        };
 
-        assert_eq!(Some(&20), oa.search(&1));
+        let bucket = self.table.take(index).unwrap();
+        // The value belongs to `f` while it runs, so if it unwinds, or
+        // returns nothing, the slot is closed up as a removal would.
+        let guard = HoleGuard { map: self, index };
+        let Some(value) = f(Some(bucket.value)) else {
+            drop(guard);
+            self.shrink_if_sparse();
+            return None;
+        };
+        core::mem::forget(guard);
+        self.table
+            .insert(index, Bucket::new(hash, bucket.key, value));
 
-        for i in 0..100 {
-            oa.insert(i, i);
+        self.table.value_mut(index)
+    }
+
+    /// Removes the entry for `key` only if `pred` accepts it, finding it
+    /// once. Returns `None` both when the key is absent and when `pred`
+    /// declines, which leaves the entry as it was.
+    pub fn remove_if<Q, F>(&mut self, key: &Q, pred: F) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&K, &V) -> bool,
+    {
+        self.remove_entry_if(key, pred).map(|(_, value)| value)
+    }
+
+    /// Like [`remove_if`](Self::remove_if), returning the stored key too.
+    pub fn remove_entry_if<Q, F>(&mut self, key: &Q, pred: F) -> Option<(K, V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&K, &V) -> bool,
+    {
+        let index = self.find_index_mut(key)?;
+        let (hashed, value) = self.table.get_pair(index).unwrap();
+        if !pred(&hashed.key, value) {
+            return None;
         }
 
-        for i in 0..100 {
+        let bucket = self.take_bucket(index);
+        self.shrink_if_sparse();
+
+        Some(bucket.into_pair())
+    }
+
+    /// Moves the value under `old` to the key `new`, dropping the old key.
+    /// Changing a key to itself does nothing. Fails, leaving the map as it
+    /// was and handing `new` back, if no entry has `old` or another already
+    /// has `new`.
+    pub fn change_key<Q>(&mut self, old: &Q, new: K) -> Result<(), RekeyError<K>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.rekey(old, new, false).map(|_| ())
+    }
+
+    /// Like [`change_key`](Self::change_key), replacing the value of any
+    /// other entry that has `new` and returning it.
+    pub fn change_key_replacing<Q>(&mut self, old: &Q, new: K) -> Result<Option<V>, RekeyError<K>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.rekey(old, new, true)
+    }
+
+    fn rekey<Q>(&mut self, old: &Q, new: K, replace: bool) -> Result<Option<V>, RekeyError<K>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        // Settle `new` first: settling `old` cannot move it back, but could
+        // shift it within the table.
+        let hash = self.make_hash(&new);
+        self.settle(hash, |key| *key == new);
+        let Some(index) = self.find_index_mut(old) else {
+            return Err(RekeyError::NotFound(new));
+        };
+        let existing = self.find_index_with(hash, |key| *key == new);
+        match existing {
+            Some(other) if other == index => return Ok(None),
+            Some(_) if !replace => return Err(RekeyError::Occupied(new)),
+            _ => {}
+        }
+
+        let value = self.take_bucket(index).value;
+        if existing.is_none() {
+            self.insert_new(Bucket::new(hash, new, value));
+            return Ok(None);
+        }
+
+        // Taking the old entry may have shifted the other one back.
+        let other = self.find_index_with(hash, |key| *key == new).unwrap();
+        let displaced = core::mem::replace(self.table.value_mut(other).unwrap(), value);
+        self.shrink_if_sparse();
+
+        Ok(Some(displaced))
+    }
+
+    /// Removes and returns some entry, whichever is quickest to reach. Each
+    /// pop looks on from where the last one found its entry, so popping a
+    /// map empty, even with inserts in between, does not walk the table
+    /// from the start every time.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        // An entry an incremental resize has not moved yet is taken straight
+        // from the old table rather than moved first.
+        let mut budget = usize::MAX;
+        if let Some((index, bucket)) = self
+            .migration
+            .as_mut()
+            .and_then(|m| m.next_bucket(&mut budget))
+        {
+            self.len -= 1;
+            self.check_hash(self.table.num_slots() + index, &bucket.key, bucket.hash);
+            self.drop_finished_migration();
+
+            return Some(bucket.into_pair());
+        }
+
+        let index = self
+            .table
+            .next_full(self.pop_from)
+            .or_else(|| self.table.next_full(0))?;
+        // A removal only shifts later entries back into the slot, so the
+        // next pop can start from it.
+        self.pop_from = index;
+        let bucket = self.take_bucket(index);
+        self.shrink_if_sparse();
+
+        Some(bucket.into_pair())
+    }
+
+    pub fn into_keys(self) -> IntoKeys<K, V, A> {
+        IntoKeys::new(self.into_iter())
+    }
+
+    pub fn into_values(self) -> IntoValues<K, V, A> {
+        IntoValues::new(self.into_iter())
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, K, V, S, A> {
+        self.finish_migration();
+
+        Drain::new(self)
+    }
+
+    /// Moves every entry of `other` into this map, reserving room for all of
+    /// them first so the map grows at most once. Where both maps hold a key,
+    /// `other`'s value replaces this map's, as inserting it would. `other` is
+    /// left empty with its table kept for reuse.
+    pub fn append(&mut self, other: &mut Self) {
+        self.reserve(other.len);
+        for (key, value) in other.drain() {
+            self.insert(key, value);
+        }
+    }
+
+    /// Moves every entry of `other` into this map. Where both maps hold a
+    /// key, `f` gets it with this map's value and then `other`'s, and the
+    /// map keeps what it returns. Each key is looked up once.
+    pub fn merge_with<F>(&mut self, other: Self, mut f: F)
+    where
+        F: FnMut(&K, V, V) -> V,
+    {
+        self.reserve(other.len);
+        for (key, value) in other {
+            let hash = self.make_hash(&key);
+            if let Err((index, value)) = self.combine(hash, &key, value, &mut f) {
+                VacantEntry::new(self, hash, key, index).insert(value);
+            }
+        }
+    }
+
+    /// Like [`merge_with`](Self::merge_with), cloning `other`'s values, and
+    /// its keys where this map does not hold them yet.
+    pub fn merge_from<F>(&mut self, other: &Self, mut f: F)
+    where
+        K: Clone,
+        V: Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        self.reserve(other.len);
+        for (key, value) in other {
+            let hash = self.make_hash(key);
+            if let Err((index, value)) = self.combine(hash, key, value.clone(), &mut f) {
+                VacantEntry::new(self, hash, key.clone(), index).insert(value);
+            }
+        }
+    }
+
+    /// Collects `iter` into a map, failing on the first key it repeats
+    /// rather than keeping the last value as [`FromIterator`] does. Sized
+    /// from the iterator's lower bound, and each key is looked up once.
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, DuplicateKeyError<K, V>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        S: Default,
+        A: Default,
+    {
+        let iter = iter.into_iter();
+        let mut map =
+            Self::with_capacity_and_hasher_in(iter.size_hint().0, S::default(), A::default());
+        map.insert_distinct(iter)?;
+
+        Ok(map)
+    }
+
+    /// Inserts every entry of `iter` if no key repeats, within `iter` or a
+    /// key the map already holds, reporting the first repeat in `iter`'s
+    /// order. The batch is gathered in a table of its own first, and its
+    /// entries move in with their cached hashes, so on error the map is left
+    /// as it was. So it is if the batch would take the map past its
+    /// [`max_entries`](OAHashMapBuilder::max_entries).
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<(), TryExtendError<K, V>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        S: Clone,
+    {
+        let iter = iter.into_iter();
+        let mut batch = self.empty_like(iter.size_hint().0);
+        for (key, value) in iter {
+            let hash = self.make_hash(&key);
+            if self.find_bucket_with(hash, |k| *k == key).is_some() {
+                return Err(DuplicateKeyError {
+                    key,
+                    first: None,
+                    second: value,
+                }
+                .into());
+            }
+            match batch.raw_entry_mut().from_key_hashed_nocheck(hash, &key) {
+                RawEntryMut::Occupied(entry) => {
+                    let (key, first) = entry.remove_entry();
+                    return Err(DuplicateKeyError {
+                        key,
+                        first: Some(first),
+                        second: value,
+                    }
+                    .into());
+                }
+                RawEntryMut::Vacant(entry) => {
+                    entry.insert_hashed_nocheck(hash, key, value);
+                }
+            }
+        }
+        batch.finish_migration();
+        if batch.len > self.config.max_entries.saturating_sub(self.len) {
+            return Err(TryExtendError::LimitReached {
+                max_entries: self.config.max_entries,
+            });
+        }
+
+        self.reserve(batch.len);
+        let mut next = batch.table.next_full(0);
+        while let Some(index) = next {
+            self.insert_new(batch.table.take(index).unwrap());
+            next = batch.table.next_full(index);
+        }
+
+        Ok(())
+    }
+
+    fn insert_distinct<I>(&mut self, iter: I) -> Result<(), DuplicateKeyError<K, V>>
+    where
+        I: Iterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            match self.entry(key) {
+                Entry::Occupied(entry) => {
+                    let (key, first) = entry.remove_entry();
+                    return Err(DuplicateKeyError {
+                        key,
+                        first: Some(first),
+                        second: value,
+                    });
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the entries `f` picks and returns them as a map of their own,
+    /// with this map's settings and a table grown only as far as they need.
+    /// Entries move with their cached hashes, so no key is hashed again.
+    pub fn split_off_by<F>(&mut self, mut f: F) -> Self
+    where
+        S: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.finish_migration();
+        let mut matching = self.empty_like(0);
+        let mut extracted = ExtractIf::new(self, |key, value| f(key, value));
+        while let Some(bucket) = extracted.next_bucket() {
+            matching.insert_new(bucket);
+        }
+        self.shrink_if_sparse();
+
+        matching
+    }
+
+    /// Splits the map into the entries `f` picks and the rest, each in a
+    /// table sized for it.
+    pub fn partition<F>(mut self, f: F) -> (Self, Self)
+    where
+        S: Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let matching = self.split_off_by(f);
+        self.shrink_to_fit();
+
+        (matching, self)
+    }
+
+    /// Replaces every value with what `f` makes of it. Each entry keeps its
+    /// slot and cached hash, so no key is hashed or probed for again.
+    pub fn map_values<V2, F>(self, mut f: F) -> OAHashMap<K, V2, S, A>
+    where
+        F: FnMut(&K, V) -> V2,
+    {
+        match self.try_map_values(|key, value| Ok::<_, Infallible>(f(key, value))) {
+            Ok(map) => map,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like [`map_values`](Self::map_values), stopping at the first error
+    /// `f` returns. The entries already mapped and those not reached yet are
+    /// dropped along with the map.
+    pub fn try_map_values<V2, E, F>(mut self, mut f: F) -> Result<OAHashMap<K, V2, S, A>, E>
+    where
+        F: FnMut(&K, V) -> Result<V2, E>,
+    {
+        self.finish_migration();
+        let mut table = RawTable::try_with_slots(self.table.num_slots(), self.allocator().clone())
+            .unwrap_or_else(|error| error.handle());
+        let mut next = self.table.next_full(0);
+        while let Some(index) = next {
+            let bucket = self.table.take(index).unwrap();
+            let value = f(&bucket.key, bucket.value)?;
+            table.insert(index, Bucket::new(bucket.hash, bucket.key, value));
+            next = self.table.next_full(index);
+        }
+
+        Ok(OAHashMap {
+            table,
+            mask: self.mask,
+            hash_builder: self.hash_builder,
+            len: self.len,
+            resizes: self.resizes,
+            auto_shrink: self.auto_shrink,
+            probing: self.probing,
+            max_distance: self.max_distance,
+            cleared: self.cleared,
+            forced_resizes: self.forced_resizes,
+            max_probe_length: self.max_probe_length,
+            incremental_resize: self.incremental_resize,
+            config: self.config,
+            foreign_hashes: self.foreign_hashes,
+            pop_from: self.pop_from,
+            migration: None,
+        })
+    }
+
+    /// An empty map with this one's hasher and settings, sized for
+    /// `capacity` entries.
+    fn empty_like(&self, capacity: usize) -> Self
+    where
+        S: Clone,
+    {
+        let mut map = Self::try_with_capacity_and_config(
+            capacity,
+            self.config,
+            self.hash_builder.clone(),
+            self.allocator().clone(),
+        )
+        .unwrap_or_else(|error| error.handle());
+        map.auto_shrink = self.auto_shrink;
+        map.probing = self.probing;
+        map.incremental_resize = self.incremental_resize;
+        map.max_probe_length = self.max_probe_length;
+        map.foreign_hashes = self.foreign_hashes;
+
+        map
+    }
+
+    /// Replaces the value held for `key` with what `f` makes of it and
+    /// `value`, or hands `value` back with the slot a new entry belongs in.
+    fn combine<F>(&mut self, hash: u64, key: &K, value: V, f: &mut F) -> Result<(), (usize, V)>
+    where
+        F: FnMut(&K, V, V) -> V,
+    {
+        self.settle(hash, |k| k == key);
+        let index = match self.probe_with(hash, |k| k == key) {
+            Ok(index) => index,
+            Err(index) => return Err((index, value)),
+        };
+
+        let bucket = self.table.take(index).unwrap();
+        // Both values belong to `f` while it runs, so if it unwinds the
+        // entry is gone and its slot is closed up as a removal would.
+        let guard = HoleGuard { map: self, index };
+        let value = f(&bucket.key, bucket.value, value);
+        core::mem::forget(guard);
+        self.table
+            .insert(index, Bucket::new(hash, bucket.key, value));
+
+        Ok(())
+    }
+
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F, S, A>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.finish_migration();
+
+        ExtractIf::new(self, pred)
+    }
+
+    /// A cursor before the first entry, for walks that decide what to keep
+    /// as they go and may stop early.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V, S, A> {
+        self.finish_migration();
+
+        CursorMut::new(self)
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.finish_migration();
+
+        // Removals shift later entries back into the visited slot, which is
+        // then checked again. Starting after an empty slot means no entry is
+        // ever shifted around the end of the walk into its beginning.
+        let slots = self.table.num_slots();
+        let start = self.empty_index();
+        let mut offset = 1;
+        while offset < slots {
+            let index = (start + offset) & self.mask;
+            let Some((hashed, value)) = self.table.get_pair_mut(index) else {
+                offset += self.table.distance_to_full(index);
+                continue;
+            };
+            if !f(&hashed.key, value) {
+                self.take_bucket(index);
+                continue;
+            }
+
+            offset += 1;
+        }
+
+        self.shrink_if_sparse();
+    }
+
+    /// Walks every entry to measure how far its probe took it, so this takes
+    /// time linear in the number of slots.
+    pub fn stats(&self) -> TableStats {
+        let mut distances = self
+            .table
+            .full_slots()
+            .map(|index| self.distance(self.table.get(index).unwrap().hash, index))
+            .collect::<alloc::vec::Vec<_>>();
+        if let Some(migration) = &self.migration {
+            let table = migration.table();
+            distances.extend(
+                table
+                    .full_slots()
+                    .map(|index| migration.distance(table.get(index).unwrap().hash, index)),
+            );
+        }
+        let slots = self.memory_stats().slots;
+        let ratio = |count: usize, of: usize| match of {
+            0 => 0.0,
+            of => count as f32 / of as f32,
+        };
+
+        TableStats {
+            live: self.len,
+            tombstones: self.cleared,
+            slots,
+            load_factor: ratio(self.len, slots),
+            max_probe_distance: distances.iter().copied().max().unwrap_or(0),
+            mean_probe_distance: ratio(distances.iter().sum(), distances.len()),
+            resizes: self.resizes,
+        }
+    }
+
+    /// Rederives everything the map keeps about its entries, which takes
+    /// time linear in the number of slots and a hash of every key, and
+    /// reports the first mismatch. Meant for tests and debugging: a map only
+    /// ever fails this after a key's `Hash` or `Eq` changed while it was in
+    /// the map, or a raw entry was inserted under the wrong hash. The slots
+    /// scattered probing has cleared are not marked, so their count is not
+    /// checked.
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        let slots = self.table.num_slots();
+        if slots != 0 && (!slots.is_power_of_two() || self.mask != slots - 1) {
+            return Err(InvariantError::SlotCount {
+                slots,
+                mask: self.mask,
+            });
+        }
+
+        let mut counted = 0;
+        for slot in 0..slots {
+            let Some(bucket) = self.check_slot(&self.table, slot, slot)? else {
+                continue;
+            };
+            match self.probe_with(bucket.hash, |k| *k == bucket.key) {
+                Ok(found) if found == slot => {}
+                Ok(other) => return Err(InvariantError::Duplicate { slot, other }),
+                Err(_) => return Err(InvariantError::Unreachable { slot }),
+            }
+            let migrating = self
+                .migration
+                .as_ref()
+                .and_then(|m| m.find(bucket.hash, |k| *k == bucket.key));
+            if let Some(other) = migrating {
+                return Err(InvariantError::Duplicate {
+                    slot,
+                    other: slots + other,
+                });
+            }
+            counted += 1;
+        }
+
+        if let Some(migration) = &self.migration {
+            let table = migration.table();
+            let mut migrating = 0;
+            for index in 0..table.num_slots() {
+                let slot = slots + index;
+                let Some(bucket) = self.check_slot(table, index, slot)? else {
+                    continue;
+                };
+                match migration.find(bucket.hash, |k| *k == bucket.key) {
+                    Some(found) if found == index => {}
+                    Some(other) => {
+                        return Err(InvariantError::Duplicate {
+                            slot,
+                            other: slots + other,
+                        })
+                    }
+                    None => return Err(InvariantError::Unreachable { slot }),
+                }
+                migrating += 1;
+            }
+            if migrating != migration.len() {
+                return Err(InvariantError::Len {
+                    stored: migration.len(),
+                    counted: migrating,
+                });
+            }
+            counted += migrating;
+        }
+
+        if counted != self.len {
+            return Err(InvariantError::Len {
+                stored: self.len,
+                counted,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the slot's control byte, occupancy bit and cached hash,
+    /// returning its key if it is full.
+    fn check_slot<'t>(
+        &self,
+        table: &'t RawTable<K, V, A>,
+        index: usize,
+        slot: usize,
+    ) -> Result<Option<&'t HashedKey<K>>, InvariantError> {
+        if table.is_full(index) != table.is_marked(index) {
+            return Err(InvariantError::Occupancy { slot });
+        }
+        let Some(bucket) = table.get(index) else {
+            return Ok(None);
+        };
+        if table.ctrl(index) != raw::h2(bucket.hash) {
+            return Err(InvariantError::Control { slot });
+        }
+        if self.make_hash(&bucket.key) != bucket.hash {
+            return Err(InvariantError::Hash { slot });
+        }
+
+        Ok(Some(bucket))
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(error) = self.try_reserve(additional) {
+            error.handle();
+        }
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        let required = self
+            .len
+            .saturating_add(additional)
+            .min(self.config.max_entries);
+        if required > self.capacity() {
+            self.try_grow_to_fit(required)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.finish_migration();
+        let required = self.len.max(min_capacity);
+        let new_capacity = if required == 0 {
+            0
+        } else {
+            // A capacity past the largest table is never smaller than this one.
+            match self
+                .config
+                .try_slots_from(1, required, RawTable::<K, V, A>::MAX_SLOTS)
+            {
+                Ok(slots) => slots,
+                Err(_) => return,
+            }
+        };
+
+        if new_capacity < self.table.num_slots() {
+            self.rebuild(new_capacity);
+        }
+    }
+
+    /// Rebuilds the table at its current size, dropping the slots scattered
+    /// probing has cleared so lookups walk no further than the live entries
+    /// need, and finishes any incremental resize. Linear probing never
+    /// leaves such slots, so there this only finishes the resize.
+    pub fn rehash(&mut self) {
+        self.finish_migration();
+        if self.cleared > 0 {
+            self.rebuild(self.table.num_slots());
+        }
+    }
+
+    /// Like [`rehash`](Self::rehash), also moving to the smallest table that
+    /// holds the entries.
+    pub fn rehash_shrink(&mut self) {
+        self.shrink_to_fit();
+        self.rehash();
+    }
+
+    /// Enables shrinking the buffer once removals leave fewer than an eighth
+    /// of the capacity in use. Off by default.
+    pub fn set_auto_shrink(&mut self, enabled: bool) {
+        self.auto_shrink = enabled;
+    }
+
+    /// Grows the table when an insert would land more than `limit` slots
+    /// along its probe, however far below the load limit the map is, so one
+    /// crowded region cannot slow every operation touching it. Tables less
+    /// than an eighth full never grow this way. Defaults to 64.
+    pub fn set_max_probe_length(&mut self, limit: usize) {
+        self.max_probe_length = limit;
+    }
+
+    /// Spreads growth over the operations that follow it: a full table moves
+    /// aside and every later insert, lookup through `&mut self` or removal
+    /// moves a few of its entries into the new one. No single operation then
+    /// rehashes more than a handful of entries, at the cost of lookups
+    /// checking both tables until the move is done. Bulk operations such as
+    /// [`retain`](Self::retain) and [`drain`](Self::drain) finish the move
+    /// first. Only linear probing grows incrementally. Off by default.
+    pub fn set_incremental_resize(&mut self, enabled: bool) {
+        self.incremental_resize = enabled;
+    }
+
+    /// Switches the probe sequence, rehashing any entries in place.
+    pub fn set_probing(&mut self, probing: Probing) {
+        if probing == self.probing {
+            return;
+        }
+
+        if self.len == 0 {
+            self.probing = probing;
+            return;
+        }
+
+        let slots = self.table.num_slots();
+        if let Err(error) = self.try_rebuild_with(slots, probing) {
+            error.handle();
+        }
+    }
+
+    /// Cleared slots count as used: lookups walk over them just as they do
+    /// over live entries.
+    fn needs_extending(&self) -> bool {
+        self.exceeds_extend_limit(self.len + self.cleared)
+    }
+
+    fn exceeds_extend_limit(&self, used: usize) -> bool {
+        let percentage = used as f32 / self.table.num_slots() as f32;
+
+        self.table.num_slots() == 0 || percentage > self.config.max_load_factor
+    }
+
+    fn is_saturated(&self) -> bool {
+        let free_slots = self.table.num_slots() - self.len;
+
+        free_slots <= 1
+    }
+
+    /// Growth stops at the slots `max_entries` needs, unless hopscotch
+    /// probing has to go past them to fit an entry.
+    fn grown_capacity(&self) -> usize {
+        let slots = self.table.num_slots();
+        let next = self
+            .config
+            .next_slots(slots, RawTable::<K, V, A>::MAX_SLOTS)
+            .unwrap_or_else(|error| error.handle());
+        let limit = self.slot_limit();
+
+        if slots < limit {
+            next.min(limit)
+        } else {
+            next
+        }
+    }
+
+    /// The slots that hold `max_entries` within the load limit.
+    fn slot_limit(&self) -> usize {
+        let max_slots = RawTable::<K, V, A>::MAX_SLOTS;
+
+        self.config
+            .try_slots_from(1, self.config.max_entries, max_slots)
+            .unwrap_or(max_slots)
+    }
+
+    fn limit_reached(&self) -> ! {
+        panic!(
+            "Map already holds its limit of {} entries",
+            self.config.max_entries
+        )
+    }
+
+    fn grow(&mut self) {
+        self.rebuild(self.grown_capacity());
+    }
+
+    /// Grows the table if one more entry would not fit. Tables always keep
+    /// at least one empty slot, which ends every probe chain.
+    fn reserve_one(&mut self) -> bool {
+        if self.len >= self.config.max_entries {
+            self.limit_reached();
+        }
+        if !self.needs_extending() && !self.is_saturated() {
+            return self.purge_cleared();
+        }
+        if !self.exceeds_extend_limit(self.len) && !self.is_saturated() {
+            // The live entries alone fit, so dropping the cleared slots makes
+            // room without growing.
+            self.trace_purge();
+            self.rebuild(self.table.num_slots());
+            return true;
+        }
+
+        if self.incremental_resize
+            && self.probing == Probing::Linear
+            && self.migration.is_none()
+            && self.table.num_slots() != 0
+        {
+            self.start_migration();
+        } else {
+            self.grow();
+        }
+
+        true
+    }
+
+    /// Moves the whole table aside for later operations to empty. The old
+    /// table holds at most the load factor of half the new slots, and each
+    /// operation visits `MIGRATION_STEP` of its slots, so the move is done
+    /// long before the new table fills up.
+    fn start_migration(&mut self) {
+        let new_capacity = self.grown_capacity();
+        let _span = self.enter_resize(new_capacity);
+        let new_table = try_empty_table(new_capacity, self.allocator().clone())
+            .unwrap_or_else(|error| error.handle());
+        self.resizes += 1;
+
+        let old_table = core::mem::replace(&mut self.table, new_table);
+        self.mask = new_capacity - 1;
+        self.migration = Some(Migration::new(old_table, self.len));
+    }
+
+    fn migrate_step(&mut self) {
+        let mut budget = MIGRATION_STEP;
+        while let Some((index, bucket)) = self
+            .migration
+            .as_mut()
+            .and_then(|m| m.next_bucket(&mut budget))
+        {
+            self.len -= 1;
+            self.check_hash(self.table.num_slots() + index, &bucket.key, bucket.hash);
+            self.place(bucket);
+        }
+        self.drop_finished_migration();
+    }
+
+    /// Rebuilds at the same size once enough slots have been cleared, which
+    /// brings `max_distance` back down to what the live entries need. The
+    /// count restarts from zero, so a map hovering at the threshold rebuilds
+    /// at most once per `CLEARED_DIVISOR`th of its slots in removals.
+    fn purge_cleared(&mut self) -> bool {
+        if self.cleared == 0 || self.cleared < self.table.num_slots() / CLEARED_DIVISOR {
+            return false;
+        }
+
+        self.trace_purge();
+        self.rebuild(self.table.num_slots());
+
+        true
+    }
+
+    fn drop_finished_migration(&mut self) {
+        if self.migration.as_ref().is_some_and(|m| m.len() == 0) {
+            self.migration = None;
+        }
+    }
+
+    fn finish_migration(&mut self) {
+        if let Some(migration) = self.migration.take() {
+            self.check_hashes(migration.table(), self.table.num_slots());
+            self.len -= migration.len();
+            for bucket in RawIntoIter::new(migration.into_table()) {
+                self.place(bucket);
+            }
+        }
+    }
+
+    /// Does a step of any running migration, then makes sure an entry
+    /// matching `hash` is not left in the old table, so that slot indices
+    /// found afterwards all refer to the current one.
+    fn settle<F>(&mut self, hash: u64, mut is_match: F)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        if self.migration.is_none() {
+            return;
+        }
+
+        self.migrate_step();
+        let Some(migration) = self.migration.as_mut() else {
+            return;
+        };
+        if let Some(index) = migration.find(hash, &mut is_match) {
+            let bucket = migration.take(index);
+            self.len -= 1;
+            self.check_hash(self.table.num_slots() + index, &bucket.key, bucket.hash);
+            self.place(bucket);
+            self.drop_finished_migration();
+        }
+    }
+
+    fn try_grow_to_fit(&mut self, len: usize) -> Result<(), TryReserveError> {
+        let new_capacity = self
+            .config
+            .try_slots_for(len, RawTable::<K, V, A>::MAX_SLOTS)?
+            .max(self.table.num_slots());
+        if new_capacity != self.table.num_slots() {
+            self.try_rebuild(new_capacity)?;
+        }
+
+        Ok(())
+    }
+
+    fn rebuild(&mut self, new_capacity: usize) {
+        if let Err(error) = self.try_rebuild(new_capacity) {
+            error.handle();
+        }
+    }
+
+    fn try_rebuild(&mut self, new_capacity: usize) -> Result<(), TryReserveError> {
+        self.try_rebuild_with(new_capacity, self.probing)
+    }
+
+    /// Copies every entry into a new table laid out for `probing`, and only
+    /// frees the old tables once all of them are in. Keys are only hashed
+    /// again to check them under the `check-hashes` feature, before anything
+    /// moves, but placing can still panic: hopscotch may need to grow past
+    /// the largest table, or the allocator may panic. The map then goes back
+    /// to the old tables with every entry in place.
+    fn try_rebuild_with(
+        &mut self,
+        new_capacity: usize,
+        probing: Probing,
+    ) -> Result<(), TryReserveError> {
+        let _span = self.enter_resize(new_capacity);
+        self.check_hashes(&self.table, 0);
+        if let Some(migration) = &self.migration {
+            self.check_hashes(migration.table(), self.table.num_slots());
+        }
+        let new_table = try_empty_table(new_capacity, self.allocator().clone())?;
+        let resized = self.table.num_slots() != 0 && new_capacity != self.table.num_slots();
+
+        let saved = SavedTables {
+            table: core::mem::replace(&mut self.table, new_table),
+            migration: self.migration.take(),
+            mask: self.mask,
+            len: self.len,
+            max_distance: self.max_distance,
+            cleared: self.cleared,
+            probing: self.probing,
+        };
+        let mut guard = RebuildGuard {
+            map: self,
+            saved: Some(saved),
+        };
+        guard.map.mask = new_capacity.saturating_sub(1);
+        guard.map.len = 0;
+        guard.map.max_distance = 0;
+        guard.map.cleared = 0;
+        guard.map.probing = probing;
+
+        let saved = guard.saved.as_ref().unwrap();
+        let migrating = saved.migration.as_ref().map(|m| m.table());
+        let tables = core::iter::once(&saved.table).chain(migrating);
+        if guard.map.rebuilds_in_parallel(saved.len) {
+            #[cfg(feature = "rayon")]
+            rehash::place_all(&mut guard.map.table, tables);
+            guard.map.len = saved.len;
+        } else {
+            for table in tables {
+                for index in table.full_slots() {
+                    // SAFETY: the copy owns the bucket from here on; the old
+                    // tables forget theirs below, and the guard forgets the
+                    // copies instead if this loop unwinds.
+                    guard.map.place(unsafe { table.read(index) });
+                }
+            }
+        }
+
+        let mut saved = guard.saved.take().unwrap();
+        saved.table.forget_all();
+        if let Some(migration) = &mut saved.migration {
+            migration.table_mut().forget_all();
+        }
+        if resized {
+            guard.map.resizes += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Only linear probing lays a table out from its entries alone, which
+    /// is what lets threads place them without contending for slots.
+    #[cfg(feature = "rayon")]
+    fn rebuilds_in_parallel(&self, len: usize) -> bool {
+        self.probing == Probing::Linear && len >= self.config.parallel_rehash_threshold.max(1)
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    fn rebuilds_in_parallel(&self, _len: usize) -> bool {
+        false
+    }
+
+    fn insert_new(&mut self, bucket: Bucket<K, V>) -> usize {
+        self.reserve_one();
+        let index = self.insertion_index(bucket.hash);
+
+        self.insert_at(index, bucket)
+    }
+
+    /// Occupies `index` for a newly inserted entry, first growing the table
+    /// if the entry would sit past the probe length limit.
+    fn insert_at(&mut self, mut index: usize, bucket: Bucket<K, V>) -> usize {
+        if self.distance(bucket.hash, index) >= self.max_probe_length
+            && self.len >= self.table.num_slots() / LONG_PROBE_FILL_DIVISOR
+            && self.table.num_slots() < self.slot_limit()
+        {
+            self.forced_resizes += 1;
+            self.grow();
+            index = self.insertion_index(bucket.hash);
+        }
+
+        self.occupy(index, bucket)
+    }
+
+    fn place(&mut self, bucket: Bucket<K, V>) -> usize {
+        let index = self.insertion_index(bucket.hash);
+
+        self.occupy(index, bucket)
+    }
+
+    /// Puts the bucket at `index` and returns where it ended up, growing the
+    /// table for as long as hopscotch probing cannot fit it.
+    fn occupy(&mut self, mut index: usize, mut bucket: Bucket<K, V>) -> usize {
+        loop {
+            match self.try_occupy(index, bucket) {
+                Ok(index) => return index,
+                Err(rejected) => {
+                    // Leaked rather than dropped if growing panics: a rebuild
+                    // places copies of buckets its old table still owns.
+                    let rejected = ManuallyDrop::new(rejected);
+                    self.assert_neighborhood_separable(rejected.hash);
+                    self.forced_resizes += 1;
+                    self.grow();
+                    index = self.insertion_index(rejected.hash);
+                    bucket = ManuallyDrop::into_inner(rejected);
+                }
+            }
+        }
+    }
+
+    /// Linear probing shifts the entries from `index` up to the next empty
+    /// slot one slot along. The other probes only hand out empty slots, and
+    /// only hopscotch ever moves anything into them.
+    fn try_occupy(&mut self, index: usize, bucket: Bucket<K, V>) -> Result<usize, Bucket<K, V>> {
+        let index = match self.probing {
+            Probing::Linear => {
+                self.shift_run(index);
+                index
+            }
+            Probing::Hopscotch => match self.hop_towards(bucket.hash, index) {
+                Some(index) => index,
+                None => return Err(bucket),
+            },
+            Probing::Quadratic | Probing::DoubleHashing => index,
+        };
+        if self.probing != Probing::Linear {
+            self.max_distance = self.max_distance.max(self.distance(bucket.hash, index));
+        }
+
+        self.len += 1;
+        self.table.insert(index, bucket);
+
+        Ok(index)
+    }
+
+    fn shift_run(&mut self, index: usize) {
+        let mut end = index;
+        while self.table.is_full(end) {
+            end = (end + 1) & self.mask;
+        }
+        while end != index {
+            let previous = end.wrapping_sub(1) & self.mask;
+            self.table.move_bucket(previous, end);
+            end = previous;
+        }
+    }
+
+    /// Moves the empty slot `free` back into the neighbourhood of `hash` by
+    /// repeatedly moving an earlier entry into it, as long as that keeps the
+    /// entry inside its own neighbourhood. Every slot between the starting
+    /// slot and `free` is full, since `free` was the first empty one.
+    fn hop_towards(&mut self, hash: u64, mut free: usize) -> Option<usize> {
+        const NEIGHBORHOOD: usize = Probing::HOPSCOTCH_NEIGHBORHOOD;
+
+        while self.distance(hash, free) >= NEIGHBORHOOD {
+            // The furthest candidate frees the slot closest to the start.
+            let (from, distance) = (1..NEIGHBORHOOD).rev().find_map(|back| {
+                let from = free.wrapping_sub(back) & self.mask;
+                let distance = self.distance(self.table.get(from)?.hash, free);
+
+                (distance < NEIGHBORHOOD).then_some((from, distance))
+            })?;
+
+            self.table.move_bucket(from, free);
+            self.max_distance = self.max_distance.max(distance);
+            free = from;
+        }
+
+        Some(free)
+    }
+
+    /// Growing only separates entries whose hashes differ, so a neighbourhood
+    /// full of one hash could never take another entry with it.
+    fn assert_neighborhood_separable(&self, hash: u64) {
+        let mut sequence = Linear::new(hash, self.mask);
+        let sharing = (0..Probing::HOPSCOTCH_NEIGHBORHOOD.min(self.table.num_slots()))
+            .map(|_| sequence.next())
+            .filter(|&index| self.table.get(index).is_some_and(|b| b.hash == hash))
+            .count();
+
+        assert!(
+            sharing < Probing::HOPSCOTCH_NEIGHBORHOOD,
+            "More than {} keys share one hash",
+            Probing::HOPSCOTCH_NEIGHBORHOOD
+        );
+    }
+
+    fn shrink_if_sparse(&mut self) {
+        if !self.auto_shrink || self.len >= self.capacity() / AUTO_SHRINK_DIVISOR {
+            return;
+        }
+
+        // Leave room for twice the live count so hovering near the threshold
+        // does not alternate between growing and shrinking.
+        let new_capacity = self
+            .config
+            .try_slots_for(self.len * 2, RawTable::<K, V, A>::MAX_SLOTS);
+        if let Ok(new_capacity) = new_capacity {
+            if new_capacity < self.table.num_slots() {
+                self.rebuild(new_capacity);
+            }
+        }
+    }
+
+    fn take_bucket(&mut self, index: usize) -> Bucket<K, V> {
+        let bucket = self
+            .table
+            .take(index)
+            .unwrap_or_else(|| panic!("Slot {index} is not occupied"));
+        self.check_hash(index, &bucket.key, bucket.hash);
+        self.close_hole(index);
+
+        bucket
+    }
+
+    /// Accounts for the entry just taken out of `index`.
+    fn close_hole(&mut self, index: usize) {
+        self.len -= 1;
+        if self.probing != Probing::Linear {
+            self.cleared += 1;
+            return;
+        }
+
+        // Shift the rest of the run back until an empty slot or an entry
+        // already in its starting slot.
+        let mut hole = index;
+        let mut next = (index + 1) & self.mask;
+        while let Some(bucket) = self.table.get(next) {
+            if self.distance(bucket.hash, next) == 0 {
+                break;
+            }
+
+            self.table.move_bucket(next, hole);
+            hole = next;
+            next = (next + 1) & self.mask;
+        }
+    }
+
+    fn make_hash<Q>(&self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized,
+    {
+        self.hash_builder.hash_one(key)
+    }
+
+    fn starting_index(&self, hash: u64) -> usize {
+        probe::starting_index(hash, self.mask)
+    }
+
+    /// How many steps `index` is along the probe sequence for `hash`.
+    fn distance(&self, hash: u64, index: usize) -> usize {
+        match self.probing {
+            Probing::Linear | Probing::Hopscotch => {
+                index.wrapping_sub(self.starting_index(hash)) & self.mask
+            }
+            Probing::Quadratic => self.steps_to::<Quadratic>(hash, index),
+            Probing::DoubleHashing => self.steps_to::<DoubleHashing>(hash, index),
+        }
+    }
+
+    fn steps_to<P: ProbeSequence>(&self, hash: u64, index: usize) -> usize {
+        let mut sequence = P::new(hash, self.mask);
+
+        (0..=self.mask)
+            .position(|_| sequence.next() == index)
+            .expect("Every sequence visits every slot")
+    }
+
+    /// An empty slot; every table with slots has one.
+    fn empty_index(&self) -> usize {
+        self.table
+            .ctrl_bytes()
+            .iter()
+            .position(|&ctrl| ctrl == raw::EMPTY)
+            .unwrap_or(0)
+    }
+
+    fn insertion_index(&self, hash: u64) -> usize {
+        self.probe_with(hash, |_| false)
+            .expect_err("Nothing matches a rejecting predicate")
+    }
+
+    /// Finds the slot holding a matching key, or else the slot a new entry
+    /// for `hash` belongs in.
+    fn probe_with<F>(&self, hash: u64, is_match: F) -> Result<usize, usize>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.probe_walk(hash, is_match).0
+    }
+
+    /// Like [`probe_with`](Self::probe_with), also counting the slots the
+    /// walk inspected.
+    fn probe_walk<F>(&self, hash: u64, is_match: F) -> (Result<usize, usize>, usize)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        // A shrunk, empty table has no slots; vacant entries grow it before
+        // using the index.
+        if self.table.num_slots() == 0 {
+            return (Err(0), 0);
+        }
+
+        match self.probing {
+            Probing::Linear => self.probe_robin_hood(hash, is_match),
+            Probing::Quadratic => self.probe_scattered::<Quadratic, F>(hash, is_match),
+            Probing::DoubleHashing => self.probe_scattered::<DoubleHashing, F>(hash, is_match),
+            Probing::Hopscotch => self.probe_scattered::<Linear, F>(hash, is_match),
+        }
+    }
+
+    /// Walks the chain one slot at a time, stopping at a match, an empty slot
+    /// or the first entry closer to its starting slot than the walk is to
+    /// the hash's. Entries are ordered by starting slot within a run, so a
+    /// missing key belongs at that last slot.
+    fn probe_robin_hood<F>(&self, hash: u64, mut is_match: F) -> (Result<usize, usize>, usize)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let h2 = raw::h2(hash);
+        let mut sequence = Linear::new(hash, self.mask);
+        for distance in 0..self.table.num_slots() {
+            let index = sequence.next();
+            let ctrl = self.table.ctrl(index);
+            if ctrl == raw::EMPTY {
+                return (Err(index), distance + 1);
+            }
+
+            // SAFETY: the control byte is not empty, so the slot is full.
+            let bucket = unsafe { self.table.key(index) };
+            self.check_hash(index, &bucket.key, bucket.hash);
+            if ctrl == h2 && bucket.hash == hash && is_match(&bucket.key) {
+                return (Ok(index), distance + 1);
+            }
+            if self.distance(bucket.hash, index) < distance {
+                return (Err(index), distance + 1);
+            }
+        }
+
+        panic!("Table has no free slot")
+    }
+
+    /// Removals leave empty slots inside chains, so the walk steps over them
+    /// until it has gone as far as any entry sits, and a missing key goes in
+    /// the first empty slot it saw.
+    fn probe_scattered<P, F>(&self, hash: u64, mut is_match: F) -> (Result<usize, usize>, usize)
+    where
+        P: ProbeSequence,
+        F: FnMut(&K) -> bool,
+    {
+        let h2 = raw::h2(hash);
+        let mut sequence = P::new(hash, self.mask);
+        let mut vacant = None;
+        for distance in 0..self.table.num_slots() {
+            let index = sequence.next();
+            let ctrl = self.table.ctrl(index);
+            if ctrl == raw::EMPTY {
+                vacant.get_or_insert(index);
+            } else if ctrl == h2 && self.is_match_at(index, hash, &mut is_match) {
+                return (Ok(index), distance + 1);
+            }
+
+            if distance >= self.max_distance {
+                if let Some(index) = vacant {
+                    return (Err(index), distance + 1);
+                }
+            }
+        }
+
+        panic!("Table has no free slot")
+    }
+
+    fn find_index<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.find_index_with(self.make_hash(key), |k| key.equivalent(k))
+    }
+
+    /// Like [`find_index`](Self::find_index), first moving the entry out of
+    /// any table being migrated away from.
+    fn find_index_mut<Q>(&mut self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.make_hash(key);
+        self.settle(hash, |k| key.equivalent(k));
+        let (index, walked) = self.find_index_walk(hash, |k| key.equivalent(k));
+        self.trace_probe("lookup", walked);
+
+        index
+    }
+
+    /// The keyed lookup behind the public getters, which alone report long
+    /// probes.
+    fn find_bucket<Q>(&self, key: &Q) -> Option<(&HashedKey<K>, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.make_hash(key);
+        let (index, walked) = self.find_index_walk(hash, |k| key.equivalent(k));
+        self.trace_probe("lookup", walked);
+
+        match index {
+            Some(index) => self.table.get_pair(index),
+            None => self.find_migrating(hash, |k| key.equivalent(k)),
+        }
+    }
+
+    /// Looks in the current table, then in any table being migrated away
+    /// from.
+    fn find_bucket_with<F>(&self, hash: u64, mut is_match: F) -> Option<(&HashedKey<K>, &V)>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        match self.find_index_with(hash, &mut is_match) {
+            Some(index) => self.table.get_pair(index),
+            None => self.find_migrating(hash, is_match),
+        }
+    }
+
+    fn find_migrating<F>(&self, hash: u64, is_match: F) -> Option<(&HashedKey<K>, &V)>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let migration = self.migration.as_ref()?;
+
+        migration.table().get_pair(migration.find(hash, is_match)?)
+    }
+
+    fn find_index_with<F>(&self, hash: u64, is_match: F) -> Option<usize>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.find_index_walk(hash, is_match).0
+    }
+
+    /// Like [`find_index_with`](Self::find_index_with), also counting the
+    /// slots the lookup inspected.
+    fn find_index_walk<F>(&self, hash: u64, is_match: F) -> (Option<usize>, usize)
+    where
+        F: FnMut(&K) -> bool,
+    {
+        match self.probing {
+            Probing::Linear => self.find_index_in::<group::Active, F>(hash, is_match),
+            Probing::Quadratic => self.find_index_scattered::<Quadratic, F>(hash, is_match),
+            Probing::DoubleHashing => self.find_index_scattered::<DoubleHashing, F>(hash, is_match),
+            Probing::Hopscotch => self.find_index_scattered::<Linear, F>(hash, is_match),
+        }
+    }
+
+    fn find_index_scattered<P, F>(&self, hash: u64, mut is_match: F) -> (Option<usize>, usize)
+    where
+        P: ProbeSequence,
+        F: FnMut(&K) -> bool,
+    {
+        if self.len == 0 {
+            return (None, 0);
+        }
+
+        let h2 = raw::h2(hash);
+        let found = self
+            .scattered_slots::<P>(hash)
+            .enumerate()
+            .find(|&(_, index)| {
+                self.table.ctrl(index) == h2 && self.is_match_at(index, hash, &mut is_match)
+            });
+        let walked = found.map_or(self.max_distance, |(distance, _)| distance) + 1;
+
+        (found.map(|(_, index)| index), walked)
+    }
+
+    /// The slots a scattered lookup for `hash` inspects, in order: as far
+    /// along the sequence as any entry sits.
+    fn scattered_slots<P: ProbeSequence>(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let mut sequence = P::new(hash, self.mask);
+
+        (0..=self.max_distance).map(move |_| sequence.next())
+    }
+
+    /// Walks the chain a group at a time, with the same result as walking it
+    /// one slot at a time. Matches are checked across the whole group before
+    /// looking for an empty slot: runs never contain empty slots, so no match
+    /// lies past one.
+    fn find_index_in<G, F>(&self, hash: u64, mut is_match: F) -> (Option<usize>, usize)
+    where
+        G: Group,
+        F: FnMut(&K) -> bool,
+    {
+        let h2 = raw::h2(hash);
+        let ctrl = self.table.ctrl_bytes();
+        let start = self.starting_index(hash);
+        let mut pos = start;
+        let mut remaining = ctrl.len();
+        while remaining > 0 {
+            let group = G::load(ctrl, pos);
+            let window = BitMask::below(remaining.min(G::WIDTH));
+            for offset in group.match_byte(h2).and(window) {
+                let index = (pos + offset) & self.mask;
+                if self.is_match_at(index, hash, &mut is_match) {
+                    return (Some(index), (index.wrapping_sub(start) & self.mask) + 1);
+                }
+            }
+
+            if let Some(offset) = group.match_empty().and(window).lowest() {
+                return (None, ((pos + offset).wrapping_sub(start) & self.mask) + 1);
+            }
+
+            pos = (pos + G::WIDTH) & self.mask;
+            remaining = remaining.saturating_sub(G::WIDTH);
+        }
+
+        (None, ctrl.len())
+    }
+
+    /// Checks the full hash before the key, since seven matching control bits
+    /// still leave one chance in 128 of a different hash.
+    fn is_match_at<F>(&self, index: usize, hash: u64, is_match: &mut F) -> bool
+    where
+        F: FnMut(&K) -> bool,
+    {
+        // SAFETY: the caller saw a full control byte at `index`.
+        let bucket = unsafe { self.table.key(index) };
+        self.check_hash(index, &bucket.key, bucket.hash);
+
+        bucket.hash == hash && is_match(&bucket.key)
+    }
+
+    /// With the `check-hashes` feature, panics if the key in `slot` no
+    /// longer hashes to what it did when it was inserted, which means its
+    /// `Hash` changed while it was in the map. Slots of a table being
+    /// migrated away from are numbered after the current table's, as in
+    /// [`check_invariants`](Self::check_invariants). Without the feature
+    /// this compiles to nothing.
+    #[inline(always)]
+    fn check_hash(&self, slot: usize, key: &K, hash: u64) {
+        if cfg!(feature = "check-hashes") && !self.foreign_hashes && self.make_hash(key) != hash {
+            panic!(
+                "The key in slot {slot} no longer hashes as it did when it was inserted; \
+                 its Hash changed while it was in the map"
+            );
+        }
+    }
+
+    #[inline(always)]
+    fn check_hashes(&self, table: &RawTable<K, V, A>, first_slot: usize) {
+        if cfg!(feature = "check-hashes") && !self.foreign_hashes {
+            for index in table.full_slots() {
+                let bucket = table.get(index).unwrap();
+                self.check_hash(first_slot + index, &bucket.key, bucket.hash);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Default for OAHashMap<K, V, S, A>
+where
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher_in(S::default(), A::default())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> FromIterator<(K, V)> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        let mut map =
+            Self::with_capacity_and_hasher_in(iter.size_hint().0, S::default(), A::default());
+        map.extend(iter);
+
+        map
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Extend<(K, V)> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V, S, A> Extend<(&'a K, &'a V)> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Copy,
+    V: Copy,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (&'a K, &'a V)>,
+    {
+        self.extend(iter.into_iter().map(|(&key, &value)| (key, value)));
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V, const N: usize> From<[(K, V); N]> for OAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn from(pairs: [(K, V); N]) -> Self {
+        Self::from_iter(pairs)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> From<HashMap<K, V, S>> for OAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn from(map: HashMap<K, V, S>) -> Self {
+        let mut oa = Self::with_capacity(map.len());
+        oa.extend(map);
+
+        oa
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> From<BTreeMap<K, V>> for OAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    fn from(map: BTreeMap<K, V>) -> Self {
+        let mut oa = Self::with_capacity(map.len());
+        oa.extend(map);
+
+        oa
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S, A> From<OAHashMap<K, V, S, A>> for HashMap<K, V>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn from(oa: OAHashMap<K, V, S, A>) -> Self {
+        let mut map = HashMap::with_capacity(oa.len);
+        map.extend(oa);
+
+        map
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> From<OAHashMap<K, V, S, A>> for BTreeMap<K, V>
+where
+    K: Hash + Ord,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn from(oa: OAHashMap<K, V, S, A>) -> Self {
+        oa.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> From<OAHashMap<K, V, S, A>> for alloc::vec::Vec<(K, V)>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn from(oa: OAHashMap<K, V, S, A>) -> Self {
+        let mut pairs = Self::with_capacity(oa.len);
+        pairs.extend(oa);
+
+        pairs
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Clone for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    A: Allocator + Clone,
+{
+    /// Clones into a table sized for the live entries.
+    fn clone(&self) -> Self {
+        let mut map = self.empty_like(self.len);
+        map.clone_buckets_from(self);
+
+        map
+    }
+
+    /// Reuses the existing buffer when it can hold `source` without growing.
+    fn clone_from(&mut self, source: &Self) {
+        if source.len > self.capacity() {
+            *self = source.clone();
+            return;
+        }
+
+        self.clear();
+        self.hash_builder.clone_from(&source.hash_builder);
+        self.auto_shrink = source.auto_shrink;
+        self.probing = source.probing;
+        self.incremental_resize = source.incremental_resize;
+        self.max_probe_length = source.max_probe_length;
+        self.config = source.config;
+        self.foreign_hashes = source.foreign_hashes;
+        self.clone_buckets_from(source);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn clone_buckets_from(&mut self, source: &Self) {
+        let migrating = source.migration.as_ref().map(|m| m.table().iter());
+        for (hashed, value) in source.table.iter().chain(migrating.unwrap_or_default()) {
+            self.place(Bucket::new(hashed.hash, hashed.key.clone(), value.clone()));
+        }
+    }
+}
+
+/// Maps are equal when they hold the same entries, however those entries are
+/// laid out in their tables.
+#[cfg(feature = "alloc")]
+impl<K, V, S1, S2, A1, A2> PartialEq<OAHashMap<K, V, S2, A2>> for OAHashMap<K, V, S1, A1>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S1: BuildHasher,
+    S2: BuildHasher,
+    A1: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    fn eq(&self, other: &OAHashMap<K, V, S2, A2>) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|v| value == v))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Eq for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> fmt::Debug for OAHashMap<K, V, S, A>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, Q, V, S, A> Index<&Q> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    Q: Hash + Equivalent<K> + ?Sized,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Output = V;
+
+    /// Panics if the key is not present.
+    #[track_caller]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in OAHashMap")
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MemoryStats {
+    pub slot_bytes: usize,
+    pub slots: usize,
+    /// The occupancy bitmap iteration uses, along with any padding the
+    /// allocation needs.
+    pub bitmap_bytes: usize,
+    pub live: usize,
+    /// Slots emptied since the last rebuild that lookups still walk over.
+    /// Always zero under linear probing, whose removals shift entries back;
+    /// under scattered probing the map rebuilds in place once a quarter of
+    /// its slots are such.
+    pub tombstones: usize,
+    /// How many slots a lookup for a missing key inspects under scattered
+    /// probing. Zero under linear probing, whose lookups stop early instead.
+    pub probe_limit: usize,
+    /// Resizes an insert forced below the load limit: hopscotch found no
+    /// entry to move out of the way, or the probe ran past
+    /// [`set_max_probe_length`](OAHashMap::set_max_probe_length).
+    pub forced_resizes: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl MemoryStats {
+    pub fn allocated_bytes(&self) -> usize {
+        self.slot_bytes * self.slots + self.bitmap_bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TableStats {
+    pub live: usize,
+    /// As in [`MemoryStats::tombstones`].
+    pub tombstones: usize,
+    /// Counting the table an incremental resize is still emptying.
+    pub slots: usize,
+    /// Live entries per slot.
+    pub load_factor: f32,
+    /// How many steps along its probe sequence the furthest entry sits from
+    /// its starting slot, zero for an entry in the slot it started from.
+    pub max_probe_distance: usize,
+    pub mean_probe_distance: f32,
+    /// Times the table has grown or shrunk since the map was created.
+    pub resizes: usize,
+}
+
+/// Prints the stats on one line, for logs.
+#[cfg(feature = "alloc")]
+impl fmt::Display for TableStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} live / {} slots (load {:.2}), {} tombstones, probe distance max {} mean {:.2}, {} resizes",
+            self.live,
+            self.slots,
+            self.load_factor,
+            self.tombstones,
+            self.max_probe_distance,
+            self.mean_probe_distance,
+            self.resizes
+        )
+    }
+}
+
+/// What a rebuild puts back if it unwinds.
+#[cfg(feature = "alloc")]
+struct SavedTables<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    table: RawTable<K, V, A>,
+    migration: Option<Migration<K, V, A>>,
+    mask: usize,
+    len: usize,
+    max_distance: usize,
+    cleared: usize,
+    probing: Probing,
+}
+
+#[cfg(feature = "alloc")]
+struct RebuildGuard<'a, K, V, S, A>
+where
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    /// Taken once the rebuild has finished.
+    saved: Option<SavedTables<K, V, A>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Drop for RebuildGuard<'_, K, V, S, A>
+where
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        let Some(saved) = self.saved.take() else {
+            return;
+        };
+
+        // Everything in the new table is a copy of an entry the old tables
+        // still own.
+        self.map.table.forget_all();
+        self.map.table = saved.table;
+        self.map.migration = saved.migration;
+        self.map.mask = saved.mask;
+        self.map.len = saved.len;
+        self.map.max_distance = saved.max_distance;
+        self.map.cleared = saved.cleared;
+        self.map.probing = saved.probing;
+    }
+}
+
+/// Closes up the slot of an entry taken out of the table if it is not put
+/// back.
+#[cfg(feature = "alloc")]
+struct HoleGuard<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    index: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S, A> Drop for HoleGuard<'_, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        self.map.close_hole(self.index);
+    }
+}
+
+struct Bucket<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+impl<K, V> Bucket<K, V> {
+    fn new(hash: u64, key: K, value: V) -> Self {
+        Self { hash, key, value }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn from_parts(key: HashedKey<K>, value: V) -> Self {
+        Self::new(key.hash, key.key, value)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn into_parts(self) -> (HashedKey<K>, V) {
+        let key = HashedKey {
+            hash: self.hash,
+            key: self.key,
+        };
+
+        (key, self.value)
+    }
+
+    fn into_pair(self) -> (K, V) {
+        (self.key, self.value)
+    }
+}
+
+/// A key with its hash, as the table stores it apart from the value.
+#[cfg(feature = "alloc")]
+struct HashedKey<K> {
+    hash: u64,
+    key: K,
+}
+
+impl<K, V> Hash for Bucket<K, V>
+where
+    K: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn try_empty_table<K, V, A>(slots: usize, alloc: A) -> Result<RawTable<K, V, A>, TryReserveError>
+where
+    A: Allocator + Clone,
+{
+    debug_assert!(slots == 0 || slots.is_power_of_two());
+
+    RawTable::try_with_slots(slots, alloc)
+}
+
+#[cfg(all(feature = "alloc", target_arch = "x86_64"))]
+fn prefetch<T>(value: &T) {
+    use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+    // SAFETY: prefetching is only a hint and never faults, even on bad
+    // addresses; SSE is part of the x86_64 baseline.
+    unsafe { _mm_prefetch::<_MM_HINT_T0>((value as *const T).cast()) }
+}
+
+#[cfg(all(feature = "alloc", not(target_arch = "x86_64")))]
+fn prefetch<T>(_value: &T) {}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash, Hasher};
+    use std::rc::Rc;
+    use std::sync::Mutex;
+
+    use allocator_api2::alloc::Global;
+
+    use crate::group::{Scalar, Swar};
+    use crate::probe::Linear;
+    use crate::raw::EMPTY;
+    use crate::test_util::{assert_layout, keys_starting_at, CountingAlloc, Rng, Tagged};
+    use crate::{
+        DuplicateKeyError, Equivalent, IdentityState, InvariantError, KeyNotFoundError, OAHashMap,
+        OAHashMapBuilder, Probing, RawEntryMut, RekeyError, TableStats, TryExtendError,
+        TryReserveError, CLEARED_DIVISOR, EXTEND_LIMIT, INITIAL_CAPACITY, MAX_PROBE_LENGTH,
+        MIGRATION_STEP,
+    };
+
+    #[test]
+    fn basic() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 10);
+
+        assert_eq!(Some(&10), oa.search(&1));
+
+        oa.delete(&1);
+
+        assert_eq!(None, oa.search(&1));
+
+        oa.insert(1, 10);
+        oa.insert(1, 20);
+
+        assert_eq!(Some(&20), oa.search(&1));
+
+        for i in 0..100 {
+            oa.insert(i, i);
+        }
+
+        for i in 0..100 {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn probing_wraps_around() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let last = oa.table.num_slots() - 1;
+        let keys = keys_starting_at(&oa, last, 4);
+
+        for &key in &keys {
+            oa.insert(key, key * 10);
+        }
+
+        assert!(oa.table.is_full(0));
+        for &key in &keys {
+            assert_eq!(Some(&(key * 10)), oa.search(&key));
+        }
+
+        oa.delete(&keys[3]);
+
+        assert_eq!(None, oa.search(&keys[3]));
+        assert_eq!(Some(&(keys[2] * 10)), oa.search(&keys[2]));
+
+        let missing = keys_starting_at(&oa, last, 5)[4];
+
+        assert_eq!(None, oa.search(&missing));
+    }
+
+    #[test]
+    fn delete_keeps_probe_chain() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 10, 3);
+
+        oa.insert(keys[0], 0);
+        oa.insert(keys[1], 1);
+        oa.delete(&keys[0]);
+
+        assert_eq!(None, oa.search(&keys[0]));
+        assert_eq!(Some(&1), oa.search(&keys[1]));
+
+        oa.insert(keys[2], 2);
+        oa.insert(keys[1], 10);
+
+        assert_eq!(Some(&10), oa.search(&keys[1]));
+        assert_eq!(Some(&2), oa.search(&keys[2]));
+
+        oa.delete(&keys[1]);
+
+        assert_eq!(None, oa.search(&keys[1]));
+        assert_eq!(Some(&2), oa.search(&keys[2]));
+    }
+
+    #[test]
+    fn delete_insert_stress() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for round in 0..50 {
+            for i in 0..40 {
+                oa.insert(i, i + round);
+            }
+            for i in (0..40).step_by(2) {
+                oa.delete(&i);
+            }
+            for i in 0..40 {
+                let expected = if i % 2 == 0 { None } else { Some(i + round) };
+                assert_eq!(expected.as_ref(), oa.search(&i));
+            }
+        }
+
+        let live = oa.table.iter().count();
+
+        assert_eq!(20, live);
+    }
+
+    #[test]
+    fn remove_shifts_chain_back() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 5, 4);
+        for key in &keys[..3] {
+            oa.insert(*key, 0);
+        }
+        oa.remove(&keys[0]);
+        oa.remove(&keys[1]);
+
+        assert_eq!(keys[2], oa.table.get(5).unwrap().key);
+        assert_eq!(EMPTY, oa.table.ctrl(6));
+        assert_eq!(Some(0), oa.insert(keys[2], 1));
+        assert_eq!(Some(&1), oa.search(&keys[2]));
+
+        assert_eq!(None, oa.insert(keys[3], 3));
+        assert_eq!(keys[3], oa.table.get(6).unwrap().key);
+        assert_eq!(2, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn insert_matches_std_on_random_ops() {
+        let mut rng = Rng::new(56);
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+        let mut std_map = HashMap::new();
+        for step in 0..50_000 {
+            let key = rng.below(300);
+            if rng.below(3) == 0 {
+                assert_eq!(std_map.remove(&key), oa.remove(&key));
+            } else {
+                assert_eq!(std_map.insert(key, step), oa.insert(key, step));
+            }
+            assert_eq!(std_map.len(), oa.len());
+        }
+
+        assert_eq!(std_map, HashMap::from(oa));
+    }
+
+    /// The behaviour every probe sequence must share, checked against std.
+    fn replay_against_std(probing: Probing, incremental: bool, seed: u64) {
+        let mut rng = Rng::new(seed);
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+        oa.set_probing(probing);
+        oa.set_incremental_resize(incremental);
+        let mut std_map = HashMap::new();
+        for step in 0..20_000 {
+            let key = rng.below(500);
+            match rng.below(100) {
+                0 => {
+                    let limit = rng.below(500);
+                    oa.retain(|k, _| *k < limit);
+                    std_map.retain(|k, _| *k < limit);
+                }
+                1 => {
+                    let modulus = rng.below(5) + 2;
+                    let mut extracted: Vec<_> = oa.extract_if(|k, _| k % modulus == 0).collect();
+                    let mut expected: Vec<_> =
+                        std_map.extract_if(|k, _| k % modulus == 0).collect();
+                    extracted.sort();
+                    expected.sort();
+                    assert_eq!(expected, extracted);
+                }
+                2 if step % 7 == 0 => {
+                    let mut drained: Vec<_> = oa.drain().collect();
+                    let mut expected: Vec<_> = std_map.drain().collect();
+                    drained.sort();
+                    expected.sort();
+                    assert_eq!(expected, drained);
+                }
+                3 => oa.shrink_to_fit(),
+                4 => oa.reserve(rng.below(300) as usize),
+                5 => oa = oa.clone(),
+                6..=35 => assert_eq!(std_map.remove(&key), oa.remove(&key)),
+                36..=45 => {
+                    *oa.entry(key).or_insert(0) += step;
+                    *std_map.entry(key).or_insert(0) += step;
+                }
+                _ => assert_eq!(std_map.insert(key, step), oa.insert(key, step)),
+            }
+
+            assert_eq!(std_map.len(), oa.len());
+            assert_eq!(std_map.get(&key), oa.get(&key));
+            if step % 1000 == 0 {
+                assert_layout(&oa);
+            }
+        }
+
+        assert_layout(&oa);
+        assert_eq!(probing, oa.probing());
+        assert_eq!(std_map, HashMap::from(oa));
+    }
+
+    #[test]
+    fn mixed_ops_match_std() {
+        replay_against_std(Probing::Linear, false, 61);
+        replay_against_std(Probing::Quadratic, false, 62);
+        replay_against_std(Probing::DoubleHashing, false, 63);
+        replay_against_std(Probing::Hopscotch, false, 64);
+        replay_against_std(Probing::Linear, true, 65);
+    }
+
+    /// A map part way through moving at least `old_len` entries out of its
+    /// old table.
+    fn migrating(old_len: usize) -> OAHashMap<i32, i32> {
+        let mut oa = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        for i in 0.. {
+            oa.insert(i, i);
+            if oa.migration.as_ref().is_some_and(|m| m.len() >= old_len) {
+                break;
+            }
+        }
+
+        oa
+    }
+
+    #[test]
+    fn incremental_resize_finds_entries_in_both_tables() {
+        let mut oa = migrating(500);
+        let len = oa.len() as i32;
+        let old_len = oa.migration.as_ref().unwrap().len();
+        for i in 0..len {
+            assert_eq!(Some(&i), oa.get(&i));
+            assert!(oa.contains_key(&i));
+        }
+        assert_eq!(old_len, oa.migration.as_ref().unwrap().len());
+
+        for i in (0..len - 2).step_by(3) {
+            *oa.get_mut(&i).unwrap() += 1;
+            assert_eq!(Some(i + 1), oa.remove(&(i + 1)));
+            assert_eq!(Some(i + 2), oa.insert(i + 2, -i));
+            assert_layout(&oa);
+        }
+        assert!(oa.migration.is_none());
+
+        for i in (0..len - 2).step_by(3) {
+            assert_eq!(Some(&(i + 1)), oa.get(&i));
+            assert_eq!(None, oa.get(&(i + 1)));
+            assert_eq!(Some(&-i), oa.get(&(i + 2)));
+        }
+    }
+
+    #[test]
+    fn incremental_resize_iterates_each_entry_once() {
+        let mut oa = migrating(500);
+        let expected: Vec<i32> = (0..oa.len() as i32).collect();
+        assert!(oa.table.iter().count() < oa.len());
+
+        let sorted = |mut keys: Vec<i32>| {
+            keys.sort();
+            keys
+        };
+        assert_eq!(expected, sorted(oa.iter().map(|(k, _)| *k).collect()));
+        assert_eq!(expected, sorted(oa.keys().copied().collect()));
+        assert_eq!(expected, sorted(oa.values().copied().collect()));
+        assert_eq!(oa.len(), oa.iter().len());
+        for (k, v) in oa.iter_mut() {
+            *v = -k;
+        }
+        assert!(oa.iter().all(|(k, v)| *v == -k));
+        assert_eq!(
+            expected,
+            sorted(oa.clone().into_iter().map(|(k, _)| k).collect())
+        );
+    }
+
+    #[test]
+    fn incremental_resize_bounds_work_per_insert() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        let mut migrations = 0;
+        for i in 0..100_000 {
+            let before = oa.migration.as_ref().map(|m| m.len());
+            oa.insert(i, i);
+            match (before, oa.migration.as_ref().map(|m| m.len())) {
+                (Some(before), after) => {
+                    assert!(before - after.unwrap_or(0) <= MIGRATION_STEP + 1)
+                }
+                (None, Some(_)) => migrations += 1,
+                (None, None) => {}
+            }
+        }
+        assert!(migrations >= 10);
+        assert_layout(&oa);
+        assert_eq!(100_000, oa.len());
+    }
+
+    #[test]
+    fn set_probing_keeps_entries() {
+        let mut oa: OAHashMap<i32, i32> = (0..500).map(|i| (i, i)).collect();
+        let slots = oa.table.num_slots();
+        for probing in [
+            Probing::DoubleHashing,
+            Probing::Hopscotch,
+            Probing::Quadratic,
+            Probing::Linear,
+        ] {
+            oa.set_probing(probing);
+
+            assert_eq!(slots, oa.table.num_slots());
+            assert_eq!(0, oa.resizes);
+            assert_layout(&oa);
+            assert!((0..500).all(|i| oa.get(&i) == Some(&i)));
+            assert_eq!(None, oa.get(&500));
+        }
+        assert_eq!(Probing::Linear, OAHashMap::<i32, i32>::new().probing());
+    }
+
+    #[test]
+    fn scattered_probing_breaks_up_clusters() {
+        // A dense block of keys sharing a starting slot, then a spread of
+        // keys starting all over the table. Returns the mean distance of each
+        // group.
+        fn mean_distances(probing: Probing) -> (f64, f64) {
+            let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(2000);
+            oa.set_probing(probing);
+            let clustered = keys_starting_at(&oa, 100, 300);
+            for &key in &clustered {
+                oa.insert(key, key);
+            }
+            let mut rng = Rng::new(0xc1);
+            let mut spread = Vec::new();
+            while oa.len() < 1200 {
+                let key = rng.below(1 << 30) as i32;
+                if oa.insert(key, key).is_none() {
+                    spread.push(key);
+                }
+            }
+            assert_layout(&oa);
+
+            let mean = |keys: &[i32]| {
+                let total: usize = keys
+                    .iter()
+                    .map(|key| oa.distance(oa.make_hash(key), oa.find_index(key).unwrap()))
+                    .sum();
+
+                total as f64 / keys.len() as f64
+            };
+
+            (mean(&clustered), mean(&spread))
+        }
+
+        let linear = mean_distances(Probing::Linear);
+        let quadratic = mean_distances(Probing::Quadratic);
+        let double = mean_distances(Probing::DoubleHashing);
+
+        // Quadratic steps stop the block from crowding out its neighbours,
+        // and double hashing also splits up the block itself.
+        assert!(quadratic.1 * 2.0 < linear.1, "{linear:?} {quadratic:?}");
+        assert!(double.0 * 10.0 < quadratic.0, "{quadratic:?} {double:?}");
+        assert!(double.1 * 2.0 < linear.1, "{linear:?} {double:?}");
+    }
+
+    #[test]
+    fn hopscotch_lookups_stay_within_neighbourhood() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(2000);
+        oa.set_probing(Probing::Hopscotch);
+        // Far more keys share a starting slot than fit in one neighbourhood.
+        let clustered = keys_starting_at(&oa, 100, 300);
+        let mut rng = Rng::new(0x4057);
+        let mut std_map = HashMap::new();
+        for (step, &key) in clustered.iter().enumerate() {
+            oa.insert(key, key);
+            std_map.insert(key, key);
+            let other = rng.below(1 << 20) as i32;
+            if step % 3 == 0 {
+                assert_eq!(std_map.remove(&other), oa.remove(&other));
+            } else {
+                assert_eq!(
+                    std_map.insert(other, step as i32),
+                    oa.insert(other, step as i32)
+                );
+            }
+        }
+
+        assert!(oa.memory_stats().forced_resizes > 0);
+        assert_layout(&oa);
+        let probes = |key: &i32| oa.scattered_slots::<Linear>(oa.make_hash(key)).count();
+        for key in std_map.keys().chain(&[-1, -2, -3]) {
+            assert!(probes(key) <= Probing::HOPSCOTCH_NEIGHBORHOOD);
+        }
+        assert_eq!(std_map, HashMap::from(oa));
+    }
+
+    #[test]
+    #[should_panic(expected = "keys share one hash")]
+    fn hopscotch_rejects_a_neighbourhood_of_one_hash() {
+        let mut oa = OAHashMap::with_hasher(CollidingState::default());
+        oa.set_probing(Probing::Hopscotch);
+        for i in 0..=Probing::HOPSCOTCH_NEIGHBORHOOD as i32 {
+            oa.insert(i, i);
+        }
+    }
+
+    #[test]
+    fn failed_rebuild_keeps_every_entry() {
+        let value = Rc::new(());
+        let mut oa = OAHashMap::with_hasher(CollidingState::default());
+        for i in 0..=Probing::HOPSCOTCH_NEIGHBORHOOD as i32 {
+            oa.insert(i, Rc::clone(&value));
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            oa.set_probing(Probing::Hopscotch)
+        }));
+        assert!(result.is_err());
+        assert_eq!(Probing::Linear, oa.probing());
+        assert_eq!(Probing::HOPSCOTCH_NEIGHBORHOOD + 1, oa.len());
+        assert_layout(&oa);
+        assert!((0..=Probing::HOPSCOTCH_NEIGHBORHOOD as i32).all(|i| oa.contains_key(&i)));
+        assert_eq!(
+            Probing::HOPSCOTCH_NEIGHBORHOOD + 2,
+            Rc::strong_count(&value)
+        );
+
+        drop(oa);
+        assert_eq!(1, Rc::strong_count(&value));
+    }
+
+    /// Panics once its shared budget of hash calls runs out.
+    #[cfg(not(feature = "check-hashes"))]
+    struct Fragile {
+        id: i32,
+        budget: Rc<Cell<usize>>,
+    }
+
+    #[cfg(not(feature = "check-hashes"))]
+    impl PartialEq for Fragile {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    #[cfg(not(feature = "check-hashes"))]
+    impl Eq for Fragile {}
+
+    #[cfg(not(feature = "check-hashes"))]
+    impl Hash for Fragile {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            let left = self.budget.get();
+            assert!(left > 0, "hash budget spent");
+            self.budget.set(left - 1);
+            self.id.hash(state);
+        }
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn resizes_never_hash_keys() {
+        let budget = Rc::new(Cell::new(1000));
+        let fragile = |id| Fragile {
+            id,
+            budget: Rc::clone(&budget),
+        };
+        let mut oa = OAHashMap::new();
+        let mut inserted = 0;
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            for id in 0.. {
+                oa.insert(fragile(id), id);
+                inserted += 1;
+            }
+        }));
+        assert!(result.is_err());
+        assert_eq!(1000, inserted);
+        assert!(oa.resizes > 0);
+
+        budget.set(usize::MAX);
+        assert_eq!(1000, oa.len());
+        assert!((0..1000).all(|id| oa.get(&fragile(id)) == Some(&id)));
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn scattered_churn_rebuilds_in_place() {
+        for probing in [Probing::Quadratic, Probing::DoubleHashing] {
+            let mut rng = Rng::new(65);
+            let mut oa = OAHashMap::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+            oa.set_probing(probing);
+            let mut live: Vec<u64> = (0..1000).collect();
+            for &key in &live {
+                oa.insert(key, key);
+            }
+            let slots = oa.table.num_slots();
+            let settled = oa.memory_stats().probe_limit;
+
+            let mut limits = 0;
+            for next in 1000..1_001_000 {
+                let slot = rng.below(live.len() as u64) as usize;
+                assert!(oa.remove(&live[slot]).is_some());
+                oa.insert(next, next);
+                live[slot] = next;
+
+                let stats = oa.memory_stats();
+                assert!(stats.tombstones <= slots / CLEARED_DIVISOR);
+                limits += stats.probe_limit;
+            }
+
+            let mean = limits / 1_000_000;
+            assert!(mean <= settled * 2, "{probing:?}: {mean} after {settled}");
+            assert_eq!(slots, oa.table.num_slots());
+            assert_eq!(1000, oa.len());
+            assert!(live.iter().all(|key| oa.get(key) == Some(key)));
+        }
+    }
+
+    #[test]
+    fn rehash_drops_cleared_slots() {
+        let mut oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        let buffer = oa.table.as_ptr();
+        oa.rehash();
+        assert_eq!(buffer, oa.table.as_ptr());
+
+        oa.set_probing(Probing::DoubleHashing);
+        for i in (0..1000).filter(|i| i % 10 != 0) {
+            oa.remove(&i);
+        }
+        let slots = oa.table.num_slots();
+        assert!(oa.memory_stats().tombstones > 0);
+
+        oa.rehash();
+        let stats = oa.memory_stats();
+        assert_eq!(0, stats.tombstones);
+        assert_eq!(slots, stats.slots);
+        assert!((0..1000).step_by(10).all(|i| oa.get(&i) == Some(&i)));
+        assert_layout(&oa);
+
+        oa.remove(&0);
+        oa.rehash_shrink();
+        assert_eq!(0, oa.memory_stats().tombstones);
+        assert!(oa.table.num_slots() < slots);
+        assert!((10..1000).step_by(10).all(|i| oa.get(&i) == Some(&i)));
+        assert_eq!(99, oa.len());
+    }
+
+    #[test]
+    fn long_probes_grow_the_table() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1000);
+        let slots = oa.table.num_slots();
+        let cluster = keys_starting_at(&oa, 0, 100);
+        for key in (10_000..10_000 + slots as i32 / 8).filter(|key| !cluster.contains(key)) {
+            oa.insert(key, key);
+        }
+
+        for (inserted, &key) in cluster.iter().enumerate() {
+            oa.insert(key, key);
+            if oa.table.num_slots() == slots {
+                assert!(inserted < MAX_PROBE_LENGTH);
+            }
+        }
+        assert!(oa.table.num_slots() > slots);
+        assert!(oa.memory_stats().forced_resizes > 0);
+        assert!(cluster.iter().all(|key| oa.get(key) == Some(key)));
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn spread_keys_never_force_a_resize() {
+        let mut limited: OAHashMap<u64, u64> = OAHashMap::new();
+        let mut unlimited: OAHashMap<u64, u64> = OAHashMap::new();
+        unlimited.set_max_probe_length(usize::MAX);
+        let mut rng = Rng::new(67);
+        for _ in 0..100_000 {
+            let key = rng.next();
+            limited.insert(key, key);
+            unlimited.insert(key, key);
+        }
+
+        assert_eq!(0, limited.memory_stats().forced_resizes);
+        assert_eq!(unlimited.table.num_slots(), limited.table.num_slots());
+    }
+
+    #[test]
+    fn cleared_slots_count_towards_the_load_limit() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+        oa.set_probing(Probing::Quadratic);
+        oa.extend((0..1200).map(|i| (i, i)));
+        let slots = oa.table.num_slots();
+
+        for round in 1..50 {
+            for key in (round - 1) * 1200..round * 1200 {
+                oa.remove(&key);
+            }
+            for key in round * 1200..(round + 1) * 1200 {
+                oa.insert(key, key);
+                let stats = oa.memory_stats();
+                assert!(
+                    (stats.live + stats.tombstones) as f32 <= slots as f32 * EXTEND_LIMIT + 1.0
+                );
+            }
+        }
+
+        assert_eq!(slots, oa.table.num_slots());
+        assert!((49 * 1200..50 * 1200).all(|key| oa.get(&key) == Some(&key)));
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn growth_stops_at_the_largest_table() {
+        let config = crate::Config::DEFAULT;
+
+        let max = 1 << 40;
+        assert_eq!(Ok(INITIAL_CAPACITY), config.next_slots(0, max));
+        assert_eq!(Ok(8), config.next_slots(0, 8));
+        assert_eq!(Ok(max), config.next_slots(max / 2, max));
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            config.next_slots(max, max)
+        );
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            config.next_slots(1 << (usize::BITS - 1), usize::MAX)
+        );
+
+        assert_eq!(Ok(max), config.try_slots_from(1, max / 2, max));
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            config.try_slots_from(1, max, max)
+        );
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            config.try_slots_from(1, usize::MAX, usize::MAX)
+        );
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            config.try_slots_from(usize::MAX, 0, usize::MAX)
+        );
+    }
+
+    #[test]
+    fn churn_keeps_probe_distance_flat() {
+        fn mean_distance(oa: &OAHashMap<u64, u64>) -> f64 {
+            let total: usize = (0..oa.table.num_slots())
+                .filter_map(|index| Some(oa.distance(oa.table.get(index)?.hash, index)))
+                .sum();
+
+            total as f64 / oa.len() as f64
+        }
+
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::with_capacity(1 << 12);
+        let live = oa.capacity() as u64 * 3 / 4;
+        for key in 0..live {
+            oa.insert(key, key);
+        }
+        let slots = oa.table.num_slots();
+        let start = mean_distance(&oa);
+
+        let mut rng = Rng::new(0xc4);
+        let mut worst = start;
+        for cycle in 0..2_000_000 {
+            // Replace the oldest key, so the set of live keys keeps moving.
+            oa.remove(&cycle);
+            oa.insert(cycle + live, rng.next());
+            if cycle % 100_000 == 0 {
+                worst = worst.max(mean_distance(&oa));
+            }
+        }
+
+        assert_eq!(slots, oa.table.num_slots());
+        assert_eq!(live as usize, oa.len());
+        assert!(worst < start * 1.5 + 0.5, "{start} grew to {worst}");
+        assert_layout(&oa);
+    }
+
+    fn assert_probes_agree<S: BuildHasher>(oa: &OAHashMap<u64, u64, S>, key: u64) {
+        let hash = oa.make_hash(&key);
+        let find = oa.find_index_in::<Scalar, _>(hash, |k| *k == key);
+
+        assert_eq!(find.0, oa.probe_with(hash, |k| *k == key).ok());
+        assert_eq!(find, oa.find_index_in::<Swar, _>(hash, |k| *k == key));
+        #[cfg(all(
+            any(target_arch = "x86", target_arch = "x86_64"),
+            target_feature = "sse2"
+        ))]
+        assert_eq!(
+            find,
+            oa.find_index_in::<crate::group::Sse2, _>(hash, |k| *k == key)
+        );
+    }
+
+    fn replay_comparing_probes<S: BuildHasher>(mut oa: OAHashMap<u64, u64, S>, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for step in 0..5_000 {
+            let key = rng.below(200);
+            match rng.below(4) {
+                0 => {
+                    oa.remove(&key);
+                }
+                1 if step % 500 == 0 => oa.shrink_to_fit(),
+                _ => {
+                    oa.insert(key, step);
+                }
+            }
+
+            assert_probes_agree(&oa, key);
+            assert_probes_agree(&oa, rng.below(400));
+        }
+    }
+
+    #[test]
+    fn group_probes_match_scalar_probes() {
+        replay_comparing_probes(OAHashMap::new(), 60);
+        // Small keys hash to themselves, so every control byte matches and
+        // the full-hash check rejects the false positives.
+        replay_comparing_probes(OAHashMap::with_hasher(IdentityState), 61);
+    }
+
+    #[test]
+    fn insert_returns_previous_value() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 5, 2);
+
+        assert_eq!(None, oa.insert(keys[0], 1));
+        assert_eq!(None, oa.insert(keys[1], 2));
+        assert_eq!(Some(2), oa.insert(keys[1], 3));
+
+        oa.delete(&keys[0]);
+
+        assert_eq!(Some(3), oa.insert(keys[1], 4));
+        assert_eq!(None, oa.insert(keys[0], 5));
+        assert_eq!(Some(5), oa.insert(keys[0], 6));
+        assert_eq!(2, oa.len());
+
+        for i in 0..100 {
+            assert_eq!(None, oa.insert(1000 + i, i));
+        }
+        for i in 0..100 {
+            assert_eq!(Some(i), oa.insert(1000 + i, -i));
+        }
+    }
+
+    #[test]
+    fn get_mut_updates_in_place() {
+        let mut oa: OAHashMap<String, Vec<u32>> = OAHashMap::new();
+        oa.insert("a".to_string(), vec![1]);
+        oa.get_mut(&"a".to_string()).unwrap().push(2);
+
+        assert_eq!(Some(&vec![1, 2]), oa.search(&"a".to_string()));
+
+        oa.delete(&"a".to_string());
+
+        assert_eq!(None, oa.get_mut(&"a".to_string()));
+
+        oa.insert("a".to_string(), vec![3]);
+        oa.get_mut(&"a".to_string()).unwrap().push(4);
+
+        assert_eq!(Some(&vec![3, 4]), oa.search(&"a".to_string()));
+    }
+
+    #[test]
+    fn get_mut_after_removal() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 30, 2);
+        oa.insert(keys[0], 0);
+        oa.insert(keys[1], 1);
+        oa.delete(&keys[0]);
+
+        *oa.get_mut(&keys[1]).unwrap() += 10;
+
+        assert_eq!(Some(&11), oa.search(&keys[1]));
+        assert_eq!(None, oa.get_mut(&keys[0]));
+    }
+
+    #[test]
+    fn remove_returns_owned_value() {
+        let mut oa: OAHashMap<i32, String> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 20, 3);
+        for &key in &keys {
+            oa.insert(key, key.to_string());
+        }
+
+        assert_eq!(Some(keys[0].to_string()), oa.remove(&keys[0]));
+        assert_eq!(None, oa.remove(&keys[0]));
+        assert_eq!(Some(&keys[2].to_string()), oa.search(&keys[2]));
+        assert_eq!(
+            Some((keys[1], keys[1].to_string())),
+            oa.remove_entry(&keys[1])
+        );
+        assert_eq!(Some(&keys[2].to_string()), oa.search(&keys[2]));
+        assert_eq!(1, oa.len());
+    }
+
+    #[test]
+    fn compute_covers_every_transition() {
+        let mut oa: OAHashMap<&str, u32> = OAHashMap::new();
+
+        let mut given = Some(0);
+        assert_eq!(
+            None,
+            oa.compute("a", |value| {
+                given = value;
+                None
+            })
+        );
+        assert_eq!(None, given);
+        assert!(oa.is_empty());
+
+        assert_eq!(
+            Some(&mut 1),
+            oa.compute("a", |value| Some(value.unwrap_or(0) + 1))
+        );
+        *oa.compute("a", |value| value.map(|n| n * 10)).unwrap() += 5;
+        assert_eq!(Some(&15), oa.get("a"));
+
+        assert_eq!(
+            None,
+            oa.compute("a", |value| {
+                given = value;
+                None
+            })
+        );
+        assert_eq!(Some(15), given);
+        assert!(oa.is_empty());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn compute_removes_from_within_a_cluster() {
+        let mut oa = OAHashMap::with_hasher(CollidingState::default());
+        for i in 0..20 {
+            oa.insert(i, i.to_string());
+        }
+
+        assert_eq!(None, oa.compute(5, |value| value.filter(|_| false)));
+        assert!(!oa.contains_key(&5));
+        assert!((0..20).filter(|&i| i != 5).all(|i| oa[&i] == i.to_string()));
+        assert_eq!(19, oa.len());
+        assert_layout(&oa);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            oa.compute(7, |_| panic!("closure failed"));
+        }));
+        assert!(result.is_err());
+        assert!(!oa.contains_key(&7));
+        assert_eq!(18, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn remove_if_removes_only_what_the_predicate_accepts() {
+        let mut oa: OAHashMap<String, u32> = (0..100).map(|i| (i.to_string(), i)).collect();
+
+        let mut seen = None;
+        assert_eq!(
+            Some(7),
+            oa.remove_if("7", |key, &value| {
+                seen = Some((key.clone(), value));
+                value == 7
+            })
+        );
+        assert_eq!(Some(("7".to_string(), 7)), seen);
+        assert_eq!(None, oa.get("7"));
+
+        assert_eq!(None, oa.remove_if("8", |_, &value| value != 8));
+        assert_eq!(Some(&8), oa.get("8"));
+        assert_eq!(99, oa.len());
+
+        let mut called = false;
+        assert_eq!(
+            None,
+            oa.remove_if("missing", |_, _| {
+                called = true;
+                true
+            })
+        );
+        assert!(!called);
+
+        assert_eq!(
+            Some(("9".to_string(), 9)),
+            oa.remove_entry_if("9", |_, _| true)
+        );
+        assert_eq!(98, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn change_key_refuses_missing_and_taken_keys_and_ignores_its_own() {
+        let mut oa: OAHashMap<String, u32> = (0..50).map(|i| (i.to_string(), i)).collect();
+        let before = oa.clone();
+
+        let taken = oa.change_key("1", "2".to_string()).unwrap_err();
+        assert_eq!(RekeyError::Occupied("2".to_string()), taken);
+        let missing = oa.change_key("x", "y".to_string()).unwrap_err();
+        assert_eq!("y", missing.into_key());
+        assert_eq!(before, oa);
+
+        let mut tagged = OAHashMap::new();
+        tagged.insert(Tagged::new(1, "stored"), 10);
+        assert!(tagged
+            .change_key(&Tagged::new(1, "probe"), Tagged::new(1, "new"))
+            .is_ok());
+        let (key, &value) = tagged.iter().next().unwrap();
+        assert_eq!(("stored", 10), (key.tag, value));
+
+        oa.change_key("7", "seven".to_string()).unwrap();
+        assert_eq!(None, oa.get("7"));
+        assert_eq!(Some(&7), oa.get("seven"));
+        assert_eq!(50, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn change_key_moves_values_within_a_cluster() {
+        let mut oa = OAHashMap::with_hasher(CollidingState::default());
+        for i in 0..20 {
+            oa.insert(i, Box::new(i));
+        }
+        let address: *const i32 = &*oa[&5];
+
+        oa.change_key(&5, 100).unwrap();
+        assert!(core::ptr::eq(address, &*oa[&100]));
+        assert!(!oa.contains_key(&5));
+        assert!((0..20).filter(|&i| i != 5).all(|i| *oa[&i] == i));
+        assert_layout(&oa);
+
+        assert_eq!(Err(RekeyError::Occupied(19)), oa.change_key(&0, 19));
+        assert_eq!(Some(Box::new(19)), oa.change_key_replacing(&3, 19).unwrap());
+        assert_eq!(3, *oa[&19]);
+        assert_eq!(None, oa.change_key_replacing(&19, 19).unwrap());
+        assert_eq!(None, oa.change_key_replacing(&0, 200).unwrap());
+        assert_eq!(Err(RekeyError::NotFound(1)), oa.change_key_replacing(&0, 1));
+        assert_eq!(19, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn remove_entry_returns_stored_key() {
+        let mut oa: OAHashMap<Tagged, i32> = OAHashMap::new();
+        oa.insert(Tagged::new(1, "stored"), 10);
+
+        let (key, value) = oa.remove_entry(&Tagged::new(1, "query")).unwrap();
+
+        assert_eq!("stored", key.tag);
+        assert_eq!(10, value);
+        assert!(oa.is_empty());
+    }
+
+    #[test]
+    fn from_iter_and_array() {
+        let oa: OAHashMap<i32, &str> = OAHashMap::from([(1, "a"), (2, "b"), (1, "c")]);
+
+        assert_eq!(2, oa.len());
+        assert_eq!(Some(&"c"), oa.search(&1));
+        assert_eq!(Some(&"b"), oa.search(&2));
+
+        let oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i % 100, i)).collect();
+
+        assert_eq!(100, oa.len());
+        for i in 0..100 {
+            assert_eq!(Some(&(900 + i)), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn from_iter_presizes() {
+        let oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        let slots = oa.table.num_slots();
+        let presized: OAHashMap<i32, i32> = OAHashMap::try_with_slots(
+            crate::Config::DEFAULT
+                .try_slots_for(1000, usize::MAX)
+                .unwrap(),
+            crate::Config::DEFAULT,
+            Default::default(),
+            Global,
+        )
+        .unwrap();
+
+        assert_eq!(presized.table.num_slots(), slots);
+        assert!(oa.len() as f32 / slots as f32 <= crate::EXTEND_LIMIT);
+
+        let empty: OAHashMap<i32, i32> = std::iter::empty().collect();
+
+        assert!(empty.is_empty());
+        assert_eq!(0, empty.table.num_slots());
+    }
+
+    #[test]
+    fn overwrites_never_resize() {
+        let alloc = CountingAlloc::default();
+        let mut oa = OAHashMap::new_in(alloc.clone());
+        let mut keys = 0;
+        loop {
+            oa.insert(keys, 0);
+            keys += 1;
+            if oa.needs_extending() {
+                break;
+            }
+        }
+
+        let slots = oa.table.num_slots();
+        let allocations = alloc.stats().allocations;
+        for round in 0..5000 {
+            assert!(oa.insert(round % keys, round).is_some());
+            *oa.entry(round % keys).or_insert(0) += 1;
+        }
+        assert_eq!(slots, oa.table.num_slots());
+        assert_eq!(allocations, alloc.stats().allocations);
+        assert_eq!(keys as usize, oa.len());
+    }
+
+    #[test]
+    fn extend_resizes_at_most_once_per_batch() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        for batch in 0..5 {
+            let resizes = oa.resizes;
+            oa.extend((0..10_000).map(|i| (batch * 10_000 + i, i)));
+
+            assert!(oa.resizes - resizes <= 1);
+        }
+
+        assert_eq!(50_000, oa.len());
+
+        let resizes = oa.resizes;
+        let capacity = oa.table.num_slots();
+        oa.extend((0..10_000).map(|i| (i, -i)));
+
+        assert_eq!(50_000, oa.len());
+        assert_eq!(Some(&-5), oa.search(&5));
+        assert!(oa.resizes - resizes <= 1);
+        assert!(oa.table.num_slots() <= capacity * 2);
+    }
+
+    #[test]
+    fn extend_from_references() {
+        let source: OAHashMap<i32, i32> = (0..10).map(|i| (i, i * i)).collect();
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(3, 0);
+        oa.extend(source.iter());
+
+        assert_eq!(10, oa.len());
+        assert_eq!(Some(&9), oa.search(&3));
+    }
+
+    #[test]
+    fn retain_keeps_chains_intact() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(1);
+        let keys = keys_starting_at(&oa, 60, 8);
+        for &key in &keys {
+            oa.insert(key, key);
+        }
+
+        oa.retain(|key, value| {
+            *value *= 2;
+            key % 3 != 0
+        });
+
+        for &key in &keys {
+            let expected = (key % 3 != 0).then_some(key * 2);
+            assert_eq!(expected.as_ref(), oa.search(&key));
+        }
+    }
+
+    #[test]
+    fn retain_compacts_after_mass_removal() {
+        let mut oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        oa.retain(|key, _| key % 10 == 0);
+
+        assert_eq!(100, oa.len());
+        assert_layout(&oa);
+        assert!(oa.keys().all(|key| key % 10 == 0));
+    }
+
+    #[test]
+    fn retain_matches_std() {
+        let mut rng = Rng::new(22);
+        for _ in 0..50 {
+            let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+            let mut std_map = HashMap::new();
+            for _ in 0..rng.below(500) {
+                let key = rng.below(200);
+                if rng.below(4) == 0 {
+                    oa.delete(&key);
+                    std_map.remove(&key);
+                } else {
+                    let value = rng.next();
+                    oa.insert(key, value);
+                    std_map.insert(key, value);
+                }
+            }
+
+            let modulus = rng.below(5) + 1;
+            oa.retain(|key, value| {
+                *value = value.wrapping_add(*key);
+                key % modulus != 0
+            });
+            std_map.retain(|key, value| {
+                *value = value.wrapping_add(*key);
+                key % modulus != 0
+            });
+
+            assert_eq!(std_map.len(), oa.len());
+            assert_eq!(
+                std_map,
+                oa.iter().map(|(&k, &v)| (k, v)).collect::<HashMap<_, _>>()
+            );
+        }
+    }
+
+    /// Counts its drops.
+    struct DropGuard(Rc<Cell<usize>>);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn removals_drop_pairs_immediately() {
+        for probing in [
+            Probing::Linear,
+            Probing::Quadratic,
+            Probing::DoubleHashing,
+            Probing::Hopscotch,
+        ] {
+            let key = Rc::new(0);
+            let drops = Rc::new(Cell::new(0));
+            let mut oa = OAHashMap::new();
+            oa.set_probing(probing);
+            for i in 0..100 {
+                oa.insert((i, Rc::clone(&key)), DropGuard(Rc::clone(&drops)));
+            }
+
+            for i in 0..50 {
+                oa.delete(&(i, Rc::clone(&key)));
+                assert_eq!(i + 1, drops.get());
+                assert_eq!(100 - i, Rc::strong_count(&key));
+            }
+
+            for i in 100..150 {
+                oa.insert((i, Rc::clone(&key)), DropGuard(Rc::clone(&drops)));
+            }
+            oa.rehash_shrink();
+            assert_eq!(50, drops.get());
+            assert_eq!(101, Rc::strong_count(&key));
+
+            oa.clear();
+            assert_eq!(150, drops.get());
+            assert_eq!(1, Rc::strong_count(&key));
+        }
+    }
+
+    #[test]
+    fn clear_leaves_an_empty_map_when_a_drop_panics() {
+        struct PanicsOnDrop(u64);
+
+        impl Drop for PanicsOnDrop {
+            fn drop(&mut self) {
+                if self.0 == 2 {
+                    panic!("drop of 2");
+                }
+            }
+        }
+
+        let mut oa: OAHashMap<u64, (Box<u64>, PanicsOnDrop)> = OAHashMap::new();
+        for i in 0..10 {
+            oa.insert(i, (Box::new(i), PanicsOnDrop(i)));
+        }
+        let cleared = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| oa.clear()));
+        assert!(cleared.is_err());
+
+        assert_eq!(0, oa.len());
+        assert_eq!(0, oa.iter().count());
+        oa.check_invariants().unwrap();
+        oa.insert(3, (Box::new(3), PanicsOnDrop(3)));
+        assert_eq!(Some(&3), oa.get(&3).map(|(boxed, _)| &**boxed));
+    }
+
+    #[test]
+    fn clear_keeps_allocation() {
+        let tracker = Rc::new(());
+        let mut oa: OAHashMap<i32, Rc<()>> = OAHashMap::new();
+        for i in 0..200 {
+            oa.insert(i, Rc::clone(&tracker));
+        }
+        oa.delete(&0);
+        let buffer = oa.table.as_ptr();
+        let slots = oa.table.num_slots();
+
+        oa.clear();
+
+        assert_eq!(1, Rc::strong_count(&tracker));
+        assert!(oa.is_empty());
+        assert!((0..slots).all(|index| oa.table.ctrl(index) == EMPTY));
+        assert_eq!(slots, oa.table.num_slots());
+
+        for i in 0..200 {
+            oa.insert(i, Rc::clone(&tracker));
+        }
+
+        assert_eq!(buffer, oa.table.as_ptr());
+        assert_eq!(201, Rc::strong_count(&tracker));
+    }
+
+    #[test]
+    fn with_capacity_never_reallocates() {
+        for n in [1, 38, 39, 100, 1000, 12_345] {
+            let mut oa: OAHashMap<usize, usize> = OAHashMap::with_capacity(n);
+            let buffer = oa.table.as_ptr();
+            let slots = oa.table.num_slots();
+
+            assert!(oa.capacity() >= n);
+
+            for i in 0..n {
+                oa.insert(i, i);
+            }
+
+            assert_eq!(buffer, oa.table.as_ptr());
+            assert_eq!(slots, oa.table.num_slots());
+            assert_eq!(0, oa.resizes);
+        }
+    }
+
+    #[test]
+    fn capacity_is_reached_before_growth() {
+        let mut oa: OAHashMap<usize, usize> = OAHashMap::with_capacity(1);
+        let capacity = oa.capacity();
+        for i in 0..capacity {
+            oa.insert(i, i);
+        }
+
+        assert_eq!(capacity, oa.capacity());
+
+        for i in capacity..capacity * 2 {
+            oa.insert(i, i);
+        }
+
+        assert!(oa.capacity() > capacity);
+    }
+
+    #[test]
+    fn reserve_covers_additional_inserts() {
+        let mut oa: OAHashMap<usize, usize> = OAHashMap::new();
+        for i in 0..30 {
+            oa.insert(i, i);
+        }
+        for i in 0..20 {
+            oa.delete(&i);
+        }
+
+        oa.reserve(500);
+        let buffer = oa.table.as_ptr();
+        let resizes = oa.resizes;
+
+        assert_layout(&oa);
+        assert!(oa.capacity() >= oa.len() + 500);
+
+        for i in 1000..1500 {
+            oa.insert(i, i);
+        }
+
+        assert_eq!(buffer, oa.table.as_ptr());
+        assert_eq!(resizes, oa.resizes);
+    }
+
+    #[test]
+    fn reserve_noop_when_covered() {
+        let mut oa: OAHashMap<usize, usize> = OAHashMap::new();
+        oa.insert(1, 1);
+        let buffer = oa.table.as_ptr();
+
+        oa.reserve(0);
+        oa.reserve(10);
+
+        assert_eq!(buffer, oa.table.as_ptr());
+        assert_eq!(0, oa.resizes);
+    }
+
+    #[test]
+    fn try_with_capacity_reports_errors() {
+        assert_eq!(
+            Some(TryReserveError::CapacityOverflow),
+            OAHashMap::<u64, u64>::try_with_capacity(usize::MAX).err()
+        );
+        assert!(matches!(
+            OAHashMap::<u64, u64>::try_with_capacity(1 << 56),
+            Err(TryReserveError::AllocError { .. })
+        ));
+
+        let oa = OAHashMap::<u64, u64>::try_with_capacity(100).unwrap();
+        assert!(oa.capacity() >= 100);
+    }
+
+    #[test]
+    fn try_reserve_keeps_map_on_failure() {
+        let mut oa: OAHashMap<u64, u64> = (0..100).map(|i| (i, i)).collect();
+        let buffer = oa.table.as_ptr();
+
+        assert_eq!(
+            Err(TryReserveError::CapacityOverflow),
+            oa.try_reserve(usize::MAX)
+        );
+        assert!(matches!(
+            oa.try_reserve(1 << 56),
+            Err(TryReserveError::AllocError { .. })
+        ));
+        assert_eq!(buffer, oa.table.as_ptr());
+        assert_eq!(100, oa.len());
+        for i in 0..100 {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+
+        assert_eq!(Ok(()), oa.try_reserve(1000));
+        assert!(oa.capacity() >= 1100);
+    }
+
+    #[test]
+    fn shrink_to_fit_after_filtering() {
+        let mut oa: OAHashMap<i32, i32> = (0..10_000).map(|i| (i, i * 2)).collect();
+        oa.retain(|k, _| k % 32 == 0);
+        for i in (1..10_000).filter(|i| i % 32 == 1) {
+            oa.insert(i, i * 2);
+            oa.remove(&i);
+        }
+        let before = oa.capacity();
+
+        oa.shrink_to_fit();
+
+        assert!(oa.capacity() < before / 16);
+        assert!(oa.capacity() >= oa.len());
+        assert_layout(&oa);
+        assert_eq!(313, oa.len());
+        for i in 0..10_000 {
+            let expected = (i % 32 == 0).then_some(i * 2);
+            assert_eq!(expected.as_ref(), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn shrink_to_respects_minimum() {
+        let mut oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        oa.retain(|k, _| *k < 10);
+
+        oa.shrink_to(200);
+        assert!(oa.capacity() >= 200);
+        assert!(oa.capacity() < 400);
+
+        let buffer = oa.table.as_ptr();
+        oa.shrink_to(300);
+        oa.shrink_to(10_000);
+        assert_eq!(buffer, oa.table.as_ptr());
+
+        oa.shrink_to(0);
+        assert!(oa.capacity() >= 10);
+        assert!(oa.capacity() < 20);
+        let buffer = oa.table.as_ptr();
+        oa.shrink_to_fit();
+        assert_eq!(buffer, oa.table.as_ptr());
+        for i in 0..10 {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+    }
+
+    #[test]
+    fn shrink_empty_map_to_zero() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.shrink_to_fit();
+        assert_eq!(0, oa.capacity());
+        assert_eq!(0, oa.table.num_slots());
+        assert_eq!(None, oa.search(&1));
+        assert!(oa.iter().next().is_none());
+
+        oa.entry(1).or_insert(10);
+        oa.shrink_to_fit();
+        oa.insert(2, 20);
+        oa.shrink_to_fit();
+        assert_eq!(Some(&10), oa.search(&1));
+        assert_eq!(Some(&20), oa.search(&2));
+
+        oa.clear();
+        oa.shrink_to_fit();
+        assert_eq!(0, oa.table.num_slots());
+        for i in 0..100 {
+            oa.insert(i, i);
+        }
+        assert_eq!(100, oa.len());
+    }
+
+    #[test]
+    fn insert_within_capacity_stops_at_threshold() {
+        let mut oa: OAHashMap<usize, usize> = OAHashMap::with_capacity(100);
+        let capacity = oa.capacity();
+        let buffer = oa.table.as_ptr();
+        for i in 0..capacity {
+            assert_eq!(Ok(None), oa.insert_within_capacity(i, i));
+        }
+
+        assert_eq!(capacity, oa.len());
+        assert_eq!(Err((capacity, 0)), oa.insert_within_capacity(capacity, 0));
+        assert_eq!(capacity, oa.len());
+        assert_eq!(None, oa.search(&capacity));
+        assert_eq!(Ok(Some(3)), oa.insert_within_capacity(3, 30));
+        assert_eq!(Some(&30), oa.search(&3));
+
+        oa.remove(&0);
+        assert_eq!(Ok(None), oa.insert_within_capacity(capacity, 0));
+        assert_eq!(buffer, oa.table.as_ptr());
+        assert_eq!(capacity, oa.capacity());
+    }
+
+    #[test]
+    fn insert_within_capacity_churns_in_place() {
+        let mut oa: OAHashMap<usize, usize> = OAHashMap::with_capacity(1);
+        let buffer = oa.table.as_ptr();
+        for i in 0..1000 {
+            assert_eq!(Ok(None), oa.insert_within_capacity(i, i));
+            oa.remove(&i);
+        }
+
+        assert!(oa.is_empty());
+        assert_eq!(buffer, oa.table.as_ptr());
+
+        oa.insert(1, 1);
+        assert_layout(&oa);
+        assert_eq!(Ok(None), oa.insert_within_capacity(2, 2));
+
+        let mut empty: OAHashMap<usize, usize> = OAHashMap::new();
+        empty.shrink_to_fit();
+        assert_eq!(Err((1, 1)), empty.insert_within_capacity(1, 1));
+    }
+
+    #[test]
+    fn unchecked_inserts_lay_out_unique_keys_like_inserts() {
+        for probing in [Probing::Linear, Probing::Quadratic, Probing::Hopscotch] {
+            for incremental in [false, true] {
+                let mut rng = Rng::new(96);
+                let mut checked = OAHashMap::new();
+                checked.set_probing(probing);
+                checked.set_incremental_resize(incremental);
+                let mut unchecked = OAHashMap::with_hasher(checked.hasher().clone());
+                unchecked.set_probing(probing);
+                unchecked.set_incremental_resize(incremental);
+
+                let mut keys: Vec<u64> = (0..5000).map(|i| i * 7919).collect();
+                for i in (1..keys.len()).rev() {
+                    keys.swap(i, rng.below(i as u64 + 1) as usize);
+                }
+                for &key in &keys {
+                    checked.insert(key, key.to_string());
+                    let (k, v) = unchecked.insert_unique_unchecked(key, key.to_string());
+                    assert_eq!((&key, &mut key.to_string()), (k, v));
+                }
+
+                assert_eq!(checked.debug_layout(), unchecked.debug_layout());
+                assert_eq!(checked, unchecked);
+                assert_eq!(Ok(()), unchecked.check_invariants());
+            }
+        }
+    }
+
+    #[test]
+    fn append_moves_every_entry_and_prefers_the_other_map() {
+        #[derive(PartialEq, Debug)]
+        struct Unclonable(&'static str, i32);
+
+        let mut oa: OAHashMap<i32, Unclonable> =
+            (0..100).map(|i| (i, Unclonable("self", i))).collect();
+        let mut other: OAHashMap<i32, Unclonable> =
+            (50..300).map(|i| (i, Unclonable("other", i))).collect();
+        let (resizes, capacity) = (oa.stats().resizes, other.capacity());
+
+        oa.append(&mut other);
+
+        assert_eq!(300, oa.len());
+        assert!(oa.stats().resizes <= resizes + 1);
+        assert_eq!(Some(&Unclonable("self", 49)), oa.get(&49));
+        assert_eq!(Some(&Unclonable("other", 50)), oa.get(&50));
+        assert_eq!(Some(&Unclonable("other", 299)), oa.get(&299));
+        assert!(other.is_empty());
+        assert_eq!(capacity, other.capacity());
+        assert_layout(&oa);
+
+        other.insert(1, Unclonable("reused", 1));
+        oa.append(&mut other);
+        assert_eq!(Some(&Unclonable("reused", 1)), oa.get(&1));
+        assert_eq!(300, oa.len());
+    }
+
+    #[test]
+    fn merging_sums_counters_and_picks_the_newest() {
+        let mut counts: OAHashMap<&str, u32> = [("a", 1), ("b", 2)].into_iter().collect();
+        let other: OAHashMap<&str, u32> = [("b", 10), ("c", 3)].into_iter().collect();
+        counts.merge_from(&other, |_, mine, theirs| mine + theirs);
+        assert_eq!(Some(&12), counts.get("b"));
+        counts.merge_with(other, |_, mine, theirs| mine + theirs);
+        let merged: HashMap<_, _> = counts.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(HashMap::from([("a", 1), ("b", 22), ("c", 6)]), merged);
+
+        let mut seen: OAHashMap<i32, (u64, String)> = (0..100)
+            .map(|i| (i, (i as u64 % 7, format!("old {i}"))))
+            .collect();
+        let updates = (50..150).map(|i| (i, (3, format!("new {i}")))).collect();
+        let mut keys = Vec::new();
+        seen.merge_with(updates, |&key, mine, theirs| {
+            keys.push(key);
+            if theirs.0 > mine.0 {
+                theirs
+            } else {
+                mine
+            }
+        });
+        keys.sort_unstable();
+        assert_eq!((50..100).collect::<Vec<_>>(), keys);
+        assert_eq!(150, seen.len());
+        assert_eq!("new 50", seen[&50].1);
+        assert_eq!("old 55", seen[&55].1);
+        assert_eq!("new 120", seen[&120].1);
+        assert_layout(&seen);
+    }
+
+    #[test]
+    fn try_from_iter_builds_clean_input_and_reports_the_first_repeat() {
+        let oa = OAHashMap::<i32, String>::try_from_iter((0..1_000).map(|i| (i, i.to_string())))
+            .unwrap();
+        assert_eq!(1_000, oa.len());
+        assert_eq!(0, oa.stats().resizes);
+        assert_eq!("999", oa[&999]);
+        assert_layout(&oa);
+
+        let records = [(7, "first"), (3, "other"), (7, "second"), (3, "again")];
+        let error = OAHashMap::<i32, &str>::try_from_iter(records).unwrap_err();
+        assert_eq!(
+            DuplicateKeyError {
+                key: 7,
+                first: Some("first"),
+                second: "second"
+            },
+            error
+        );
+        assert_eq!(
+            "key 7 repeats, with value \"first\" and then \"second\"",
+            error.to_string()
+        );
+    }
+
+    fn repeat<K, V>(result: Result<(), TryExtendError<K, V>>) -> DuplicateKeyError<K, V> {
+        match result {
+            Err(TryExtendError::Duplicate(error)) => error,
+            _ => panic!("Expected a repeated key"),
+        }
+    }
+
+    #[test]
+    fn try_extend_leaves_the_map_as_it_was_on_a_repeat() {
+        let mut oa: OAHashMap<i32, String> = (0..10).map(|i| (i, i.to_string())).collect();
+        let before = oa.clone();
+
+        let error = repeat(oa.try_extend([(20, "a".to_string()), (5, "b".to_string())]));
+        assert_eq!(
+            (5, None, "b".to_string()),
+            (error.key, error.first, error.second)
+        );
+        assert_eq!(before, oa);
+
+        let error = repeat(oa.try_extend([
+            (20, "a".to_string()),
+            (21, "b".to_string()),
+            (20, "c".to_string()),
+        ]));
+        assert_eq!(Some("a".to_string()), error.first);
+        assert_eq!(before, oa);
+
+        oa.try_extend((10..100).map(|i| (i, i.to_string())))
+            .unwrap();
+        assert_eq!(100, oa.len());
+        assert_eq!("42", oa[&42]);
+        assert_layout(&oa);
+        assert!(oa.try_extend([]).is_ok());
+    }
+
+    #[test]
+    fn try_extend_reports_the_first_repeat_in_input_order() {
+        let mut oa: OAHashMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let before = oa.clone();
+
+        let batch = (200..300).chain((0..100).rev()).map(|i| (i, -i));
+        let error = repeat(oa.try_extend(batch));
+        assert_eq!((99, None, -99), (error.key, error.first, error.second));
+
+        let error = repeat(oa.try_extend([(200, 1), (200, 2), (5, 3)]));
+        assert_eq!((200, Some(1), 2), (error.key, error.first, error.second));
+        assert_eq!(before, oa);
+    }
+
+    #[test]
+    fn try_extend_past_max_entries_fails_before_moving_anything() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMapBuilder::new().max_entries(12).build();
+        oa.extend((0..10).map(|i| (i, i)));
+        let before = oa.clone();
+
+        assert_eq!(
+            Err(TryExtendError::LimitReached { max_entries: 12 }),
+            oa.try_extend((10..13).map(|i| (i, i)))
+        );
+        assert_eq!(before, oa);
+        oa.check_invariants().unwrap();
+
+        oa.try_extend((10..12).map(|i| (i, i))).unwrap();
+        assert_eq!(12, oa.len());
+    }
+
+    #[test]
+    fn merging_with_an_empty_map_on_either_side() {
+        let full: OAHashMap<i32, String> = (0..20).map(|i| (i, i.to_string())).collect();
+        let never = |_: &i32, _: String, _: String| -> String { unreachable!() };
+
+        let mut empty = OAHashMap::with_hasher(full.hasher().clone());
+        empty.merge_from(&full, never);
+        assert_eq!(full, empty);
+
+        let mut kept = full.clone();
+        kept.merge_with(OAHashMap::new(), never);
+        kept.merge_from(&OAHashMap::new(), never);
+        assert_eq!(full, kept);
+    }
+
+    #[test]
+    fn merging_closes_up_an_entry_whose_closure_panicked() {
+        for probing in [Probing::Linear, Probing::Quadratic] {
+            let mut oa: OAHashMap<i32, String> = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.extend((0..200).map(|i| (i, i.to_string())));
+            let other: OAHashMap<i32, String> = [(7, "x".to_string())].into_iter().collect();
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                oa.merge_from(&other, |_, _, _| panic!("merge failed"));
+            }));
+            assert!(result.is_err());
+            assert_eq!(199, oa.len());
+            assert_eq!(None, oa.get(&7));
+            assert!((0..200)
+                .filter(|&i| i != 7)
+                .all(|i| oa[&i] == i.to_string()));
+            assert_layout(&oa);
+        }
+    }
+
+    #[test]
+    fn partition_moves_each_entry_into_one_half() {
+        for probing in [Probing::Linear, Probing::DoubleHashing] {
+            let token = Rc::new(());
+            let mut oa = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.extend((0..4000).map(|i| (i, (i.to_string(), Rc::clone(&token)))));
+            let original_capacity = oa.capacity();
+
+            let (small, rest) = oa.partition(|&key, (text, _)| key % 10 == 0 && text.len() > 1);
+            assert_eq!(4001, Rc::strong_count(&token));
+            assert_eq!((399, 3601), (small.len(), rest.len()));
+            assert!(small.keys().all(|key| !rest.contains_key(key)));
+            let mut union: Vec<_> = small.keys().chain(rest.keys()).copied().collect();
+            union.sort_unstable();
+            assert_eq!((0..4000).collect::<Vec<_>>(), union);
+            assert!(small.capacity() < original_capacity / 4);
+            assert_eq!(
+                OAHashMap::<i32, ()>::with_capacity(rest.len()).capacity(),
+                rest.capacity()
+            );
+            assert_eq!(probing, small.probing());
+            assert_layout(&small);
+            assert_layout(&rest);
+
+            drop(small);
+            assert_eq!(3602, Rc::strong_count(&token));
+            drop(rest);
+            assert_eq!(1, Rc::strong_count(&token));
+        }
+    }
+
+    #[test]
+    fn split_off_by_leaves_the_rest_reachable() {
+        for probing in [Probing::Linear, Probing::Quadratic] {
+            let mut oa: OAHashMap<i32, String> = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.extend((0..1000).map(|i| (i, i.to_string())));
+
+            let odd = oa.split_off_by(|&key, _| key % 2 == 1);
+            assert_eq!((500, 500), (odd.len(), oa.len()));
+            assert!((0..1000).all(|i| {
+                let (here, there) = if i % 2 == 1 { (&odd, &oa) } else { (&oa, &odd) };
+                here[&i] == i.to_string() && !there.contains_key(&i)
+            }));
+            assert_layout(&oa);
+            assert_eq!(Ok(()), oa.check_invariants());
+            assert_eq!(Ok(()), odd.check_invariants());
+
+            assert!(oa.split_off_by(|_, _| false).is_empty());
+            assert_eq!(500, oa.len());
+        }
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn map_values_keeps_every_slot_without_hashing() {
+        use crate::test_util::Hashed;
+
+        fn slots<V>(oa: &OAHashMap<Hashed, V>) -> Vec<Option<(i32, usize)>> {
+            oa.debug_layout()
+                .slots
+                .iter()
+                .map(|slot| match slot {
+                    crate::SlotLayout::Occupied { key, distance, .. } => Some((key.0, *distance)),
+                    crate::SlotLayout::Empty => None,
+                })
+                .collect()
+        }
+        for probing in [Probing::Linear, Probing::Quadratic, Probing::Hopscotch] {
+            let mut oa = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.extend((0..1000).map(|i| (Hashed(i), i)));
+            oa.retain(|key, _| key.0 % 3 != 0);
+            let before = slots(&oa);
+            let hashes = Hashed::hashes();
+
+            let mut oa = oa.map_values(|key, value| i64::from(key.0 + value));
+            assert_eq!(before, slots(&oa));
+            oa.transform_values(|key, value| *value -= i64::from(key.0));
+            assert_eq!(hashes, Hashed::hashes());
+            assert_eq!(Ok(()), oa.check_invariants());
+            assert_eq!(Some(&7), oa.get(&Hashed(7)));
+            assert_eq!(None, oa.get(&Hashed(9)));
+        }
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn insert_mut_hashes_once_and_points_into_the_grown_table() {
+        use crate::test_util::Hashed;
+
+        for probing in [Probing::Linear, Probing::Quadratic, Probing::Hopscotch] {
+            let mut oa: OAHashMap<Hashed, Vec<i32>> = OAHashMap::new();
+            oa.set_probing(probing);
+            let before = Hashed::hashes();
+            for i in 0..1000 {
+                oa.insert_mut(Hashed(i), Vec::new()).push(i);
+            }
+            *oa.insert_mut(Hashed(7), vec![0]) = vec![70];
+            assert_eq!(1001, Hashed::hashes() - before);
+
+            assert!(oa.stats().resizes > 4);
+            assert_eq!(1000, oa.len());
+            assert_eq!(Some(&vec![70]), oa.get(&Hashed(7)));
+            assert!((0..1000)
+                .filter(|&i| i != 7)
+                .all(|i| oa.get(&Hashed(i)) == Some(&vec![i])));
+        }
+    }
+
+    #[test]
+    fn maps_of_keys_without_hash_can_be_named_made_and_read() {
+        #[derive(Default)]
+        struct NoHash;
+
+        #[derive(Default)]
+        struct Wrapper<K, V>(OAHashMap<K, V>);
+
+        fn summary<K, V>(wrapper: &Wrapper<K, V>) -> (usize, bool, usize) {
+            let map = &wrapper.0;
+
+            (map.len(), map.is_empty(), map.iter().count())
+        }
+
+        let mut unhashable: Wrapper<NoHash, i32> = Wrapper::default();
+        assert_eq!((0, true, 0), summary(&unhashable));
+        unhashable.0.clear();
+        unhashable.0.transform_values(|_, value| *value += 1);
+        assert_eq!(0, unhashable.0.values_mut().count());
+        assert!(OAHashMap::<NoHash, ()>::with_capacity(10).capacity() >= 10);
+
+        let mut words: Wrapper<&str, i32> = Wrapper::default();
+        words.0.insert("one", 1);
+        assert_eq!((1, false, 1), summary(&words));
+    }
+
+    #[test]
+    fn pop_yields_every_entry_once() {
+        let mut empty: OAHashMap<i32, i32> = OAHashMap::new();
+        assert_eq!(None, empty.pop());
+
+        for (probing, incremental) in [
+            (Probing::Linear, false),
+            (Probing::Linear, true),
+            (Probing::Quadratic, true),
+        ] {
+            let mut oa = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.set_incremental_resize(incremental);
+            oa.extend((0..1000).map(|i| (i, -i)));
+
+            let mut popped = HashSet::new();
+            while let Some((key, value)) = oa.pop() {
+                assert_eq!(-key, value);
+                assert!(popped.insert(key));
+                assert_eq!(1000 - popped.len(), oa.len());
+            }
+            assert_eq!(1000, popped.len());
+            assert_eq!(None, oa.pop());
+            assert_eq!(Ok(()), oa.check_invariants());
+        }
+    }
+
+    #[test]
+    fn pop_with_inserts_in_between_works_through_a_worklist() {
+        // Each number pushes its successors under the Collatz step until it
+        // reaches one, so every number is inserted at most once.
+        let mut work = OAHashMap::from([(27u64, ())]);
+        let mut done = HashSet::new();
+        while let Some((n, ())) = work.pop() {
+            assert!(done.insert(n));
+            let next = if n % 2 == 0 { n / 2 } else { 3 * n + 1 };
+            if n != 1 && !done.contains(&next) {
+                work.insert(next, ());
+            }
+            for m in [2 * n, 2 * n + 1] {
+                if m < 200 && !done.contains(&m) {
+                    work.insert(m, ());
+                }
+            }
+        }
+        assert!(work.is_empty());
+        assert!((1..200).all(|n| done.contains(&n)));
+    }
+
+    #[test]
+    fn try_map_values_drops_every_entry_when_it_stops() {
+        let token = Rc::new(());
+        let mut oa = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        oa.extend((0..100).map(|i| (i, Rc::clone(&token))));
+        let mapped = oa.clone().try_map_values(|&key, value| match key {
+            50 => Err(key),
+            _ => Ok((value, Rc::clone(&token))),
+        });
+        assert_eq!(Some(50), mapped.err());
+        assert_eq!(101, Rc::strong_count(&token));
+
+        let mapped = oa.try_map_values(|_, value| Ok::<_, ()>(vec![value]));
+        let mapped = mapped.unwrap();
+        assert_eq!(100, mapped.len());
+        assert!((0..100).all(|key| mapped[&key].len() == 1));
+        drop(mapped);
+        assert_eq!(1, Rc::strong_count(&token));
+    }
+
+    #[test]
+    fn replace_swaps_in_the_new_key() {
+        let mut oa = OAHashMap::new();
+        assert!(oa.replace(Tagged::new(1, "first"), 10).is_none());
+        oa.insert(Tagged::new(1, "kept by insert"), 11);
+        assert_eq!("first", oa.keys().next().unwrap().tag);
+
+        let (old_key, old_value) = oa.replace(Tagged::new(1, "second"), 12).unwrap();
+        assert_eq!(("first", 11), (old_key.tag, old_value));
+        let (key, value) = oa.get_key_value(&Tagged::new(1, "lookup")).unwrap();
+        assert_eq!(("second", 12), (key.tag, *value));
+        assert_eq!(1, oa.len());
+    }
+
+    #[test]
+    fn get_or_insert_only_builds_missing_values() {
+        let mut oa: OAHashMap<i32, Vec<i32>> = OAHashMap::new();
+        oa.get_or_insert_with(1, Vec::new).push(10);
+        oa.get_or_insert_with(1, || unreachable!()).push(11);
+        oa.get_or_insert(2, vec![20]).push(21);
+        oa.get_or_insert(2, vec![0]).push(22);
+        oa.get_or_insert_default(3).push(30);
+        oa.get_or_insert_default(3).push(31);
+
+        assert_eq!(vec![10, 11], oa[&1]);
+        assert_eq!(vec![20, 21, 22], oa[&2]);
+        assert_eq!(vec![30, 31], oa[&3]);
+        assert_eq!(3, oa.len());
+    }
+
+    #[test]
+    fn get_or_insert_finds_keys_past_tombstones() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(32);
+        oa.set_probing(Probing::Quadratic);
+        let keys = keys_starting_at(&oa, 5, 5);
+        for &key in &keys[..4] {
+            oa.insert(key, key);
+        }
+        oa.remove(&keys[0]);
+        oa.remove(&keys[1]);
+        assert_eq!(2, oa.memory_stats().tombstones);
+
+        assert_eq!(keys[3], *oa.get_or_insert_with(keys[3], || unreachable!()));
+        *oa.get_or_insert_default(keys[4]) += 1;
+        assert_eq!(1, oa[&keys[4]]);
+        assert_eq!(keys[2], *oa.get_or_insert(keys[2], 0));
+        assert_eq!(3, oa.len());
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn get_or_insert_grows_on_a_miss_into_a_full_table() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(10);
+        // The table grows once it holds more than its capacity.
+        let capacity = oa.capacity();
+        for key in 0..=capacity as i32 {
+            oa.insert(key, key);
+        }
+        assert_eq!(0, oa.stats().resizes);
+
+        *oa.get_or_insert_with(-1, || 100) += 1;
+        assert_eq!(1, oa.stats().resizes);
+        assert!(oa.capacity() > capacity);
+        assert_eq!(101, oa[&-1]);
+        assert!((0..=capacity as i32).all(|key| oa[&key] == key));
+        assert_layout(&oa);
+    }
+
+    #[test]
+    fn unchecked_duplicates_stay_memory_safe() {
+        let mut oa: OAHashMap<i32, String> = (0..10).map(|i| (i, i.to_string())).collect();
+        oa.insert_unique_unchecked(3, "again".to_string());
+
+        assert_eq!(11, oa.len());
+        assert!(matches!(
+            oa.check_invariants(),
+            Err(InvariantError::Duplicate { .. })
+        ));
+        assert!(oa.remove(&3).is_some());
+        assert!(oa.remove(&3).is_some());
+        assert_eq!(None, oa.remove(&3));
+        assert_eq!(Ok(()), oa.check_invariants());
+    }
+
+    #[test]
+    fn new_does_not_allocate() {
+        let mut oa: OAHashMap<String, i32> = OAHashMap::new();
+
+        assert_eq!(0, oa.capacity());
+        assert_eq!(0, oa.table.num_slots());
+        assert_eq!(0, OAHashMap::<i32, i32>::with_capacity(0).table.num_slots());
+        assert_eq!(None, oa.search(&"a".to_string()));
+        oa.delete(&"a".to_string());
+        assert_eq!(None, oa.remove(&"a".to_string()));
+        assert_eq!(None, oa.get_mut(&"a".to_string()));
+        assert!(oa.iter().next().is_none());
+        assert!(oa.drain().next().is_none());
+        oa.retain(|_, _| false);
+        oa.clear();
+        assert_eq!(0, oa.table.num_slots());
+
+        oa.insert("a".to_string(), 1);
+        assert_eq!(crate::INITIAL_CAPACITY, oa.table.num_slots());
+        assert_eq!(Some(&1), oa.search(&"a".to_string()));
+
+        let mut reserved: OAHashMap<i32, i32> = OAHashMap::new();
+        reserved.reserve(1);
+        assert_eq!(crate::INITIAL_CAPACITY, reserved.table.num_slots());
+    }
+
+    #[test]
+    fn const_new_and_default() {
+        static MAP: Mutex<OAHashMap<u32, u32>> = Mutex::new(OAHashMap::new());
+
+        #[derive(Default)]
+        struct Scope {
+            names: OAHashMap<String, usize>,
+        }
+
+        MAP.lock().unwrap().insert(1, 2);
+        assert_eq!(Some(&2), MAP.lock().unwrap().search(&1));
+
+        let scope = Scope::default();
+        assert!(scope.names.is_empty());
+        assert_eq!(0, scope.names.capacity());
+    }
+
+    #[test]
+    fn auto_shrink_reclaims_after_mass_removal() {
+        let mut oa: OAHashMap<usize, usize> = (0..10_000).map(|i| (i, i)).collect();
+        oa.set_auto_shrink(true);
+        let peak = oa.capacity();
+
+        for i in 0..9_500 {
+            oa.remove(&i);
+        }
+
+        assert!(oa.capacity() < peak / 4);
+        assert!(oa.capacity() >= oa.len() * 2);
+        for i in 9_500..10_000 {
+            assert_eq!(Some(&i), oa.search(&i));
+        }
+
+        oa.retain(|k, _| *k >= 9_990);
+        assert_eq!(10, oa.len());
+        assert_eq!(crate::INITIAL_CAPACITY, oa.table.num_slots());
+    }
+
+    #[test]
+    fn auto_shrink_does_not_thrash() {
+        let mut oa: OAHashMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+        oa.set_auto_shrink(true);
+        oa.retain(|k, _| *k < 100);
+        let resizes = oa.resizes;
+
+        for i in 100..1000 {
+            oa.insert(i, i);
+            oa.remove(&i);
+            if let crate::Entry::Occupied(entry) = oa.entry(i % 100) {
+                let (k, v) = entry.remove_entry();
+                oa.insert(k, v);
+            }
+        }
+
+        assert_eq!(resizes, oa.resizes);
+    }
+
+    #[test]
+    fn auto_shrink_is_off_by_default() {
+        let mut oa: OAHashMap<usize, usize> = (0..10_000).map(|i| (i, i)).collect();
+        let peak = oa.capacity();
+
+        for i in 0..9_990 {
+            oa.remove(&i);
+        }
+        oa.retain(|k, _| *k % 2 == 0);
+
+        assert_eq!(peak, oa.capacity());
+    }
+
+    #[test]
+    fn allocations_go_through_allocator() {
+        let alloc = CountingAlloc::default();
+        let mut oa = OAHashMap::new_in(alloc.clone());
+        assert_eq!(0, alloc.stats().allocations);
+
+        for i in 0..1000 {
+            oa.insert(i, i.to_string());
+        }
+        let grown = alloc.stats();
+        assert_eq!(oa.resizes + 1, grown.allocations);
+        assert_eq!(grown.allocations - 1, grown.deallocations);
+
+        oa.retain(|k, _| *k < 10);
+        oa.shrink_to_fit();
+        let shrunk = alloc.stats();
+        assert!(shrunk.allocations > grown.allocations);
+        assert_eq!(shrunk.allocations - 1, shrunk.deallocations);
+        assert!(shrunk.live_bytes < grown.live_bytes);
+        assert_eq!(Some(&"3".to_string()), oa.search(&3));
+
+        let keys: Vec<i32> = oa.into_keys().collect();
+        assert_eq!(10, keys.len());
+        assert_eq!(0, alloc.stats().live_bytes);
+        assert_eq!(alloc.stats().allocations, alloc.stats().deallocations);
+    }
+
+    #[test]
+    fn with_capacity_in_uses_allocator() {
+        let alloc = CountingAlloc::default();
+        let mut oa = OAHashMap::with_capacity_in(500, alloc.clone());
+        oa.extend((0..500).map(|i| (i, i)));
+
+        assert_eq!(1, oa.allocator().stats().allocations);
+        drop(oa);
+        assert_eq!(1, alloc.stats().deallocations);
+        assert_eq!(0, alloc.stats().live_bytes);
+    }
+
+    #[test]
+    fn allocated_bytes_matches_allocator() {
+        let alloc = CountingAlloc::default();
+        let mut oa = OAHashMap::new_in(alloc.clone());
+        assert_eq!(0, oa.allocated_bytes());
+
+        for i in 0..1000 {
+            oa.insert(i, i);
+            assert_eq!(alloc.stats().live_bytes, oa.allocated_bytes());
+        }
+        for i in 0..990 {
+            oa.remove(&i);
+        }
+
+        let stats = oa.memory_stats();
+        assert_eq!(oa.allocated_bytes(), stats.allocated_bytes());
+        assert_eq!(oa.table.num_slots(), stats.slots);
+        assert_eq!(
+            std::mem::size_of::<crate::HashedKey<i32>>() + std::mem::size_of::<i32>() + 1,
+            stats.slot_bytes
+        );
+        assert_eq!(10, stats.live);
+        assert_eq!(0, stats.tombstones);
+
+        let before = oa.allocated_bytes();
+        oa.shrink_to_fit();
+        assert!(oa.allocated_bytes() < before);
+        assert_eq!(alloc.stats().live_bytes, oa.allocated_bytes());
+        assert_eq!(0, oa.memory_stats().tombstones);
+    }
+
+    #[derive(Clone, Default)]
+    struct CollidingState {
+        builds: Rc<Cell<usize>>,
+    }
+
+    struct CollidingHasher;
+
+    impl Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            7
+        }
+
+        fn write(&mut self, _bytes: &[u8]) {}
+    }
+
+    impl BuildHasher for CollidingState {
+        type Hasher = CollidingHasher;
+
+        fn build_hasher(&self) -> CollidingHasher {
+            self.builds.set(self.builds.get() + 1);
+
+            CollidingHasher
+        }
+    }
+
+    #[test]
+    fn custom_hasher_is_used() {
+        let state = CollidingState::default();
+        let mut oa = OAHashMap::with_hasher(state.clone());
+        for i in 0..100 {
+            oa.insert(i, i * 10);
+        }
+
+        assert!(state.builds.get() >= 100);
+        assert_eq!(7, oa.hash_key(&12345));
+        assert_eq!(state.builds.get(), oa.hasher().builds.get());
+        for i in 0..100 {
+            assert_eq!(Some(&(i * 10)), oa.search(&i));
+        }
+        oa.entry(5).and_modify(|v| *v += 1);
+        assert_eq!(Some(&51), oa.search(&5));
+
+        let presized: OAHashMap<i32, i32, _> =
+            OAHashMap::with_capacity_and_hasher(500, CollidingState::default());
+        assert!(presized.capacity() >= 500);
+    }
+
+    #[test]
+    fn invariants_hold_after_every_operation() {
+        for probing in [
+            Probing::Linear,
+            Probing::Quadratic,
+            Probing::DoubleHashing,
+            Probing::Hopscotch,
+        ] {
+            for incremental in [false, true] {
+                let mut rng = Rng::new(90);
+                let mut oa = OAHashMap::new();
+                oa.set_probing(probing);
+                oa.set_incremental_resize(incremental);
+                oa.set_auto_shrink(true);
+                let mut std = HashMap::new();
+                for step in 0..3000 {
+                    let key = rng.below(400);
+                    match rng.below(40) {
+                        0 => {
+                            oa.retain(|k, _| k % 3 != 0);
+                            std.retain(|k, _| k % 3 != 0);
+                        }
+                        1 => {
+                            oa.shrink_to_fit();
+                        }
+                        2 => {
+                            oa.reserve(100);
+                        }
+                        3 if step % 500 == 3 => {
+                            oa.clear();
+                            std.clear();
+                        }
+                        4..=15 => assert_eq!(std.remove(&key), oa.remove(&key)),
+                        16..=19 => {
+                            *oa.entry(key).or_insert(0) += 1;
+                            *std.entry(key).or_insert(0) += 1;
+                        }
+                        20..=23 => assert_eq!(std.get_mut(&key), oa.get_mut(&key)),
+                        _ => assert_eq!(std.insert(key, step), oa.insert(key, step)),
+                    }
+
+                    assert_eq!(Ok(()), oa.check_invariants(), "{probing:?} at step {step}");
+                }
+                assert_eq!(
+                    std,
+                    oa.iter().map(|(&k, &v)| (k, v)).collect::<HashMap<_, _>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invariant_checks_report_the_broken_slot() {
+        let mut oa: OAHashMap<i32, i32> = (0..50).map(|i| (i, i)).collect();
+        assert_eq!(Ok(()), oa.check_invariants());
+
+        let hash = oa.hash_key(&7);
+        let RawEntryMut::Vacant(entry) = oa.raw_entry_mut().from_hash(hash, |_| false) else {
+            unreachable!();
+        };
+        entry.insert_hashed_nocheck(hash, 7, 70);
+        assert!(matches!(
+            oa.check_invariants(),
+            Err(InvariantError::Duplicate { .. })
+        ));
+
+        let mut oa: OAHashMap<i32, i32> = (0..50).map(|i| (i, i)).collect();
+        let RawEntryMut::Vacant(entry) = oa.raw_entry_mut().from_hash(12345, |_| false) else {
+            unreachable!();
+        };
+        entry.insert_hashed_nocheck(12345, 100, 100);
+        let slot = oa
+            .table
+            .full_slots()
+            .find(|&i| oa.table.get(i).unwrap().hash == 12345);
+        assert_eq!(
+            Err(InvariantError::Hash {
+                slot: slot.unwrap()
+            }),
+            oa.check_invariants()
+        );
+
+        let mut oa: OAHashMap<i32, i32> = (0..50).map(|i| (i, i)).collect();
+        oa.len += 1;
+        assert_eq!(
+            Err(InvariantError::Len {
+                stored: 51,
+                counted: 50
+            }),
+            oa.check_invariants()
+        );
+        oa.len -= 1;
+        oa.mask += 1;
+        let error = oa.check_invariants().unwrap_err();
+        assert!(matches!(error, InvariantError::SlotCount { .. }));
+        assert!(error.to_string().contains("slots has mask"));
+    }
+
+    /// Hashes a salt shared with its clones, which `Eq` ignores, so changing
+    /// the salt changes the hash of a key already in a map.
+    #[cfg(feature = "check-hashes")]
+    #[derive(Clone, Debug)]
+    struct Salted {
+        id: i32,
+        salt: Rc<Cell<u64>>,
+    }
+
+    #[cfg(feature = "check-hashes")]
+    impl Salted {
+        fn new(id: i32) -> Self {
+            Self {
+                id,
+                salt: Rc::default(),
+            }
+        }
+    }
+
+    #[cfg(feature = "check-hashes")]
+    impl Hash for Salted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.id.hash(state);
+            self.salt.get().hash(state);
+        }
+    }
+
+    #[cfg(feature = "check-hashes")]
+    impl PartialEq for Salted {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    #[cfg(feature = "check-hashes")]
+    impl Eq for Salted {}
+
+    #[cfg(feature = "check-hashes")]
+    #[test]
+    fn keys_whose_hash_changed_are_caught_by_the_next_lookup() {
+        for probing in [Probing::Linear, Probing::Quadratic] {
+            let mut oa = OAHashMap::new();
+            oa.set_probing(probing);
+            let keys: Vec<_> = (0..20).map(Salted::new).collect();
+            for key in &keys {
+                oa.insert(key.clone(), key.id);
+            }
+            assert_eq!(Some(&7), oa.get(&Salted::new(7)));
+
+            let slot = oa.find_index(&Salted::new(7)).unwrap();
+            keys[7].salt.set(1);
+            let lookup = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                oa.get(&Salted::new(7)).copied()
+            }));
+            let message = lookup.unwrap_err().downcast::<String>().unwrap();
+            assert!(
+                message.starts_with(&format!("The key in slot {slot} ")),
+                "{message}"
+            );
+        }
+    }
+
+    #[cfg(feature = "check-hashes")]
+    #[test]
+    #[should_panic(expected = "its Hash changed while it was in the map")]
+    fn keys_whose_hash_changed_are_caught_by_a_resize() {
+        let mut oa = OAHashMap::new();
+        let key = Salted::new(1);
+        oa.insert(key.clone(), ());
+        key.salt.set(1);
+
+        oa.reserve(1000);
+    }
+
+    #[test]
+    fn stats_measure_probe_distances() {
+        for probing in [Probing::Linear, Probing::Quadratic] {
+            let mut oa = OAHashMap::with_capacity_and_hasher(16, CollidingState::default());
+            oa.set_probing(probing);
+            for i in 0..8 {
+                oa.insert(i, i);
+            }
+
+            let stats = oa.stats();
+            let slots = oa.table.num_slots();
+            assert_eq!((8, slots, 0), (stats.live, stats.slots, stats.resizes));
+            assert_eq!(8.0 / slots as f32, stats.load_factor);
+            assert_eq!(7, stats.max_probe_distance);
+            assert_eq!(3.5, stats.mean_probe_distance);
+        }
+
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::with_capacity(32);
+        let slots = oa.table.num_slots();
+        let keys = keys_starting_at(&oa, 10, 3);
+        for key in keys.into_iter().chain(keys_starting_at(&oa, 11, 2)) {
+            oa.insert(key, key);
+        }
+        let stats = oa.stats();
+        assert_eq!(slots, stats.slots);
+        assert_eq!(3, stats.max_probe_distance);
+        assert_eq!(1.6, stats.mean_probe_distance);
+
+        oa.extend((1000..2000).map(|i| (i, i)));
+        assert_eq!(oa.resizes, oa.stats().resizes);
+        assert!(oa.stats().resizes > 0);
+        assert_eq!(
+            0.0,
+            OAHashMap::<i32, i32>::new().stats().mean_probe_distance
+        );
+    }
+
+    #[test]
+    fn stats_print_on_one_line() {
+        let stats = TableStats {
+            live: 8,
+            tombstones: 1,
+            slots: 16,
+            load_factor: 0.5,
+            max_probe_distance: 7,
+            mean_probe_distance: 3.5,
+            resizes: 2,
+        };
+
+        assert_eq!(
+            "8 live / 16 slots (load 0.50), 1 tombstones, probe distance max 7 mean 3.50, 2 resizes",
+            stats.to_string()
+        );
+    }
+
+    fn longest_probe<K: Hash + Eq, V, S: BuildHasher>(oa: &OAHashMap<K, V, S>) -> usize {
+        let slots = oa.table.num_slots();
+        let mut longest = 0;
+        for index in 0..slots {
+            if let Some(bucket) = oa.table.get(index) {
+                let start = oa.starting_index(bucket.hash);
+                longest = longest.max((index + slots - start) % slots);
+            }
+        }
+
+        longest
+    }
+
+    // Benchmark note: 100k release-mode lookups into 5000 such keys took
+    // ~280 ms with plain masking and ~0.3 ms with the multiply.
+    #[test]
+    fn shared_low_bits_stay_spread() {
+        let mut oa = OAHashMap::with_hasher(IdentityState);
+        for i in 0..500u64 {
+            oa.insert(i << 16 | 0xbeef, i);
+        }
+
+        // Masking alone would start every key in the same slot.
+        let longest = longest_probe(&oa);
+        assert!(longest < 32, "longest probe distance was {longest}");
+        assert!((0..500u64).all(|i| oa.get(&(i << 16 | 0xbeef)) == Some(&i)));
+    }
+
+    #[test]
+    fn precomputed_collisions_stay_spread() {
+        let slots = crate::Config::DEFAULT
+            .try_slots_for(300, usize::MAX)
+            .unwrap() as u64;
+        let colliding: Vec<String> = (0..)
+            .map(|i| format!("param{i}"))
+            .filter(|key| {
+                let mut hasher = DefaultHasher::new();
+                key.hash(&mut hasher);
+                hasher.finish().is_multiple_of(slots)
+            })
+            .take(300)
+            .collect();
+
+        let mut oa = OAHashMap::with_capacity(colliding.len());
+        for key in &colliding {
+            oa.insert(key.clone(), ());
+        }
+
+        assert_eq!(slots as usize, oa.table.num_slots());
+        let longest = longest_probe(&oa);
+        assert!(longest < 100, "longest probe distance was {longest}");
+    }
+
+    #[cfg(any(feature = "ahash", not(feature = "fxhash")))]
+    #[test]
+    fn maps_use_independent_seeds() {
+        let first: OAHashMap<i32, ()> = OAHashMap::new();
+        let second: OAHashMap<i32, ()> = OAHashMap::new();
+
+        assert!((0..8).any(|key| first.hash_key(&key) != second.hash_key(&key)));
+        assert_eq!(first.hash_key(&1), first.hash_key(&1));
+
+        let shared: OAHashMap<i32, ()> = OAHashMap::with_hasher(second.hasher().clone());
+        assert!((0..8).all(|key| shared.hash_key(&key) == second.hash_key(&key)));
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn keys_are_hashed_once_per_insert() {
+        use crate::test_util::Hashed;
+
+        let mut oa = OAHashMap::new();
+        let before = Hashed::hashes();
+        for i in 0..10_000 {
+            oa.insert(Hashed(i), i);
+        }
+
+        assert!(oa.resizes > 5);
+        assert_eq!(10_000, Hashed::hashes() - before);
+
+        let before = Hashed::hashes();
+        for i in 10_000..20_000 {
+            oa.entry(Hashed(i)).or_insert(i);
+        }
+        oa.reserve(100_000);
+        oa.shrink_to_fit();
+        assert_eq!(10_000, Hashed::hashes() - before);
+    }
+
+    #[test]
+    fn borrowed_lookups() {
+        let mut names: OAHashMap<String, i32> = OAHashMap::new();
+        names.insert("hello".to_string(), 1);
+        names.insert("world".to_string(), 2);
+
+        assert_eq!(Some(&1), names.search("hello"));
+        assert!(names.contains_key("world"));
+        assert!(!names.contains_key("missing"));
+        *names.get_mut("world").unwrap() += 10;
+        assert_eq!(Some(12), names.remove("world"));
+        names.delete("hello");
+        assert!(names.is_empty());
+
+        let mut bytes: OAHashMap<Vec<u8>, usize> = OAHashMap::new();
+        bytes.insert(b"abc".to_vec(), 3);
+        assert_eq!(Some(&3), bytes.search(&b"abc"[..]));
+        assert_eq!(Some((b"abc".to_vec(), 3)), bytes.remove_entry(&b"abc"[..]));
+
+        let mut boxed: OAHashMap<Box<u64>, &str> = OAHashMap::new();
+        boxed.insert(Box::new(7), "seven");
+        assert_eq!(Some(&"seven"), boxed.search(&7));
+        assert!(boxed.contains_key(&7));
+
+        let mut paths: OAHashMap<std::path::PathBuf, i32> = OAHashMap::new();
+        paths.insert("src/lib.rs".into(), 1);
+        assert_eq!(Some(&1), paths.search(std::path::Path::new("src/lib.rs")));
+    }
+
+    #[derive(Hash)]
+    struct Lookup<'a>(&'a str, u32);
+
+    impl Equivalent<(String, u32)> for Lookup<'_> {
+        fn equivalent(&self, key: &(String, u32)) -> bool {
+            self.0 == key.0 && self.1 == key.1
+        }
+    }
+
+    #[test]
+    fn equivalent_lookups() {
+        let mut oa: OAHashMap<(String, u32), i32> = OAHashMap::new();
+        for i in 0..100 {
+            oa.insert((format!("name{i}"), i), i as i32);
+        }
+
+        let name = String::from("name42");
+        assert_eq!(Some(&42), oa.search(&Lookup(&name, 42)));
+        assert!(!oa.contains_key(&Lookup(&name, 43)));
+        *oa.get_mut(&Lookup("name7", 7)).unwrap() = -7;
+        assert_eq!(Some(-7), oa.remove(&Lookup("name7", 7)));
+        assert_eq!(
+            Some((("name8".to_string(), 8), 8)),
+            oa.remove_entry(&Lookup("name8", 8))
+        );
+        assert_eq!(
+            Some((&("name9".to_string(), 9), &9)),
+            oa.raw_entry().from_key(&Lookup("name9", 9))
+        );
+        assert_eq!(98, oa.len());
+    }
+
+    #[test]
+    fn get_key_value_returns_stored_key() {
+        let mut oa: OAHashMap<Tagged, i32> = OAHashMap::new();
+        oa.insert(Tagged::new(1, "stored"), 10);
+
+        let (key, value) = oa.get_key_value(&Tagged::new(1, "query")).unwrap();
+        assert_eq!("stored", key.tag);
+        assert_eq!(10, *value);
+
+        let (key, value) = oa.get_key_value_mut(&Tagged::new(1, "query")).unwrap();
+        assert_eq!("stored", key.tag);
+        *value += 1;
+        assert_eq!(Some(&11), oa.search(&Tagged::new(1, "other")));
+        assert!(oa.get_key_value(&Tagged::new(2, "stored")).is_none());
+        assert!(oa.get_key_value_mut(&Tagged::new(2, "stored")).is_none());
+    }
+
+    #[test]
+    fn try_get_names_the_missing_key() {
+        fn timeout(config: &OAHashMap<String, u32>) -> Result<u32, Box<dyn std::error::Error>> {
+            Ok(*config.try_get("timeout")? * 1_000)
+        }
+
+        let mut config: OAHashMap<String, u32> = [("retries".to_string(), 3)].into();
+        let error = config.try_get("timeout").unwrap_err();
+        assert_eq!("timeout", error.key());
+        assert_eq!(r#"key "timeout" not found"#, error.to_string());
+        assert_eq!(
+            "key \"timeout\" not found",
+            timeout(&config).unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(KeyNotFoundError::new("port")),
+            config.try_get_mut("port")
+        );
+
+        config.insert("timeout".to_string(), 30);
+        assert_eq!(30_000, timeout(&config).unwrap());
+        assert!(core::ptr::eq(
+            config.search("retries").unwrap(),
+            config.try_get("retries").unwrap()
+        ));
+        *config.try_get_mut("retries").unwrap() += 1;
+        assert_eq!(Some(&4), config.search("retries"));
+
+        let ids: OAHashMap<u64, &str> = OAHashMap::new();
+        assert_eq!("key 7 not found", ids.try_get(&7).unwrap_err().to_string());
+    }
+
+    #[test]
+    fn get_disjoint_mut_all_present() {
+        let mut oa: OAHashMap<String, i32> = (0..10).map(|i| (i.to_string(), i)).collect();
+
+        let [a, b, c] = oa.get_disjoint_mut(["1", "5", "9"]);
+        let (a, b, c) = (a.unwrap(), b.unwrap(), c.unwrap());
+        std::mem::swap(a, b);
+        *c += 100;
+
+        assert_eq!(Some(&5), oa.search("1"));
+        assert_eq!(Some(&1), oa.search("5"));
+        assert_eq!(Some(&109), oa.search("9"));
+    }
+
+    #[test]
+    fn get_disjoint_mut_missing_keys() {
+        let mut oa: OAHashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+
+        let [a, b, c] = oa.get_disjoint_mut([&3, &42, &43]);
+        assert_eq!(Some(&mut 3), a);
+        assert_eq!(None, b);
+        assert_eq!(None, c);
+
+        let [a, b] = unsafe { oa.get_disjoint_unchecked_mut([&4, &6]) };
+        *a.unwrap() = 40;
+        *b.unwrap() = 60;
+        assert_eq!(Some(&40), oa.search(&4));
+        assert_eq!(Some(&60), oa.search(&6));
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate keys")]
+    fn get_disjoint_mut_duplicates_panic() {
+        let mut oa: OAHashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+
+        oa.get_disjoint_mut([&1, &2, &1]);
+    }
+
+    #[test]
+    fn index_by_key() {
+        let oa: OAHashMap<String, i32> = [("a".to_string(), 1), ("b".to_string(), 2)].into();
+
+        assert_eq!(1, oa["a"]);
+        assert_eq!(2, oa[&"b".to_string()]);
+    }
+
+    #[test]
+    fn index_panics_at_caller() {
+        thread_local! {
+            static LOCATION: Cell<Option<u32>> = const { Cell::new(None) };
+        }
+
+        let oa: OAHashMap<i32, i32> = OAHashMap::new();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|info| {
+            let location = info.location().unwrap();
+            if location.file() == file!() {
+                LOCATION.set(Some(location.line()));
+            }
+        }));
+        let line = line!() + 1;
+        let result = std::panic::catch_unwind(|| oa[&1]);
+        std::panic::set_hook(previous);
+
+        let message = result.unwrap_err();
+        assert_eq!(
+            Some("key not found in OAHashMap"),
+            message.downcast_ref::<String>().map(String::as_str)
+        );
+        assert_eq!(Some(line), LOCATION.get());
+    }
+
+    #[test]
+    fn get_batch_matches_get() {
+        let mut rng = Rng::new(44);
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+        assert_eq!(vec![None], oa.get_batch(&[&1]));
+
+        for _ in 0..5_000 {
+            let key = rng.below(10_000);
+            oa.insert(key, key * 3);
+            if rng.below(4) == 0 {
+                oa.remove(&rng.below(10_000));
+            }
+        }
+
+        for _ in 0..20 {
+            let keys: Vec<u64> = (0..rng.below(500)).map(|_| rng.below(12_000)).collect();
+            let refs: Vec<&u64> = keys.iter().collect();
+            let expected: Vec<Option<&u64>> = keys.iter().map(|key| oa.get(key)).collect();
+
+            assert_eq!(expected, oa.get_batch(&refs));
+        }
+
+        let names: OAHashMap<String, usize> = (0..10).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(vec![Some(&3), None], names.get_batch(&["3", "x"]));
+    }
+
+    #[test]
+    fn debug_shows_live_entries_only() {
+        let mut oa: OAHashMap<&str, i32> = OAHashMap::new();
+        assert_eq!("{}", format!("{oa:?}"));
+
+        oa.insert("kept", 1);
+        oa.insert("deleted", 2);
+        oa.delete("deleted");
+
+        assert_eq!(r#"{"kept": 1}"#, format!("{oa:?}"));
+        assert_eq!("{\n    \"kept\": 1,\n}", format!("{oa:#?}"));
+
+        oa.insert("other", 3);
+        let pretty = format!("{oa:#?}");
+        assert!(!pretty.contains("deleted"));
+        assert_eq!(4, pretty.lines().count());
+    }
+
+    #[test]
+    fn clone_is_deep_and_compact() {
+        let mut oa: OAHashMap<i32, Rc<String>> =
+            (0..1000).map(|i| (i, Rc::new(i.to_string()))).collect();
+        oa.retain(|k, _| *k < 100);
+
+        let mut copy = oa.clone();
+        assert_eq!(2, Rc::strong_count(oa.get(&5).unwrap()));
+        assert_layout(&copy);
+        assert!(copy.table.num_slots() < oa.table.num_slots());
+
+        copy.insert(5, Rc::new("changed".to_string()));
+        copy.remove(&6);
+        assert_eq!("5", oa.get(&5).unwrap().as_str());
+        assert_eq!(Some(&"6".to_string()), oa.get(&6).map(|v| &**v));
+        assert_eq!(100, oa.len());
+        assert_eq!(99, copy.len());
+        for i in 7..100 {
+            assert_eq!(oa.get(&i), copy.get(&i));
+        }
+    }
+
+    #[test]
+    fn clone_from_reuses_allocation() {
+        let alloc = CountingAlloc::default();
+        let mut source = OAHashMap::new_in(alloc.clone());
+        let mut scratch = OAHashMap::with_capacity_in(500, alloc.clone());
+        let buffer = scratch.table.as_ptr();
+
+        for tick in 0..10 {
+            source.insert(tick, vec![tick; 3]);
+            let allocations = alloc.stats().allocations;
+            scratch.clone_from(&source);
+
+            assert_eq!(allocations, alloc.stats().allocations);
+            assert_eq!(buffer, scratch.table.as_ptr());
+            assert_eq!(source.len(), scratch.len());
+            assert_eq!(Some(&vec![tick; 3]), scratch.get(&tick));
+        }
+
+        let mut small = OAHashMap::new_in(alloc.clone());
+        small.clone_from(&source);
+        assert_eq!(10, small.len());
+    }
+
+    #[test]
+    fn equality_ignores_layout() {
+        let mut forward = OAHashMap::with_hasher(CollidingState::default());
+        for i in 0..10 {
+            forward.insert(i, i * 10);
+        }
+        forward.remove(&3);
+
+        let mut backward = OAHashMap::with_hasher(CollidingState::default());
+        for i in (0..200).rev() {
+            backward.insert(i, i * 10);
+        }
+        backward.retain(|k, _| *k < 10 && *k != 3);
+        backward.insert(42, 0);
+        backward.remove(&42);
+
+        let hash = forward.hash_key(&0);
+        assert_ne!(
+            forward.find_index_with(hash, |k| *k == 0),
+            backward.find_index_with(hash, |k| *k == 0)
+        );
+        assert_eq!(forward, backward);
+
+        let plain: OAHashMap<i32, i32> = forward.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(plain, backward);
+        assert_eq!(backward, plain);
+
+        backward.insert(9, 0);
+        assert_ne!(forward, backward);
+        backward.insert(9, 90);
+        backward.insert(3, 30);
+        assert_ne!(forward, backward);
+        assert_ne!(backward, forward);
+    }
+
+    #[test]
+    fn std_conversions_move_entries() {
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        struct Token(u32);
+
+        let tokens = || (0..300).map(|i| (Token(i), Token(i * 3)));
+        let mut oa: OAHashMap<Token, Token> = tokens().collect();
+        oa.retain(|key, _| key.0 % 4 != 0);
+
+        let std_map = HashMap::from(oa);
+        assert_eq!(225, std_map.len());
+        let oa = OAHashMap::from(std_map);
+        assert_eq!(225, oa.len());
+        assert_eq!(0, oa.resizes);
+
+        let tree = BTreeMap::from(oa);
+        assert!(tree.iter().all(|(k, v)| k.0 % 4 != 0 && v.0 == k.0 * 3));
+        let oa = OAHashMap::from(tree);
+
+        let mut pairs = Vec::from(oa);
+        pairs.sort_unstable();
+        let expected: Vec<_> = tokens().filter(|(k, _)| k.0 % 4 != 0).collect();
+        assert_eq!(expected, pairs);
+    }
+
+    #[test]
+    fn slot_counts_are_powers_of_two() {
+        let check = |oa: &OAHashMap<i32, i32>| {
+            let slots = oa.table.num_slots();
+            assert!(slots.is_power_of_two());
+            assert_eq!(slots - 1, oa.mask);
+        };
+
+        for capacity in [1, 3, 39, 100, 1000, 12_345] {
+            let mut oa = OAHashMap::with_capacity(capacity);
+            check(&oa);
+            for i in 0..capacity as i32 {
+                oa.insert(i, i * 2);
+            }
+            check(&oa);
+            assert!((0..capacity as i32).all(|i| oa.get(&i) == Some(&(i * 2))));
+
+            oa.reserve(777);
+            check(&oa);
+            oa.retain(|k, _| k % 5 == 0);
+            oa.shrink_to(capacity / 3 + 1);
+            check(&oa);
+            assert!((0..capacity as i32).all(|i| oa.get(&i) == (i % 5 == 0).then_some(&(i * 2))));
+        }
+    }
+
+    #[test]
+    fn len_matches_std() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        let mut std_map = HashMap::new();
+
+        assert!(oa.is_empty());
+
+        for i in 0..2000 {
+            let key = (i * 7919) % 500;
+            if i % 3 == 0 {
+                oa.delete(&key);
+                std_map.remove(&key);
+            } else {
+                oa.insert(key, i);
+                std_map.insert(key, i);
+            }
+
+            assert_eq!(std_map.len(), oa.len());
+        }
+
+        assert_layout(&oa);
+        assert!(!oa.is_empty());
+    }
+
+    #[test]
+    fn insert_million() {
+        let mut oa: OAHashMap<u32, u32> = OAHashMap::new();
+        for i in 0..1_000_000 {
+            oa.insert(i, i);
+        }
+
+        assert_eq!(1_000_000, oa.len());
+        assert_eq!(Some(&999_999), oa.search(&999_999));
+    }
+
+    #[test]
+    fn churn_terminates() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        let capacity = oa.table.num_slots() as i32;
+        for i in 0..capacity * 20 {
+            oa.insert(i, i);
+            oa.delete(&i);
+
+            assert_eq!(None, oa.search(&i));
+            assert!((0..oa.table.num_slots()).any(|index| oa.table.ctrl(index) == EMPTY));
+        }
+
+        for i in 0..10 {
+            oa.insert(i, i);
+        }
+        for i in 0..10 {
             assert_eq!(Some(&i), oa.search(&i));
         }
+        assert_eq!(None, oa.search(&-1));
     }
 }
@@ -0,0 +1,505 @@
+//! A cache holding at most a fixed number of entries, evicting the least
+//! recently used one to make room for each new key.
+//!
+//! The entries sit in a dense `Vec`, linked into a list from the most to the
+//! least recently used by their positions there, and found through a table
+//! of those positions. Removing an entry moves the last one into its place,
+//! repointing that entry's neighbours and its position in the table, so
+//! every operation takes constant time.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+
+use crate::position_index::PositionIndex;
+use crate::{Bucket, DefaultHashBuilder, Equivalent};
+
+/// Links the ends of the recency list.
+const NIL: usize = usize::MAX;
+
+struct Node<K, V>
+where
+    K: Hash + Eq,
+{
+    bucket: Bucket<K, V>,
+    /// The entry used just after this one.
+    newer: usize,
+    /// The entry used just before this one.
+    older: usize,
+}
+
+/// What an [`insert`](LruOAHashMap::insert) did to make room for its entry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Insertion<K, V> {
+    /// The key was new and the cache had room for it.
+    Inserted,
+    /// The key was present, and this was its value. The stored key stays.
+    Replaced(V),
+    /// The key was new and the cache full, so its least recently used entry
+    /// went.
+    Evicted(K, V),
+}
+
+pub struct LruOAHashMap<K, V, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    entries: Vec<Node<K, V>>,
+    indices: PositionIndex<S>,
+    newest: usize,
+    oldest: usize,
+    capacity: usize,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> LruOAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> LruOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        assert!(capacity > 0, "An LRU cache needs room for an entry");
+
+        Self {
+            entries: Vec::with_capacity(capacity),
+            indices: PositionIndex::with_capacity_and_hasher(capacity, hash_builder),
+            newest: NIL,
+            oldest: NIL,
+            capacity,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.indices.hasher()
+    }
+
+    /// Inserts the entry as the most recently used, replacing the key's
+    /// value if it is present and otherwise evicting the least recently used
+    /// entry if the cache is full.
+    pub fn insert(&mut self, key: K, value: V) -> Insertion<K, V> {
+        let hash = self.indices.hash_key(&key);
+        let entries = &self.entries;
+        match self
+            .indices
+            .find_or_vacant(hash, |index| entries[index].bucket.key == key)
+        {
+            Ok(index) => {
+                let old = core::mem::replace(&mut self.entries[index].bucket.value, value);
+                self.touch_index(index);
+
+                Insertion::Replaced(old)
+            }
+            Err(vacant) => {
+                let index = self.entries.len();
+                vacant.insert(index);
+                self.entries.push(Node {
+                    bucket: Bucket::new(hash, key, value),
+                    newer: NIL,
+                    older: NIL,
+                });
+                self.link_newest(index);
+
+                if self.entries.len() > self.capacity {
+                    let (key, value) = self.pop_lru().unwrap();
+
+                    Insertion::Evicted(key, value)
+                } else {
+                    Insertion::Inserted
+                }
+            }
+        }
+    }
+
+    /// Marks the entry as the most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Marks the entry as the most recently used.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.index_of(key)?;
+        self.touch_index(index);
+
+        Some(&mut self.entries[index].bucket.value)
+    }
+
+    /// Looks the entry up without marking it as used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.index_of(key)?;
+
+        Some(&self.entries[index].bucket.value)
+    }
+
+    /// The entry the next insert of a new key into a full cache would evict.
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        let bucket = &self.entries.get(self.oldest)?.bucket;
+
+        Some((&bucket.key, &bucket.value))
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.index_of(key).is_some()
+    }
+
+    /// Marks the entry as the most recently used, returning whether it was
+    /// present.
+    pub fn touch<Q>(&mut self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let Some(index) = self.index_of(key) else {
+            return false;
+        };
+        self.touch_index(index);
+
+        true
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let index = self.index_of(key)?;
+
+        Some(self.remove_index(index).1)
+    }
+
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        if self.oldest == NIL {
+            return None;
+        }
+
+        Some(self.remove_index(self.oldest))
+    }
+
+    /// Iterates from the most to the least recently used entry, marking none
+    /// of them as used.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            entries: &self.entries,
+            next: self.newest,
+            remaining: self.entries.len(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+        self.newest = NIL;
+        self.oldest = NIL;
+    }
+
+    /// How many entries the cache holds before it starts evicting.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn index_of<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.indices.hash_key(key);
+
+        self.indices.find(hash, |index| {
+            key.equivalent(&self.entries[index].bucket.key)
+        })
+    }
+
+    fn touch_index(&mut self, index: usize) {
+        if self.newest != index {
+            self.unlink(index);
+            self.link_newest(index);
+        }
+    }
+
+    fn link_newest(&mut self, index: usize) {
+        let node = &mut self.entries[index];
+        node.newer = NIL;
+        node.older = self.newest;
+        match self.newest {
+            NIL => self.oldest = index,
+            newest => self.entries[newest].newer = index,
+        }
+        self.newest = index;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let Node { newer, older, .. } = self.entries[index];
+        match newer {
+            NIL => self.newest = older,
+            newer => self.entries[newer].older = older,
+        }
+        match older {
+            NIL => self.oldest = newer,
+            older => self.entries[older].newer = newer,
+        }
+    }
+
+    fn remove_index(&mut self, index: usize) -> (K, V) {
+        self.unlink(index);
+        self.indices.remove(self.entries[index].bucket.hash, index);
+
+        let last = self.entries.len() - 1;
+        if index != last {
+            self.indices
+                .replace(self.entries[last].bucket.hash, last, index);
+            let Node { newer, older, .. } = self.entries[last];
+            match newer {
+                NIL => self.newest = index,
+                newer => self.entries[newer].older = index,
+            }
+            match older {
+                NIL => self.oldest = index,
+                older => self.entries[older].newer = index,
+            }
+        }
+
+        self.entries.swap_remove(index).bucket.into_pair()
+    }
+}
+
+impl<K, V, S> fmt::Debug for LruOAHashMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a LruOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Yields the entries from the most to the least recently used.
+pub struct Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    entries: &'a [Node<K, V>],
+    next: usize,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.entries.get(self.next)?;
+        self.next = node.older;
+        self.remaining -= 1;
+
+        Some((&node.bucket.key, &node.bucket.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> Clone for Iter<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries,
+            next: self.next,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Iter<'_, K, V>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Insertion, LruOAHashMap};
+    use crate::test_util::{Rng, Tagged};
+
+    /// Keeps the entries from the most to the least recently used.
+    struct Model {
+        entries: Vec<(u64, u64)>,
+        capacity: usize,
+    }
+
+    impl Model {
+        fn position(&self, key: u64) -> Option<usize> {
+            self.entries.iter().position(|&(k, _)| k == key)
+        }
+
+        fn touch(&mut self, key: u64) -> Option<u64> {
+            let entry = self.entries.remove(self.position(key)?);
+            self.entries.insert(0, entry);
+
+            Some(entry.1)
+        }
+
+        fn insert(&mut self, key: u64, value: u64) -> Insertion<u64, u64> {
+            if let Some(position) = self.position(key) {
+                let (_, old) = self.entries.remove(position);
+                self.entries.insert(0, (key, value));
+
+                return Insertion::Replaced(old);
+            }
+            self.entries.insert(0, (key, value));
+
+            if self.entries.len() > self.capacity {
+                let (key, value) = self.entries.pop().unwrap();
+
+                Insertion::Evicted(key, value)
+            } else {
+                Insertion::Inserted
+            }
+        }
+    }
+
+    #[test]
+    fn evicts_in_recency_order_through_interleaved_operations() {
+        for capacity in [1, 2, 7, 50] {
+            let mut rng = Rng::new(85 + capacity as u64);
+            let mut lru = LruOAHashMap::with_capacity(capacity);
+            let mut model = Model {
+                entries: Vec::new(),
+                capacity,
+            };
+            for step in 0..3000 {
+                let key = rng.below(capacity as u64 * 3);
+                match rng.below(8) {
+                    0 => assert_eq!(model.touch(key), lru.get(&key).copied()),
+                    1 => assert_eq!(model.touch(key).is_some(), lru.touch(&key)),
+                    2 => {
+                        let position = model.position(key);
+                        assert_eq!(
+                            position.map(|p| model.entries[p].1),
+                            lru.peek(&key).copied()
+                        );
+                    }
+                    3 => {
+                        let removed = model.position(key).map(|p| model.entries.remove(p).1);
+                        assert_eq!(removed, lru.remove(&key));
+                    }
+                    4 => assert_eq!(model.entries.pop(), lru.pop_lru()),
+                    _ => assert_eq!(model.insert(key, step), lru.insert(key, step)),
+                }
+
+                let order: Vec<_> = lru.iter().map(|(&k, &v)| (k, v)).collect();
+                assert_eq!(model.entries, order);
+                assert_eq!(
+                    model.entries.last(),
+                    lru.peek_lru().map(|(k, v)| (*k, *v)).as_ref()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn capacity_one_keeps_the_latest_key() {
+        let mut lru = LruOAHashMap::with_capacity(1);
+        assert_eq!(Insertion::Inserted, lru.insert("a", 1));
+        assert_eq!(Insertion::Evicted("a", 1), lru.insert("b", 2));
+        assert_eq!(Insertion::Replaced(2), lru.insert("b", 3));
+        assert!(lru.touch("b"));
+        assert_eq!(Some(&3), lru.get("b"));
+        assert_eq!(1, lru.len());
+
+        assert_eq!(Some(("b", 3)), lru.pop_lru());
+        assert_eq!(None, lru.pop_lru());
+        assert!(lru.is_empty());
+    }
+
+    #[test]
+    fn evicted_keys_come_back_as_new() {
+        let mut lru = LruOAHashMap::with_capacity(3);
+        for key in 0..3 {
+            lru.insert(key, key * 10);
+        }
+        lru.get(&0);
+
+        assert_eq!(Insertion::Evicted(1, 10), lru.insert(3, 30));
+        assert!(!lru.contains_key(&1));
+        assert_eq!(None, lru.get(&1));
+
+        assert_eq!(Insertion::Evicted(2, 20), lru.insert(1, 11));
+        assert_eq!(Some(&11), lru.peek(&1));
+        let order: Vec<_> = lru.iter().map(|(&k, _)| k).collect();
+        assert_eq!([1, 3, 0], order.as_slice());
+    }
+
+    #[test]
+    fn replacing_a_value_keeps_the_stored_key() {
+        let mut lru = LruOAHashMap::with_capacity(2);
+        lru.insert(Tagged::new(1, "stored"), 10);
+
+        assert!(matches!(
+            lru.insert(Tagged::new(1, "new"), 11),
+            Insertion::Replaced(10)
+        ));
+        let (key, &value) = lru.iter().next().unwrap();
+        assert_eq!(("stored", 11), (key.tag, value));
+    }
+
+    #[test]
+    #[should_panic(expected = "An LRU cache needs room for an entry")]
+    fn zero_capacity_panics() {
+        LruOAHashMap::<u32, u32>::with_capacity(0);
+    }
+}
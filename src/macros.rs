@@ -0,0 +1,105 @@
+/// Builds an [`OAHashMap`](crate::OAHashMap) from `key => value` pairs,
+/// sized up front for all of them. A key given twice keeps its last value,
+/// as with [`FromIterator`].
+#[macro_export]
+macro_rules! oahashmap {
+    () => {
+        $crate::OAHashMap::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)?) => {
+        $crate::OAHashMap::from([$(($key, $value)),+])
+    };
+}
+
+/// Builds an [`OAHashSet`](crate::OAHashSet) from its values, sized up
+/// front for all of them.
+#[macro_export]
+macro_rules! oahashset {
+    () => {
+        $crate::OAHashSet::new()
+    };
+    ($($value:expr),+ $(,)?) => {
+        $crate::OAHashSet::from([$($value),+])
+    };
+}
+
+/// Declares a `static` [`StaticOAHashMap`](crate::StaticOAHashMap) from
+/// `&'static str` keys and const values, laid out while compiling. A key
+/// given twice is a compile error.
+///
+/// ```compile_fail,E0080
+/// oahm::static_oahashmap! {
+///     static CODES: u8 = { "a" => 1, "b" => 2, "a" => 3 };
+/// }
+/// ```
+#[macro_export]
+macro_rules! static_oahashmap {
+    (
+        $(#[$attr:meta])*
+        $vis:vis static $name:ident: $value:ty = {
+            $($key:expr => $val:expr),* $(,)?
+        };
+    ) => {
+        $(#[$attr])*
+        $vis static $name: $crate::StaticOAHashMap<
+            $value,
+            { $crate::frozen::slots_for(<[&str]>::len(&[$($key),*])) },
+        > = $crate::StaticOAHashMap::new([$(($key, $val)),*]);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OAHashMap, OAHashSet};
+
+    #[test]
+    fn maps_take_expressions_and_trailing_commas() {
+        let empty: OAHashMap<String, Vec<i32>> = oahashmap! {};
+        assert!(empty.is_empty());
+        assert_eq!(0, empty.capacity());
+
+        let base = 10;
+        let map = oahashmap! {
+            format!("key{}", base) => vec![base; 2],
+            "plain".to_string() => (0..3).map(|i| i * base).collect(),
+            String::from("nested") => oahashmap! { 1 => 2 }.into_values().collect(),
+        };
+        assert_eq!(3, map.len());
+        assert_eq!(Some(&vec![10, 10]), map.get("key10"));
+        assert_eq!(Some(&vec![0, 10, 20]), map.get("plain"));
+        assert_eq!(Some(&vec![2]), map.get("nested"));
+
+        let nested = oahashmap! { "outer" => oahashmap! { "inner" => 1, } };
+        assert_eq!(Some(&1), nested["outer"].get("inner"));
+    }
+
+    #[test]
+    fn maps_are_sized_once_and_keep_the_last_duplicate() {
+        let map = oahashmap! { 1 => "a", 2 => "b", 1 => "c", 3 => "d" };
+        assert_eq!(3, map.len());
+        assert_eq!(Some(&"c"), map.get(&1));
+        assert_eq!(
+            OAHashMap::<i32, &str>::with_capacity(4).capacity(),
+            map.capacity()
+        );
+        assert_eq!(0, map.stats().resizes);
+        let collected: OAHashMap<_, _> = [(1, "a"), (2, "b"), (1, "c"), (3, "d")]
+            .into_iter()
+            .collect();
+        assert_eq!(collected, map);
+    }
+
+    #[test]
+    fn sets_take_expressions_and_trailing_commas() {
+        let empty: OAHashSet<String> = oahashset! {};
+        assert!(empty.is_empty());
+
+        let set = oahashset! { "a".to_string(), "b".repeat(2), "a".to_string(), };
+        assert_eq!(2, set.len());
+        assert!(set.contains("bb"));
+        assert_eq!(
+            OAHashSet::<String>::with_capacity(3).capacity(),
+            set.capacity()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! An outgrown table whose entries move to the new one a few at a time.
+//!
+//! The old table stays a valid Robin Hood table throughout: entries leave it
+//! with the same backward shift a removal uses, so lookups can still walk it.
+//! The walk starts after an empty slot, as `retain` does, so no entry is ever
+//! shifted behind it.
+
+use allocator_api2::alloc::Allocator;
+
+use crate::raw::{self, RawTable};
+use crate::{probe, Bucket};
+
+pub(crate) struct Migration<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    table: RawTable<K, V, A>,
+    mask: usize,
+    len: usize,
+    start: usize,
+    offset: usize,
+}
+
+impl<K, V, A> Migration<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(table: RawTable<K, V, A>, len: usize) -> Self {
+        let start = table
+            .ctrl_bytes()
+            .iter()
+            .position(|&ctrl| ctrl == raw::EMPTY)
+            .unwrap_or(0);
+
+        Self {
+            mask: table.num_slots() - 1,
+            table,
+            len,
+            start,
+            offset: 1,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn table(&self) -> &RawTable<K, V, A> {
+        &self.table
+    }
+
+    pub(crate) fn table_mut(&mut self) -> &mut RawTable<K, V, A> {
+        &mut self.table
+    }
+
+    pub(crate) fn into_table(self) -> RawTable<K, V, A> {
+        self.table
+    }
+
+    pub(crate) fn distance(&self, hash: u64, index: usize) -> usize {
+        index.wrapping_sub(probe::starting_index(hash, self.mask)) & self.mask
+    }
+
+    pub(crate) fn find<F>(&self, hash: u64, mut is_match: F) -> Option<usize>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let h2 = raw::h2(hash);
+        let mut index = probe::starting_index(hash, self.mask);
+        for distance in 0..self.table.num_slots() {
+            let bucket = self.table.get(index)?;
+            if self.table.ctrl(index) == h2 && bucket.hash == hash && is_match(&bucket.key) {
+                return Some(index);
+            }
+            if self.distance(bucket.hash, index) < distance {
+                return None;
+            }
+
+            index = (index + 1) & self.mask;
+        }
+
+        None
+    }
+
+    pub(crate) fn take(&mut self, index: usize) -> Bucket<K, V> {
+        let bucket = self
+            .table
+            .take(index)
+            .unwrap_or_else(|| panic!("Slot {index} is not occupied"));
+        self.len -= 1;
+
+        let mut hole = index;
+        let mut next = (index + 1) & self.mask;
+        while let Some(bucket) = self.table.get(next) {
+            if self.distance(bucket.hash, next) == 0 {
+                break;
+            }
+
+            self.table.move_bucket(next, hole);
+            hole = next;
+            next = (next + 1) & self.mask;
+        }
+
+        bucket
+    }
+
+    /// Visits slots until one holds an entry or `budget` slots have been
+    /// visited, and takes that entry along with its slot. A taken entry's
+    /// slot is visited again, since the shift may have refilled it.
+    pub(crate) fn next_bucket(&mut self, budget: &mut usize) -> Option<(usize, Bucket<K, V>)> {
+        while *budget > 0 && self.len > 0 {
+            *budget -= 1;
+            let index = (self.start + self.offset) & self.mask;
+            if self.table.is_full(index) {
+                return Some((index, self.take(index)));
+            }
+
+            self.offset += 1;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,69 @@
+//! Replays random operation sequences against both `OAHashMap` and std's
+//! `HashMap`, checking every result. Proptest generates the sequences and
+//! shrinks any failing one; the fuzzer drives the same interpreter.
+
+use proptest::prelude::*;
+use proptest::sample::select;
+
+use crate::{OAHashMap, Probing};
+
+mod ops;
+
+use ops::{replay, Config, Op};
+
+/// Few enough keys that removals and overwrites keep hitting present ones.
+const KEYS: u8 = 24;
+
+const CONFIGS: [Config; 5] = [
+    Config {
+        probing: Probing::Linear,
+        incremental_resize: false,
+    },
+    Config {
+        probing: Probing::Linear,
+        incremental_resize: true,
+    },
+    Config {
+        probing: Probing::Quadratic,
+        incremental_resize: false,
+    },
+    Config {
+        probing: Probing::DoubleHashing,
+        incremental_resize: true,
+    },
+    Config {
+        probing: Probing::Hopscotch,
+        incremental_resize: false,
+    },
+];
+
+/// Operations weighted towards inserts and removals, over a few keys.
+fn op() -> impl Strategy<Value = Op> {
+    let key = || 0..KEYS;
+    let value = || 0..1000u32;
+
+    prop_oneof![
+        35 => (key(), value()).prop_map(|(key, value)| Op::Insert(key, value)),
+        25 => key().prop_map(Op::Remove),
+        15 => key().prop_map(Op::Search),
+        10 => (key(), value()).prop_map(|(key, value)| Op::Entry(key, value)),
+        4 => (2..6u8).prop_map(Op::Retain),
+        4 => (0..64u8).prop_map(Op::Reserve),
+        3 => Just(Op::ShrinkToFit),
+        3 => Just(Op::Iterate),
+        1 => Just(Op::Clear),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(200))]
+
+    #[test]
+    fn random_sequences_match_std(
+        config in select(&CONFIGS[..]),
+        ops in prop::collection::vec(op(), 0..400),
+    ) {
+        let outcome = replay(config, OAHashMap::new(), &ops);
+        prop_assert!(outcome.is_ok(), "{config:?}: {}", outcome.unwrap_err());
+    }
+}
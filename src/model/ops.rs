@@ -0,0 +1,104 @@
+//! The operations the model tests and the fuzzer replay, and the interpreter
+//! that checks `OAHashMap` against std's `HashMap` after each of them.
+//!
+//! Only the crate's public API is used, through the parent module's imports,
+//! so the fuzzer can include this file as it is.
+
+use std::collections::HashMap;
+
+use super::{OAHashMap, Probing};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, arbitrary::Arbitrary)]
+pub enum Op {
+    Insert(u8, u32),
+    Remove(u8),
+    Search(u8),
+    Entry(u8, u32),
+    Retain(u8),
+    Reserve(u8),
+    ShrinkToFit,
+    Iterate,
+    Clear,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub probing: Probing,
+    pub incremental_resize: bool,
+}
+
+/// Replays `ops` against `initial` and a `HashMap` of the same entries,
+/// describing the first step at which they disagree. A panic inside the map
+/// is left to unwind.
+pub fn replay(config: Config, initial: OAHashMap<u8, u32>, ops: &[Op]) -> Result<(), String> {
+    let mut oa = initial;
+    oa.set_probing(config.probing);
+    oa.set_incremental_resize(config.incremental_resize);
+    let mut std: HashMap<u8, u32> = oa.iter().map(|(&k, &v)| (k, v)).collect();
+
+    for (step, &op) in ops.iter().enumerate() {
+        apply(op, &mut oa, &mut std)
+            .and_then(|()| oa.check_invariants().map_err(|error| error.to_string()))
+            .map_err(|reason| format!("step {step}, {op:?}: {reason}"))?;
+    }
+
+    let mut oa: Vec<_> = oa.into_iter().collect();
+    let mut std: Vec<_> = std.into_iter().collect();
+    oa.sort_unstable();
+    std.sort_unstable();
+    if oa != std {
+        return Err(format!("final contents {oa:?}, expected {std:?}"));
+    }
+
+    Ok(())
+}
+
+fn apply(op: Op, oa: &mut OAHashMap<u8, u32>, std: &mut HashMap<u8, u32>) -> Result<(), String> {
+    let agree = |ours: String, theirs: String| match ours == theirs {
+        true => Ok(()),
+        false => Err(format!("got {ours}, expected {theirs}")),
+    };
+    match op {
+        Op::Insert(key, value) => agree(
+            format!("{:?}", oa.insert(key, value)),
+            format!("{:?}", std.insert(key, value)),
+        )?,
+        Op::Remove(key) => agree(
+            format!("{:?}", oa.remove(&key)),
+            format!("{:?}", std.remove(&key)),
+        )?,
+        Op::Search(key) => agree(
+            format!("{:?}", oa.get(&key)),
+            format!("{:?}", std.get(&key)),
+        )?,
+        Op::Entry(key, value) => {
+            *oa.entry(key).or_insert(value) += 1;
+            *std.entry(key).or_insert(value) += 1;
+        }
+        Op::Retain(modulus) => {
+            let modulus = modulus.max(1);
+            oa.retain(|key, _| key % modulus != 0);
+            std.retain(|key, _| key % modulus != 0);
+        }
+        Op::Reserve(additional) => {
+            oa.reserve(additional.into());
+            if oa.capacity() < oa.len() + usize::from(additional) {
+                return Err(format!("capacity {} after reserving", oa.capacity()));
+            }
+        }
+        Op::ShrinkToFit => oa.shrink_to_fit(),
+        Op::Iterate => {
+            let mut ours: Vec<_> = oa.iter().map(|(&k, &v)| (k, v)).collect();
+            let mut theirs: Vec<_> = std.iter().map(|(&k, &v)| (k, v)).collect();
+            ours.sort_unstable();
+            theirs.sort_unstable();
+            agree(format!("{ours:?}"), format!("{theirs:?}"))?;
+        }
+        Op::Clear => {
+            oa.clear();
+            std.clear();
+        }
+    }
+
+    agree(oa.len().to_string(), std.len().to_string())
+}
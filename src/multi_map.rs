@@ -0,0 +1,455 @@
+//! A map from each key to every value inserted under it, stored as an
+//! [`OAHashMap`] of vectors that never holds an empty one.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+use core::slice;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{iter, DefaultHashBuilder, Equivalent, OAHashMap, RawEntryMut};
+
+/// [`len`](Self::len) counts keys and [`values_len`](Self::values_len)
+/// counts values, so a key inserted twice adds one to the first and two to
+/// the second.
+pub struct OAMultiMap<K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: OAHashMap<K, Vec<V>, S, A>,
+    values_len: usize,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> OAMultiMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+
+    /// Room for `capacity` keys, however many values each ends up with.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> OAMultiMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            map: OAHashMap::with_hasher(hash_builder),
+            values_len: 0,
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            map: OAHashMap::with_capacity_and_hasher(capacity, hash_builder),
+            values_len: 0,
+        }
+    }
+}
+
+impl<K, V, S, A> OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub const fn with_hasher_in(hash_builder: S, alloc: A) -> Self {
+        Self {
+            map: OAHashMap::with_hasher_in(hash_builder, alloc),
+            values_len: 0,
+        }
+    }
+
+    /// Groups `values` under the keys `key_fn` gives them, keeping each
+    /// group in iteration order.
+    pub fn from_grouped<I, F>(values: I, mut key_fn: F) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        F: FnMut(&V) -> K,
+        S: Default,
+        A: Default,
+    {
+        let mut map = Self::with_hasher_in(S::default(), A::default());
+        map.extend(values.into_iter().map(|value| (key_fn(&value), value)));
+
+        map
+    }
+
+    /// Appends `value` to the ones already under `key`, probing for it once.
+    pub fn insert_append(&mut self, key: K, value: V) {
+        self.map.entry(key).or_default().push(value);
+        self.values_len += 1;
+    }
+
+    /// Every value under `key` in insertion order, or none if it is absent.
+    pub fn get_all<Q>(&self, key: &Q) -> &[V]
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.get(key).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn remove_all<Q>(&mut self, key: &Q) -> Option<Vec<V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let values = self.map.remove(key)?;
+        self.values_len -= values.len();
+
+        Some(values)
+    }
+
+    /// Removes the first value under `key` equal to `value`, and the key with
+    /// it once no values are left.
+    pub fn remove_one<Q>(&mut self, key: &Q, value: &V) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        V: PartialEq,
+    {
+        let RawEntryMut::Occupied(mut entry) = self.map.raw_entry_mut().from_key(key) else {
+            return None;
+        };
+        let values = entry.get_mut();
+        let position = values.iter().position(|v| v == value)?;
+        let removed = values.remove(position);
+        if values.is_empty() {
+            entry.remove();
+        }
+        self.values_len -= 1;
+
+        Some(removed)
+    }
+
+    /// Every key paired with each of its values in turn.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            groups: self.map.iter(),
+            group: None,
+            remaining: self.values_len,
+        }
+    }
+
+    pub fn keys(&self) -> iter::Keys<'_, K, Vec<V>> {
+        self.map.keys()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.values_len = 0;
+    }
+
+    /// The number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The number of values under all the keys together.
+    pub fn values_len(&self) -> usize {
+        self.values_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V, S, A> Default for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher_in(S::default(), A::default())
+    }
+}
+
+impl<K, V, S, A> FromIterator<(K, V)> for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut map = Self::default();
+        map.extend(iter);
+
+        map
+    }
+}
+
+impl<K, V, S, A> Extend<(K, V)> for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Reserves nothing up front, since the pairs may share only a few keys.
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (key, value) in iter {
+            self.insert_append(key, value);
+        }
+    }
+}
+
+impl<K, V, S, A> Clone for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            values_len: self.values_len,
+        }
+    }
+}
+
+/// Maps are equal when every key holds the same values in the same order.
+impl<K, V, S, A> PartialEq for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.values_len == other.values_len && self.map == other.map
+    }
+}
+
+impl<K, V, S, A> Eq for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, S, A> fmt::Debug for OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.map.iter()).finish()
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a OAMultiMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    groups: iter::Iter<'a, K, Vec<V>>,
+    group: Option<(&'a K, slice::Iter<'a, V>)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, values)) = &mut self.group {
+                if let Some(value) = values.next() {
+                    self.remaining -= 1;
+
+                    return Some((key, value));
+                }
+            }
+            let (key, values) = self.groups.next()?;
+            self.group = Some((key, values.iter()));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> where K: Hash + Eq {}
+
+impl<K, V> Clone for Iter<'_, K, V>
+where
+    K: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            groups: self.groups.clone(),
+            group: self.group.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Iter<'_, K, V>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::OAMultiMap;
+    use crate::test_util::Rng;
+
+    #[test]
+    fn counts_keys_and_values_apart() {
+        let mut map = OAMultiMap::new();
+        map.insert_append("rust", 1);
+        map.insert_append("rust", 4);
+        map.insert_append("hash", 4);
+
+        assert_eq!(2, map.len());
+        assert_eq!(3, map.values_len());
+        assert_eq!([1, 4], map.get_all("rust"));
+        assert!(map.get_all("map").is_empty());
+
+        assert_eq!(None, map.remove_one("rust", &2));
+        assert_eq!(Some(1), map.remove_one("rust", &1));
+        assert_eq!((2, 2), (map.len(), map.values_len()));
+
+        assert_eq!(Some(4), map.remove_one("rust", &4));
+        assert!(!map.contains_key("rust"));
+        assert_eq!((1, 1), (map.len(), map.values_len()));
+
+        assert_eq!(Some(vec![4]), map.remove_all("hash"));
+        assert_eq!(None, map.remove_all("hash"));
+        assert!(map.is_empty());
+        assert_eq!(0, map.values_len());
+    }
+
+    #[test]
+    fn matches_a_map_of_vectors() {
+        let mut rng = Rng::new(84);
+        let mut multi = OAMultiMap::new();
+        let mut std: HashMap<u64, Vec<u64>> = HashMap::new();
+        for _ in 0..5000 {
+            let (key, value) = (rng.below(200), rng.below(5));
+            match rng.below(4) {
+                0 => {
+                    let removed = std.get_mut(&key).and_then(|values| {
+                        let position = values.iter().position(|v| *v == value)?;
+
+                        Some(values.remove(position))
+                    });
+                    if std.get(&key).is_some_and(Vec::is_empty) {
+                        std.remove(&key);
+                    }
+                    assert_eq!(removed, multi.remove_one(&key, &value));
+                }
+                1 if value == 0 => assert_eq!(std.remove(&key), multi.remove_all(&key)),
+                _ => {
+                    std.entry(key).or_default().push(value);
+                    multi.insert_append(key, value);
+                }
+            }
+        }
+
+        assert_eq!(std.len(), multi.len());
+        assert_eq!(
+            std.values().map(Vec::len).sum::<usize>(),
+            multi.values_len()
+        );
+        for (key, values) in &std {
+            assert_eq!(values.as_slice(), multi.get_all(key));
+        }
+
+        let pairs = multi.iter();
+        assert_eq!(multi.values_len(), pairs.len());
+        let mut regrouped: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (key, value) in pairs {
+            regrouped.entry(*key).or_default().push(*value);
+        }
+        assert_eq!(std, regrouped);
+    }
+
+    #[test]
+    fn groups_by_a_key_function() {
+        let words = [
+            "apple",
+            "avocado",
+            "banana",
+            "blueberry",
+            "cherry",
+            "apricot",
+        ];
+        let map: OAMultiMap<char, &str> =
+            OAMultiMap::from_grouped(words, |word| word.chars().next().unwrap());
+
+        assert_eq!(3, map.len());
+        assert_eq!(["apple", "avocado", "apricot"], map.get_all(&'a'));
+        assert_eq!(["cherry"], map.get_all(&'c'));
+        assert_eq!(words.len(), map.iter().count());
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn building_hashes_each_pair_once() {
+        use crate::test_util::Hashed;
+
+        let before = Hashed::hashes();
+        let map: OAMultiMap<Hashed, u32> = (0..1_000_000)
+            .map(|i| (Hashed(i % 1000), i as u32))
+            .collect();
+
+        assert_eq!(1_000_000, Hashed::hashes() - before);
+        assert_eq!(1000, map.len());
+        assert_eq!(1_000_000, map.values_len());
+    }
+}
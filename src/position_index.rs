@@ -0,0 +1,171 @@
+//! The lookup table behind the maps that keep their entries in a `Vec` or an
+//! arena of their own: [`OAIndexMap`](crate::OAIndexMap),
+//! [`LruOAHashMap`](crate::LruOAHashMap), [`OABiMap`](crate::OABiMap) and
+//! [`OAHandleMap`](crate::OAHandleMap).
+//!
+//! The table stores only positions into that storage, each keyed by itself
+//! under the hash of the entry it points at. Entries cache their hashes, so
+//! the table is hashed with those and never sees a key: a lookup hashes the
+//! key once and compares it against the entries the matching positions point
+//! at, and moving or removing an entry finds its position from the cached
+//! hash alone.
+
+use core::hash::{BuildHasher, Hash};
+
+use crate::{OAHashMap, RawEntryMut, RawVacantEntryMut};
+
+pub(crate) struct PositionIndex<S> {
+    table: OAHashMap<usize, (), S>,
+}
+
+/// Where a position goes once a lookup has found no entry matching it.
+pub(crate) struct VacantPosition<'a, S> {
+    entry: RawVacantEntryMut<'a, usize, (), S>,
+    hash: u64,
+}
+
+impl<S> PositionIndex<S>
+where
+    S: BuildHasher,
+{
+    pub(crate) const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            table: OAHashMap::with_hasher(hash_builder).with_foreign_hashes(),
+        }
+    }
+
+    pub(crate) fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            table: OAHashMap::with_capacity_and_hasher(capacity, hash_builder)
+                .with_foreign_hashes(),
+        }
+    }
+
+    pub(crate) fn hasher(&self) -> &S {
+        self.table.hasher()
+    }
+
+    /// The hash an entry with this key is filed under.
+    pub(crate) fn hash_key<Q>(&self, key: &Q) -> u64
+    where
+        Q: Hash + ?Sized,
+    {
+        self.table.hash_key(key)
+    }
+
+    /// The first position filed under `hash` that `is_match` accepts.
+    pub(crate) fn find(&self, hash: u64, mut is_match: impl FnMut(usize) -> bool) -> Option<usize> {
+        let (&position, _) = self
+            .table
+            .raw_entry()
+            .from_hash(hash, |&position| is_match(position))?;
+
+        Some(position)
+    }
+
+    /// Like [`find`](Self::find), but a miss hands back the slot a new
+    /// position goes in, so filing it takes no second probe.
+    pub(crate) fn find_or_vacant(
+        &mut self,
+        hash: u64,
+        mut is_match: impl FnMut(usize) -> bool,
+    ) -> Result<usize, VacantPosition<'_, S>> {
+        match self
+            .table
+            .raw_entry_mut()
+            .from_hash(hash, |&position| is_match(position))
+        {
+            RawEntryMut::Occupied(entry) => Ok(*entry.key()),
+            RawEntryMut::Vacant(entry) => Err(VacantPosition { entry, hash }),
+        }
+    }
+
+    /// Files `position` under `hash` without checking what is there already.
+    pub(crate) fn insert(&mut self, hash: u64, position: usize) {
+        let Err(vacant) = self.find_or_vacant(hash, |_| false) else {
+            unreachable!("A lookup matching nothing found position {position}");
+        };
+
+        vacant.insert(position);
+    }
+
+    /// Like [`find`](Self::find), also removing the position it finds.
+    pub(crate) fn take(
+        &mut self,
+        hash: u64,
+        mut is_match: impl FnMut(usize) -> bool,
+    ) -> Option<usize> {
+        let RawEntryMut::Occupied(entry) = self
+            .table
+            .raw_entry_mut()
+            .from_hash(hash, |&position| is_match(position))
+        else {
+            return None;
+        };
+        let (position, ()) = entry.remove_entry();
+
+        Some(position)
+    }
+
+    /// Panics if `position` is not filed under `hash`.
+    pub(crate) fn remove(&mut self, hash: u64, position: usize) {
+        if self.take(hash, |i| i == position).is_none() {
+            panic!("Position {position} is not indexed");
+        }
+    }
+
+    /// Repoints the entry filed under `hash` at `from` to `to`, for an entry
+    /// that moved.
+    ///
+    /// Panics if `from` is not filed under `hash`.
+    pub(crate) fn replace(&mut self, hash: u64, from: usize, to: usize) {
+        let RawEntryMut::Occupied(mut entry) =
+            self.table.raw_entry_mut().from_hash(hash, |&i| i == from)
+        else {
+            panic!("Position {from} is not indexed");
+        };
+
+        *entry.key_mut() = to;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.table.reserve(additional);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn stats(&self) -> crate::TableStats {
+        self.table.stats()
+    }
+}
+
+impl<S> VacantPosition<'_, S>
+where
+    S: BuildHasher,
+{
+    pub(crate) fn insert(self, position: usize) {
+        self.entry.insert_hashed_nocheck(self.hash, position, ());
+    }
+}
+
+impl<S> Clone for PositionIndex<S>
+where
+    S: BuildHasher + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The order in which a probe visits slots.
+//!
+//! Every sequence starts at the slot picked by [`starting_index`] and visits
+//! each slot of a power-of-two table exactly once in its first `mask + 1`
+//! steps, so a walk that gives up after that many steps has seen them all.
+
+/// 2^64 divided by the golden ratio.
+pub(crate) const FIBONACCI_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// How a map picks the next slot when the current one is taken.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Probing {
+    /// The next slot along. Entries are kept in Robin Hood order and removals
+    /// shift later entries back, so chains only ever hold live entries.
+    #[default]
+    Linear,
+    /// Steps of one, two, three and so on, which keeps keys that start near
+    /// each other from piling into one cluster.
+    Quadratic,
+    /// A fixed step drawn from the hash, so even keys that share a starting
+    /// slot take different paths.
+    DoubleHashing,
+    /// The next slot along, but every entry stays within
+    /// [`HOPSCOTCH_NEIGHBORHOOD`](Self::HOPSCOTCH_NEIGHBORHOOD) slots of its
+    /// starting slot, so no lookup inspects more slots than that. Inserts
+    /// move entries closer to make room, and grow the table when they cannot.
+    Hopscotch,
+}
+
+#[cfg(feature = "alloc")]
+impl Probing {
+    pub const HOPSCOTCH_NEIGHBORHOOD: usize = 16;
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) trait ProbeSequence {
+    fn new(hash: u64, mask: usize) -> Self;
+
+    /// The next slot to visit; the first call returns the starting slot.
+    fn next(&mut self) -> usize;
+}
+
+/// Fibonacci hashing: the multiply carries entropy from every bit of the
+/// hash into the top bits, which the rotate then brings down under the mask,
+/// so hashers with weak low bits still spread across the table.
+#[cfg(feature = "alloc")]
+pub(crate) fn starting_index(hash: u64, mask: usize) -> usize {
+    let bits = usize::BITS - mask.leading_zeros();
+
+    hash.wrapping_mul(FIBONACCI_MULTIPLIER).rotate_left(bits) as usize & mask
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) struct Linear {
+    index: usize,
+    mask: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl ProbeSequence for Linear {
+    fn new(hash: u64, mask: usize) -> Self {
+        Self {
+            index: starting_index(hash, mask).wrapping_sub(1),
+            mask,
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        self.index = self.index.wrapping_add(1) & self.mask;
+
+        self.index
+    }
+}
+
+/// Triangular steps, which cover a power-of-two table.
+#[cfg(feature = "alloc")]
+pub(crate) struct Quadratic {
+    index: usize,
+    step: usize,
+    mask: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl ProbeSequence for Quadratic {
+    fn new(hash: u64, mask: usize) -> Self {
+        Self {
+            index: starting_index(hash, mask),
+            step: 0,
+            mask,
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        let index = self.index;
+        self.step += 1;
+        self.index = (self.index + self.step) & self.mask;
+
+        index
+    }
+}
+
+/// Any odd step is coprime with a power-of-two slot count, so it cycles
+/// through every slot.
+#[cfg(feature = "alloc")]
+pub(crate) struct DoubleHashing {
+    index: usize,
+    step: usize,
+    mask: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl ProbeSequence for DoubleHashing {
+    fn new(hash: u64, mask: usize) -> Self {
+        // The starting slot comes from the top bits of the product, so the
+        // step takes bits from further down.
+        let step = (hash.wrapping_mul(FIBONACCI_MULTIPLIER) >> 24) as usize | 1;
+
+        Self {
+            index: starting_index(hash, mask),
+            step,
+            mask,
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        let index = self.index;
+        self.index = self.index.wrapping_add(self.step) & self.mask;
+
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{starting_index, DoubleHashing, Linear, ProbeSequence, Quadratic};
+    use crate::test_util::Rng;
+
+    fn assert_covers<P: ProbeSequence>(hash: u64, mask: usize) {
+        let mut sequence = P::new(hash, mask);
+        let mut seen = vec![false; mask + 1];
+        assert_eq!(starting_index(hash, mask), sequence.next());
+        seen[starting_index(hash, mask)] = true;
+        for _ in 0..mask {
+            let index = sequence.next();
+            assert!(!seen[index], "slot {index} visited twice");
+            seen[index] = true;
+        }
+    }
+
+    #[test]
+    fn sequences_visit_every_slot_once() {
+        let mut rng = Rng::new(62);
+        for bits in 0..12 {
+            let mask = (1 << bits) - 1;
+            for _ in 0..20 {
+                let hash = rng.next();
+                assert_covers::<Linear>(hash, mask);
+                assert_covers::<Quadratic>(hash, mask);
+                assert_covers::<DoubleHashing>(hash, mask);
+            }
+        }
+    }
+}
@@ -0,0 +1,1241 @@
+//! Slot storage split into a control byte per slot, an array of possibly
+//! uninitialized keys with their hashes, and a parallel array of values.
+//!
+//! A control byte is [`EMPTY`], or the top seven bits of the bucket's hash
+//! with the high bit clear. Probes compare those seven bits
+//! before touching the bucket, so most mismatches never load a key.
+//! Probes only ever read the keys, so however large the values are, a chain
+//! of them costs no more cache lines than the keys take up.
+//!
+//! An occupancy bitmap mirrors the control bytes one bit per slot, so
+//! iteration skips 64 empty slots per word it loads and a sparse table costs
+//! little more to walk than its live entries.
+//!
+//! With the `zeroize` feature, a slot's key and value bytes are wiped as its
+//! bucket leaves it, and the whole buffer before it is freed.
+
+#[cfg(feature = "alloc")]
+use core::alloc::Layout;
+#[cfg(feature = "alloc")]
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use core::mem::MaybeUninit;
+#[cfg(feature = "alloc")]
+use core::ptr::NonNull;
+#[cfg(feature = "alloc")]
+use core::slice;
+
+#[cfg(feature = "alloc")]
+use allocator_api2::alloc::Allocator;
+
+use crate::probe::FIBONACCI_MULTIPLIER;
+#[cfg(feature = "alloc")]
+use crate::{Bucket, HashedKey, TryReserveError};
+
+pub(crate) const EMPTY: u8 = 0xff;
+
+/// The control byte stored for a bucket with this hash: seven bits of the
+/// product [`starting_index`](crate::probe::starting_index) mixes it into,
+/// from below the bits it takes the index from. Hashes with empty top bits,
+/// such as integers that are their own hashes, still get distinct bytes.
+pub(crate) fn h2(hash: u64) -> u8 {
+    (hash.wrapping_mul(FIBONACCI_MULTIPLIER) >> 32) as u8 & 0x7f
+}
+
+pub(crate) fn is_full(ctrl: u8) -> bool {
+    ctrl & 0x80 == 0
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) type Keys<K> = [MaybeUninit<HashedKey<K>>];
+#[cfg(feature = "alloc")]
+pub(crate) type Values<V> = [MaybeUninit<V>];
+
+/// All four arrays live in one allocation: the keys, the values, the control
+/// bytes and the occupancy bitmap, in that order. Only the keys and control
+/// bytes have their own pointers; the rest sit at offsets fixed by the slot
+/// count.
+///
+/// Invariant: a slot's key and value are initialized exactly when its
+/// control byte is full, and its bit in the bitmap is set exactly then too.
+#[cfg(feature = "alloc")]
+pub(crate) struct RawTable<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    keys: NonNull<MaybeUninit<HashedKey<K>>>,
+    ctrl: NonNull<u8>,
+    num_slots: usize,
+    alloc: A,
+    marker: PhantomData<Bucket<K, V>>,
+}
+
+// SAFETY: the table owns its buckets like a `Vec` would.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V, A> Send for RawTable<K, V, A>
+where
+    K: Send,
+    V: Send,
+    A: Allocator + Clone + Send,
+{
+}
+
+// SAFETY: shared access only hands out shared references to the buckets.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V, A> Sync for RawTable<K, V, A>
+where
+    K: Sync,
+    V: Sync,
+    A: Allocator + Clone + Sync,
+{
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, A> RawTable<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// The largest power of two whose arrays, padded to their alignments,
+    /// fit in `isize::MAX` bytes.
+    pub(crate) const MAX_SLOTS: usize = {
+        let eighths_per_slot = 8 * Self::SLOT_BYTES + 1;
+        let padding = align_of::<HashedKey<K>>() + align_of::<V>() + 2 * size_of::<u64>();
+        let max = (isize::MAX as usize - padding) / eighths_per_slot * 8;
+
+        1 << (usize::BITS - 1 - max.leading_zeros())
+    };
+
+    /// A key, a value and a control byte.
+    pub(crate) const SLOT_BYTES: usize = size_of::<HashedKey<K>>() + size_of::<V>() + 1;
+
+    pub(crate) const fn new_in(alloc: A) -> Self {
+        Self {
+            keys: NonNull::dangling(),
+            ctrl: NonNull::dangling(),
+            num_slots: 0,
+            alloc,
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn try_with_slots(num_slots: usize, alloc: A) -> Result<Self, TryReserveError> {
+        if num_slots == 0 {
+            return Ok(Self::new_in(alloc));
+        }
+
+        let layout = Self::layout(num_slots)?;
+        let ptr = alloc
+            .allocate(layout)
+            .map_err(|_| TryReserveError::AllocError { layout })?
+            .cast::<u8>();
+
+        // SAFETY: the control bytes are inside the allocation.
+        let ctrl = unsafe { ptr.add(Self::ctrl_offset(num_slots)) };
+        // SAFETY: the control bytes are in bounds and `u8` has no invariants.
+        unsafe { ctrl.write_bytes(EMPTY, num_slots) };
+
+        let table = Self {
+            keys: ptr.cast(),
+            ctrl,
+            num_slots,
+            alloc,
+            marker: PhantomData,
+        };
+        // SAFETY: the bitmap is in bounds, suitably aligned, and any bit
+        // pattern is a valid `u64`.
+        unsafe {
+            table
+                .occupied_ptr()
+                .write_bytes(0, Self::occupied_words(num_slots))
+        };
+
+        Ok(table)
+    }
+
+    fn layout(num_slots: usize) -> Result<Layout, TryReserveError> {
+        let overflow = |_| TryReserveError::CapacityOverflow;
+        let keys = Layout::array::<HashedKey<K>>(num_slots).map_err(overflow)?;
+        let values = Layout::array::<V>(num_slots).map_err(overflow)?;
+        let ctrl = Layout::array::<u8>(num_slots).map_err(overflow)?;
+        let occupied = Layout::array::<u64>(Self::occupied_words(num_slots)).map_err(overflow)?;
+        let (layout, values_offset) = keys.extend(values).map_err(overflow)?;
+        let (layout, ctrl_offset) = layout.extend(ctrl).map_err(overflow)?;
+        let (layout, occupied_offset) = layout.extend(occupied).map_err(overflow)?;
+        debug_assert_eq!(Self::values_offset(num_slots), values_offset);
+        debug_assert_eq!(Self::ctrl_offset(num_slots), ctrl_offset);
+        debug_assert_eq!(Self::occupied_offset(num_slots), occupied_offset);
+
+        Ok(layout.pad_to_align())
+    }
+
+    fn values_offset(num_slots: usize) -> usize {
+        (num_slots * size_of::<HashedKey<K>>()).next_multiple_of(align_of::<V>())
+    }
+
+    fn ctrl_offset(num_slots: usize) -> usize {
+        Self::values_offset(num_slots) + num_slots * size_of::<V>()
+    }
+
+    fn occupied_offset(num_slots: usize) -> usize {
+        (Self::ctrl_offset(num_slots) + num_slots).next_multiple_of(align_of::<u64>())
+    }
+
+    fn occupied_words(num_slots: usize) -> usize {
+        num_slots.div_ceil(u64::BITS as usize)
+    }
+
+    fn values(&self) -> NonNull<MaybeUninit<V>> {
+        if self.num_slots == 0 {
+            return NonNull::dangling();
+        }
+
+        // SAFETY: the values are inside the allocation the keys start.
+        unsafe {
+            self.keys
+                .cast::<u8>()
+                .add(Self::values_offset(self.num_slots))
+                .cast()
+        }
+    }
+
+    /// Only valid while the table is allocated.
+    fn occupied_ptr(&self) -> *mut u64 {
+        // SAFETY: the bitmap is inside the allocation the keys start.
+        unsafe {
+            self.keys
+                .cast::<u8>()
+                .add(Self::occupied_offset(self.num_slots))
+                .cast()
+                .as_ptr()
+        }
+    }
+
+    fn occupied(&self) -> &[u64] {
+        if self.num_slots == 0 {
+            return &[];
+        }
+
+        // SAFETY: there are `occupied_words` initialized words.
+        unsafe { slice::from_raw_parts(self.occupied_ptr(), Self::occupied_words(self.num_slots)) }
+    }
+
+    fn occupied_mut(&mut self) -> &mut [u64] {
+        if self.num_slots == 0 {
+            return &mut [];
+        }
+
+        // SAFETY: as in `occupied`, and `&mut self` makes the borrow unique.
+        unsafe {
+            slice::from_raw_parts_mut(self.occupied_ptr(), Self::occupied_words(self.num_slots))
+        }
+    }
+
+    pub(crate) fn is_marked(&self, index: usize) -> bool {
+        self.occupied()[index / 64] >> (index % 64) & 1 == 1
+    }
+
+    fn mark(&mut self, index: usize, full: bool) {
+        let word = &mut self.occupied_mut()[index / 64];
+        let bit = 1 << (index % 64);
+        if full {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+    }
+
+    /// Sets the occupancy bitmap from the control bytes, after writers that
+    /// only filled those in.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn sync_occupied(&mut self) {
+        for word in 0..Self::occupied_words(self.num_slots) {
+            let ctrl = &self.ctrl_bytes()[word * 64..self.num_slots.min(word * 64 + 64)];
+            let bits = ctrl
+                .iter()
+                .rev()
+                .fold(0, |bits, &byte| bits << 1 | u64::from(is_full(byte)));
+            self.occupied_mut()[word] = bits;
+        }
+    }
+
+    /// The first full slot at `index` or after it, without wrapping.
+    pub(crate) fn next_full(&self, index: usize) -> Option<usize> {
+        FullSlots::new(self.occupied(), index).next()
+    }
+
+    /// How many slots along from `index`, wrapping around the end, the
+    /// nearest full one sits; the slot count when there is none.
+    pub(crate) fn distance_to_full(&self, index: usize) -> usize {
+        match self.next_full(index).or_else(|| self.next_full(0)) {
+            Some(full) => full.wrapping_sub(index) & (self.num_slots - 1),
+            None => self.num_slots,
+        }
+    }
+
+    /// The indices of the full slots, in order.
+    pub(crate) fn full_slots(&self) -> FullSlots<'_> {
+        FullSlots::new(self.occupied(), 0)
+    }
+
+    pub(crate) fn num_slots(&self) -> usize {
+        self.num_slots
+    }
+
+    pub(crate) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        if self.num_slots == 0 {
+            return 0;
+        }
+
+        Self::layout(self.num_slots)
+            .expect("layout was valid when allocated")
+            .size()
+    }
+
+    /// Identifies the allocation, for checking that it was reused.
+    #[cfg(test)]
+    pub(crate) fn as_ptr(&self) -> *const u8 {
+        self.ctrl.as_ptr()
+    }
+
+    pub(crate) fn ctrl(&self, index: usize) -> u8 {
+        self.ctrl_bytes()[index]
+    }
+
+    pub(crate) fn is_full(&self, index: usize) -> bool {
+        is_full(self.ctrl(index))
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&HashedKey<K>> {
+        // SAFETY: the slot is full.
+        self.is_full(index).then(|| unsafe { self.key(index) })
+    }
+
+    pub(crate) fn get_pair(&self, index: usize) -> Option<(&HashedKey<K>, &V)> {
+        // SAFETY: the slot is full.
+        self.is_full(index).then(|| unsafe {
+            (
+                self.key(index),
+                self.values().add(index).as_ref().assume_init_ref(),
+            )
+        })
+    }
+
+    pub(crate) fn get_pair_mut(&mut self, index: usize) -> Option<(&mut HashedKey<K>, &mut V)> {
+        if !self.is_full(index) {
+            return None;
+        }
+
+        // SAFETY: the slot is full, and the two arrays do not overlap.
+        unsafe {
+            Some((
+                self.keys.add(index).as_mut().assume_init_mut(),
+                self.values().add(index).as_mut().assume_init_mut(),
+            ))
+        }
+    }
+
+    pub(crate) fn value_mut(&mut self, index: usize) -> Option<&mut V> {
+        self.get_pair_mut(index).map(|(_, value)| value)
+    }
+
+    /// # Safety
+    ///
+    /// The slot at `index` must be full.
+    pub(crate) unsafe fn key(&self, index: usize) -> &HashedKey<K> {
+        // SAFETY: full slots are in bounds and initialized.
+        unsafe { self.keys.add(index).as_ref().assume_init_ref() }
+    }
+
+    /// Pointer to the first value, for handing out disjoint borrows.
+    pub(crate) fn values_ptr(&mut self) -> *mut V {
+        self.values().as_ptr().cast()
+    }
+
+    /// Copies the bucket out of a full slot, which stays marked full.
+    ///
+    /// # Safety
+    ///
+    /// The slot must be full, and the copy must end up owning the bucket:
+    /// the table may never drop it.
+    pub(crate) unsafe fn read(&self, index: usize) -> Bucket<K, V> {
+        // SAFETY: the caller guarantees the slot is full and hands over its
+        // ownership.
+        unsafe {
+            let key = self.keys.add(index).as_ref().assume_init_read();
+            let value = self.values().add(index).as_ref().assume_init_read();
+
+            Bucket::from_parts(key, value)
+        }
+    }
+
+    /// Fills a slot that is not full.
+    pub(crate) fn insert(&mut self, index: usize, bucket: Bucket<K, V>) {
+        assert!(!self.is_full(index), "Slot {index} is already occupied");
+        self.ctrl_bytes_mut()[index] = h2(bucket.hash);
+        self.mark(index, true);
+
+        let (key, value) = bucket.into_parts();
+        // SAFETY: `index` was checked against the control bytes, so it is in
+        // bounds.
+        unsafe {
+            self.keys.add(index).as_mut().write(key);
+            self.values().add(index).as_mut().write(value);
+        }
+    }
+
+    /// Moves the bucket out of a full slot, which becomes empty.
+    pub(crate) fn take(&mut self, index: usize) -> Option<Bucket<K, V>> {
+        if !self.is_full(index) {
+            return None;
+        }
+
+        self.ctrl_bytes_mut()[index] = EMPTY;
+        self.mark(index, false);
+
+        // SAFETY: the slot was full and is now marked free, so the bucket is
+        // read out exactly once.
+        let bucket = unsafe { self.read(index) };
+        self.wipe_slot(index);
+
+        Some(bucket)
+    }
+
+    /// Moves the bucket in a full slot to an empty one.
+    pub(crate) fn move_bucket(&mut self, from: usize, to: usize) {
+        assert!(self.is_full(from), "Slot {from} is not occupied");
+        assert!(!self.is_full(to), "Slot {to} is already occupied");
+
+        let ctrl = self.ctrl_bytes_mut();
+        ctrl[to] = ctrl[from];
+        ctrl[from] = EMPTY;
+        self.mark(to, true);
+        self.mark(from, false);
+
+        // SAFETY: both slots are in bounds and distinct, `from` was full and
+        // `to` was not, so the bucket is moved without being duplicated.
+        unsafe {
+            let values = self.values();
+            let key = self.keys.add(from).as_ref().assume_init_read();
+            self.keys.add(to).as_mut().write(key);
+            let value = values.add(from).as_ref().assume_init_read();
+            values.add(to).as_mut().write(value);
+        }
+        self.wipe_slot(from);
+    }
+
+    /// Drops every bucket and marks every slot empty. Goes by the control
+    /// bytes alone, so it also drops whatever the parallel consuming
+    /// iterator left behind without updating the bitmap. If a drop panics,
+    /// the buckets not yet dropped are leaked and the table still ends up
+    /// empty, with no slot left marked full over a dropped bucket.
+    ///
+    /// Buckets with nothing to drop or wipe are forgotten in bulk instead.
+    pub(crate) fn clear(&mut self) {
+        if !core::mem::needs_drop::<Bucket<K, V>>() && !cfg!(feature = "zeroize") {
+            self.forget_all();
+            return;
+        }
+
+        let guard = ForgetGuard { table: self };
+        let values = guard.table.values();
+        for index in 0..guard.table.num_slots {
+            if guard.table.is_full(index) {
+                guard.table.ctrl_bytes_mut()[index] = EMPTY;
+                guard.table.mark(index, false);
+                // SAFETY: the slot was full and is now marked empty, so the
+                // bucket is dropped exactly once.
+                unsafe {
+                    guard.table.keys.add(index).as_mut().assume_init_drop();
+                    values.add(index).as_mut().assume_init_drop();
+                }
+                guard.table.wipe_slot(index);
+            }
+        }
+    }
+
+    /// Zeroes the key and value bytes of a slot whose bucket has left it,
+    /// under the `zeroize` feature.
+    fn wipe_slot(&mut self, index: usize) {
+        if cfg!(feature = "zeroize") {
+            // SAFETY: the slot is in bounds, and with its bucket gone its
+            // bytes belong to nobody.
+            unsafe {
+                wipe(self.keys.add(index).cast(), size_of::<HashedKey<K>>());
+                wipe(self.values().add(index).cast(), size_of::<V>());
+            }
+        }
+    }
+
+    /// Marks every slot empty without dropping the buckets, whose ownership
+    /// has moved elsewhere.
+    pub(crate) fn forget_all(&mut self) {
+        self.ctrl_bytes_mut().fill(EMPTY);
+        self.occupied_mut().fill(0);
+    }
+
+    pub(crate) fn iter(&self) -> RawIter<'_, K, V> {
+        self.iter_from(0)
+    }
+
+    /// Iterates the full slots at `start` and after.
+    pub(crate) fn iter_from(&self, start: usize) -> RawIter<'_, K, V> {
+        RawIter {
+            full: FullSlots::new(self.occupied(), start),
+            keys: self.keys,
+            values: self.values(),
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> RawIterMut<'_, K, V> {
+        RawIterMut {
+            full: FullSlots::new(self.occupied(), 0),
+            keys: self.keys,
+            values: self.values(),
+            marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn parts(&self) -> (&[u8], &Keys<K>, &Values<V>) {
+        // SAFETY: both arrays hold `num_slots` elements, and `MaybeUninit`
+        // makes no claim about their contents.
+        unsafe {
+            (
+                self.ctrl_bytes(),
+                slice::from_raw_parts(self.keys.as_ptr(), self.num_slots),
+                slice::from_raw_parts(self.values().as_ptr(), self.num_slots),
+            )
+        }
+    }
+
+    /// Writing to the control bytes must keep the invariant: a slot marked
+    /// full must be initialized, and a bucket must be moved out or dropped
+    /// before its slot is marked free.
+    pub(crate) fn parts_mut(&mut self) -> (&mut [u8], &mut Keys<K>, &mut Values<V>) {
+        // SAFETY: as in `parts`; the arrays do not overlap and `&mut self`
+        // makes the borrows unique.
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.ctrl.as_ptr(), self.num_slots),
+                slice::from_raw_parts_mut(self.keys.as_ptr(), self.num_slots),
+                slice::from_raw_parts_mut(self.values().as_ptr(), self.num_slots),
+            )
+        }
+    }
+
+    pub(crate) fn ctrl_bytes(&self) -> &[u8] {
+        // SAFETY: there are `num_slots` control bytes, all initialized.
+        unsafe { slice::from_raw_parts(self.ctrl.as_ptr(), self.num_slots) }
+    }
+
+    fn ctrl_bytes_mut(&mut self) -> &mut [u8] {
+        self.parts_mut().0
+    }
+}
+
+/// Marks every slot of the table empty once a sweep over it ends, however
+/// it ends.
+#[cfg(feature = "alloc")]
+struct ForgetGuard<'a, K, V, A>
+where
+    A: Allocator + Clone,
+{
+    table: &'a mut RawTable<K, V, A>,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, A> Drop for ForgetGuard<'_, K, V, A>
+where
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        self.table.forget_all();
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, A> Drop for RawTable<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        if self.num_slots == 0 {
+            return;
+        }
+
+        if core::mem::needs_drop::<Bucket<K, V>>() {
+            self.clear();
+        }
+
+        let layout = Self::layout(self.num_slots).expect("layout was valid when allocated");
+        if cfg!(feature = "zeroize") {
+            // SAFETY: the allocation is `layout.size()` bytes long and is
+            // about to be freed.
+            unsafe { wipe(self.keys.cast(), layout.size()) };
+        }
+        // SAFETY: the allocation was made by this allocator with this layout.
+        unsafe { self.alloc.deallocate(self.keys.cast(), layout) };
+    }
+}
+
+/// Zeroes `len` bytes through `zeroize`, whose writes the compiler may not
+/// drop as dead stores to memory about to be freed. Only called under the
+/// `zeroize` feature.
+///
+/// # Safety
+///
+/// The bytes must be valid for writes.
+#[cfg(feature = "alloc")]
+unsafe fn wipe(ptr: NonNull<u8>, len: usize) {
+    #[cfg(feature = "zeroize")]
+    {
+        use zeroize::Zeroize;
+
+        // SAFETY: the caller guarantees the bytes are writable, and they
+        // are only written.
+        let bytes =
+            unsafe { slice::from_raw_parts_mut(ptr.as_ptr().cast::<MaybeUninit<u8>>(), len) };
+        bytes.zeroize();
+    }
+    #[cfg(not(feature = "zeroize"))]
+    let _ = (ptr, len);
+}
+
+/// The indices of the set bits in a bitmap, lowest first.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Default)]
+pub(crate) struct FullSlots<'a> {
+    words: slice::Iter<'a, u64>,
+    current: u64,
+    base: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> FullSlots<'a> {
+    /// Starts at bit `start`, which may be one past the last.
+    fn new(words: &'a [u64], start: usize) -> Self {
+        let first = start / 64;
+        let Some(&word) = words.get(first) else {
+            return Self::default();
+        };
+
+        Self {
+            words: words[first + 1..].iter(),
+            current: word & (u64::MAX << (start % 64)),
+            base: first * 64,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Iterator for FullSlots<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.current = *self.words.next()?;
+            self.base += 64;
+        }
+
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+
+        Some(self.base + bit)
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) struct RawIter<'a, K, V> {
+    full: FullSlots<'a>,
+    keys: NonNull<MaybeUninit<HashedKey<K>>>,
+    values: NonNull<MaybeUninit<V>>,
+    marker: PhantomData<(&'a HashedKey<K>, &'a V)>,
+}
+
+// SAFETY: the iterator only hands out shared references, like `Iter`.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V> Send for RawIter<'_, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
+// SAFETY: as for `Send`.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V> Sync for RawIter<'_, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V> Iterator for RawIter<'a, K, V> {
+    type Item = (&'a HashedKey<K>, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.full.next()?;
+
+        // SAFETY: the bitmap says the slot is in bounds and initialized.
+        unsafe {
+            Some((
+                self.keys.add(index).as_ref().assume_init_ref(),
+                self.values.add(index).as_ref().assume_init_ref(),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Clone for RawIter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            full: self.full.clone(),
+            keys: self.keys,
+            values: self.values,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Default for RawIter<'_, K, V> {
+    fn default() -> Self {
+        Self {
+            full: FullSlots::default(),
+            keys: NonNull::dangling(),
+            values: NonNull::dangling(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Hands out each full slot once, so the mutable borrows never overlap.
+#[cfg(feature = "alloc")]
+pub(crate) struct RawIterMut<'a, K, V> {
+    full: FullSlots<'a>,
+    keys: NonNull<MaybeUninit<HashedKey<K>>>,
+    values: NonNull<MaybeUninit<V>>,
+    marker: PhantomData<(&'a HashedKey<K>, &'a mut V)>,
+}
+
+// SAFETY: the iterator is a unique borrow of the values, like `IterMut`.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V> Send for RawIterMut<'_, K, V>
+where
+    K: Sync,
+    V: Send,
+{
+}
+
+// SAFETY: shared access only reads the bitmap.
+#[cfg(feature = "alloc")]
+unsafe impl<K, V> Sync for RawIterMut<'_, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V> RawIterMut<'a, K, V> {
+    pub(crate) fn as_iter(&self) -> RawIter<'_, K, V> {
+        // Only the slots not yet handed out are left to reach.
+        RawIter {
+            full: self.full.clone(),
+            keys: self.keys,
+            values: self.values,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V> Iterator for RawIterMut<'a, K, V> {
+    type Item = (&'a HashedKey<K>, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.full.next()?;
+
+        // SAFETY: the bitmap says the slot is initialized, and each index
+        // comes up once.
+        unsafe {
+            Some((
+                self.keys.add(index).as_ref().assume_init_ref(),
+                self.values.add(index).as_mut().assume_init_mut(),
+            ))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V> Default for RawIterMut<'_, K, V> {
+    fn default() -> Self {
+        Self {
+            full: FullSlots::default(),
+            keys: NonNull::dangling(),
+            values: NonNull::dangling(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Moves the buckets out of a table; whatever is not yielded is dropped with
+/// the table.
+#[cfg(feature = "alloc")]
+pub(crate) struct RawIntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    table: RawTable<K, V, A>,
+    index: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, A> RawIntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(table: RawTable<K, V, A>) -> Self {
+        Self { table, index: 0 }
+    }
+
+    pub(crate) fn as_iter(&self) -> RawIter<'_, K, V> {
+        self.table.iter_from(self.index)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, A> Iterator for RawIntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = Bucket<K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.table.next_full(self.index)?;
+        self.index = index + 1;
+
+        self.table.take(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    #[cfg(feature = "zeroize")]
+    use core::alloc::Layout;
+    #[cfg(feature = "zeroize")]
+    use core::ptr::NonNull;
+    #[cfg(feature = "zeroize")]
+    use core::slice;
+
+    use allocator_api2::alloc::Global;
+    #[cfg(feature = "zeroize")]
+    use allocator_api2::alloc::{AllocError, Allocator};
+
+    use super::{RawIntoIter, RawTable, EMPTY};
+    use crate::test_util::{assert_layout, CountingAlloc, Rng};
+    use crate::{Bucket, HashedKey, OAHashMap, Probing, TryReserveError};
+
+    fn bucket(key: i32, value: &Rc<()>) -> Bucket<i32, Rc<()>> {
+        Bucket::new(key as u64, key, Rc::clone(value))
+    }
+
+    #[test]
+    fn drops_each_bucket_once() {
+        let value = Rc::new(());
+        let mut table = RawTable::try_with_slots(8, Global).unwrap();
+        for index in 0..6 {
+            table.insert(index, bucket(index as i32, &value));
+        }
+        assert_eq!(7, Rc::strong_count(&value));
+
+        drop(table.take(0));
+        assert_eq!(6, Rc::strong_count(&value));
+        assert!(table.take(0).is_none());
+
+        table.clear();
+        assert_eq!(1, Rc::strong_count(&value));
+        assert!((0..8).all(|index| table.ctrl(index) == EMPTY));
+
+        table.insert(3, bucket(3, &value));
+        drop(table);
+        assert_eq!(1, Rc::strong_count(&value));
+    }
+
+    #[test]
+    fn clearing_plain_buckets_empties_the_table() {
+        let mut table = RawTable::try_with_slots(64, Global).unwrap();
+        for index in (0..64).step_by(3) {
+            table.insert(index, Bucket::new(index as u64, index as u64, !0u64));
+        }
+
+        table.clear();
+        assert!((0..64).all(|index| table.ctrl(index) == EMPTY));
+        assert!(table.occupied().iter().all(|&word| word == 0));
+        assert!(table.iter().next().is_none());
+
+        table.insert(5, Bucket::new(5, 5, 50));
+        assert_eq!(
+            Some((5, &50)),
+            table.get_pair(5).map(|(hashed, value)| (hashed.key, value))
+        );
+    }
+
+    #[test]
+    fn partially_consumed_into_iter_drops_the_rest() {
+        let value = Rc::new(());
+        let mut table = RawTable::try_with_slots(8, Global).unwrap();
+        for index in [1, 4, 6] {
+            table.insert(index, bucket(index as i32, &value));
+        }
+
+        let mut iter = RawIntoIter::new(table);
+        assert_eq!(1, iter.next().unwrap().key);
+        assert_eq!(
+            vec![4, 6],
+            iter.as_iter().map(|(b, _)| b.key).collect::<Vec<_>>()
+        );
+        drop(iter);
+
+        assert_eq!(1, Rc::strong_count(&value));
+    }
+
+    #[test]
+    fn taken_slots_are_reused() {
+        let value = Rc::new(());
+        let mut table = RawTable::try_with_slots(4, Global).unwrap();
+        table.insert(2, bucket(2, &value));
+        drop(table.take(2));
+        assert_eq!(EMPTY, table.ctrl(2));
+
+        table.insert(2, bucket(7, &value));
+        assert_eq!(7, table.get(2).unwrap().key);
+        assert_eq!(
+            vec![7],
+            table.iter().map(|(b, _)| b.key).collect::<Vec<_>>()
+        );
+        drop(table);
+
+        let mut oa = OAHashMap::with_capacity(1);
+        for key in 0..20 {
+            oa.insert(key, Rc::clone(&value));
+        }
+        let slots = oa.table.num_slots();
+        for round in 0..5 {
+            for key in 0..20 {
+                oa.remove(&key);
+                oa.insert(key + round, Rc::clone(&value));
+                oa.remove(&(key + round));
+                oa.insert(key, Rc::clone(&value));
+            }
+        }
+
+        assert_eq!(slots, oa.table.num_slots());
+        assert_eq!(21, Rc::strong_count(&value));
+        drop(oa);
+        assert_eq!(1, Rc::strong_count(&value));
+    }
+
+    #[test]
+    fn resize_moves_buckets_without_dropping() {
+        let value = Rc::new(());
+        let alloc = CountingAlloc::default();
+        let mut oa = OAHashMap::new_in(alloc.clone());
+        for key in 0..200 {
+            oa.insert(key, Rc::clone(&value));
+        }
+        for key in 0..150 {
+            oa.remove(&key);
+        }
+        oa.shrink_to_fit();
+
+        assert!(oa.resizes > 0);
+        assert_eq!(51, Rc::strong_count(&value));
+        assert!((150..200).all(|key| oa.contains_key(&key)));
+        assert_eq!(alloc.stats().live_bytes, oa.allocated_bytes());
+
+        let clone = oa.clone();
+        assert_eq!(101, Rc::strong_count(&value));
+        drop(oa);
+        drop(clone);
+        assert_eq!(1, Rc::strong_count(&value));
+        assert_eq!(0, alloc.stats().live_bytes);
+    }
+
+    #[test]
+    fn max_slots_is_the_largest_layout() {
+        type Table = RawTable<u64, [u8; 40], Global>;
+        assert!(Table::MAX_SLOTS.is_power_of_two());
+        assert!(Table::layout(Table::MAX_SLOTS).is_ok());
+        assert!(Table::layout(Table::MAX_SLOTS * 2).is_err());
+        assert_eq!(
+            Some(TryReserveError::CapacityOverflow),
+            Table::try_with_slots(Table::MAX_SLOTS * 2, Global).err()
+        );
+    }
+
+    #[test]
+    fn sparse_tables_iterate_their_live_entries() {
+        let mut oa: OAHashMap<u64, u64> = OAHashMap::with_capacity(1_000_000);
+        let keys: Vec<u64> = (0..100).map(|i| i * 7919).collect();
+        oa.extend(keys.iter().map(|&key| (key, key + 1)));
+        assert!(oa.table.num_slots() >= 1 << 20);
+
+        let mut seen: Vec<u64> = oa.keys().copied().collect();
+        seen.sort_unstable();
+        assert_eq!(keys, seen);
+        assert_eq!(100, oa.values().count());
+        oa.values_mut().for_each(|value| *value -= 1);
+        assert!(oa.iter().all(|(key, value)| key == value));
+
+        let mut half = oa.clone();
+        half.retain(|&key, _| key % 2 == 0);
+        assert_eq!(50, half.len());
+        assert_eq!(50, half.extract_if(|_, _| true).count());
+
+        let mut drained: Vec<u64> = oa.clone().drain().map(|(key, _)| key).collect();
+        drained.sort_unstable();
+        assert_eq!(keys, drained);
+
+        let mut owned: Vec<u64> = oa.into_keys().collect();
+        owned.sort_unstable();
+        assert_eq!(keys, owned);
+    }
+
+    #[test]
+    fn bitmap_tracks_the_control_bytes_through_churn() {
+        for probing in [
+            Probing::Linear,
+            Probing::Quadratic,
+            Probing::DoubleHashing,
+            Probing::Hopscotch,
+        ] {
+            let mut rng = Rng::new(76);
+            let mut oa: OAHashMap<u64, u64> = OAHashMap::new();
+            oa.set_probing(probing);
+            oa.set_auto_shrink(true);
+            for round in 0..20_000 {
+                let key = rng.next() % 2000;
+                if rng.next().is_multiple_of(3) {
+                    oa.remove(&key);
+                } else {
+                    oa.insert(key, key);
+                }
+                if round % 1000 == 0 {
+                    assert_layout(&oa);
+                }
+            }
+            assert_layout(&oa);
+        }
+    }
+
+    #[test]
+    fn keys_and_values_drop_apart_through_resizes() {
+        let keys = Rc::new(());
+        let values = Rc::new(());
+        let mut oa = OAHashMap::new();
+        for key in 0..300 {
+            oa.insert((key, Rc::clone(&keys)), Rc::clone(&values));
+        }
+        assert_eq!(301, Rc::strong_count(&keys));
+        assert_eq!(301, Rc::strong_count(&values));
+
+        // Overwriting keeps the stored key and drops the old value.
+        oa.insert((7, Rc::clone(&keys)), Rc::new(()));
+        assert_eq!(301, Rc::strong_count(&keys));
+        assert_eq!(300, Rc::strong_count(&values));
+
+        for key in 0..200 {
+            oa.remove(&(key, Rc::clone(&keys)));
+        }
+        oa.shrink_to_fit();
+        assert!(oa.resizes > 1);
+        assert_eq!(101, Rc::strong_count(&keys));
+        assert_eq!(101, Rc::strong_count(&values));
+        assert_layout(&oa);
+
+        oa.retain(|(key, _), _| key % 2 == 0);
+        assert_eq!(51, Rc::strong_count(&keys));
+        assert_eq!(51, Rc::strong_count(&values));
+
+        oa.clear();
+        assert_eq!(1, Rc::strong_count(&keys));
+        assert_eq!(1, Rc::strong_count(&values));
+
+        oa.insert((1, Rc::clone(&keys)), Rc::clone(&values));
+        drop(oa);
+        assert_eq!(1, Rc::strong_count(&keys));
+        assert_eq!(1, Rc::strong_count(&values));
+    }
+
+    #[test]
+    fn values_follow_the_keys_in_one_allocation() {
+        type Table = RawTable<u64, [u8; 200], Global>;
+        let table = Table::try_with_slots(8, Global).unwrap();
+        let (ctrl, keys, values) = table.parts();
+
+        let keys_at = keys.as_ptr() as usize;
+        assert_eq!(
+            keys_at + 8 * size_of::<HashedKey<u64>>(),
+            values.as_ptr() as usize
+        );
+        assert_eq!(values.as_ptr() as usize + 8 * 200, ctrl.as_ptr() as usize);
+    }
+
+    /// Every key hashes alike, so a lookup compares itself against each key
+    /// in the one chain, and records where that key sits.
+    struct Probed(u64);
+
+    std::thread_local! {
+        static COMPARED: std::cell::RefCell<Vec<usize>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    impl core::hash::Hash for Probed {
+        fn hash<H: core::hash::Hasher>(&self, _: &mut H) {}
+    }
+
+    impl PartialEq for Probed {
+        fn eq(&self, other: &Self) -> bool {
+            COMPARED.with(|compared| compared.borrow_mut().push(other as *const Self as usize));
+
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Probed {}
+
+    /// The bytes a missed lookup spans, from the first key it compares to
+    /// the end of the last. The chain may wrap around the end of the keys.
+    fn lookup_span<V>(value: V) -> usize
+    where
+        V: Clone,
+    {
+        let mut oa = OAHashMap::with_capacity(16);
+        for key in 0..6 {
+            oa.insert(Probed(key), value.clone());
+        }
+
+        COMPARED.with(|compared| compared.borrow_mut().clear());
+        assert!(oa.get(&Probed(6)).is_none());
+        let compared = COMPARED.with(|compared| compared.take());
+        assert_eq!(6, compared.len());
+
+        let keys_bytes = size_of_val(oa.table.parts().1);
+        let steps: usize = compared
+            .windows(2)
+            .map(|pair| (pair[1] + keys_bytes - pair[0]) % keys_bytes)
+            .sum();
+
+        steps + size_of::<HashedKey<Probed>>()
+    }
+
+    #[test]
+    fn lookups_never_stride_over_values() {
+        let small = lookup_span(0u8);
+        let large = lookup_span([0u8; 200]);
+
+        assert_eq!(6 * size_of::<HashedKey<Probed>>(), small);
+        assert_eq!(small, large);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn vacated_slots_are_zeroed() {
+        type Secret = [u64; 4];
+
+        fn bytes(table: &RawTable<u64, Secret, Global>, index: usize) -> Vec<u8> {
+            let (_, keys, values) = table.parts();
+            // SAFETY: every slot looked at was written, and has been wiped
+            // since if it is no longer full.
+            unsafe {
+                let key = slice::from_raw_parts(
+                    keys[index].as_ptr().cast::<u8>(),
+                    size_of::<HashedKey<u64>>(),
+                );
+                let value =
+                    slice::from_raw_parts(values[index].as_ptr().cast(), size_of::<Secret>());
+
+                [key, value].concat()
+            }
+        }
+        let wiped = |table: &RawTable<_, _, _>, index| bytes(table, index).iter().all(|&b| b == 0);
+
+        let mut table = RawTable::try_with_slots(8, Global).unwrap();
+        for index in 0..4 {
+            table.insert(index, Bucket::new(!0, index as u64 | 1 << 40, [0xa5; 4]));
+        }
+        assert!(!wiped(&table, 0));
+
+        assert!(table.take(0).is_some());
+        assert!(wiped(&table, 0));
+        table.move_bucket(1, 5);
+        assert!(wiped(&table, 1));
+        assert!(!wiped(&table, 5));
+        table.clear();
+        assert!([2, 3, 5].into_iter().all(|index| wiped(&table, index)));
+    }
+
+    /// Counts the buffers freed with any byte left unzeroed.
+    #[cfg(feature = "zeroize")]
+    #[derive(Clone, Default)]
+    struct WipeChecking {
+        frees: Rc<std::cell::Cell<usize>>,
+        unwiped: Rc<std::cell::Cell<usize>>,
+    }
+
+    #[cfg(feature = "zeroize")]
+    unsafe impl Allocator for WipeChecking {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            // SAFETY: the table wipes the whole buffer before freeing it, so
+            // every byte is initialized.
+            let bytes = unsafe { slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+            self.frees.set(self.frees.get() + 1);
+            if bytes.iter().any(|&b| b != 0) {
+                self.unwiped.set(self.unwiped.get() + 1);
+            }
+
+            unsafe { Global.deallocate(ptr, layout) }
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn every_freed_buffer_is_zeroed() {
+        let alloc = WipeChecking::default();
+        let mut oa = OAHashMap::new_in(alloc.clone());
+        for key in 0..2_000u64 {
+            oa.insert(key, [0xa5u8; 24]);
+        }
+        oa.retain(|key, _| key % 4 != 0);
+        oa.extend((2_000..3_000).map(|key| (key, [0x5a; 24])));
+        oa.shrink_to_fit();
+        oa.set_incremental_resize(true);
+        oa.extend((3_000..6_000).map(|key| (key, [0xa5; 24])));
+        oa.drain().take(100).for_each(drop);
+        assert!(oa.is_empty());
+        drop(oa);
+
+        assert!(alloc.frees.get() > 8);
+        assert_eq!(0, alloc.unwiped.get());
+    }
+
+    #[test]
+    fn table_is_two_pointers_and_a_slot_count() {
+        assert_eq!(
+            3 * size_of::<usize>(),
+            size_of::<RawTable<u32, u32, Global>>()
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{Bucket, DefaultHashBuilder, Equivalent, HashedKey, OAHashMap};
+
+pub struct RawEntryBuilder<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a OAHashMap<K, V, S, A>,
+}
+
+impl<'a, K, V, S, A> RawEntryBuilder<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a OAHashMap<K, V, S, A>) -> Self {
+        Self { map }
+    }
+
+    pub fn from_key<Q>(self, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.map.make_hash(key);
+
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q) -> Option<(&'a K, &'a V)>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    pub fn from_hash<F>(self, hash: u64, is_match: F) -> Option<(&'a K, &'a V)>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let (hashed, value) = self.map.find_bucket_with(hash, is_match)?;
+
+        Some((&hashed.key, value))
+    }
+}
+
+pub struct RawEntryBuilderMut<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+}
+
+impl<'a, K, V, S, A> RawEntryBuilderMut<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub(crate) fn new(map: &'a mut OAHashMap<K, V, S, A>) -> Self {
+        Self { map }
+    }
+
+    pub fn from_key<Q>(self, key: &Q) -> RawEntryMut<'a, K, V, S, A>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.map.make_hash(key);
+
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q) -> RawEntryMut<'a, K, V, S, A>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.from_hash(hash, |k| key.equivalent(k))
+    }
+
+    pub fn from_hash<F>(self, hash: u64, mut is_match: F) -> RawEntryMut<'a, K, V, S, A>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        self.map.settle(hash, &mut is_match);
+        match self.map.find_index_with(hash, is_match) {
+            Some(index) => RawEntryMut::Occupied(RawOccupiedEntryMut {
+                map: self.map,
+                index,
+            }),
+            None => RawEntryMut::Vacant(RawVacantEntryMut { map: self.map }),
+        }
+    }
+}
+
+pub enum RawEntryMut<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    Occupied(RawOccupiedEntryMut<'a, K, V, S, A>),
+    Vacant(RawVacantEntryMut<'a, K, V, S, A>),
+}
+
+impl<'a, K, V, S, A> RawEntryMut<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn insert(self, key: K, value: V) -> RawOccupiedEntryMut<'a, K, V, S, A> {
+        match self {
+            RawEntryMut::Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            }
+            RawEntryMut::Vacant(entry) => {
+                let hash = entry.map.make_hash(&key);
+                let index = entry.map.insert_new(Bucket::new(hash, key, value));
+
+                RawOccupiedEntryMut {
+                    map: entry.map,
+                    index,
+                }
+            }
+        }
+    }
+
+    pub fn or_insert(self, default_key: K, default_value: V) -> (&'a mut K, &'a mut V) {
+        match self {
+            RawEntryMut::Occupied(entry) => entry.into_key_value(),
+            RawEntryMut::Vacant(entry) => entry.insert(default_key, default_value),
+        }
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> (&'a mut K, &'a mut V)
+    where
+        F: FnOnce() -> (K, V),
+    {
+        match self {
+            RawEntryMut::Occupied(entry) => entry.into_key_value(),
+            RawEntryMut::Vacant(entry) => {
+                let (key, value) = default();
+                entry.insert(key, value)
+            }
+        }
+    }
+
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut K, &mut V),
+    {
+        if let RawEntryMut::Occupied(entry) = &mut self {
+            let (key, value) = entry.get_key_value_mut();
+            f(key, value);
+        }
+
+        self
+    }
+}
+
+pub struct RawOccupiedEntryMut<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+    index: usize,
+}
+
+impl<'a, K, V, S, A> RawOccupiedEntryMut<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn key(&self) -> &K {
+        &self.bucket().0.key
+    }
+
+    pub fn key_mut(&mut self) -> &mut K {
+        &mut self.bucket_mut().0.key
+    }
+
+    pub fn into_key(self) -> &'a mut K {
+        self.into_key_value().0
+    }
+
+    pub fn get(&self) -> &V {
+        self.bucket().1
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.bucket_mut().1
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.into_key_value().1
+    }
+
+    pub fn get_key_value(&self) -> (&K, &V) {
+        let (hashed, value) = self.bucket();
+
+        (&hashed.key, value)
+    }
+
+    pub fn get_key_value_mut(&mut self) -> (&mut K, &mut V) {
+        let (hashed, value) = self.bucket_mut();
+
+        (&mut hashed.key, value)
+    }
+
+    pub fn into_key_value(self) -> (&'a mut K, &'a mut V) {
+        let (hashed, value) = self.map.table.get_pair_mut(self.index).unwrap();
+
+        (&mut hashed.key, value)
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn insert_key(&mut self, key: K) -> K {
+        core::mem::replace(self.key_mut(), key)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let bucket = self.map.take_bucket(self.index);
+        self.map.shrink_if_sparse();
+
+        bucket.into_pair()
+    }
+
+    fn bucket(&self) -> (&HashedKey<K>, &V) {
+        self.map.table.get_pair(self.index).unwrap()
+    }
+
+    fn bucket_mut(&mut self) -> (&mut HashedKey<K>, &mut V) {
+        self.map.table.get_pair_mut(self.index).unwrap()
+    }
+}
+
+pub struct RawVacantEntryMut<'a, K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+}
+
+impl<'a, K, V, S, A> RawVacantEntryMut<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn insert(self, key: K, value: V) -> (&'a mut K, &'a mut V) {
+        let hash = self.map.make_hash(&key);
+
+        self.insert_hashed_nocheck(hash, key, value)
+    }
+
+    /// Inserts the pair into the probe chain selected by `hash`.
+    ///
+    /// The hash is trusted as given and cached with the entry, so resizes keep
+    /// it too. If it does not match the hash the map computes for `key`, the
+    /// entry may be missed by later lookups or end up duplicated by later
+    /// inserts, but the map stays memory safe.
+    pub fn insert_hashed_nocheck(self, hash: u64, key: K, value: V) -> (&'a mut K, &'a mut V) {
+        let index = self.map.insert_new(Bucket::new(hash, key, value));
+        let (hashed, value) = self.map.table.get_pair_mut(index).unwrap();
+
+        (&mut hashed.key, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OAHashMap, RawEntryMut};
+
+    #[test]
+    fn shared_hash_across_maps() {
+        let mut names: OAHashMap<(u32, String), &str> = OAHashMap::new();
+        let mut sizes: OAHashMap<(u32, String), usize> =
+            OAHashMap::with_hasher(names.hasher().clone());
+        let key = (7, "composite".to_string());
+        let hash = names.hash_key(&key);
+
+        names
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, &key)
+            .or_insert(key.clone(), "seven");
+        match sizes.raw_entry_mut().from_hash(hash, |k| k == &key) {
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, key.clone(), 9);
+            }
+            RawEntryMut::Occupied(_) => unreachable!(),
+        }
+
+        assert_eq!(
+            Some((&key, &"seven")),
+            names.raw_entry().from_hash(hash, |k| k.0 == 7)
+        );
+        assert_eq!(Some(&9), sizes.search(&key));
+        assert_eq!(Some((&key, &9)), sizes.raw_entry().from_key(&key));
+    }
+
+    #[test]
+    fn occupied_raw_entry() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        oa.insert(1, 10);
+        let hash = oa.hash_key(&1);
+
+        oa.raw_entry_mut()
+            .from_hash(hash, |k| *k == 1)
+            .and_modify(|_, value| *value += 1);
+
+        assert_eq!(Some(&11), oa.search(&1));
+
+        match oa.raw_entry_mut().from_key(&1) {
+            RawEntryMut::Occupied(mut entry) => {
+                assert_eq!((&1, &11), entry.get_key_value());
+                assert_eq!(11, entry.insert(12));
+                assert_eq!((1, 12), entry.remove_entry());
+            }
+            RawEntryMut::Vacant(_) => unreachable!(),
+        }
+
+        assert!(oa.is_empty());
+
+        let entry = oa.raw_entry_mut().from_key(&2).insert(2, 20);
+
+        assert_eq!(&20, entry.get());
+        assert_eq!(Some(&20), oa.search(&2));
+    }
+
+    // The check rehashes keys, and panics on any filed under a wrong hash.
+    #[cfg(not(feature = "check-hashes"))]
+    #[test]
+    fn wrong_hash_only_misses() {
+        let mut oa: OAHashMap<i32, i32> = OAHashMap::new();
+        let wrong_hash = oa.hash_key(&1).wrapping_add(1);
+        match oa.raw_entry_mut().from_hash(wrong_hash, |k| *k == 1) {
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(wrong_hash, 1, 10);
+            }
+            RawEntryMut::Occupied(_) => unreachable!(),
+        }
+
+        assert_eq!(
+            Some((&1, &10)),
+            oa.raw_entry().from_key_hashed_nocheck(wrong_hash, &1)
+        );
+
+        for i in 100..1000 {
+            oa.insert(i, i);
+        }
+
+        assert_eq!(None, oa.search(&1));
+        assert_eq!(
+            Some((&1, &10)),
+            oa.raw_entry().from_key_hashed_nocheck(wrong_hash, &1)
+        );
+        assert_eq!(901, oa.len());
+    }
+}
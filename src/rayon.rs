@@ -0,0 +1,442 @@
+//! Parallel iteration with rayon.
+//!
+//! Every iterator splits the slot array into ranges and skips the free slots
+//! of each range on its own thread. Ranges hold equal numbers of slots rather
+//! than of entries, so clustered tables may balance poorly.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{
+    FromParallelIterator, IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelExtend, ParallelIterator,
+};
+
+use crate::raw::{self, Keys, Values, EMPTY};
+use crate::{Bucket, OAHashMap};
+
+pub struct ParIter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    slots: (&'a [u8], &'a Keys<K>, &'a Values<V>),
+    /// The table an incremental resize is moving entries out of.
+    migrating: (&'a [u8], &'a Keys<K>, &'a Values<V>),
+}
+
+impl<'a, K, V> ParIter<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    fn new<S, A>(map: &'a OAHashMap<K, V, S, A>) -> Self
+    where
+        A: Allocator + Clone,
+    {
+        let migrating = map.migration.as_ref().map(|m| m.table().parts());
+
+        Self {
+            slots: map.table.parts(),
+            migrating: migrating.unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a, K, V> ParallelIterator for ParIter<'a, K, V>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+{
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let (ctrl, keys, values) = self.slots;
+        let (migrating_ctrl, migrating_keys, migrating_values) = self.migrating;
+        ctrl.par_iter()
+            .zip(keys)
+            .zip(values)
+            .chain(
+                migrating_ctrl
+                    .par_iter()
+                    .zip(migrating_keys)
+                    .zip(migrating_values),
+            )
+            .filter_map(|((&ctrl, key), value)| {
+                // SAFETY: a full control byte marks an initialized slot.
+                raw::is_full(ctrl)
+                    .then(|| unsafe { (&key.assume_init_ref().key, value.assume_init_ref()) })
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+pub struct ParIterMut<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    slots: (&'a [u8], &'a Keys<K>, &'a mut Values<V>),
+    migrating: (&'a [u8], &'a Keys<K>, &'a mut Values<V>),
+}
+
+impl<'a, K, V> ParIterMut<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    fn new<S, A>(map: &'a mut OAHashMap<K, V, S, A>) -> Self
+    where
+        A: Allocator + Clone,
+    {
+        let (ctrl, keys, values) = map.table.parts_mut();
+        let migrating = map.migration.as_mut().map(|m| m.table_mut().parts_mut());
+        let (migrating_ctrl, migrating_keys, migrating_values) = migrating.unwrap_or_default();
+
+        Self {
+            slots: (&*ctrl, &*keys, values),
+            migrating: (&*migrating_ctrl, &*migrating_keys, migrating_values),
+        }
+    }
+}
+
+impl<'a, K, V> ParallelIterator for ParIterMut<'a, K, V>
+where
+    K: Hash + Eq + Sync + Send,
+    V: Send,
+{
+    type Item = (&'a K, &'a mut V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let (ctrl, keys, values) = self.slots;
+        let (migrating_ctrl, migrating_keys, migrating_values) = self.migrating;
+        ctrl.par_iter()
+            .zip(keys)
+            .zip(values)
+            .chain(
+                migrating_ctrl
+                    .par_iter()
+                    .zip(migrating_keys)
+                    .zip(migrating_values),
+            )
+            .filter_map(|((&ctrl, key), value)| {
+                // SAFETY: a full control byte marks an initialized slot.
+                raw::is_full(ctrl)
+                    .then(|| unsafe { (&key.assume_init_ref().key, value.assume_init_mut()) })
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+pub struct IntoParIter<K, V, S, A>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: OAHashMap<K, V, S, A>,
+}
+
+impl<K, V, S, A> ParallelIterator for IntoParIter<K, V, S, A>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: Send,
+    A: Allocator + Clone + Send,
+{
+    type Item = (K, V);
+
+    fn drive_unindexed<C>(mut self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let (ctrl, keys, values) = self.map.table.parts_mut();
+        let migrating = self
+            .map
+            .migration
+            .as_mut()
+            .map(|m| m.table_mut().parts_mut());
+        let (migrating_ctrl, migrating_keys, migrating_values) = migrating.unwrap_or_default();
+
+        // Each slot is marked empty as its bucket is moved out, so the table only
+        // drops what the consumer never took.
+        ctrl.par_iter_mut()
+            .zip(keys)
+            .zip(values)
+            .chain(
+                migrating_ctrl
+                    .par_iter_mut()
+                    .zip(migrating_keys)
+                    .zip(migrating_values),
+            )
+            .filter_map(|((ctrl, key), value)| {
+                raw::is_full(*ctrl).then(|| {
+                    *ctrl = EMPTY;
+                    // SAFETY: the slot was full and is now marked free, so
+                    // the bucket is read out exactly once.
+                    unsafe { Bucket::from_parts(key.assume_init_read(), value.assume_init_read()) }
+                })
+            })
+            .map(Bucket::into_pair)
+            .drive_unindexed(consumer)
+    }
+}
+
+pub struct ParKeys<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    inner: ParIter<'a, K, V>,
+}
+
+impl<'a, K, V> ParallelIterator for ParKeys<'a, K, V>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+{
+    type Item = &'a K;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.inner.map(|(key, _)| key).drive_unindexed(consumer)
+    }
+}
+
+pub struct ParValues<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    inner: ParIter<'a, K, V>,
+}
+
+impl<'a, K, V> ParallelIterator for ParValues<'a, K, V>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+{
+    type Item = &'a V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.inner.map(|(_, value)| value).drive_unindexed(consumer)
+    }
+}
+
+pub struct ParValuesMut<'a, K, V>
+where
+    K: Hash + Eq,
+{
+    inner: ParIterMut<'a, K, V>,
+}
+
+impl<'a, K, V> ParallelIterator for ParValuesMut<'a, K, V>
+where
+    K: Hash + Eq + Sync + Send,
+    V: Send,
+{
+    type Item = &'a mut V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.inner.map(|(_, value)| value).drive_unindexed(consumer)
+    }
+}
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn par_keys(&self) -> ParKeys<'_, K, V> {
+        ParKeys {
+            inner: ParIter::new(self),
+        }
+    }
+
+    pub fn par_values(&self) -> ParValues<'_, K, V> {
+        ParValues {
+            inner: ParIter::new(self),
+        }
+    }
+
+    pub fn par_values_mut(&mut self) -> ParValuesMut<'_, K, V> {
+        ParValuesMut {
+            inner: ParIterMut::new(self),
+        }
+    }
+}
+
+impl<'a, K, V, S, A> IntoParallelIterator for &'a OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+    A: Allocator + Clone,
+{
+    type Iter = ParIter<'a, K, V>;
+    type Item = (&'a K, &'a V);
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter::new(self)
+    }
+}
+
+impl<'a, K, V, S, A> IntoParallelIterator for &'a mut OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Sync + Send,
+    V: Send,
+    A: Allocator + Clone,
+{
+    type Iter = ParIterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIterMut::new(self)
+    }
+}
+
+impl<K, V, S, A> IntoParallelIterator for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: Send,
+    A: Allocator + Clone + Send,
+{
+    type Iter = IntoParIter<K, V, S, A>;
+    type Item = (K, V);
+
+    fn into_par_iter(self) -> Self::Iter {
+        IntoParIter { map: self }
+    }
+}
+
+impl<K, V, S, A> ParallelExtend<(K, V)> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Collects the pairs in parallel, then inserts them on the calling thread.
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let pairs: Vec<(K, V)> = par_iter.into_par_iter().collect();
+        self.extend(pairs);
+    }
+}
+
+impl<K, V, S, A> FromParallelIterator<(K, V)> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Send,
+    V: Send,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut map = Self::with_hasher_in(S::default(), A::default());
+        map.par_extend(par_iter);
+
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::iter::{IntoParallelIterator, ParallelExtend, ParallelIterator};
+
+    use crate::OAHashMap;
+
+    fn sparse() -> OAHashMap<u32, u64> {
+        let mut oa: OAHashMap<u32, u64> = (0..20_000).map(|i| (i, u64::from(i) * 3)).collect();
+        oa.retain(|key, _| key % 7 != 0);
+
+        oa
+    }
+
+    fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+        items.sort_unstable();
+
+        items
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let oa = sparse();
+
+        assert_eq!(
+            sorted(oa.iter().collect()),
+            sorted((&oa).into_par_iter().collect())
+        );
+        assert_eq!(sorted(oa.keys().collect()), sorted(oa.par_keys().collect()));
+        assert_eq!(
+            sorted(oa.values().collect()),
+            sorted(oa.par_values().collect())
+        );
+        assert_eq!(
+            sorted(oa.iter().map(|(k, v)| (*k, *v)).collect()),
+            sorted(oa.into_par_iter().collect())
+        );
+    }
+
+    #[test]
+    fn parallel_mutation() {
+        let mut oa = sparse();
+        oa.par_values_mut().for_each(|value| *value += 1);
+        (&mut oa)
+            .into_par_iter()
+            .for_each(|(key, value)| *value -= u64::from(*key));
+
+        assert!(oa
+            .iter()
+            .all(|(key, value)| *value == u64::from(*key) * 2 + 1));
+    }
+
+    #[test]
+    fn parallel_iteration_mid_migration() {
+        let mut oa: OAHashMap<u32, u64> = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        let mut key = 0;
+        while oa.migration.as_ref().is_none_or(|m| m.len() < 1000) {
+            oa.insert(key, u64::from(key));
+            key += 1;
+        }
+        let expected: Vec<u32> = (0..key).collect();
+
+        assert_eq!(expected, sorted(oa.par_keys().copied().collect()));
+        oa.par_values_mut().for_each(|value| *value += 1);
+        assert!(oa.iter().all(|(key, value)| *value == u64::from(*key) + 1));
+        assert_eq!(
+            expected,
+            sorted(oa.into_par_iter().map(|(key, _)| key).collect())
+        );
+    }
+
+    #[test]
+    fn par_extend_and_collect() {
+        let mut oa = sparse();
+        oa.par_extend((0..30_000u32).into_par_iter().map(|i| (i, 0)));
+
+        assert_eq!(30_000, oa.len());
+        assert!(oa.values().all(|value| *value == 0));
+
+        let collected: OAHashMap<u32, u32> = (0..5000u32)
+            .into_par_iter()
+            .map(|i| (i % 1000, i))
+            .collect();
+        assert_eq!(1000, collected.len());
+        assert!(collected.iter().all(|(key, value)| value % 1000 == *key));
+    }
+}
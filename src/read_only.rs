@@ -0,0 +1,243 @@
+//! A map frozen after its build phase. Only the lookup half of the API is
+//! left, so a `&ReadOnlyOAHashMap` or an `Arc` of one can be shared between
+//! threads with no lock around it.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::ops::Index;
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{DefaultHashBuilder, Equivalent, Iter, Keys, MemoryStats, OAHashMap, Values};
+
+pub struct ReadOnlyOAHashMap<K, V, S = DefaultHashBuilder, A = Global>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: OAHashMap<K, V, S, A>,
+}
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Freezes the map, first moving it to the smallest table that holds its
+    /// entries with none of the slots removals left behind, so every lookup
+    /// walks as little as it can.
+    pub fn into_read_only(mut self) -> ReadOnlyOAHashMap<K, V, S, A> {
+        self.rehash_shrink();
+
+        ReadOnlyOAHashMap { map: self }
+    }
+}
+
+impl<K, V, S, A> ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.get_key_value(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.map.iter()
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        self.map.keys()
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        self.map.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.map.memory_stats()
+    }
+
+    /// Thaws the map, which keeps the compacted table it was frozen with.
+    pub fn into_inner(self) -> OAHashMap<K, V, S, A> {
+        self.map
+    }
+}
+
+impl<K, V, S, A> Clone for ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    S: BuildHasher + Clone,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<K, V, S, A> PartialEq for ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: PartialEq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K, V, S, A> Eq for ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    V: Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<K, V, S, A> fmt::Debug for ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+impl<K, Q, V, S, A> Index<&Q> for ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    Q: Hash + Equivalent<K> + ?Sized,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Output = V;
+
+    /// Panics if the key is not present.
+    #[track_caller]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in ReadOnlyOAHashMap")
+    }
+}
+
+impl<'a, K, V, S, A> IntoIterator for &'a ReadOnlyOAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::{OAHashMap, Probing, ReadOnlyOAHashMap};
+
+    fn assert_shareable<T: Send + Sync>() {}
+
+    #[test]
+    fn frozen_maps_are_shareable() {
+        assert_shareable::<ReadOnlyOAHashMap<String, Vec<u8>>>();
+    }
+
+    #[test]
+    fn threads_read_a_frozen_map() {
+        let mut oa = OAHashMap::new();
+        let mut std = HashMap::new();
+        for key in 0..2000u32 {
+            oa.insert(key, key.to_string());
+            std.insert(key, key.to_string());
+        }
+        for key in (0..2000).step_by(3) {
+            oa.remove(&key);
+            std.remove(&key);
+        }
+
+        let frozen = Arc::new(oa.into_read_only());
+        thread::scope(|scope| {
+            for offset in 0..4 {
+                let (frozen, std) = (Arc::clone(&frozen), &std);
+                scope.spawn(move || {
+                    for key in (offset..2100).step_by(4) {
+                        assert_eq!(std.get(&key), frozen.get(&key));
+                        assert_eq!(std.contains_key(&key), frozen.contains_key(&key));
+                    }
+                    assert_eq!(std.len(), frozen.len());
+                    assert_eq!(std.len(), frozen.iter().count());
+                });
+            }
+        });
+
+        assert_eq!("1", frozen[&1]);
+        assert_eq!(Some((&2, &"2".to_string())), frozen.get_key_value(&2));
+        assert_eq!(std.keys().sum::<u32>(), frozen.keys().sum::<u32>());
+    }
+
+    #[test]
+    fn freezing_compacts_the_table() {
+        let mut oa: OAHashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+        oa.set_probing(Probing::DoubleHashing);
+        for key in (0..1000).filter(|key| key % 10 != 0) {
+            oa.remove(&key);
+        }
+        assert!(oa.memory_stats().tombstones > 0);
+
+        let slots = oa.memory_stats().slots;
+        let frozen = oa.into_read_only();
+        let stats = frozen.memory_stats();
+        assert_eq!(0, stats.tombstones);
+        assert!(stats.slots < slots);
+        assert_eq!(100, frozen.values().count());
+
+        let mut thawed = frozen.into_inner();
+        thawed.insert(1000, 1000);
+        assert_eq!(101, thawed.len());
+    }
+}
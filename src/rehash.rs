@@ -0,0 +1,333 @@
+//! Rebuilding large tables from rayon's threads.
+//!
+//! Under linear probing a table's layout follows from its entries alone:
+//! every run holds its entries in order of starting slot, those sharing one
+//! in the order they went in, each as close to its starting slot as the
+//! entries before it allow. So rather than have threads contend for slots,
+//! they work that layout out and then fill every slot exactly once:
+//!
+//! - the old slots are split into chunks, and each chunk stages the starting
+//!   slot and scan position of its entries, sorted;
+//! - the new table is split into stripes, and each stripe gathers the staged
+//!   entries starting in it and sorts them into the order they sit in;
+//! - a pass over the stripes works out how far each one's last run spills
+//!   into the next, which only needs each stripe's own spill and count;
+//! - each stripe copies its entries into place, after whatever spilled into
+//!   it.
+//!
+//! Sequential rebuilds place entries in scan order, which is the order the
+//! ties are broken in here, so both give the same table slot for slot.
+//! Cached hashes find the starting slots, so no key is hashed, compared or
+//! dropped, and nothing can panic halfway but the allocator.
+
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use allocator_api2::alloc::Allocator;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::probe;
+use crate::raw::{self, RawTable};
+use crate::HashedKey;
+
+/// The arrays of a table, for threads that copy bytes in and out of them
+/// without ever treating those bytes as keys or values.
+struct Slots<K, V> {
+    ctrl: *mut u8,
+    keys: *mut MaybeUninit<HashedKey<K>>,
+    values: *mut MaybeUninit<V>,
+    len: usize,
+}
+
+// SAFETY: the threads only read hashes and control bytes and copy slots
+// bitwise, while the thread that owns the buckets waits for them. No key or
+// value is borrowed, moved out or dropped on another thread, so sharing the
+// pointers needs neither `K` nor `V` to be `Send` or `Sync`.
+unsafe impl<K, V> Send for Slots<K, V> {}
+
+// SAFETY: as for `Send`.
+unsafe impl<K, V> Sync for Slots<K, V> {}
+
+impl<K, V> Slots<K, V> {
+    fn of<A>(table: &RawTable<K, V, A>) -> Self
+    where
+        A: Allocator + Clone,
+    {
+        let (ctrl, keys, values) = table.parts();
+
+        Self {
+            ctrl: ctrl.as_ptr().cast_mut(),
+            keys: keys.as_ptr().cast_mut(),
+            values: values.as_ptr().cast_mut(),
+            len: ctrl.len(),
+        }
+    }
+
+    fn of_mut<A>(table: &mut RawTable<K, V, A>) -> Self
+    where
+        A: Allocator + Clone,
+    {
+        let (ctrl, keys, values) = table.parts_mut();
+
+        Self {
+            ctrl: ctrl.as_mut_ptr(),
+            keys: keys.as_mut_ptr(),
+            values: values.as_mut_ptr(),
+            len: ctrl.len(),
+        }
+    }
+
+    /// The cached hash in slot `index`, if it is full.
+    ///
+    /// # Safety
+    ///
+    /// `index` is in bounds, and nothing writes to the slot meanwhile.
+    unsafe fn hash(&self, index: usize) -> Option<u64> {
+        // SAFETY: a full control byte marks an initialized key, whose hash
+        // is read without borrowing the key beside it.
+        unsafe {
+            raw::is_full(*self.ctrl.add(index))
+                .then(|| ptr::addr_of!((*self.keys.add(index).cast::<HashedKey<K>>()).hash).read())
+        }
+    }
+
+    /// Copies the bucket in `from`'s slot `index` to slot `to`, which no
+    /// other thread touches.
+    ///
+    /// # Safety
+    ///
+    /// Both slots are in bounds, the source slot is full and the target one
+    /// is written by this call alone.
+    unsafe fn copy_from(&self, to: usize, from: &Self, index: usize) {
+        // SAFETY: as the caller promises; the tables are distinct.
+        unsafe {
+            ptr::copy_nonoverlapping(from.keys.add(index), self.keys.add(to), 1);
+            ptr::copy_nonoverlapping(from.values.add(index), self.values.add(to), 1);
+            *self.ctrl.add(to) = *from.ctrl.add(index);
+        }
+    }
+}
+
+/// The entries starting in one stripe of the new table, in the order they
+/// sit in, as their starting slots and scan positions.
+struct Stripe {
+    entries: Vec<(usize, usize)>,
+    /// The slots past the stripe's end its entries take if nothing spills
+    /// into it.
+    spill: usize,
+}
+
+/// Copies every full slot of `sources`, taken in order, into the empty
+/// `table` as linear probing lays them out. The sources keep their copies
+/// and must forget them afterwards.
+pub(crate) fn place_all<'a, K, V, A>(
+    table: &mut RawTable<K, V, A>,
+    sources: impl Iterator<Item = &'a RawTable<K, V, A>>,
+) where
+    K: 'a,
+    V: 'a,
+    A: Allocator + Clone + 'a,
+{
+    let sources: Vec<_> = sources.map(Slots::of).collect();
+    let num_slots = table.num_slots();
+    let mask = num_slots - 1;
+    let target = Slots::of_mut(table);
+    let locate = |mut position: usize| {
+        for source in &sources {
+            match position.checked_sub(source.len) {
+                Some(rest) => position = rest,
+                None => return (source, position),
+            }
+        }
+        unreachable!("positions are below the sources' total length");
+    };
+
+    let pieces = rayon::current_num_threads() * 4;
+    let total: usize = sources.iter().map(|source| source.len).sum();
+    let chunk_len = total.div_ceil(pieces).max(1);
+    let staged: Vec<Vec<(usize, usize)>> = (0..total.div_ceil(chunk_len))
+        .into_par_iter()
+        .map(|chunk| {
+            let positions = chunk * chunk_len..total.min((chunk + 1) * chunk_len);
+            let mut staged: Vec<_> = positions
+                .filter_map(|position| {
+                    let (source, index) = locate(position);
+                    // SAFETY: the sources are only read until every thread
+                    // is done.
+                    let hash = unsafe { source.hash(index)? };
+
+                    Some((probe::starting_index(hash, mask), position))
+                })
+                .collect();
+            staged.sort_unstable();
+            staged
+        })
+        .collect();
+
+    let stripe_len = num_slots / pieces.next_power_of_two().min(num_slots);
+    let stripes: Vec<Stripe> = (0..num_slots / stripe_len)
+        .into_par_iter()
+        .map(|stripe| {
+            let (start, end) = (stripe * stripe_len, (stripe + 1) * stripe_len);
+            let mut entries: Vec<_> = staged
+                .iter()
+                .flat_map(|chunk| {
+                    let from = chunk.partition_point(|&(home, _)| home < start);
+                    let to = chunk.partition_point(|&(home, _)| home < end);
+                    &chunk[from..to]
+                })
+                .copied()
+                .collect();
+            entries.sort_unstable();
+            let last = entries
+                .iter()
+                .fold(start, |next, &(home, _)| next.max(home) + 1);
+
+            Stripe {
+                entries,
+                spill: last.saturating_sub(end),
+            }
+        })
+        .collect();
+    drop(staged);
+
+    // What spills into a stripe pushes its entries along by as much, and
+    // what spills out of the last one lands in the first. A second time
+    // round starts from that and settles every stripe, since the table's
+    // empty slots absorb any spill before it comes back round again.
+    let mut carries = alloc::vec![0; stripes.len()];
+    let mut carry = 0;
+    for _ in 0..2 {
+        for (stripe, into) in stripes.iter().zip(&mut carries) {
+            *into = carry;
+            carry = stripe
+                .spill
+                .max((carry + stripe.entries.len()).saturating_sub(stripe_len));
+        }
+    }
+    debug_assert_eq!(carries[0], carry);
+
+    (0..stripes.len()).into_par_iter().for_each(|stripe| {
+        let mut next = stripe * stripe_len + carries[stripe];
+        for &(home, position) in &stripes[stripe].entries {
+            let at = next.max(home);
+            next = at + 1;
+            let (source, index) = locate(position);
+            // SAFETY: the layout gives every entry its own slot, and the
+            // staged positions are full slots of the sources.
+            unsafe { target.copy_from(at & mask, source, index) };
+        }
+    });
+
+    table.sync_occupied();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::Rng;
+    use crate::{DeterministicState, OAHashMap, OAHashMapBuilder};
+
+    type Map = OAHashMap<u64, u64, DeterministicState>;
+
+    /// Maps that only differ in whether they rebuild from several threads.
+    fn pair(builder: OAHashMapBuilder) -> [Map; 2] {
+        let hasher = DeterministicState(121);
+        [
+            builder
+                .clone()
+                .parallel_rehash_threshold(usize::MAX)
+                .build_with_hasher(hasher),
+            builder
+                .parallel_rehash_threshold(0)
+                .build_with_hasher(hasher),
+        ]
+    }
+
+    /// The parallel rebuild must leave the table exactly as the sequential
+    /// one does.
+    fn assert_same(sequential: &Map, parallel: &Map) {
+        parallel.check_invariants().unwrap();
+        assert_eq!(sequential.len(), parallel.len());
+        assert_eq!(sequential.table.ctrl_bytes(), parallel.table.ctrl_bytes());
+        assert!(sequential.iter().eq(parallel.iter()));
+        assert!(sequential
+            .iter()
+            .all(|(key, value)| parallel.get(key) == Some(value)));
+        assert_eq!(
+            sequential.memory_stats().slots,
+            parallel.memory_stats().slots
+        );
+    }
+
+    #[test]
+    fn parallel_growth_matches_sequential_growth() {
+        let mut rng = Rng::new(121);
+        let keys: Vec<u64> = (0..2_000_000).map(|_| rng.next()).collect();
+        let [mut sequential, mut parallel] = pair(OAHashMapBuilder::new());
+        for &key in &keys {
+            sequential.insert(key, !key);
+        }
+        // More threads than cores still split the table into more stripes.
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(8)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            for &key in &keys {
+                parallel.insert(key, !key);
+            }
+        });
+        assert!(parallel.stats().resizes >= 15);
+        assert_same(&sequential, &parallel);
+
+        sequential.retain(|&key, _| key % 16 == 0);
+        sequential.shrink_to_fit();
+        pool.install(|| {
+            parallel.retain(|&key, _| key % 16 == 0);
+            parallel.shrink_to_fit();
+        });
+        assert_same(&sequential, &parallel);
+    }
+
+    #[test]
+    fn parallel_rebuilds_handle_crowded_tables_and_migrations() {
+        let mut rng = Rng::new(1210);
+        // Long runs cross stripe edges and the end of the table.
+        let [mut sequential, mut parallel] = pair(
+            OAHashMapBuilder::new()
+                .max_load_factor(0.97)
+                .growth_factor(4.0),
+        );
+        for _ in 0..300_000 {
+            let key = rng.next();
+            sequential.insert(key, key);
+            parallel.insert(key, key);
+        }
+        assert_same(&sequential, &parallel);
+
+        // A rebuild while entries are still in the old table takes both.
+        let [mut sequential, mut parallel] = pair(OAHashMapBuilder::new());
+        for map in [&mut sequential, &mut parallel] {
+            map.set_incremental_resize(true);
+            map.extend((0..100_000).map(|i| (i, i)));
+            let mut key = u64::MAX;
+            while map.migration.is_none() {
+                map.insert(key, key);
+                key -= 1;
+            }
+            map.rebuild(map.table.num_slots());
+        }
+        assert!(parallel.migration.is_none());
+        assert_same(&sequential, &parallel);
+
+        let [mut sequential, mut parallel] = pair(OAHashMapBuilder::new());
+        for map in [&mut sequential, &mut parallel] {
+            map.insert(1, 1);
+            map.rehash();
+            map.clear();
+            map.shrink_to_fit();
+        }
+        assert_same(&sequential, &parallel);
+    }
+}
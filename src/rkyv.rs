@@ -0,0 +1,130 @@
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use rkyv::collections::swiss_table::map::{ArchivedHashMap, HashMapResolver};
+use rkyv::rancor::{Fallible, Source};
+use rkyv::ser::{Allocator as ArenaAllocator, Writer};
+use rkyv::{Archive, Deserialize, Place, Serialize};
+
+use crate::OAHashMap;
+
+const LOAD_FACTOR: (usize, usize) = (7, 8);
+
+/// The archived form of an [`OAHashMap`].
+///
+/// The archive is its own hash table, keyed by a fixed hash that does not
+/// depend on the map's hasher, so it can be queried in place with
+/// [`get`](ArchivedHashMap::get) and iterated without copying any entry.
+/// Spare capacity is not archived.
+pub type ArchivedOAHashMap<K, V> = ArchivedHashMap<K, V>;
+
+impl<K, V, S, A> Archive for OAHashMap<K, V, S, A>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Archive,
+    A: Allocator + Clone,
+{
+    type Archived = ArchivedOAHashMap<K::Archived, V::Archived>;
+    type Resolver = HashMapResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        ArchivedHashMap::resolve_from_len(self.len, LOAD_FACTOR, resolver, out);
+    }
+}
+
+impl<K, V, S, A, T> Serialize<T> for OAHashMap<K, V, S, A>
+where
+    K: Serialize<T> + Hash + Eq,
+    K::Archived: Hash + Eq,
+    V: Serialize<T>,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: Fallible + Writer + ArenaAllocator + ?Sized,
+    T::Error: Source,
+{
+    fn serialize(&self, serializer: &mut T) -> Result<Self::Resolver, T::Error> {
+        ArchivedHashMap::<K::Archived, V::Archived>::serialize_from_iter::<_, _, _, K, V, _>(
+            self.iter(),
+            LOAD_FACTOR,
+            serializer,
+        )
+    }
+}
+
+impl<K, V, S, A, D> Deserialize<OAHashMap<K, V, S, A>, D>
+    for ArchivedOAHashMap<K::Archived, V::Archived>
+where
+    K: Archive + Hash + Eq,
+    K::Archived: Deserialize<K, D> + Hash + Eq,
+    V: Archive,
+    V::Archived: Deserialize<V, D>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+    D: Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<OAHashMap<K, V, S, A>, D::Error> {
+        let mut map =
+            OAHashMap::with_capacity_and_hasher_in(self.len(), S::default(), A::default());
+        for (key, value) in self.iter() {
+            map.insert(
+                key.deserialize(deserializer)?,
+                value.deserialize(deserializer)?,
+            );
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rkyv::rancor::Error;
+    use rkyv::string::ArchivedString;
+    use rkyv::vec::ArchivedVec;
+    use rkyv::Archived;
+
+    use crate::test_util::assert_layout;
+    use crate::{ArchivedOAHashMap, OAHashMap};
+
+    type Table = OAHashMap<String, Vec<u32>>;
+
+    fn table() -> Table {
+        let mut oa: Table = (0..2000).map(|i| (i.to_string(), vec![i, i + 1])).collect();
+        oa.retain(|key, _| key.len() != 3);
+        assert!(oa.len() < 2000);
+
+        oa
+    }
+
+    fn native(value: Option<&ArchivedVec<Archived<u32>>>) -> Option<Vec<u32>> {
+        value.map(|v| v.iter().map(|n| n.to_native()).collect())
+    }
+
+    #[test]
+    fn archived_lookups_match() {
+        let oa = table();
+        let bytes = rkyv::to_bytes::<Error>(&oa).unwrap();
+        let archived: &ArchivedOAHashMap<ArchivedString, ArchivedVec<Archived<u32>>> =
+            rkyv::access::<_, Error>(&bytes).unwrap();
+
+        assert_eq!(oa.len(), archived.len());
+        for i in 0..2100u32 {
+            let key = i.to_string();
+            assert_eq!(oa.get(&key), native(archived.get(key.as_str())).as_ref());
+        }
+        for (key, value) in archived.iter() {
+            assert_eq!(oa.get(key.as_str()), native(Some(value)).as_ref());
+        }
+    }
+
+    #[test]
+    fn deserialize_round_trip() {
+        let oa = table();
+        let bytes = rkyv::to_bytes::<Error>(&oa).unwrap();
+        let back: Table = rkyv::from_bytes::<Table, Error>(&bytes).unwrap();
+
+        assert_eq!(oa, back);
+        assert_layout(&back);
+    }
+}
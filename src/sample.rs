@@ -0,0 +1,283 @@
+//! Picking live entries uniformly at random.
+//!
+//! The `_with` methods take the randomness as a closure returning uniformly
+//! random `u64`s, so any generator can drive them. With the `rand` feature,
+//! the methods without the suffix draw from a [`rand::Rng`] instead.
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+
+use allocator_api2::alloc::Allocator;
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+use crate::OAHashMap;
+
+/// Random slots tried before falling back to picking by rank. A map at
+/// least an eighth full finds an entry within this many tries all but once
+/// in 70 draws; sparser maps mostly need the fallback.
+const SLOT_TRIES: usize = 32;
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Picks one of the live entries, each as likely as the others, or
+    /// returns `None` if the map is empty.
+    ///
+    /// Tries random slots first, which finds an entry in a few tries unless
+    /// the map is sparse, and then counts along the occupancy bitmap to an
+    /// entry of a random rank instead.
+    pub fn random_entry_with<R>(&self, random: R) -> Option<(&K, &V)>
+    where
+        R: FnMut() -> u64,
+    {
+        let (in_old, index) = self.random_slot(random)?;
+        let table = match (in_old, &self.migration) {
+            (true, Some(migration)) => migration.table(),
+            _ => &self.table,
+        };
+        let (hashed, value) = table.get_pair(index).unwrap();
+
+        Some((&hashed.key, value))
+    }
+
+    /// Like [`random_entry_with`](Self::random_entry_with), with the value
+    /// mutable.
+    pub fn random_entry_mut_with<R>(&mut self, random: R) -> Option<(&K, &mut V)>
+    where
+        R: FnMut() -> u64,
+    {
+        let (in_old, index) = self.random_slot(random)?;
+        let table = match (in_old, &mut self.migration) {
+            (true, Some(migration)) => migration.table_mut(),
+            _ => &mut self.table,
+        };
+        let (hashed, value) = table.get_pair_mut(index).unwrap();
+
+        Some((&hashed.key, value))
+    }
+
+    /// Picks `n` distinct entries, every set of `n` as likely as any other,
+    /// or every entry if the map holds no more than `n`. They come in table
+    /// order rather than the order they were picked in. Takes a walk over
+    /// the map.
+    pub fn random_entries_with<R>(&self, n: usize, mut random: R) -> Vec<(&K, &V)>
+    where
+        R: FnMut() -> u64,
+    {
+        if n >= self.len {
+            return self.iter().collect();
+        }
+
+        // Floyd's algorithm: one draw per rank picked.
+        let mut ranks = BTreeSet::new();
+        for top in self.len - n..self.len {
+            let rank = below(&mut random, top as u64 + 1) as usize;
+            if !ranks.insert(rank) {
+                ranks.insert(top);
+            }
+        }
+
+        let mut ranks = ranks.into_iter().peekable();
+        self.iter()
+            .enumerate()
+            .filter(|&(rank, _)| ranks.next_if_eq(&rank).is_some())
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Like [`random_entry_with`](Self::random_entry_with), drawing from
+    /// `rng`.
+    #[cfg(feature = "rand")]
+    pub fn random_entry<R>(&self, rng: &mut R) -> Option<(&K, &V)>
+    where
+        R: Rng + ?Sized,
+    {
+        self.random_entry_with(|| rng.next_u64())
+    }
+
+    /// Like [`random_entry_mut_with`](Self::random_entry_mut_with), drawing
+    /// from `rng`.
+    #[cfg(feature = "rand")]
+    pub fn random_entry_mut<R>(&mut self, rng: &mut R) -> Option<(&K, &mut V)>
+    where
+        R: Rng + ?Sized,
+    {
+        self.random_entry_mut_with(|| rng.next_u64())
+    }
+
+    /// Like [`random_entries_with`](Self::random_entries_with), drawing from
+    /// `rng`.
+    #[cfg(feature = "rand")]
+    pub fn random_entries<R>(&self, n: usize, rng: &mut R) -> Vec<(&K, &V)>
+    where
+        R: Rng + ?Sized,
+    {
+        self.random_entries_with(n, || rng.next_u64())
+    }
+
+    /// The slot of a uniformly random live entry, and whether it is in the
+    /// table a migration is emptying.
+    fn random_slot<R>(&self, mut random: R) -> Option<(bool, usize)>
+    where
+        R: FnMut() -> u64,
+    {
+        if self.len == 0 {
+            return None;
+        }
+
+        let old = self.migration.as_ref().map(|m| m.table());
+        let old_len = self.migration.as_ref().map_or(0, |m| m.len());
+        let slots = self.table.num_slots();
+        let old_slots = old.map_or(0, |table| table.num_slots());
+        for _ in 0..SLOT_TRIES {
+            let slot = below(&mut random, (slots + old_slots) as u64) as usize;
+            let found = match slot.checked_sub(slots) {
+                None => self.table.is_full(slot).then_some((false, slot)),
+                Some(index) => old.unwrap().is_full(index).then_some((true, index)),
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+
+        let rank = below(&mut random, self.len as u64) as usize;
+        match rank.checked_sub(self.len - old_len) {
+            None => Some((false, self.table.full_slots().nth(rank).unwrap())),
+            Some(rank) => Some((true, old.unwrap().full_slots().nth(rank).unwrap())),
+        }
+    }
+}
+
+/// A uniformly random number below `n`, rejecting the draws past the last
+/// whole multiple of `n` that would otherwise favour the small ones.
+fn below<R>(random: &mut R, n: u64) -> u64
+where
+    R: FnMut() -> u64,
+{
+    let limit = u64::MAX - (u64::MAX - n + 1) % n;
+    loop {
+        let draw = random();
+        if draw <= limit {
+            return draw % n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::below;
+    use crate::test_util::Rng;
+    use crate::OAHashMap;
+
+    /// Pearson's statistic for how far `counts` stray from all being equal.
+    fn chi_squared(counts: &HashMap<i32, usize>, draws: usize) -> f64 {
+        let expected = draws as f64 / counts.len() as f64;
+
+        counts
+            .values()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    fn draw_counts(oa: &OAHashMap<i32, i32>, rng: &mut Rng, draws: usize) -> HashMap<i32, usize> {
+        let mut counts: HashMap<i32, usize> = oa.keys().map(|&key| (key, 0)).collect();
+        for _ in 0..draws {
+            let (key, value) = oa.random_entry_with(|| rng.next()).unwrap();
+            assert_eq!(key, value);
+            *counts.get_mut(key).unwrap() += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn entries_are_drawn_uniformly_from_dense_and_sparse_maps() {
+        let mut rng = Rng::new(110);
+        let mut dense = OAHashMap::with_capacity(1);
+        dense.extend((0..30).map(|i| (i, i)));
+
+        let mut sparse = OAHashMap::new();
+        sparse.extend((0..4_000).map(|i| (i, i)));
+        sparse.retain(|&key, _| key % 100 == 0);
+        assert!(sparse.memory_stats().slots > 100 * sparse.len());
+
+        // What chance exceeds once in a thousand runs, for 29 and 39 degrees
+        // of freedom.
+        for (oa, critical) in [(&dense, 58.3), (&sparse, 72.1)] {
+            let counts = draw_counts(oa, &mut rng, 60_000);
+            let statistic = chi_squared(&counts, 60_000);
+            assert!(statistic < critical, "chi-squared {statistic}");
+        }
+
+        let empty: OAHashMap<i32, i32> = OAHashMap::new();
+        assert!(empty.random_entry_with(|| rng.next()).is_none());
+        assert!(empty.random_entries_with(3, || rng.next()).is_empty());
+    }
+
+    #[test]
+    fn entries_still_in_the_old_table_are_drawn_too() {
+        let mut rng = Rng::new(1100);
+        let mut oa = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        oa.extend((0..38).map(|i| (i, i)));
+        oa.insert(38, 38);
+        assert!(oa.memory_stats().slots > oa.capacity());
+
+        let counts = draw_counts(&oa, &mut rng, 39_000);
+        assert!(chi_squared(&counts, 39_000) < 70.7);
+
+        let (&key, value) = oa.random_entry_mut_with(|| rng.next()).unwrap();
+        *value = -1;
+        assert_eq!(Some(&-1), oa.get(&key));
+    }
+
+    #[test]
+    fn entries_are_sampled_without_replacement() {
+        let mut rng = Rng::new(111);
+        let oa: OAHashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        let mut counts = [0; 10];
+        for _ in 0..10_000 {
+            let picked = oa.random_entries_with(3, || rng.next());
+            assert_eq!(3, picked.len());
+            let mut keys: Vec<_> = picked.iter().map(|(&key, _)| key).collect();
+            keys.dedup();
+            assert_eq!(3, keys.len());
+            keys.iter().for_each(|&key| counts[key as usize] += 1);
+        }
+        assert!(counts.iter().all(|&count| (2_700..3_300).contains(&count)));
+        assert_eq!(10, oa.random_entries_with(20, || rng.next()).len());
+
+        let mut draws = [u64::MAX, u64::MAX - 1, 5].into_iter();
+        assert_eq!(2, below(&mut || draws.next().unwrap(), 3));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn rand_generators_drive_the_same_draws() {
+        use rand::rngs::SmallRng;
+        use rand::{RngCore, SeedableRng};
+
+        let mut oa: OAHashMap<i32, i32> = (0..30).map(|i| (i, i)).collect();
+        let mut rng = SmallRng::seed_from_u64(110);
+        let mut same = rng.clone();
+        for _ in 0..100 {
+            assert_eq!(
+                oa.random_entry_with(|| same.next_u64()),
+                oa.random_entry(&mut rng)
+            );
+        }
+
+        let rng: &mut dyn rand::RngCore = &mut rng;
+        let picked = oa.random_entries(5, rng);
+        assert_eq!(5, picked.len());
+        let (&key, value) = oa.random_entry_mut(rng).unwrap();
+        *value = -1;
+        assert_eq!(Some(&-1), oa.get(&key));
+    }
+}
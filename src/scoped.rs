@@ -0,0 +1,395 @@
+//! A stack of maps searched from the innermost outwards, as for the lexical
+//! scopes of a program.
+//!
+//! Every scope hashes with a clone of one hasher, so a lookup hashes its key
+//! once and hands that hash to each scope it searches through the raw entry
+//! API.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::FusedIterator;
+
+use crate::{iter, DefaultHashBuilder, Equivalent, OAHashMap, RawEntryMut};
+
+/// Always holds at least the outermost scope, which
+/// [`pop_scope`](Self::pop_scope) never removes.
+pub struct ScopedOAHashMap<K, V, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    /// From the outermost scope to the innermost.
+    scopes: Vec<OAHashMap<K, V, S>>,
+    hash_builder: S,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<K, V> ScopedOAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> ScopedOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            scopes: vec![OAHashMap::with_hasher(hash_builder.clone())],
+            hash_builder,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn push_scope(&mut self) {
+        let scope = OAHashMap::with_hasher(self.hash_builder.clone());
+        self.scopes.push(scope);
+    }
+
+    /// Pushes `scope` as the new innermost scope, keeping its entries. Keys
+    /// are looked up in it under the hash this map's hasher gives, so it
+    /// must hash alike: one [`pop_scope`](Self::pop_scope) returned, or one
+    /// built with a clone of [`hasher`](Self::hasher).
+    pub fn push_scope_with(&mut self, scope: OAHashMap<K, V, S>) {
+        self.scopes.push(scope);
+    }
+
+    /// Hands back the innermost scope's map for reuse, or `None` if only the
+    /// outermost scope is left.
+    pub fn pop_scope(&mut self) -> Option<OAHashMap<K, V, S>> {
+        if self.scopes.len() == 1 {
+            return None;
+        }
+
+        self.scopes.pop()
+    }
+
+    /// How many scopes there are, counting the outermost.
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// The scopes from the outermost to the innermost.
+    pub fn scopes(&self) -> &[OAHashMap<K, V, S>] {
+        &self.scopes
+    }
+
+    /// Binds the key in the innermost scope, shadowing any outer binding,
+    /// and returns the value it replaced in that scope.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = self.hash_builder.hash_one(&key);
+        match self
+            .innermost_mut()
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, &key)
+        {
+            RawEntryMut::Occupied(mut entry) => Some(entry.insert(value)),
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, key, value);
+
+                None
+            }
+        }
+    }
+
+    /// The binding in the innermost scope that has one.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_with_depth(key).map(|(_, value)| value)
+    }
+
+    /// Like [`get`](Self::get), also returning the position of the scope the
+    /// binding is in, counting from zero for the outermost.
+    pub fn get_with_depth<Q>(&self, key: &Q) -> Option<(usize, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, scope)| {
+                let (_, value) = scope.raw_entry().from_key_hashed_nocheck(hash, key)?;
+
+                Some((depth, value))
+            })
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+
+        self.scopes.iter_mut().rev().find_map(|scope| {
+            match scope.raw_entry_mut().from_key_hashed_nocheck(hash, key) {
+                RawEntryMut::Occupied(entry) => Some(entry.into_mut()),
+                RawEntryMut::Vacant(_) => None,
+            }
+        })
+    }
+
+    /// Looks only in the innermost scope.
+    pub fn get_innermost<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let (_, value) = self
+            .innermost()
+            .raw_entry()
+            .from_key_hashed_nocheck(hash, key)?;
+
+        Some(value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Removes the key from the innermost scope only, uncovering any outer
+    /// binding it shadowed.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        match self
+            .innermost_mut()
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, key)
+        {
+            RawEntryMut::Occupied(entry) => Some(entry.remove()),
+            RawEntryMut::Vacant(_) => None,
+        }
+    }
+
+    /// Yields every key once, with its innermost binding, starting from the
+    /// innermost scope. Each outer entry is checked against the scopes
+    /// inside it, so this walks further the deeper the stack is.
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
+        let depth = self.scopes.len() - 1;
+
+        Iter {
+            map: self,
+            depth,
+            entries: self.scopes[depth].iter(),
+        }
+    }
+
+    /// Counts the keys [`iter`](Self::iter) yields, which takes a walk over
+    /// every scope.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.iter().all(OAHashMap::is_empty)
+    }
+
+    /// Drops every scope but an emptied outermost one.
+    pub fn clear(&mut self) {
+        self.scopes.truncate(1);
+        self.scopes[0].clear();
+    }
+
+    fn innermost(&self) -> &OAHashMap<K, V, S> {
+        self.scopes.last().unwrap()
+    }
+
+    fn innermost_mut(&mut self) -> &mut OAHashMap<K, V, S> {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn is_shadowed(&self, key: &K, depth: usize) -> bool {
+        let hash = self.hash_builder.hash_one(key);
+
+        self.scopes[depth + 1..].iter().any(|scope| {
+            scope
+                .raw_entry()
+                .from_key_hashed_nocheck(hash, key)
+                .is_some()
+        })
+    }
+}
+
+impl<K, V, S> Default for ScopedOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K, V, S> fmt::Debug for ScopedOAHashMap<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.scopes).finish()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a ScopedOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, K, V, S>
+where
+    K: Hash + Eq,
+{
+    map: &'a ScopedOAHashMap<K, V, S>,
+    /// The scope `entries` walks.
+    depth: usize,
+    entries: iter::Iter<'a, K, V>,
+}
+
+impl<'a, K, V, S> Iterator for Iter<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for (key, value) in &mut self.entries {
+                if !self.map.is_shadowed(key, self.depth) {
+                    return Some((key, value));
+                }
+            }
+            self.depth = self.depth.checked_sub(1)?;
+            self.entries = self.map.scopes[self.depth].iter();
+        }
+    }
+}
+
+impl<K, V, S> FusedIterator for Iter<'_, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+}
+
+impl<K, V, S> Clone for Iter<'_, K, V, S>
+where
+    K: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            depth: self.depth,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::ScopedOAHashMap;
+
+    #[test]
+    fn inner_bindings_shadow_outer_ones() {
+        let mut scopes = ScopedOAHashMap::new();
+        scopes.insert("x".to_string(), 1);
+        scopes.insert("y".to_string(), 2);
+
+        scopes.push_scope();
+        scopes.insert("x".to_string(), 10);
+        assert_eq!(Some(&10), scopes.get("x"));
+        assert_eq!(Some(&2), scopes.get("y"));
+        assert_eq!(Some((0, &2)), scopes.get_with_depth("y"));
+        assert_eq!(Some(&10), scopes.get_innermost("x"));
+        assert_eq!(None, scopes.get_innermost("y"));
+
+        scopes.push_scope();
+        assert_eq!(Some((1, &10)), scopes.get_with_depth("x"));
+        *scopes.get_mut("x").unwrap() += 1;
+        assert_eq!(None, scopes.insert("y".to_string(), 20));
+        assert_eq!(Some(20), scopes.insert("y".to_string(), 21));
+        assert_eq!(3, scopes.depth());
+
+        let popped = scopes.pop_scope().unwrap();
+        assert_eq!(Some(&21), popped.get("y"));
+        assert_eq!(Some(&2), scopes.get("y"));
+        assert_eq!(Some(&11), scopes.get("x"));
+
+        assert_eq!(Some(11), scopes.remove("x"));
+        assert_eq!(Some(&1), scopes.get("x"));
+        assert!(scopes.pop_scope().is_some());
+        assert!(scopes.pop_scope().is_none());
+        assert_eq!(1, scopes.depth());
+        assert!(!scopes.contains_key("z"));
+    }
+
+    #[test]
+    fn iteration_yields_each_innermost_binding_once() {
+        let mut scopes = ScopedOAHashMap::new();
+        let mut visible = HashMap::new();
+        for depth in 0..4 {
+            if depth > 0 {
+                scopes.push_scope();
+            }
+            for key in depth * 5..depth * 5 + 20 {
+                scopes.insert(key, depth);
+                visible.insert(key, depth);
+            }
+        }
+        scopes.push_scope();
+
+        let seen: HashMap<_, _> = scopes.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(visible, seen);
+        assert_eq!(visible.len(), scopes.len());
+        assert_eq!(visible.len(), scopes.iter().count());
+    }
+
+    #[test]
+    fn popped_scopes_are_pushed_back_with_their_entries() {
+        let mut scopes = ScopedOAHashMap::new();
+        scopes.insert(1, "outer");
+        scopes.push_scope();
+        scopes.insert(1, "inner");
+
+        let mut scope = scopes.pop_scope().unwrap();
+        assert_eq!(Some(&"outer"), scopes.get(&1));
+        scope.insert(2, "reused");
+        scopes.push_scope_with(scope);
+        assert_eq!(Some(&"inner"), scopes.get(&1));
+        assert_eq!(Some(&"reused"), scopes.get(&2));
+
+        scopes.clear();
+        assert!(scopes.is_empty());
+        assert_eq!(1, scopes.depth());
+    }
+}
@@ -0,0 +1,180 @@
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use allocator_api2::alloc::Allocator;
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::OAHashMap;
+
+/// Upper bound on the entries pre-allocated from an untrusted size hint.
+const MAX_PREALLOCATED: usize = 1 << 16;
+
+impl<K, V, S, A> Serialize for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Serialize,
+    V: Serialize,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error>
+    where
+        T: Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+impl<'de, K, V, S, A> Deserialize<'de> for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MapVisitor {
+            marker: PhantomData,
+        })
+    }
+
+    fn deserialize_in_place<D>(deserializer: D, place: &mut Self) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(InPlaceVisitor { map: place })
+    }
+}
+
+struct MapVisitor<K, V, S, A> {
+    marker: PhantomData<(K, V, S, A)>,
+}
+
+impl<'de, K, V, S, A> Visitor<'de> for MapVisitor<K, V, S, A>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    type Value = OAHashMap<K, V, S, A>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut map = OAHashMap::with_capacity_and_hasher_in(
+            cautious(access.size_hint()),
+            S::default(),
+            A::default(),
+        );
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+}
+
+struct InPlaceVisitor<'a, K, V, S, A>
+where
+    K: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: &'a mut OAHashMap<K, V, S, A>,
+}
+
+impl<'de, K, V, S, A> Visitor<'de> for InPlaceVisitor<'_, K, V, S, A>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<(), M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        self.map.clear();
+        self.map.reserve(cautious(access.size_hint()));
+        while let Some((key, value)) = access.next_entry()? {
+            self.map.insert(key, value);
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn cautious(hint: Option<usize>) -> usize {
+    hint.unwrap_or(0).min(MAX_PREALLOCATED)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::OAHashMap;
+
+    fn sample() -> OAHashMap<String, Vec<u32>> {
+        (0..500).map(|i| (i.to_string(), vec![i; 3])).collect()
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let oa = sample();
+        let json = serde_json::to_string(&oa).unwrap();
+        let back: OAHashMap<String, Vec<u32>> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(oa, back);
+        assert_eq!(
+            "{\"1\":[1,1,1]}",
+            serde_json::to_string(&OAHashMap::from([("1", [1, 1, 1])])).unwrap()
+        );
+    }
+
+    #[test]
+    fn postcard_round_trip_is_presized() {
+        let oa = sample();
+        let bytes = postcard::to_allocvec(&oa).unwrap();
+        let back: OAHashMap<String, Vec<u32>> = postcard::from_bytes(&bytes).unwrap();
+
+        assert_eq!(oa, back);
+        assert_eq!(0, back.resizes);
+    }
+
+    #[test]
+    fn duplicate_keys_keep_last_value() {
+        let oa: OAHashMap<String, i32> = serde_json::from_str(r#"{"a":1,"b":2,"a":3}"#).unwrap();
+
+        assert_eq!(2, oa.len());
+        assert_eq!(Some(&3), oa.get("a"));
+    }
+
+    #[test]
+    fn deserialize_in_place_replaces_contents() {
+        let mut oa: OAHashMap<String, i32> = (0..100).map(|i| (i.to_string(), i)).collect();
+        let buffer = oa.table.as_ptr();
+        let mut deserializer = serde_json::Deserializer::from_str(r#"{"x":1,"y":2}"#);
+        OAHashMap::deserialize_in_place(&mut deserializer, &mut oa).unwrap();
+
+        assert_eq!(
+            OAHashMap::from([("x".to_string(), 1), ("y".to_string(), 2)]),
+            oa
+        );
+        assert_eq!(buffer, oa.table.as_ptr());
+    }
+}
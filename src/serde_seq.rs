@@ -0,0 +1,129 @@
+//! Serializes a map as a sequence of `(key, value)` pairs.
+//!
+//! Formats like JSON only accept string keys in maps, so maps keyed by tuples
+//! or structs need this representation instead:
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use oahm::OAHashMap;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Grid {
+//!     #[serde(with = "oahm::serde_seq")]
+//!     cells: OAHashMap<(u32, u32), char>,
+//! }
+//! ```
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+
+use allocator_api2::alloc::Allocator;
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use crate::serde::cautious;
+use crate::OAHashMap;
+
+pub fn serialize<K, V, S, A, T>(
+    map: &OAHashMap<K, V, S, A>,
+    serializer: T,
+) -> Result<T::Ok, T::Error>
+where
+    K: Hash + Eq + Serialize,
+    V: Serialize,
+    S: BuildHasher,
+    A: Allocator + Clone,
+    T: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+/// Later pairs overwrite earlier ones with the same key.
+pub fn deserialize<'de, D, K, V, S, A>(deserializer: D) -> Result<OAHashMap<K, V, S, A>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    deserializer.deserialize_seq(SeqVisitor {
+        marker: PhantomData,
+    })
+}
+
+struct SeqVisitor<K, V, S, A> {
+    marker: PhantomData<(K, V, S, A)>,
+}
+
+impl<'de, K, V, S, A> Visitor<'de> for SeqVisitor<K, V, S, A>
+where
+    K: Hash + Eq + Deserialize<'de>,
+    V: Deserialize<'de>,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    type Value = OAHashMap<K, V, S, A>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of key-value pairs")
+    }
+
+    fn visit_seq<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: SeqAccess<'de>,
+    {
+        let mut map = OAHashMap::with_capacity_and_hasher_in(
+            cautious(access.size_hint()),
+            S::default(),
+            A::default(),
+        );
+        while let Some((key, value)) = access.next_element()? {
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::OAHashMap;
+
+    #[derive(Serialize, Deserialize)]
+    struct Grid {
+        #[serde(with = "crate::serde_seq")]
+        cells: OAHashMap<(u32, u32), char>,
+    }
+
+    #[test]
+    fn tuple_keys_round_trip_through_json() {
+        let cells: OAHashMap<(u32, u32), char> = (0..200)
+            .map(|i| ((i, i * 2), if i % 2 == 0 { 'x' } else { 'o' }))
+            .collect();
+        assert!(serde_json::to_string(&cells).is_err());
+
+        let grid = Grid { cells };
+        let json = serde_json::to_string(&grid).unwrap();
+        let back: Grid = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(grid.cells, back.cells);
+
+        let bytes = postcard::to_allocvec(&grid).unwrap();
+        let back: Grid = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(grid.cells, back.cells);
+        assert_eq!(0, back.cells.resizes);
+    }
+
+    #[test]
+    fn duplicate_pairs_keep_last_value() {
+        let grid: Grid =
+            serde_json::from_str(r#"{"cells":[[[1,2],"a"],[[3,4],"b"],[[1,2],"c"]]}"#).unwrap();
+
+        assert_eq!(2, grid.cells.len());
+        assert_eq!(Some(&'c'), grid.cells.get(&(1, 2)));
+    }
+}
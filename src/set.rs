@@ -0,0 +1,1085 @@
+//! A hash set storing its elements as the keys of an [`OAHashMap`] with `()`
+//! values. Values take no room in the slots, so each slot costs the same as
+//! one in a map of the element type to a zero-sized value.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::iter::{Chain, FusedIterator};
+use core::ops::{BitAnd, BitOr, BitXor, Sub};
+
+use allocator_api2::alloc::{Allocator, Global};
+
+use crate::{iter, DefaultHashBuilder, Equivalent, OAHashMap, RawEntryMut, TryReserveError};
+
+pub struct OAHashSet<T, S = DefaultHashBuilder, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    map: OAHashMap<T, (), S, A>,
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<T> OAHashSet<T>
+where
+    T: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self {
+            map: OAHashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: OAHashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, TryReserveError> {
+        Ok(Self {
+            map: OAHashMap::try_with_capacity(capacity)?,
+        })
+    }
+}
+
+impl<T, S> OAHashSet<T, S>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+{
+    pub const fn with_hasher(hash_builder: S) -> Self {
+        Self {
+            map: OAHashMap::with_hasher(hash_builder),
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        Self {
+            map: OAHashMap::with_capacity_and_hasher(capacity, hash_builder),
+        }
+    }
+}
+
+impl<T, S, A> OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    pub const fn with_hasher_in(hash_builder: S, alloc: A) -> Self {
+        Self {
+            map: OAHashMap::with_hasher_in(hash_builder, alloc),
+        }
+    }
+
+    pub fn with_capacity_and_hasher_in(capacity: usize, hash_builder: S, alloc: A) -> Self {
+        Self {
+            map: OAHashMap::with_capacity_and_hasher_in(capacity, hash_builder, alloc),
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        self.map.hasher()
+    }
+
+    pub fn allocator(&self) -> &A {
+        self.map.allocator()
+    }
+
+    /// Returns whether the value was new. An equal value already in the set
+    /// stays, and the one passed in is dropped.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+
+    /// Inserts the value, handing back the equal one it displaced.
+    pub fn replace(&mut self, value: T) -> Option<T> {
+        let hash = self.map.make_hash(&value);
+        match self
+            .map
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, &value)
+        {
+            RawEntryMut::Occupied(mut entry) => Some(entry.insert_key(value)),
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, value, ());
+
+                None
+            }
+        }
+    }
+
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+
+    pub fn get<Q>(&self, value: &Q) -> Option<&T>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.map.get_key_value(value).map(|(value, _)| value)
+    }
+
+    /// Returns whether the value was present.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+
+    pub fn take<Q>(&mut self, value: &Q) -> Option<T>
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+    {
+        self.map.remove_entry(value).map(|(value, _)| value)
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.keys(),
+        }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.map.retain(|value, _| f(value));
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.map.allocated_bytes()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.map.reserve(additional);
+    }
+
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.map.try_reserve(additional)
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.map.shrink_to_fit();
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Hands back the stored value equal to `value`, inserting `value` first
+    /// if there is none.
+    pub fn get_or_insert(&mut self, value: T) -> &T {
+        let hash = self.map.make_hash(&value);
+        match self
+            .map
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, &value)
+        {
+            RawEntryMut::Occupied(entry) => entry.into_key(),
+            RawEntryMut::Vacant(entry) => entry.insert_hashed_nocheck(hash, value, ()).0,
+        }
+    }
+
+    /// Hands back the stored value equivalent to `value`, building one with
+    /// `f` and inserting it only if there is none, so a hit never clones.
+    ///
+    /// Panics if the built value is not equivalent to `value`.
+    pub fn get_or_insert_with<Q, F>(&mut self, value: &Q, f: F) -> &T
+    where
+        Q: Hash + Equivalent<T> + ?Sized,
+        F: FnOnce(&Q) -> T,
+    {
+        let hash = self.map.make_hash(value);
+        match self
+            .map
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, value)
+        {
+            RawEntryMut::Occupied(entry) => entry.into_key(),
+            RawEntryMut::Vacant(entry) => {
+                let built = f(value);
+                assert!(
+                    value.equivalent(&built),
+                    "Built value is not equivalent to the one looked up"
+                );
+
+                entry.insert_hashed_nocheck(hash, built, ()).0
+            }
+        }
+    }
+
+    /// The values in `self` but not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, S, A> {
+        Difference {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// The values in exactly one of the sets.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a Self) -> SymmetricDifference<'a, T, S, A> {
+        SymmetricDifference {
+            iter: self.difference(other).chain(other.difference(self)),
+        }
+    }
+
+    /// Walks the smaller set and looks each value up in the larger.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, S, A> {
+        let (smaller, larger) = by_len(self, other);
+
+        Intersection {
+            iter: smaller.iter(),
+            other: larger,
+        }
+    }
+
+    /// Yields all of the larger set, then whatever of the smaller it lacks.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, S, A> {
+        let (smaller, larger) = by_len(self, other);
+
+        Union {
+            iter: larger.iter().chain(smaller.difference(larger)),
+        }
+    }
+
+    pub fn is_subset<S2, A2>(&self, other: &OAHashSet<T, S2, A2>) -> bool
+    where
+        S2: BuildHasher,
+        A2: Allocator + Clone,
+    {
+        self.len() <= other.len() && self.iter().all(|value| other.contains(value))
+    }
+
+    pub fn is_superset<S2, A2>(&self, other: &OAHashSet<T, S2, A2>) -> bool
+    where
+        S2: BuildHasher,
+        A2: Allocator + Clone,
+    {
+        other.is_subset(self)
+    }
+
+    pub fn is_disjoint<S2, A2>(&self, other: &OAHashSet<T, S2, A2>) -> bool
+    where
+        S2: BuildHasher,
+        A2: Allocator + Clone,
+    {
+        if self.len() <= other.len() {
+            self.iter().all(|value| !other.contains(value))
+        } else {
+            other.iter().all(|value| !self.contains(value))
+        }
+    }
+}
+
+fn by_len<'a, T, S, A>(
+    a: &'a OAHashSet<T, S, A>,
+    b: &'a OAHashSet<T, S, A>,
+) -> (&'a OAHashSet<T, S, A>, &'a OAHashSet<T, S, A>)
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    if a.len() <= b.len() {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl<T, S, A> Default for OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self {
+            map: OAHashMap::default(),
+        }
+    }
+}
+
+impl<T, S, A> FromIterator<T> for OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher + Default,
+    A: Allocator + Clone + Default,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self {
+            map: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+impl<T, S, A> Extend<T> for OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.map.extend(iter.into_iter().map(|value| (value, ())));
+    }
+}
+
+impl<'a, T, S, A> Extend<&'a T> for OAHashSet<T, S, A>
+where
+    T: Hash + Eq + Copy,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = &'a T>,
+    {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(any(feature = "std", feature = "ahash", feature = "fxhash"))]
+impl<T, const N: usize> From<[T; N]> for OAHashSet<T>
+where
+    T: Hash + Eq,
+{
+    fn from(values: [T; N]) -> Self {
+        values.into_iter().collect()
+    }
+}
+
+impl<T, S, A> Clone for OAHashSet<T, S, A>
+where
+    T: Hash + Eq + Clone,
+    S: BuildHasher + Clone,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.map.clone_from(&source.map);
+    }
+}
+
+/// Sets are equal when they hold the same values, however those values are
+/// laid out in their tables.
+impl<T, S1, S2, A1, A2> PartialEq<OAHashSet<T, S2, A2>> for OAHashSet<T, S1, A1>
+where
+    T: Hash + Eq,
+    S1: BuildHasher,
+    S2: BuildHasher,
+    A1: Allocator + Clone,
+    A2: Allocator + Clone,
+{
+    fn eq(&self, other: &OAHashSet<T, S2, A2>) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<T, S, A> Eq for OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, S, A> fmt::Debug for OAHashSet<T, S, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+macro_rules! set_operator {
+    ($trait:ident, $method:ident, $op:ident) => {
+        impl<T, S, A> $trait<&OAHashSet<T, S, A>> for &OAHashSet<T, S, A>
+        where
+            T: Hash + Eq + Clone,
+            S: BuildHasher + Default,
+            A: Allocator + Clone + Default,
+        {
+            type Output = OAHashSet<T, S, A>;
+
+            fn $method(self, other: &OAHashSet<T, S, A>) -> Self::Output {
+                self.$op(other).cloned().collect()
+            }
+        }
+    };
+}
+
+set_operator!(BitOr, bitor, union);
+set_operator!(BitAnd, bitand, intersection);
+set_operator!(BitXor, bitxor, symmetric_difference);
+set_operator!(Sub, sub, difference);
+
+impl<T, S, A> IntoIterator for OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            inner: self.map.into_keys(),
+        }
+    }
+}
+
+impl<'a, T, S, A> IntoIterator for &'a OAHashSet<T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct Iter<'a, T>
+where
+    T: Hash + Eq,
+{
+    inner: iter::Keys<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T>
+where
+    T: Hash + Eq,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> where T: Hash + Eq {}
+
+impl<T> FusedIterator for Iter<'_, T> where T: Hash + Eq {}
+
+impl<T> Clone for Iter<'_, T>
+where
+    T: Hash + Eq,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Default for Iter<'_, T>
+where
+    T: Hash + Eq,
+{
+    fn default() -> Self {
+        Self {
+            inner: iter::Keys::default(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Iter<'_, T>
+where
+    T: Hash + Eq + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct IntoIter<T, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    inner: iter::IntoKeys<T, (), A>,
+}
+
+impl<T, A> Iterator for IntoIter<T, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T, A> ExactSizeIterator for IntoIter<T, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, A> FusedIterator for IntoIter<T, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, A> Default for IntoIter<T, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone + Default,
+{
+    fn default() -> Self {
+        Self {
+            inner: iter::IntoKeys::default(),
+        }
+    }
+}
+
+impl<T, A> fmt::Debug for IntoIter<T, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+pub struct Intersection<'a, T, S, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    iter: Iter<'a, T>,
+    other: &'a OAHashSet<T, S, A>,
+}
+
+impl<'a, T, S, A> Iterator for Intersection<'a, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(|value| self.other.contains(*value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<T, S, A> FusedIterator for Intersection<'_, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, S, A> Clone for Intersection<'_, T, S, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            other: self.other,
+        }
+    }
+}
+
+impl<T, S, A> fmt::Debug for Intersection<'_, T, S, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct Difference<'a, T, S, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    iter: Iter<'a, T>,
+    other: &'a OAHashSet<T, S, A>,
+}
+
+impl<'a, T, S, A> Iterator for Difference<'a, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(|value| !self.other.contains(*value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<T, S, A> FusedIterator for Difference<'_, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, S, A> Clone for Difference<'_, T, S, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            other: self.other,
+        }
+    }
+}
+
+impl<T, S, A> fmt::Debug for Difference<'_, T, S, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct SymmetricDifference<'a, T, S, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    iter: Chain<Difference<'a, T, S, A>, Difference<'a, T, S, A>>,
+}
+
+impl<'a, T, S, A> Iterator for SymmetricDifference<'a, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, S, A> FusedIterator for SymmetricDifference<'_, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, S, A> Clone for SymmetricDifference<'_, T, S, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<T, S, A> fmt::Debug for SymmetricDifference<'_, T, S, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+pub struct Union<'a, T, S, A = Global>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    iter: Chain<Iter<'a, T>, Difference<'a, T, S, A>>,
+}
+
+impl<'a, T, S, A> Iterator for Union<'a, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, S, A> FusedIterator for Union<'_, T, S, A>
+where
+    T: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+}
+
+impl<T, S, A> Clone for Union<'_, T, S, A>
+where
+    T: Hash + Eq,
+    A: Allocator + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+        }
+    }
+}
+
+impl<T, S, A> fmt::Debug for Union<'_, T, S, A>
+where
+    T: Hash + Eq + fmt::Debug,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T, S, A> zeroize::Zeroize for OAHashSet<T, S, A>
+where
+    T: Hash + Eq + zeroize::Zeroize,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    fn zeroize(&mut self) {
+        self.map.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    use super::OAHashSet;
+    use crate::test_util::{Counted, Rng, Tagged};
+    use crate::HashedKey;
+
+    #[test]
+    fn insert_reports_new_values() {
+        let mut set = OAHashSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert(3));
+        assert!(set.insert(5));
+        assert!(!set.insert(3));
+
+        assert_eq!(2, set.len());
+        assert!(set.contains(&3));
+        assert!(!set.contains(&4));
+    }
+
+    #[test]
+    fn duplicate_insert_keeps_the_stored_value() {
+        let mut set = OAHashSet::new();
+        assert!(set.insert(Tagged::new(1, "first")));
+        assert!(!set.insert(Tagged::new(1, "second")));
+        assert_eq!("first", set.get(&Tagged::new(1, "probe")).unwrap().tag);
+
+        let old = set.replace(Tagged::new(1, "third")).unwrap();
+        assert_eq!("first", old.tag);
+        assert_eq!("third", set.get(&Tagged::new(1, "probe")).unwrap().tag);
+        assert!(set.replace(Tagged::new(2, "new")).is_none());
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    fn remove_and_take() {
+        let mut set: OAHashSet<String> = ["a", "b", "c"].map(String::from).into();
+        assert!(set.remove("a"));
+        assert!(!set.remove("a"));
+        assert_eq!(Some("b".to_string()), set.take("b"));
+        assert_eq!(None, set.take("b"));
+
+        assert_eq!(1, set.len());
+        assert!(set.contains("c"));
+    }
+
+    #[test]
+    fn matches_std_under_random_churn() {
+        let mut rng = Rng::new(78);
+        let mut set = OAHashSet::new();
+        let mut expected = HashSet::new();
+        for _ in 0..5000 {
+            let value = rng.below(300);
+            match rng.below(3) {
+                0 => assert_eq!(expected.remove(&value), set.remove(&value)),
+                _ => assert_eq!(expected.insert(value), set.insert(value)),
+            }
+            assert_eq!(expected.len(), set.len());
+        }
+
+        let mut values: Vec<u64> = set.iter().copied().collect();
+        values.sort_unstable();
+        let mut std_values: Vec<u64> = expected.into_iter().collect();
+        std_values.sort_unstable();
+        assert_eq!(std_values, values);
+    }
+
+    #[test]
+    fn iterators_yield_each_value_once() {
+        let set: OAHashSet<i32> = (0..100).chain(0..100).collect();
+        assert_eq!(100, set.len());
+        assert_eq!(100, set.iter().len());
+        assert_eq!(4950, (&set).into_iter().sum::<i32>());
+
+        let mut owned: Vec<i32> = set.into_iter().collect();
+        owned.sort_unstable();
+        assert_eq!((0..100).collect::<Vec<_>>(), owned);
+    }
+
+    #[test]
+    fn extend_from_values_and_references() {
+        let mut set = OAHashSet::new();
+        set.extend([1, 2, 3]);
+        set.extend(&[3, 4]);
+        set.extend(std::iter::empty::<i32>());
+
+        assert_eq!(OAHashSet::from([1, 2, 3, 4]), set);
+    }
+
+    #[test]
+    fn equality_ignores_layout() {
+        let forward: OAHashSet<i32> = (0..500).collect();
+        let mut backward: OAHashSet<i32> = OAHashSet::with_capacity(2000);
+        backward.extend((0..500).rev());
+
+        assert_eq!(forward, backward);
+        backward.remove(&7);
+        assert_ne!(forward, backward);
+        assert!(backward.is_subset(&forward));
+        assert!(!forward.is_subset(&backward));
+    }
+
+    #[test]
+    fn clones_are_independent() {
+        let mut set: OAHashSet<i32> = (0..50).collect();
+        let clone = set.clone();
+        set.remove(&0);
+        set.insert(50);
+
+        assert!(clone.contains(&0));
+        assert!(!clone.contains(&50));
+        assert_eq!(50, clone.len());
+
+        let mut target = OAHashSet::new();
+        target.clone_from(&set);
+        assert_eq!(set, target);
+    }
+
+    #[test]
+    fn debug_lists_the_values() {
+        let set = OAHashSet::from([7]);
+
+        assert_eq!("{7}", format!("{set:?}"));
+        assert_eq!("[7]", format!("{:?}", set.iter()));
+        assert_eq!("[7]", format!("{:?}", set.into_iter()));
+    }
+
+    #[test]
+    fn values_are_dropped_once() {
+        let marker = Rc::new(());
+        let mut set = OAHashSet::new();
+        for id in 0..200 {
+            set.insert((id, Rc::clone(&marker)));
+        }
+        // The duplicate passed in is the one that gets dropped.
+        set.insert((0, Rc::clone(&marker)));
+        assert_eq!(201, Rc::strong_count(&marker));
+
+        set.retain(|(id, _)| id % 2 == 0);
+        assert_eq!(101, Rc::strong_count(&marker));
+        drop(set.take(&(0, Rc::clone(&marker))));
+        assert_eq!(100, Rc::strong_count(&marker));
+
+        let mut iter = set.into_iter();
+        drop(iter.next());
+        drop(iter);
+        assert_eq!(1, Rc::strong_count(&marker));
+    }
+
+    #[test]
+    fn unit_values_take_no_room() {
+        let set: OAHashSet<u64> = (0..1000).collect();
+        let stats = set.map.memory_stats();
+
+        assert_eq!(size_of::<HashedKey<u64>>() + 1, stats.slot_bytes);
+        assert_eq!(stats.allocated_bytes(), set.allocated_bytes());
+    }
+
+    fn random_pair(rng: &mut Rng) -> (OAHashSet<u64>, HashSet<u64>, OAHashSet<u64>, HashSet<u64>) {
+        let a: Vec<u64> = (0..rng.below(200)).map(|_| rng.below(300)).collect();
+        let b: Vec<u64> = (0..rng.below(200)).map(|_| rng.below(300)).collect();
+
+        (
+            a.iter().copied().collect(),
+            a.into_iter().collect(),
+            b.iter().copied().collect(),
+            b.into_iter().collect(),
+        )
+    }
+
+    fn sorted<'a>(values: impl Iterator<Item = &'a u64>) -> Vec<u64> {
+        let mut values: Vec<u64> = values.copied().collect();
+        values.sort_unstable();
+
+        values
+    }
+
+    #[test]
+    fn set_operations_match_std() {
+        let mut rng = Rng::new(79);
+        for _ in 0..50 {
+            let (a, std_a, b, std_b) = random_pair(&mut rng);
+
+            assert_eq!(sorted(std_a.union(&std_b)), sorted(a.union(&b)));
+            assert_eq!(
+                sorted(std_a.intersection(&std_b)),
+                sorted(a.intersection(&b))
+            );
+            assert_eq!(sorted(std_a.difference(&std_b)), sorted(a.difference(&b)));
+            assert_eq!(
+                sorted(std_a.symmetric_difference(&std_b)),
+                sorted(a.symmetric_difference(&b))
+            );
+
+            assert_eq!(std_a.is_subset(&std_b), a.is_subset(&b));
+            assert_eq!(std_a.is_superset(&std_b), a.is_superset(&b));
+            assert_eq!(std_a.is_disjoint(&std_b), a.is_disjoint(&b));
+        }
+    }
+
+    #[test]
+    fn operators_build_owned_sets() {
+        let a = OAHashSet::from([1, 2, 3]);
+        let b = OAHashSet::from([3, 4]);
+
+        assert_eq!(OAHashSet::from([1, 2, 3, 4]), &a | &b);
+        assert_eq!(OAHashSet::from([3]), &a & &b);
+        assert_eq!(OAHashSet::from([1, 2, 4]), &a ^ &b);
+        assert_eq!(OAHashSet::from([1, 2]), &a - &b);
+        assert!((&a & &OAHashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn lazy_operations_walk_the_smaller_set() {
+        let small = OAHashSet::from([1, 2]);
+        let large: OAHashSet<i32> = (0..1000).collect();
+
+        assert_eq!(Some(2), small.intersection(&large).size_hint().1);
+        assert_eq!(Some(2), large.intersection(&small).size_hint().1);
+        assert_eq!(1000, large.union(&small).count());
+        assert!(small.is_disjoint(&OAHashSet::from([5])));
+        assert!(large.is_superset(&small));
+    }
+
+    #[test]
+    fn get_or_insert_interns_without_cloning() {
+        let mut set = OAHashSet::new();
+        let first: *const Counted = set.get_or_insert(Counted::from("a"));
+        let constructed = Counted::constructed();
+
+        let again: *const Counted = set.get_or_insert_with("a", |value| Counted::from(value));
+        assert_eq!(first, again);
+        assert_eq!(constructed, Counted::constructed());
+
+        let other = set.get_or_insert_with("b", |value| Counted::from(value));
+        assert_eq!(&Counted::from("b"), other);
+        assert_eq!(constructed + 2, Counted::constructed());
+        let duplicate: *const Counted = set.get_or_insert(Counted::from("a"));
+        assert_eq!(first, duplicate);
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Built value is not equivalent to the one looked up")]
+    fn get_or_insert_with_checks_the_built_value() {
+        let mut set: OAHashSet<String> = OAHashSet::new();
+        set.get_or_insert_with("a", |_| "b".to_string());
+    }
+}
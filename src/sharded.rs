@@ -0,0 +1,292 @@
+//! A map split across independently locked shards, for sharing between
+//! threads.
+//!
+//! Each key's hash picks its shard and is then handed to that shard's table
+//! through the raw entry API, so every operation hashes its key once. The
+//! shard comes from bits 32 and up of the hash: the control bytes use the top
+//! seven, and the starting slot mixes in every bit, so keys in one shard still
+//! spread across its table.
+
+use std::boxed::Box;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+
+use crate::{DefaultHashBuilder, Equivalent, OAHashMap, RawEntryMut};
+
+/// How many shards to make per thread the machine can run at once.
+const SHARDS_PER_THREAD: usize = 4;
+
+pub struct ShardedOAHashMap<K, V, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    shards: Box<[RwLock<OAHashMap<K, V, S>>]>,
+    hash_builder: S,
+}
+
+impl<K, V> ShardedOAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    /// Makes four shards per available thread, rounded up to a power of two.
+    pub fn new() -> Self {
+        Self::with_hasher(DefaultHashBuilder::new())
+    }
+
+    /// Rounds `shards` up to a power of two, and makes at least one.
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, S> ShardedOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone,
+{
+    pub fn with_hasher(hash_builder: S) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+
+        Self::with_shards_and_hasher(threads * SHARDS_PER_THREAD, hash_builder)
+    }
+
+    /// Every shard hashes with a clone of `hash_builder`, so the clones must
+    /// hash alike.
+    pub fn with_shards_and_hasher(shards: usize, hash_builder: S) -> Self {
+        let count = shards.max(1).next_power_of_two();
+
+        Self {
+            shards: (0..count)
+                .map(|_| RwLock::new(OAHashMap::with_hasher(hash_builder.clone())))
+                .collect(),
+            hash_builder,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let hash = self.hash_builder.hash_one(&key);
+        let mut shard = self.write(hash);
+        match shard.raw_entry_mut().from_key_hashed_nocheck(hash, &key) {
+            RawEntryMut::Occupied(mut entry) => Some(entry.insert(value)),
+            RawEntryMut::Vacant(entry) => {
+                entry.insert_hashed_nocheck(hash, key, value);
+
+                None
+            }
+        }
+    }
+
+    /// Calls `f` on the value while its shard is read-locked.
+    pub fn get<Q, F, R>(&self, key: &Q, f: F) -> Option<R>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let shard = self.read(hash);
+        let (_, value) = shard.raw_entry().from_key_hashed_nocheck(hash, key)?;
+
+        Some(f(value))
+    }
+
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+        V: Clone,
+    {
+        self.get(key, V::clone)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key, |_| ()).is_some()
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        match self
+            .write(hash)
+            .raw_entry_mut()
+            .from_key_hashed_nocheck(hash, key)
+        {
+            RawEntryMut::Occupied(entry) => Some(entry.remove()),
+            RawEntryMut::Vacant(_) => None,
+        }
+    }
+
+    /// Locks the shards one at a time, so concurrent writes may make the sum
+    /// match no single moment.
+    pub fn len(&self) -> usize {
+        self.iter_shards().map(|shard| shard.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter_shards().all(|shard| shard.is_empty())
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            lock_write(shard).clear();
+        }
+    }
+
+    /// Read-locks each shard in turn as the iterator reaches it.
+    pub fn iter_shards(&self) -> impl Iterator<Item = RwLockReadGuard<'_, OAHashMap<K, V, S>>> {
+        self.shards.iter().map(lock_read)
+    }
+
+    fn shard(&self, hash: u64) -> &RwLock<OAHashMap<K, V, S>> {
+        &self.shards[(hash >> 32) as usize & (self.shards.len() - 1)]
+    }
+
+    fn read(&self, hash: u64) -> RwLockReadGuard<'_, OAHashMap<K, V, S>> {
+        lock_read(self.shard(hash))
+    }
+
+    fn write(&self, hash: u64) -> RwLockWriteGuard<'_, OAHashMap<K, V, S>> {
+        lock_write(self.shard(hash))
+    }
+}
+
+impl<K, V, S> Default for ShardedOAHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher + Clone + Default,
+{
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+/// A panic while a shard was locked leaves its table consistent, since the
+/// map is sound across unwinding, so the lock is taken over regardless.
+fn lock_read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn lock_write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::thread;
+
+    use super::ShardedOAHashMap;
+    use crate::test_util::Rng;
+
+    #[test]
+    fn shard_counts_round_up_to_a_power_of_two() {
+        assert_eq!(
+            1,
+            ShardedOAHashMap::<u32, u32>::with_shards(0).shard_count()
+        );
+        assert_eq!(
+            8,
+            ShardedOAHashMap::<u32, u32>::with_shards(5).shard_count()
+        );
+
+        let default = ShardedOAHashMap::<u32, u32>::new().shard_count();
+        assert!(default.is_power_of_two());
+        assert!(default >= 4);
+    }
+
+    #[test]
+    fn single_threaded_operations() {
+        let map = ShardedOAHashMap::with_shards(4);
+        for key in 0..1000 {
+            assert_eq!(None, map.insert(key, key * 2));
+        }
+        assert_eq!(Some(20), map.insert(10, 21));
+
+        assert_eq!(1000, map.len());
+        assert_eq!(Some(21), map.get_cloned(&10));
+        assert_eq!(Some(true), map.get(&11, |value| *value == 22));
+        assert_eq!(Some(21), map.remove(&10));
+        assert!(!map.contains_key(&10));
+        assert!(map.iter_shards().all(|shard| !shard.is_empty()));
+        assert_eq!(
+            999,
+            map.iter_shards()
+                .map(|shard| shard.iter().count())
+                .sum::<usize>()
+        );
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn shards_find_keys_by_their_own_hasher() {
+        let map = ShardedOAHashMap::with_shards(8);
+        map.insert("key".to_string(), 1);
+
+        let shard = map.iter_shards().find(|shard| !shard.is_empty()).unwrap();
+        assert_eq!(Some(&1), shard.get("key"));
+    }
+
+    #[test]
+    fn concurrent_writers_and_readers_match_a_locked_reference() {
+        const WRITERS: u64 = 6;
+        const READERS: u64 = 4;
+        const KEYS: u64 = 600;
+
+        let map = ShardedOAHashMap::with_shards(16);
+        let reference = Mutex::new(HashMap::new());
+        thread::scope(|scope| {
+            for writer in 0..WRITERS {
+                let (map, reference) = (&map, &reference);
+                scope.spawn(move || {
+                    let mut rng = Rng::new(82 + writer);
+                    for step in 0..4000 {
+                        // Each writer owns the keys congruent to its number,
+                        // so their histories never interleave.
+                        let key = rng.below(KEYS / WRITERS) * WRITERS + writer;
+                        if rng.below(3) == 0 {
+                            let removed = map.remove(&key);
+                            assert_eq!(reference.lock().unwrap().remove(&key), removed);
+                        } else {
+                            let value = key * 1_000_000 + step;
+                            let old = map.insert(key, value);
+                            assert_eq!(reference.lock().unwrap().insert(key, value), old);
+                        }
+                    }
+                });
+            }
+            for reader in 0..READERS {
+                let map = &map;
+                scope.spawn(move || {
+                    let mut rng = Rng::new(820 + reader);
+                    for _ in 0..8000 {
+                        let key = rng.below(KEYS);
+                        if let Some(value) = map.get_cloned(&key) {
+                            assert_eq!(key, value / 1_000_000);
+                        }
+                    }
+                });
+            }
+        });
+
+        let reference = reference.into_inner().unwrap();
+        assert_eq!(reference.len(), map.len());
+        for (key, value) in &reference {
+            assert_eq!(Some(*value), map.get_cloned(key));
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Written against `std::collections::HashMap`; only the `use` line differs.
+
+use crate::OAHashMap as HashMap;
+
+fn word_counts(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        *counts.entry(word.to_string()).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+#[test]
+fn insert_get_remove() {
+    let mut map: HashMap<String, i32> = HashMap::new();
+    assert!(map.is_empty());
+    assert_eq!(None, map.insert("a".to_string(), 1));
+    assert_eq!(Some(1), map.insert("a".to_string(), 2));
+    assert_eq!(Some(&2), map.get("a"));
+    assert!(map.contains_key("a"));
+    assert_eq!(1, map.len());
+
+    if let Some(value) = map.get_mut("a") {
+        *value += 1;
+    }
+    assert_eq!(3, map["a"]);
+    assert_eq!(Some((&"a".to_string(), &3)), map.get_key_value("a"));
+    assert_eq!(Some(3), map.remove("a"));
+    assert_eq!(None, map.remove("a"));
+    assert_eq!(None, map.remove_entry("a"));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn entry_api() {
+    let counts = word_counts("a b a c b a");
+
+    assert_eq!(Some(&3), counts.get("a"));
+    assert_eq!(Some(&2), counts.get("b"));
+    assert_eq!(Some(&1), counts.get("c"));
+    assert_eq!(None, counts.get("d"));
+
+    let mut map: HashMap<&str, Vec<i32>> = HashMap::new();
+    map.entry("x").or_default().push(1);
+    map.entry("x").or_insert_with(|| vec![0]).push(2);
+    map.entry("x").and_modify(|v| v.push(3)).or_default();
+    assert_eq!(Some(&vec![1, 2, 3]), map.get("x"));
+}
+
+#[test]
+fn iteration_and_bulk() {
+    let mut map: HashMap<u32, u32> = (0..100).map(|i| (i, i * i)).collect();
+    map.extend([(100, 10_000), (101, 10_201)]);
+
+    let mut keys: Vec<u32> = map.keys().copied().collect();
+    keys.sort_unstable();
+    assert_eq!((0..102).collect::<Vec<_>>(), keys);
+    assert_eq!(
+        map.values().map(|&v| v as u64).sum::<u64>(),
+        map.iter().map(|(_, &v)| v as u64).sum::<u64>()
+    );
+
+    for value in map.values_mut() {
+        *value += 1;
+    }
+    for (_, value) in map.iter_mut() {
+        *value -= 1;
+    }
+    for (&key, &value) in &map {
+        assert_eq!(key * key, value);
+    }
+
+    map.retain(|&k, _| k % 2 == 0);
+    assert_eq!(51, map.len());
+    let drained: Vec<(u32, u32)> = map.drain().collect();
+    assert_eq!(51, drained.len());
+    assert!(map.is_empty());
+
+    map.reserve(10);
+    assert!(map.capacity() >= 10);
+    map.insert(1, 1);
+    map.shrink_to_fit();
+    map.clear();
+    assert_eq!(0, map.len());
+}
+
+#[test]
+fn construction() {
+    let map = HashMap::from([(1, "one"), (2, "two")]);
+    assert_eq!(2, map.len());
+
+    let map: HashMap<i32, i32> = HashMap::with_capacity(16);
+    assert!(map.capacity() >= 16);
+
+    let map: HashMap<i32, i32> = HashMap::default();
+    assert!(map.is_empty());
+
+    let map: HashMap<i32, i32> = HashMap::with_hasher(Default::default());
+    let _ = map.hasher();
+
+    let values: Vec<&str> = HashMap::from([(1, "one")]).into_values().collect();
+    assert_eq!(vec!["one"], values);
+    let keys: Vec<i32> = HashMap::from([(1, "one")]).into_keys().collect();
+    assert_eq!(vec![1], keys);
+}
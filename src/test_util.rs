@@ -0,0 +1,193 @@
+use std::alloc::Layout;
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+use crate::{OAHashMap, Probing};
+
+pub(crate) fn keys_starting_at<V>(oa: &OAHashMap<i32, V>, index: usize, count: usize) -> Vec<i32> {
+    (0..)
+        .filter(|key| oa.starting_index(oa.make_hash(key)) == index)
+        .take(count)
+        .collect()
+}
+
+/// Checks the layout the map's probing keeps. Under linear probing every
+/// entry sits after the entries that started before it, with no empty slot
+/// between an entry and its starting slot; otherwise every entry is within
+/// the distance lookups walk, which hopscotch keeps inside the neighbourhood.
+pub(crate) fn assert_layout<K, V, S, A>(oa: &OAHashMap<K, V, S, A>)
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    let slots = oa.table.num_slots();
+    for index in 0..slots {
+        assert_eq!(oa.table.is_full(index), oa.table.is_marked(index));
+        let Some(bucket) = oa.table.get(index) else {
+            continue;
+        };
+        let distance = oa.distance(bucket.hash, index);
+        if oa.probing == Probing::Hopscotch {
+            assert!(oa.max_distance < Probing::HOPSCOTCH_NEIGHBORHOOD);
+        }
+        if oa.probing != Probing::Linear {
+            assert!(distance <= oa.max_distance);
+            continue;
+        }
+
+        let previous = index.wrapping_sub(1) & oa.mask;
+        match oa.table.get(previous) {
+            Some(before) => assert!(distance <= oa.distance(before.hash, previous) + 1),
+            None => assert_eq!(0, distance),
+        }
+    }
+    let migrating = oa.migration.as_ref().map_or(0, |m| {
+        let table = m.table();
+        assert!((0..table.num_slots()).all(|i| table.is_full(i) == table.is_marked(i)));
+        assert_eq!(m.len(), table.iter().count());
+        m.len()
+    });
+    assert_eq!(oa.len(), oa.table.iter().count() + migrating);
+    assert_eq!(Ok(()), oa.check_invariants());
+}
+
+pub(crate) struct Tagged {
+    pub(crate) id: i32,
+    pub(crate) tag: &'static str,
+}
+
+impl Tagged {
+    pub(crate) fn new(id: i32, tag: &'static str) -> Self {
+        Self { id, tag }
+    }
+}
+
+impl PartialEq for Tagged {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Tagged {}
+
+impl Hash for Tagged {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+thread_local! {
+    static CONSTRUCTED: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Counted(String);
+
+impl Counted {
+    pub(crate) fn constructed() -> usize {
+        CONSTRUCTED.with(Cell::get)
+    }
+}
+
+impl From<&str> for Counted {
+    fn from(value: &str) -> Self {
+        CONSTRUCTED.with(|constructed| constructed.set(constructed.get() + 1));
+
+        Self(value.to_string())
+    }
+}
+
+impl Borrow<str> for Counted {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(not(feature = "check-hashes"))]
+thread_local! {
+    static HASHES: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(not(feature = "check-hashes"))]
+#[derive(PartialEq, Eq)]
+pub(crate) struct Hashed(pub(crate) i32);
+
+#[cfg(not(feature = "check-hashes"))]
+impl Hashed {
+    pub(crate) fn hashes() -> usize {
+        HASHES.with(Cell::get)
+    }
+}
+
+#[cfg(not(feature = "check-hashes"))]
+impl Hash for Hashed {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        HASHES.with(|hashes| hashes.set(hashes.get() + 1));
+        self.0.hash(state);
+    }
+}
+
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0
+    }
+
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct CountingAlloc {
+    stats: Rc<Cell<AllocStats>>,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub(crate) struct AllocStats {
+    pub(crate) allocations: usize,
+    pub(crate) deallocations: usize,
+    pub(crate) live_bytes: usize,
+}
+
+impl CountingAlloc {
+    pub(crate) fn stats(&self) -> AllocStats {
+        self.stats.get()
+    }
+}
+
+unsafe impl Allocator for CountingAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let memory = Global.allocate(layout)?;
+        let mut stats = self.stats.get();
+        stats.allocations += 1;
+        stats.live_bytes += layout.size();
+        self.stats.set(stats);
+
+        Ok(memory)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let mut stats = self.stats.get();
+        stats.deallocations += 1;
+        stats.live_bytes -= layout.size();
+        self.stats.set(stats);
+
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
@@ -0,0 +1,287 @@
+//! Events for the `tracing` feature: a span around every resize, an event
+//! whenever a probe walks further than the map's threshold, and one when
+//! removals leave enough cleared slots to rebuild the table in place.
+//!
+//! Without the feature every hook here is empty and compiles away.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+
+use crate::OAHashMap;
+
+/// What a resize holds on to for as long as it runs.
+#[cfg(feature = "tracing")]
+pub(crate) type ResizeSpan = tracing::span::EnteredSpan;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct ResizeSpan;
+
+impl<K, V, S, A> OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Enters a span for moving the entries into a table of `new_slots`.
+    #[inline(always)]
+    pub(crate) fn enter_resize(&self, new_slots: usize) -> ResizeSpan {
+        #[cfg(feature = "tracing")]
+        {
+            let new_capacity = (new_slots as f32 * self.config.max_load_factor) as usize;
+            tracing::info_span!(
+                target: "oahm",
+                "resize",
+                old_capacity = self.capacity(),
+                new_capacity,
+                live = self.len,
+                tombstones = self.cleared,
+            )
+            .entered()
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = new_slots;
+            ResizeSpan
+        }
+    }
+
+    /// Reports a probe that inspected `walked` slots, if that is more than
+    /// the threshold.
+    #[inline(always)]
+    pub(crate) fn trace_probe(&self, op: &'static str, walked: usize) {
+        #[cfg(feature = "tracing")]
+        if walked > self.config.probe_event_threshold {
+            tracing::warn!(
+                target: "oahm",
+                op,
+                walked,
+                threshold = self.config.probe_event_threshold,
+                len = self.len,
+                capacity = self.capacity(),
+                "long probe"
+            );
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (op, walked);
+    }
+
+    /// Reports rebuilding in place to drop the slots removals cleared.
+    #[inline(always)]
+    pub(crate) fn trace_purge(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            target: "oahm",
+            tombstones = self.cleared,
+            live = self.len,
+            slots = self.table.num_slots(),
+            "rehashing to drop cleared slots"
+        );
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::fmt::Debug;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::Attributes;
+    use tracing::{Event, Id, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    use crate::{OAHashMap, OAHashMapBuilder, Probing};
+
+    /// A span or event's name or message and its fields, as text.
+    type Record = (String, Vec<(String, String)>);
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<Record>>>);
+
+    #[derive(Default)]
+    struct Fields(Vec<(String, String)>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push((field.name().to_string(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    impl<S> Layer<S> for Capture
+    where
+        S: Subscriber,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            let name = attrs.metadata().name().to_string();
+            self.0.lock().unwrap().push((name, fields.0));
+        }
+
+        fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let message = fields
+                .0
+                .iter()
+                .position(|(name, _)| name == "message")
+                .map(|at| fields.0.remove(at).1)
+                .unwrap_or_default();
+            self.0.lock().unwrap().push((message, fields.0));
+        }
+    }
+
+    impl Capture {
+        fn run(&self, f: impl FnOnce()) -> Vec<Record> {
+            let subscriber = tracing_subscriber::registry().with(self.clone());
+            tracing::subscriber::with_default(subscriber, f);
+
+            core::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    /// Sends every key to the same slot.
+    #[derive(Clone, Copy, Default)]
+    struct Colliding;
+
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            7
+        }
+
+        fn write(&mut self, _bytes: &[u8]) {}
+    }
+
+    impl BuildHasher for Colliding {
+        type Hasher = Self;
+
+        fn build_hasher(&self) -> Self {
+            Self
+        }
+    }
+
+    fn field<'a>(record: &'a Record, name: &str) -> &'a str {
+        let (_, value) = record.1.iter().find(|(field, _)| field == name).unwrap();
+
+        value
+    }
+
+    #[test]
+    fn resizes_run_in_a_span_with_the_capacities() {
+        let capture = Capture::default();
+        let records = capture.run(|| {
+            let mut oa = OAHashMap::with_capacity(30);
+            for i in 0..40u32 {
+                oa.insert(i, i);
+            }
+        });
+
+        let resizes: Vec<_> = records
+            .iter()
+            .filter(|(name, _)| name == "resize")
+            .collect();
+        assert_eq!(1, resizes.len());
+        let growth = resizes[0];
+        assert_eq!("38", field(growth, "old_capacity"));
+        assert_eq!("76", field(growth, "new_capacity"));
+        assert_eq!("39", field(growth, "live"));
+        assert_eq!("0", field(growth, "tombstones"));
+    }
+
+    #[test]
+    fn long_probes_report_past_the_builder_threshold() {
+        let capture = Capture::default();
+        let records = capture.run(|| {
+            let mut oa: OAHashMap<u64, u64, _> = OAHashMapBuilder::new()
+                .probe_event_threshold(4)
+                .build_with_hasher(Colliding);
+            oa.extend((0..6).map(|i| (i, i)));
+            assert_eq!(Some(&5), oa.get(&5));
+            assert_eq!(None, oa.get(&6));
+        });
+
+        let probes: Vec<_> = records
+            .iter()
+            .filter(|(message, _)| message == "long probe")
+            .collect();
+        let walks: Vec<_> = probes
+            .iter()
+            .map(|probe| (field(probe, "op"), field(probe, "walked")))
+            .collect();
+        assert_eq!(
+            vec![
+                ("insert", "5"),
+                ("insert", "6"),
+                ("lookup", "6"),
+                ("lookup", "7")
+            ],
+            walks
+        );
+        assert!(probes.iter().all(|probe| field(probe, "threshold") == "4"));
+
+        let quiet = capture.run(|| {
+            let oa: OAHashMap<u64, u64> = (0..1_000).map(|i| (i, i)).collect();
+            assert!((0..1_000).all(|i| oa.get(&i) == Some(&i)));
+        });
+        assert!(quiet.iter().all(|(message, _)| message != "long probe"));
+    }
+
+    #[test]
+    fn resizing_past_the_threshold_reports_no_probes() {
+        let mut oa: OAHashMap<u64, u64, _> = OAHashMapBuilder::new()
+            .probe_event_threshold(4)
+            .build_with_hasher(Colliding);
+        for i in 0..20 {
+            oa.entry(i).or_insert(i);
+        }
+
+        let records = Capture::default().run(|| {
+            oa.reserve(1_000);
+            oa.rehash();
+            oa.check_invariants().unwrap();
+        });
+
+        assert!(records.iter().any(|(name, _)| name == "resize"));
+        assert!(records.iter().all(|(message, _)| message != "long probe"));
+    }
+
+    #[test]
+    fn rebuilding_to_drop_cleared_slots_is_reported() {
+        let capture = Capture::default();
+        let records = capture.run(|| {
+            let mut oa = OAHashMap::with_capacity(30);
+            oa.set_probing(Probing::Quadratic);
+            oa.extend((0..30u32).map(|i| (i, i)));
+            for i in 0..20 {
+                oa.remove(&i);
+            }
+            for i in 100..120 {
+                oa.insert(i, i);
+            }
+        });
+
+        let purges: Vec<_> = records
+            .iter()
+            .filter(|(message, _)| message == "rehashing to drop cleared slots")
+            .collect();
+        assert!(!purges.is_empty());
+        assert!(purges
+            .iter()
+            .all(|purge| field(purge, "tombstones").parse::<usize>().unwrap() >= 16));
+        let rebuild = records
+            .iter()
+            .skip_while(|(message, _)| message != "rehashing to drop cleared slots")
+            .find(|(name, _)| name == "resize")
+            .unwrap();
+        assert_eq!(
+            field(rebuild, "old_capacity"),
+            field(rebuild, "new_capacity")
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! A map whose entries expire a set time after they were inserted, or after
+//! they were last refreshed.
+//!
+//! Expired entries stay in the table until something reclaims them, but no
+//! lookup, iterator or count sees them. Lookups through `&mut self` remove
+//! an expired entry they land on, and [`purge_expired`](TtlOAHashMap::purge_expired)
+//! sweeps them all. Both remove the entry as any removal does, so the probe
+//! chains passing over it stay intact.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use std::time::{Duration, Instant};
+
+use crate::{DefaultHashBuilder, Equivalent, OAHashMap, RawEntryMut};
+
+/// Where a [`TtlOAHashMap`] reads the time from, so tests can move it along
+/// by hand.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// Reads [`Instant::now`].
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+struct Expiring<V> {
+    value: V,
+    ttl: Duration,
+    /// Never reached when the lifetime runs past what `Instant` can hold.
+    deadline: Option<Instant>,
+}
+
+impl<V> Expiring<V> {
+    fn new(value: V, ttl: Duration, now: Instant) -> Self {
+        Self {
+            value,
+            ttl,
+            deadline: now.checked_add(ttl),
+        }
+    }
+
+    fn is_live(&self, now: Instant) -> bool {
+        self.deadline.is_none_or(|deadline| now < deadline)
+    }
+}
+
+pub struct TtlOAHashMap<K, V, C = SystemClock, S = DefaultHashBuilder>
+where
+    K: Hash + Eq,
+{
+    map: OAHashMap<K, Expiring<V>, S>,
+    clock: C,
+}
+
+impl<K, V> TtlOAHashMap<K, V>
+where
+    K: Hash + Eq,
+{
+    pub const fn new() -> Self {
+        Self::with_clock_and_hasher(SystemClock, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, C> TtlOAHashMap<K, V, C>
+where
+    K: Hash + Eq,
+    C: Clock,
+{
+    pub const fn with_clock(clock: C) -> Self {
+        Self::with_clock_and_hasher(clock, DefaultHashBuilder::new())
+    }
+}
+
+impl<K, V, C, S> TtlOAHashMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    C: Clock,
+    S: BuildHasher,
+{
+    pub const fn with_clock_and_hasher(clock: C, hash_builder: S) -> Self {
+        Self {
+            map: OAHashMap::with_hasher(hash_builder),
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Inserts the entry to expire `ttl` from now, returning the value it
+    /// replaced if that had not expired yet.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        let now = self.clock.now();
+        let old = self.map.insert(key, Expiring::new(value, ttl, now))?;
+
+        old.is_live(now).then_some(old.value)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let (key, entry) = self.map.get_key_value(key)?;
+
+        entry
+            .is_live(self.clock.now())
+            .then_some((key, &entry.value))
+    }
+
+    /// Removes the entry instead if it has expired.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let entry = self.live_entry(key)?;
+
+        Some(&mut entry.value)
+    }
+
+    /// Restarts the entry's lifetime, the one it was inserted with, from now.
+    /// Removes the entry instead if it has expired.
+    pub fn get_refreshed<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let now = self.clock.now();
+        let entry = self.live_entry(key)?;
+        entry.deadline = now.checked_add(entry.ttl);
+
+        Some(&mut entry.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// How long the entry has left, or `None` if it is absent or expired.
+    /// A lifetime past what `Instant` can hold reports as `Duration::MAX`.
+    pub fn ttl<Q>(&self, key: &Q) -> Option<Duration>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let entry = self.map.get(key)?;
+        let now = self.clock.now();
+        if !entry.is_live(now) {
+            return None;
+        }
+
+        Some(
+            entry
+                .deadline
+                .map_or(Duration::MAX, |deadline| deadline - now),
+        )
+    }
+
+    /// Returns the value only if it had not expired.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let entry = self.map.remove(key)?;
+
+        entry.is_live(self.clock.now()).then_some(entry.value)
+    }
+
+    /// Removes every expired entry, returning how many there were.
+    pub fn purge_expired(&mut self) -> usize {
+        let (now, before) = (self.clock.now(), self.map.len());
+        self.map.retain(|_, entry| entry.is_live(now));
+
+        before - self.map.len()
+    }
+
+    /// Skips the expired entries.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        let now = self.clock.now();
+        self.map
+            .iter()
+            .filter(move |(_, entry)| entry.is_live(now))
+            .map(|(key, entry)| (key, &entry.value))
+    }
+
+    /// Counts the entries that have not expired, which takes a walk over the
+    /// table. Straight after [`purge_expired`](Self::purge_expired),
+    /// [`stored_len`](Self::stored_len) gives the same count in constant time.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// The entries in the table, counting the expired ones not yet reclaimed.
+    pub fn stored_len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    fn live_entry<Q>(&mut self, key: &Q) -> Option<&mut Expiring<V>>
+    where
+        Q: Hash + Equivalent<K> + ?Sized,
+    {
+        let now = self.clock.now();
+        let RawEntryMut::Occupied(entry) = self.map.raw_entry_mut().from_key(key) else {
+            return None;
+        };
+        if !entry.get().is_live(now) {
+            entry.remove();
+            return None;
+        }
+
+        Some(entry.into_mut())
+    }
+}
+
+impl<K, V, C, S> Default for TtlOAHashMap<K, V, C, S>
+where
+    K: Hash + Eq,
+    C: Clock + Default,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::with_clock_and_hasher(C::default(), S::default())
+    }
+}
+
+impl<K, V, C, S> fmt::Debug for TtlOAHashMap<K, V, C, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    C: Clock,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use super::{Clock, TtlOAHashMap};
+    use crate::test_util::assert_layout;
+    use crate::Probing;
+
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<Instant>>);
+
+    impl ManualClock {
+        fn new() -> Self {
+            Self(Rc::new(Cell::new(Instant::now())))
+        }
+
+        fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + Duration::from_secs(seconds));
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    const TEN_SECONDS: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let clock = ManualClock::new();
+        let mut map = TtlOAHashMap::with_clock(clock.clone());
+        map.insert_with_ttl("session", 1, TEN_SECONDS);
+        map.insert_with_ttl("forever", 2, Duration::MAX);
+
+        clock.advance(9);
+        assert_eq!(Some(&1), map.get("session"));
+        assert_eq!(Some(Duration::from_secs(1)), map.ttl("session"));
+        assert_eq!(2, map.len());
+
+        clock.advance(2);
+        assert_eq!(None, map.get("session"));
+        assert_eq!(None, map.ttl("session"));
+        assert!(!map.contains_key("session"));
+        assert_eq!(1, map.len());
+        assert_eq!(2, map.stored_len());
+        assert_eq!(vec![(&"forever", &2)], map.iter().collect::<Vec<_>>());
+        assert_eq!(Some(Duration::MAX), map.ttl("forever"));
+
+        assert_eq!(None, map.get_mut("session"));
+        assert_eq!(1, map.stored_len());
+        assert_eq!(None, map.insert_with_ttl("session", 3, TEN_SECONDS));
+        assert_eq!(Some(3), map.insert_with_ttl("session", 4, TEN_SECONDS));
+    }
+
+    #[test]
+    fn refreshing_restarts_the_lifetime() {
+        let clock = ManualClock::new();
+        let mut map = TtlOAHashMap::with_clock(clock.clone());
+        map.insert_with_ttl(1, "a", TEN_SECONDS);
+
+        clock.advance(8);
+        assert_eq!(Some(&mut "a"), map.get_refreshed(&1));
+        clock.advance(8);
+        assert_eq!(Some(Duration::from_secs(2)), map.ttl(&1));
+        clock.advance(2);
+        assert_eq!(None, map.get_refreshed(&1));
+        assert_eq!(0, map.stored_len());
+    }
+
+    #[test]
+    fn reclaiming_keeps_the_other_entries_reachable() {
+        for probing in [Probing::Linear, Probing::Quadratic] {
+            let clock = ManualClock::new();
+            let mut map = TtlOAHashMap::with_clock(clock.clone());
+            map.map.set_probing(probing);
+            for key in 0..1000 {
+                let seconds = if key % 3 == 0 { 5 } else { 20 };
+                map.insert_with_ttl(key, key, Duration::from_secs(seconds));
+            }
+            clock.advance(10);
+
+            assert_eq!(666, map.len());
+            for key in (0..1000).step_by(6) {
+                assert_eq!(None, map.remove(&key));
+            }
+            assert_eq!(334 - 167, map.purge_expired());
+            assert_eq!(666, map.stored_len());
+            assert_layout(&map.map);
+            for key in 0..1000 {
+                assert_eq!(key % 3 != 0, map.get(&key) == Some(&key));
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! [`Zeroize`] for maps whose keys and values implement it, and sets of
+//! such keys.
+//!
+//! The table already zeroes its own slots under the `zeroize` feature, but
+//! only what the entries' bytes hold and not what their keys or values point
+//! to. Zeroizing the whole map runs each key's and value's own `zeroize`
+//! first, so wrapping a map in [`Zeroizing`](zeroize::Zeroizing) wipes the
+//! entries' secrets as it drops.
+
+use core::hash::{BuildHasher, Hash};
+
+use allocator_api2::alloc::Allocator;
+use zeroize::Zeroize;
+
+use crate::OAHashMap;
+
+impl<K, V, S, A> Zeroize for OAHashMap<K, V, S, A>
+where
+    K: Hash + Eq + Zeroize,
+    V: Zeroize,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    /// Zeroizes every key and value in place, then drops them and leaves
+    /// the map empty.
+    fn zeroize(&mut self) {
+        self.finish_migration();
+        for index in 0..self.table.num_slots() {
+            if let Some((hashed, value)) = self.table.get_pair_mut(index) {
+                hashed.key.zeroize();
+                value.zeroize();
+            }
+        }
+
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use zeroize::{Zeroize, Zeroizing};
+
+    use crate::{OAHashMap, OAHashSet};
+
+    /// Counts the values zeroized through it.
+    struct Counted {
+        secret: u64,
+        zeroized: Rc<Cell<usize>>,
+    }
+
+    impl Zeroize for Counted {
+        fn zeroize(&mut self) {
+            self.secret.zeroize();
+            self.zeroized.set(self.zeroized.get() + 1);
+        }
+    }
+
+    #[test]
+    fn zeroizing_a_map_zeroizes_each_entry_once() {
+        let zeroized = Rc::new(Cell::new(0));
+        let counted = |secret| Counted {
+            secret,
+            zeroized: Rc::clone(&zeroized),
+        };
+
+        let mut oa = OAHashMap::new();
+        oa.set_incremental_resize(true);
+        for key in 0..1_000u64 {
+            oa.insert(key, counted(!key));
+        }
+        let removed: Vec<_> = (0..100).filter_map(|key| oa.remove(&key)).collect();
+        assert_eq!(100, removed.len());
+        assert_eq!(0, zeroized.get());
+
+        oa.zeroize();
+        assert_eq!(900, zeroized.get());
+        assert!(oa.is_empty());
+        oa.check_invariants().unwrap();
+
+        let mut oa = Zeroizing::new(oa);
+        oa.extend((0..50).map(|key| (key, counted(key))));
+        drop(oa);
+        assert_eq!(950, zeroized.get());
+        assert!(removed
+            .iter()
+            .zip(0..100u64)
+            .all(|(value, key)| value.secret == !key));
+    }
+
+    #[test]
+    fn zeroizing_a_set_empties_it() {
+        let mut set: OAHashSet<u64> = (0..100).collect();
+        set.zeroize();
+        assert!(set.is_empty());
+    }
+}